@@ -2,20 +2,24 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use bson::{doc, serde_helpers::deserialize_hex_string_from_object_id};
+use bson::{doc, serde_helpers::deserialize_hex_string_from_object_id, DateTime};
+use futures::TryStreamExt;
 use mongodb::{
-    options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
+    options::{AggregateOptions, CreateCollectionOptions, ValidationAction, ValidationLevel},
     results::{DeleteResult, InsertOneResult, UpdateResult},
     Client,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::database::{
-    document::{Document, DocumentBase},
+    collections::board::BOARD_COLLECTION_NAME,
+    config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT},
+    document::{empty_update_response, Document, DocumentBase},
+    timestamp::{default_timestamp, serialize_optional_timestamp, serialize_timestamp},
     validator::Validator,
 };
 
-const ACTIVE_MEMBER_COLLECTION_NAME: &str = "active_member";
+pub const ACTIVE_MEMBER_COLLECTION_NAME: &str = "active_member";
 const ACTIVE_MEMBER_DOCUMENT_NAME: &str = "Active Member";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,6 +34,14 @@ pub struct ActiveMember {
     pub board_id: String,
     pub x: f32,
     pub y: f32,
+    #[serde(default = "default_timestamp", serialize_with = "serialize_timestamp")]
+    pub created_at: DateTime,
+    // Missing until the position was updated for the first time
+    #[serde(default, skip_serializing)]
+    pub position_updated_at: Option<DateTime>,
+    // Refreshed by `keepalive` messages, missing if the member never sent one
+    #[serde(default, serialize_with = "serialize_optional_timestamp")]
+    pub last_seen_at: Option<DateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,6 +51,16 @@ pub struct CreateActiveMember {
     pub board_id: String,
     pub x: f32,
     pub y: f32,
+    pub created_at: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveBoard {
+    pub board_id: String,
+    // Missing if the board was deleted while members were still active on it
+    pub board_name: Option<String>,
+    pub member_count: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,6 +69,33 @@ pub struct UpdateActiveMember {
     pub board_id: Option<String>,
     pub x: Option<f32>,
     pub y: Option<f32>,
+    // Defaults to the time of the update if a position is set
+    pub position_updated_at: Option<DateTime>,
+}
+
+impl UpdateActiveMember {
+    pub fn to_update_fields(&self) -> bson::Document {
+        let mut update_fields = doc! {};
+        if let Some(board_id) = self.board_id.clone() {
+            update_fields.insert("boardId", board_id);
+        }
+        if let Some(x) = self.x {
+            update_fields.insert("x", x);
+        }
+        if let Some(y) = self.y {
+            update_fields.insert("y", y);
+        }
+        match self.position_updated_at {
+            Some(position_updated_at) => {
+                update_fields.insert("positionUpdatedAt", position_updated_at);
+            }
+            None if update_fields.contains_key("x") || update_fields.contains_key("y") => {
+                update_fields.insert("positionUpdatedAt", DateTime::now());
+            }
+            None => {}
+        }
+        update_fields
+    }
 }
 
 impl Document<ActiveMember, CreateActiveMember, UpdateActiveMember> for ActiveMember {
@@ -92,15 +141,9 @@ impl Document<ActiveMember, CreateActiveMember, UpdateActiveMember> for ActiveMe
         query_doc: bson::Document,
         update_document: UpdateActiveMember,
     ) -> Result<UpdateResult, Response> {
-        let mut update_fields = doc! {};
-        if let Some(board_id) = update_document.board_id {
-            update_fields.insert("boardId", board_id);
-        }
-        if let Some(x) = update_document.x {
-            update_fields.insert("x", x);
-        }
-        if let Some(y) = update_document.y {
-            update_fields.insert("y", y);
+        let update_fields = update_document.to_update_fields();
+        if let Some(error_response) = empty_update_response(&update_fields) {
+            return Err(error_response);
         }
         let update_doc = doc! {
             "$set": update_fields
@@ -152,6 +195,128 @@ impl Document<ActiveMember, CreateActiveMember, UpdateActiveMember> for ActiveMe
 }
 
 impl ActiveMember {
+    pub async fn find_one_and_delete(
+        client: &Client,
+        query_doc: bson::Document,
+    ) -> Result<Option<ActiveMember>, Response> {
+        DocumentBase::find_one_and_delete::<ActiveMember>(
+            client,
+            ACTIVE_MEMBER_COLLECTION_NAME,
+            query_doc,
+            ACTIVE_MEMBER_DOCUMENT_NAME,
+        )
+        .await
+    }
+
+    pub async fn delete_all_of_board(
+        board_id: String,
+        database_client: &mongodb::Client,
+    ) -> Result<DeleteResult, Response> {
+        let query_doc = doc! {
+            "boardId": board_id,
+        };
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<ActiveMember>(ACTIVE_MEMBER_COLLECTION_NAME)
+            .delete_many(query_doc, None)
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} deletion", ACTIVE_MEMBER_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Sorted by member count, boards with the same count by their ID
+    pub async fn get_active_boards(client: &Client) -> Result<Vec<ActiveBoard>, Response> {
+        let pipeline = vec![
+            doc! { "$group": { "_id": "$boardId", "memberCount": { "$sum": 1 } } },
+            doc! { "$sort": { "memberCount": -1, "_id": 1 } },
+            doc! {
+                "$lookup": {
+                    "from": BOARD_COLLECTION_NAME,
+                    "let": {
+                        "boardObjectId": {
+                            "$convert": { "input": "$_id", "to": "objectId", "onError": null, "onNull": null }
+                        }
+                    },
+                    "pipeline": [
+                        { "$match": { "$expr": { "$eq": ["$_id", "$$boardObjectId"] } } },
+                        { "$project": { "name": 1 } },
+                    ],
+                    "as": "board",
+                }
+            },
+            doc! {
+                "$project": {
+                    "_id": 0,
+                    "boardId": "$_id",
+                    "boardName": { "$first": "$board.name" },
+                    "memberCount": { "$toLong": "$memberCount" },
+                }
+            },
+        ];
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<ActiveMember>(ACTIVE_MEMBER_COLLECTION_NAME)
+            .aggregate(
+                pipeline,
+                AggregateOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Error during active Board fetching",
+                )
+                    .into_response())
+            }
+        };
+        match cursor
+            .with_type::<ActiveBoard>()
+            .try_collect::<Vec<ActiveBoard>>()
+            .await
+        {
+            Ok(active_boards) => Ok(active_boards),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found active Boards could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn keep_alive(
+        user_id: &str,
+        board_id: &str,
+        database_client: &mongodb::Client,
+    ) -> Result<UpdateResult, Response> {
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<ActiveMember>(ACTIVE_MEMBER_COLLECTION_NAME)
+            .update_one(
+                doc! { "userId": user_id, "boardId": board_id },
+                doc! { "$set": doc! { "lastSeenAt": DateTime::now() } },
+                None,
+            )
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} keepalive", ACTIVE_MEMBER_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
     pub async fn get_existing_active_member_by_user_id(
         user_id: String,
         database_client: &mongodb::Client,
@@ -179,10 +344,10 @@ impl Validator for ActiveMember {
             "$jsonSchema": doc! {
                 "bsonType": "object",
                 "title": "Active Member Validation",
-                "required": vec!["_id", "user_id", "board_id", "x", "y"],
+                "required": vec!["_id", "userId", "boardId", "x", "y", "createdAt"],
                 "properties": doc! {
                     "_id": doc! {
-                        "bsonType": "string",
+                        "bsonType": "objectId",
                         "description": "ID of this active member"
                     },
                     "userId": doc! {
@@ -201,6 +366,18 @@ impl Validator for ActiveMember {
                         "bsonType": "double",
                         "description": "Y Coordinate of the active member to display the cursor"
                     },
+                    "createdAt": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the creation of the active member"
+                    },
+                    "positionUpdatedAt": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the latest position of the active member"
+                    },
+                    "lastSeenAt": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the latest keepalive of the active member"
+                    },
                 }
             }
         };
@@ -214,3 +391,46 @@ impl Validator for ActiveMember {
         Ok(validation_opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    #[test]
+    fn created_active_member_satisfies_validator() {
+        let stored_active_member = bson::to_document(&CreateActiveMember {
+            user_id: "user".to_string(),
+            board_id: "board".to_string(),
+            x: 0.0,
+            y: 0.0,
+            created_at: DateTime::now(),
+        })
+        .unwrap();
+        assert_satisfies_validator::<ActiveMember>(&stored_active_member);
+    }
+
+    #[test]
+    fn update_without_fields_is_rejected() {
+        let update_active_member = UpdateActiveMember {
+            board_id: None,
+            x: None,
+            y: None,
+            position_updated_at: None,
+        };
+        assert!(empty_update_response(&update_active_member.to_update_fields()).is_some());
+    }
+
+    #[test]
+    fn position_update_stamps_position_updated_at() {
+        let update_active_member = UpdateActiveMember {
+            board_id: None,
+            x: Some(1.0),
+            y: None,
+            position_updated_at: None,
+        };
+        assert!(update_active_member
+            .to_update_fields()
+            .contains_key("positionUpdatedAt"));
+    }
+}
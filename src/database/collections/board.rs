@@ -1,30 +1,101 @@
-use std::str::FromStr;
+use std::{env::var, str::FromStr, sync::OnceLock};
 
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use bson::{oid::ObjectId, serde_helpers::deserialize_hex_string_from_object_id};
+use bson::{oid::ObjectId, serde_helpers::deserialize_hex_string_from_object_id, DateTime};
+use futures::TryStreamExt;
 use mongodb::{
     bson::doc,
-    options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
+    options::{
+        AggregateOptions, CreateCollectionOptions, FindOneAndUpdateOptions, FindOptions,
+        IndexOptions, ReturnDocument, ValidationAction, ValidationLevel,
+    },
     results::{DeleteResult, InsertOneResult, UpdateResult},
-    Client,
+    Client, IndexModel,
 };
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
-use crate::database::{
-    config::DATABASE_NAME,
-    document::{Document, DocumentBase},
-    validator::Validator,
+use crate::{
+    database::{
+        config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT},
+        document::{empty_update_response, Document, DocumentBase},
+        timestamp::{default_timestamp, serialize_timestamp},
+        validator::Validator,
+    },
+    utils::text_encryption::encrypt_text,
 };
 
-use super::user::User;
+use super::{
+    active_member::ACTIVE_MEMBER_COLLECTION_NAME,
+    element::{Element, ELEMENT_COLLECTION_NAME, MAX_ELEMENTS_PER_BOARD},
+    element_history::ElementHistory,
+    user::User,
+};
 
-const BOARD_COLLECTION_NAME: &str = "board";
+pub const BOARD_COLLECTION_NAME: &str = "board";
 const BOARD_DOCUMENT_NAME: &str = "Board";
+const MAX_BOARD_NAME_LENGTH: usize = 100;
+const HOST_NAME_INDEX_NAME: &str = "host_name_unique";
+
+// Boards that already share a name with another board of their host are kept as they are, the unique
+// index cannot be built while such duplicates exist, so until they are renamed only the check on
+// create, clone and rename applies and a failed index creation is logged on startup
+#[allow(non_snake_case)]
+pub fn BOARD_NAME_UNIQUE_PER_HOST() -> bool {
+    static BOARD_NAME_UNIQUE_PER_HOST: OnceLock<bool> = OnceLock::new();
+    *BOARD_NAME_UNIQUE_PER_HOST.get_or_init(|| match var("BOARD_NAME_UNIQUE_PER_HOST") {
+        Ok(board_name_unique_per_host) => board_name_unique_per_host
+            .parse()
+            .expect("Failed to parse `BOARD_NAME_UNIQUE_PER_HOST` environment variable."),
+        Err(_) => false,
+    })
+}
+
+// Per board toggles the host can change, boards stored before settings existed get the defaults
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BoardSettings {
+    // Guests may join the board at all, public boards still require this
+    pub allow_guests: bool,
+    // Lowers the element limit of the board below `BOARD_MAX_ELEMENTS`, it can never raise it
+    pub max_elements: Option<u64>,
+}
+
+impl Default for BoardSettings {
+    fn default() -> Self {
+        Self {
+            allow_guests: true,
+            max_elements: None,
+        }
+    }
+}
 
+impl BoardSettings {
+    pub fn element_limit(&self) -> u64 {
+        self.max_elements
+            .map_or(MAX_ELEMENTS_PER_BOARD(), |max_elements| {
+                max_elements.min(MAX_ELEMENTS_PER_BOARD())
+            })
+    }
+}
+
+// Permission tiers of a board
+//
+// Members (`allowedMembers`, always including the host) may edit the board: create, update, lock,
+// move, reorder and delete its elements, over REST as well as over WebTransport.
+//
+// Viewers (`viewers`) may open board, element and active member streams and read the board and
+// its elements, but every edit is rejected. REST answers with 403 through `Board::assert_member`,
+// the WebTransport element handlers answer with an error response through `assert_editor`. Both
+// check `Board::can_edit` for the `userId` of each request against the stored board, so the
+// stream a message arrives on does not matter and a member demoted to viewer loses edit rights
+// with their next message. Releasing a lock held from before the demotion stays possible.
+//
+// The lists are managed via `/board/:boardId/allowed-member/:userId` and
+// `/board/:boardId/viewer/:userId`. Users in both lists count as members.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Board {
@@ -35,7 +106,36 @@ pub struct Board {
     pub _id: String,
     pub name: String,
     pub host: String,
+    // Members may edit the board, viewers may only subscribe to its events and read its state
     pub allowed_members: Vec<String>,
+    #[serde(default)]
+    pub viewers: Vec<String>,
+    // Guests may only join public boards
+    #[serde(default)]
+    pub is_public: bool,
+    // Element texts of encrypted boards are stored encrypted, see `utils::text_encryption`
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub settings: BoardSettings,
+    // Templates are curated by admins and copied into new boards, they are not listed as boards of
+    // their members
+    #[serde(default)]
+    pub is_template: bool,
+    #[serde(default = "default_timestamp", serialize_with = "serialize_timestamp")]
+    pub created_at: DateTime,
+    #[serde(default = "default_timestamp", serialize_with = "serialize_timestamp")]
+    pub updated_at: DateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardSummary {
+    pub id: String,
+    pub name: String,
+    pub host: String,
+    pub element_count: i64,
+    pub active_member_count: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -44,6 +144,11 @@ pub struct CreateBoard {
     pub name: String,
     pub host: String,
     pub allowed_members: Vec<String>,
+    pub viewers: Vec<String>,
+    pub is_public: bool,
+    pub settings: BoardSettings,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -52,6 +157,40 @@ pub struct UpdateBoard {
     pub name: Option<String>,
     pub host: Option<String>,
     pub allowed_members: Option<Vec<String>>,
+    pub viewers: Option<Vec<String>>,
+    pub is_public: Option<bool>,
+}
+
+impl UpdateBoard {
+    // Whether the board still contains its host among the allowed members after the update, a
+    // new host without a member list is added to the stored members
+    pub fn keeps_host(&self, stored_host: &str) -> bool {
+        let host = self.host.as_deref().unwrap_or(stored_host);
+        match &self.allowed_members {
+            Some(allowed_members) => allowed_members.iter().any(|member| member == host),
+            None => true,
+        }
+    }
+
+    pub fn to_update_fields(&self) -> bson::Document {
+        let mut update_fields = doc! {};
+        if let Some(name) = self.name.clone() {
+            update_fields.insert("name", name);
+        }
+        if let Some(host) = self.host.clone() {
+            update_fields.insert("host", host);
+        }
+        if let Some(allowed_members) = self.allowed_members.clone() {
+            update_fields.insert("allowedMembers", allowed_members);
+        }
+        if let Some(viewers) = self.viewers.clone() {
+            update_fields.insert("viewers", viewers);
+        }
+        if let Some(is_public) = self.is_public {
+            update_fields.insert("isPublic", is_public);
+        }
+        update_fields
+    }
 }
 
 impl Document<Board, CreateBoard, UpdateBoard> for Board {
@@ -63,7 +202,11 @@ impl Document<Board, CreateBoard, UpdateBoard> for Board {
             create_collection_opts,
             BOARD_DOCUMENT_NAME,
         )
-        .await
+        .await?;
+        if BOARD_NAME_UNIQUE_PER_HOST() {
+            Board::create_host_name_index(client).await;
+        }
+        Ok(())
     }
 
     async fn create_document(
@@ -97,27 +240,60 @@ impl Document<Board, CreateBoard, UpdateBoard> for Board {
         query_doc: bson::Document,
         update_document: UpdateBoard,
     ) -> Result<UpdateResult, Response> {
-        let mut update_fields = doc! {};
-        if let Some(name) = update_document.name {
-            update_fields.insert("name", name);
-        }
-        if let Some(host) = update_document.host {
-            update_fields.insert("host", host);
+        let mut query_doc = query_doc;
+        let board_query_doc = query_doc.clone();
+        let mut added_host = None;
+        let mut host_filtered = false;
+        // The host always has to stay part of `allowedMembers`, otherwise the board is orphaned
+        match (&update_document.host, &update_document.allowed_members) {
+            (Some(host), Some(_)) if !update_document.keeps_host(host) => {
+                return Err((
+                    StatusCode::CONFLICT,
+                    "The new host has to be an allowed member of the board",
+                )
+                    .into_response());
+            }
+            (Some(host), None) => added_host = Some(host.clone()),
+            // Only applies the new members if they still contain the stored host
+            (None, Some(allowed_members)) => {
+                query_doc.insert("host", doc! { "$in": allowed_members.clone() });
+                host_filtered = true;
+            }
+            _ => {}
         }
-        if let Some(allowed_members) = update_document.allowed_members {
-            update_fields.insert("allowedMembers", allowed_members);
+        let mut update_fields = update_document.to_update_fields();
+        if let Some(error_response) = empty_update_response(&update_fields) {
+            return Err(error_response);
         }
-        let update_doc = doc! {
+        update_fields.insert("updatedAt", DateTime::now());
+        let mut update_doc = doc! {
             "$set": update_fields,
         };
-        DocumentBase::update_document::<Board>(
+        if let Some(host) = added_host {
+            update_doc.insert("$addToSet", doc! { "allowedMembers": host });
+        }
+        let result = DocumentBase::update_document::<Board>(
             client,
             BOARD_COLLECTION_NAME,
             query_doc,
             update_doc,
             BOARD_DOCUMENT_NAME,
         )
-        .await
+        .await?;
+        // The board matches without the host filter, so the new members dropped the host
+        if host_filtered
+            && result.matched_count == 0
+            && Board::get_document(client, board_query_doc)
+                .await?
+                .is_some()
+        {
+            return Err((
+                StatusCode::CONFLICT,
+                "The allowed members have to contain the host of the board",
+            )
+                .into_response());
+        }
+        Ok(result)
     }
 
     async fn delete_collection(client: &Client) -> Result<(), Response> {
@@ -153,12 +329,337 @@ impl Document<Board, CreateBoard, UpdateBoard> for Board {
 }
 
 impl Board {
+    pub async fn is_encrypted(board_id: &str, database_client: &Client) -> Result<bool, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Ok(false);
+        };
+        Ok(
+            Board::get_document(database_client, doc! { "_id": board_object_id })
+                .await?
+                .is_some_and(|board| board.encrypted),
+        )
+    }
+
+    // Returns the text as it has to be stored for the board, encrypted if the board is encrypted
+    pub async fn text_for_storage(
+        board_id: &str,
+        text: String,
+        database_client: &Client,
+    ) -> Result<String, Response> {
+        match Board::is_encrypted(board_id, database_client).await? {
+            true => encrypt_text(board_id, &text).map_err(|error_message| {
+                error!("{}", error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+            }),
+            false => Ok(text),
+        }
+    }
+
+    pub async fn set_encrypted(
+        board_id: &str,
+        encrypted: bool,
+        database_client: &Client,
+    ) -> Result<UpdateResult, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Err((StatusCode::BAD_REQUEST, "Board ID is invalid").into_response());
+        };
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": board_object_id },
+                doc! { "$set": doc! { "encrypted": encrypted } },
+                None,
+            )
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} update", BOARD_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Unknown boards get the server wide limit, creates on them fail elsewhere
+    pub async fn get_element_limit(
+        board_id: &str,
+        database_client: &Client,
+    ) -> Result<u64, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Ok(MAX_ELEMENTS_PER_BOARD());
+        };
+        Ok(
+            match Board::get_document(database_client, doc! { "_id": board_object_id }).await? {
+                Some(board) => board.settings.element_limit(),
+                None => MAX_ELEMENTS_PER_BOARD(),
+            },
+        )
+    }
+
+    pub async fn set_settings(
+        board_id: &str,
+        settings: &BoardSettings,
+        database_client: &Client,
+    ) -> Result<UpdateResult, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Err((StatusCode::BAD_REQUEST, "Board ID is invalid").into_response());
+        };
+        let Ok(settings_bson) = bson::to_bson(settings) else {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Board settings could not be serialized",
+            )
+                .into_response());
+        };
+        DocumentBase::update_document::<Board>(
+            database_client,
+            BOARD_COLLECTION_NAME,
+            doc! { "_id": board_object_id },
+            doc! { "$set": { "settings": settings_bson, "updatedAt": DateTime::now() } },
+            BOARD_DOCUMENT_NAME,
+        )
+        .await
+    }
+
+    // Counts after the write, so concurrent creates cannot both miss or both hit the threshold. The
+    // first write that finds the board at or above it claims `nearLimitWarned` in one atomic update
+    // and gets the count and limit to warn with, a board found below the threshold again is re-armed
+    pub async fn claim_limit_warning(
+        board_id: &str,
+        database_client: &Client,
+    ) -> Result<Option<(u64, u64)>, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Err((StatusCode::BAD_REQUEST, "Board ID is invalid").into_response());
+        };
+        let element_count = Element::count_of_board(database_client, board_id.to_string()).await?;
+        let element_limit = Board::get_element_limit(board_id, database_client).await?;
+        let reaches_limit_warning = Element::reaches_limit_warning(element_count, element_limit);
+        let query_doc = match reaches_limit_warning {
+            true => doc! { "_id": board_object_id, "nearLimitWarned": doc! { "$ne": true } },
+            false => doc! { "_id": board_object_id, "nearLimitWarned": true },
+        };
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .update_one(
+                query_doc,
+                doc! { "$set": doc! { "nearLimitWarned": reaches_limit_warning } },
+                None,
+            )
+            .await
+        {
+            Ok(result) => Ok((reaches_limit_warning && result.modified_count > 0)
+                .then_some((element_count, element_limit))),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} update", BOARD_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Hands out z-indices above every element of the board. The counter is raised in one atomic
+    // update, so concurrent creates never get the same z-index. It starts from the highest stored
+    // z-index and catches up with z-indices that were set explicitly in the meantime
+    pub async fn next_z_index(board_id: &str, database_client: &Client) -> Result<i32, Response> {
+        Board::claim_extreme_z_index(board_id, true, database_client).await
+    }
+
+    // Counterpart of `next_z_index` for sending elements to the back of the board
+    pub async fn previous_z_index(
+        board_id: &str,
+        database_client: &Client,
+    ) -> Result<i32, Response> {
+        Board::claim_extreme_z_index(board_id, false, database_client).await
+    }
+
+    async fn claim_extreme_z_index(
+        board_id: &str,
+        to_front: bool,
+        database_client: &Client,
+    ) -> Result<i32, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Err((StatusCode::BAD_REQUEST, "Board ID is invalid").into_response());
+        };
+        let (counter_field, extreme_operator, step) = match to_front {
+            true => ("zIndexCounter", "$max", 1),
+            false => ("zIndexBackCounter", "$min", -1),
+        };
+        let extreme_z_index =
+            Element::get_extreme_z_index(database_client, board_id.to_string(), to_front)
+                .await?
+                .unwrap_or(-step);
+        let update_pipeline = vec![doc! {
+            "$set": {
+                counter_field: {
+                    "$add": [
+                        { extreme_operator: [{ "$ifNull": [format!("${}", counter_field), extreme_z_index] }, extreme_z_index] },
+                        step,
+                    ],
+                },
+            },
+        }];
+        let find_options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .projection(doc! { counter_field: 1 })
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<bson::Document>(BOARD_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": board_object_id },
+                update_pipeline,
+                find_options,
+            )
+            .await
+        {
+            Ok(Some(board)) => match board.get_i32(counter_field) {
+                Ok(z_index) => Ok(z_index),
+                Err(_) => Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Board z-index counter is invalid",
+                )
+                    .into_response()),
+            },
+            Ok(None) => Err((StatusCode::NOT_FOUND, "Board does not exist").into_response()),
+            Err(error) => {
+                error!("Board z-index counter could not be raised: {}", error);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} update", BOARD_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        }
+    }
+
+    // Rewrites the stored element and history texts so they match the new encryption of the board
+    pub async fn switch_encryption(
+        board_id: &str,
+        encrypted: bool,
+        database_client: &Client,
+    ) -> Result<(u64, u64), Response> {
+        if Board::set_encrypted(board_id, encrypted, database_client)
+            .await?
+            .matched_count
+            == 0
+        {
+            return Err((StatusCode::NOT_FOUND, "Board does not exist").into_response());
+        }
+        let rewritten_elements = Element::rewrite_texts_of_board(database_client, board_id).await?;
+        let rewritten_history_entries =
+            ElementHistory::rewrite_texts_of_board(database_client, board_id).await?;
+        Ok((rewritten_elements, rewritten_history_entries))
+    }
+
+    // Counts are joined in one aggregation, sorted by element count and boards with the same count by
+    // their ID, so offsets stay stable while the counts do not change
+    pub async fn get_summaries(
+        database_client: &Client,
+        offset: u64,
+        limit: i64,
+    ) -> Result<Vec<BoardSummary>, Response> {
+        let pipeline = vec![
+            doc! { "$addFields": { "boardId": { "$toString": "$_id" } } },
+            doc! {
+                "$lookup": {
+                    "from": ELEMENT_COLLECTION_NAME,
+                    "localField": "boardId",
+                    "foreignField": "boardId",
+                    "pipeline": [{ "$count": "count" }],
+                    "as": "elementCount",
+                }
+            },
+            doc! {
+                "$lookup": {
+                    "from": ACTIVE_MEMBER_COLLECTION_NAME,
+                    "localField": "boardId",
+                    "foreignField": "boardId",
+                    "pipeline": [{ "$count": "count" }],
+                    "as": "activeMemberCount",
+                }
+            },
+            doc! {
+                "$project": {
+                    "_id": 1,
+                    "id": "$boardId",
+                    "name": 1,
+                    "host": 1,
+                    "elementCount": { "$toLong": { "$ifNull": [{ "$first": "$elementCount.count" }, 0] } },
+                    "activeMemberCount": { "$toLong": { "$ifNull": [{ "$first": "$activeMemberCount.count" }, 0] } },
+                }
+            },
+            doc! { "$sort": { "elementCount": -1, "_id": 1 } },
+            doc! { "$skip": offset as i64 },
+            doc! { "$limit": limit },
+        ];
+        let cursor = match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .aggregate(
+                pipeline,
+                AggregateOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(error) => {
+                error!("Board summaries could not be aggregated: {}", error);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Error during Board summary fetching",
+                )
+                    .into_response());
+            }
+        };
+        match cursor
+            .with_type::<BoardSummary>()
+            .try_collect::<Vec<BoardSummary>>()
+            .await
+        {
+            Ok(board_summaries) => Ok(board_summaries),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Board summaries could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn get_page(
+        database_client: &Client,
+        after: Option<ObjectId>,
+        limit: i64,
+    ) -> Result<Vec<Board>, Response> {
+        DocumentBase::find_after_id::<Board>(
+            database_client,
+            BOARD_COLLECTION_NAME,
+            after,
+            limit,
+            BOARD_DOCUMENT_NAME,
+        )
+        .await
+    }
+
     pub async fn get_existing_board(
         board_id: String,
         database_client: &Client,
     ) -> Result<Board, Response> {
+        // The ID often comes straight from a client, so a malformed one is a bad request
+        let board_object_id = match ObjectId::from_str(board_id.as_str()) {
+            Ok(board_object_id) => board_object_id,
+            Err(_) => {
+                error!("Board ID {} is malformed", board_id);
+                return Err((StatusCode::BAD_REQUEST, "Board ID is malformed").into_response());
+            }
+        };
         let query_doc = doc! {
-            "_id": ObjectId::from_str(board_id.clone().as_str()).unwrap(),
+            "_id": board_object_id,
         };
         info!("{}", board_id);
         let board_result = Board::get_document(database_client, query_doc).await;
@@ -177,20 +678,248 @@ impl Board {
         }
     }
 
+    pub async fn assert_member(
+        board_id: String,
+        user_id: String,
+        database_client: &Client,
+    ) -> Result<(), Response> {
+        let board = Board::get_existing_board(board_id.clone(), database_client).await?;
+        match board.can_edit(&user_id) {
+            true => Ok(()),
+            false => {
+                error!("User {} is not part of Board {}", user_id, board_id);
+                Err((StatusCode::FORBIDDEN, "User is not part of this board").into_response())
+            }
+        }
+    }
+
+    pub async fn get_boards_of_user(
+        user_id: String,
+        database_client: &Client,
+    ) -> Result<Vec<Board>, Response> {
+        let query_doc = doc! {
+            "allowedMembers": doc!{ "$in": vec![user_id] },
+            "isTemplate": doc! { "$ne": true },
+        };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "updatedAt": -1, "_id": 1 })
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .find(query_doc, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} retrieval", BOARD_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        Ok(cursor.try_collect().await.unwrap_or_else(|_| vec![]))
+    }
+
+    pub async fn get_public_templates(database_client: &Client) -> Result<Vec<Board>, Response> {
+        let query_doc = doc! {
+            "isTemplate": true,
+            "isPublic": true,
+        };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "name": 1, "_id": 1 })
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .find(query_doc, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} retrieval", BOARD_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        match cursor.try_collect().await {
+            Ok(templates) => Ok(templates),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found templates could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn set_template(
+        board_id: &str,
+        is_template: bool,
+        database_client: &Client,
+    ) -> Result<UpdateResult, Response> {
+        let Ok(board_object_id) = ObjectId::from_str(board_id) else {
+            return Err((StatusCode::BAD_REQUEST, "Board ID is invalid").into_response());
+        };
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": board_object_id },
+                doc! {
+                    "$set": doc! {
+                        "isTemplate": is_template,
+                        "updatedAt": DateTime::now(),
+                    }
+                },
+                None,
+            )
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} update", BOARD_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn remove_user_from_all_boards(
+        user_id: String,
+        database_client: &Client,
+    ) -> Result<UpdateResult, Response> {
+        // Boards hosted by the user keep them as member, so the host never goes missing
+        let query_doc = doc! {
+            "host": doc! { "$ne": user_id.clone() },
+            "$or": vec![
+                doc! { "allowedMembers": user_id.clone() },
+                doc! { "viewers": user_id.clone() },
+            ]
+        };
+        let update_doc = doc! {
+            "$pull": doc! {
+                "allowedMembers": user_id.clone(),
+                "viewers": user_id,
+            },
+            "$set": doc! {
+                "updatedAt": DateTime::now(),
+            }
+        };
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .update_many(query_doc, update_doc, None)
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} update", BOARD_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    pub fn validate_name(name: &str) -> Result<(), String> {
+        if name.trim().is_empty() {
+            return Err("Board name must not be empty".to_string());
+        }
+        if name.chars().count() > MAX_BOARD_NAME_LENGTH {
+            return Err(format!(
+                "Board name must not be longer than {} characters",
+                MAX_BOARD_NAME_LENGTH
+            ));
+        }
+        Ok(())
+    }
+
+    async fn create_host_name_index(client: &Client) {
+        let index = IndexModel::builder()
+            .keys(doc! { "host": 1, "name": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(HOST_NAME_INDEX_NAME.to_string())
+                    .unique(true)
+                    .build(),
+            )
+            .build();
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Board>(BOARD_COLLECTION_NAME)
+            .create_index(index, None)
+            .await
+        {
+            Ok(_) => info!("Board names are unique per host"),
+            Err(err) => error!(
+                "Unique index on host and name could not be created, existing duplicate names have to be renamed first: {:?}",
+                err
+            ),
+        }
+    }
+
+    // Always false unless `BOARD_NAME_UNIQUE_PER_HOST` is enabled
+    pub async fn is_name_taken(
+        host: &str,
+        name: &str,
+        except_board_id: Option<&str>,
+        database_client: &Client,
+    ) -> Result<bool, Response> {
+        if !BOARD_NAME_UNIQUE_PER_HOST() {
+            return Ok(false);
+        }
+        let mut query_doc = doc! {
+            "host": host,
+            "name": name,
+        };
+        if let Some(except_board_object_id) =
+            except_board_id.and_then(|board_id| ObjectId::from_str(board_id).ok())
+        {
+            query_doc.insert("_id", doc! { "$ne": except_board_object_id });
+        }
+        Ok(Board::get_document(database_client, query_doc)
+            .await?
+            .is_some())
+    }
+
+    pub fn can_view(&self, user_id: &String) -> bool {
+        self.allowed_members.contains(user_id) || self.viewers.contains(user_id)
+    }
+
+    // Viewers are not part of `allowedMembers`, so they can follow the board but never edit it
+    pub fn can_edit(&self, user_id: &str) -> bool {
+        self.allowed_members.iter().any(|member| member == user_id)
+    }
+
+    // The host always has to stay part of `allowedMembers`, otherwise the board is orphaned
+    pub fn member_removal_error(&self, member_id: &str) -> Option<&'static str> {
+        if !self.can_edit(member_id) {
+            return Some("Member not part of this board");
+        }
+        (self.host == member_id).then_some("The host cannot be removed from the board")
+    }
+
     pub async fn add_member(
         board_id: String,
         member_id: String,
         database_client: &Client,
     ) -> Result<String, String> {
-        let _ = match User::get_existing_user(member_id.clone(), database_client).await {
-            Ok(user) => user._id,
+        let user = match User::get_existing_user(member_id.clone(), database_client).await {
+            Ok(user) => user,
             Err(message) => return Err(message),
         };
-        let mut current_board_members =
-            match Board::get_existing_board(board_id.clone(), database_client).await {
-                Ok(board) => board.allowed_members,
-                Err(_) => return Err("Board does not exist".to_string()),
-            };
+        let board = match Board::get_existing_board(board_id.clone(), database_client).await {
+            Ok(board) => board,
+            Err(_) => return Err("Board does not exist".to_string()),
+        };
+        if user.is_guest && !board.is_public {
+            return Err("Guests can only join public boards".to_string());
+        }
+        let mut current_board_members = board.allowed_members;
         if current_board_members.contains(&member_id) {
             return Err("Member already part of this board".to_string());
         }
@@ -201,6 +930,7 @@ impl Board {
         let update_doc = doc! {
             "$set": doc! {
               "allowedMembers": current_board_members,
+              "updatedAt": DateTime::now(),
             }
         };
         let result = database_client
@@ -222,25 +952,24 @@ impl Board {
         member_id: String,
         database_client: &Client,
     ) -> Result<String, String> {
-        let mut current_board_members =
-            match Board::get_existing_board(board_id.clone(), database_client).await {
-                Ok(board) => board.allowed_members,
-                Err(_) => return Err("Board does not exist".to_string()),
-            };
-        if let Some(position) = current_board_members
-            .iter()
-            .position(|member| *member == member_id)
-        {
-            current_board_members.remove(position);
-        } else {
-            return Err("Member not part of this board".to_string());
+        let board = match Board::get_existing_board(board_id.clone(), database_client).await {
+            Ok(board) => board,
+            Err(_) => return Err("Board does not exist".to_string()),
+        };
+        if let Some(message) = board.member_removal_error(&member_id) {
+            return Err(message.to_string());
         }
+        let mut current_board_members = board.allowed_members;
+        current_board_members.retain(|member| *member != member_id);
+        // The host may have changed since the board was read
         let query_doc = doc! {
             "_id": ObjectId::from_str(board_id.as_str()).unwrap(),
+            "host": doc! { "$in": current_board_members.clone() },
         };
         let update_doc = doc! {
             "$set": doc! {
               "allowedMembers": current_board_members,
+              "updatedAt": DateTime::now(),
             }
         };
         let result = database_client
@@ -264,10 +993,10 @@ impl Validator for Board {
             "$jsonSchema": doc! {
                 "bsonType": "object",
                 "title": "Board Validation",
-                "required": vec! ["_id", "name", "host", "active_members"],
+                "required": vec! ["_id", "name", "host", "allowedMembers", "createdAt", "updatedAt"],
                 "properties": doc! {
                     "_id": doc! {
-                        "bsonType": "int",
+                        "bsonType": "objectId",
                         "description": "ID of the Board"
                     },
                     "name": doc! {
@@ -275,12 +1004,56 @@ impl Validator for Board {
                         "description": "Name of the Board given by the user"
                     },
                     "host": doc! {
-                        "bsonType": "int",
+                        "bsonType": "string",
                         "description": "ID of the host member"
                     },
-                    "allowed_members": doc! {
-                        "bsonType": vec! ["object"],
-                        "description": "Member array"
+                    "allowedMembers": doc! {
+                        "bsonType": "array",
+                        "items": doc! {
+                            "bsonType": "string"
+                        },
+                        "description": "IDs of the users allowed to edit this board"
+                    },
+                    "viewers": doc! {
+                        "bsonType": "array",
+                        "items": doc! {
+                            "bsonType": "string"
+                        },
+                        "description": "IDs of the users allowed to view this board"
+                    },
+                    "isPublic": doc! {
+                        "bsonType": "bool",
+                        "description": "Whether guests may join this board"
+                    },
+                    "encrypted": doc! {
+                        "bsonType": "bool",
+                        "description": "Whether the texts of the elements on this board are stored encrypted"
+                    },
+                    "isTemplate": doc! {
+                        "bsonType": "bool",
+                        "description": "Whether this board is a template new boards can be created from"
+                    },
+                    "settings": doc! {
+                        "bsonType": "object",
+                        "description": "Toggles of the board the host can change",
+                        "properties": doc! {
+                            "allowGuests": doc! {
+                                "bsonType": "bool",
+                                "description": "Whether guests may join this board"
+                            },
+                            "maxElements": doc! {
+                                "bsonType": vec!["int", "long", "null"],
+                                "description": "Element limit of this board below the server wide limit"
+                            },
+                        }
+                    },
+                    "createdAt": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the creation of the board"
+                    },
+                    "updatedAt": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the last update of the board"
                     },
                 }
             }
@@ -295,3 +1068,135 @@ impl Validator for Board {
         Ok(validation_opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    fn board_with(allowed_members: &[&str], viewers: &[&str]) -> Board {
+        Board {
+            _id: ObjectId::new().to_hex(),
+            name: "Board".to_string(),
+            host: allowed_members[0].to_string(),
+            allowed_members: allowed_members.iter().map(|id| id.to_string()).collect(),
+            viewers: viewers.iter().map(|id| id.to_string()).collect(),
+            is_public: false,
+            encrypted: false,
+            settings: BoardSettings::default(),
+            is_template: false,
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        }
+    }
+
+    #[test]
+    fn members_can_edit() {
+        let board = board_with(&["host", "member"], &[]);
+        assert!(board.can_edit("host"));
+        assert!(board.can_edit("member"));
+    }
+
+    #[test]
+    fn non_members_are_denied() {
+        let board = board_with(&["host"], &[]);
+        assert!(!board.can_edit("stranger"));
+        assert!(!board.can_view(&"stranger".to_string()));
+    }
+
+    #[test]
+    fn viewers_can_view_but_not_edit() {
+        let board = board_with(&["host"], &["viewer"]);
+        assert!(board.can_view(&"viewer".to_string()));
+        assert!(!board.can_edit("viewer"));
+    }
+
+    #[test]
+    fn created_board_satisfies_validator() {
+        let stored_board = bson::to_document(&CreateBoard {
+            name: "Board".to_string(),
+            host: "host".to_string(),
+            allowed_members: vec!["host".to_string()],
+            viewers: vec![],
+            is_public: false,
+            settings: BoardSettings {
+                allow_guests: false,
+                max_elements: Some(10),
+            },
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        })
+        .unwrap();
+        assert_satisfies_validator::<Board>(&stored_board);
+    }
+
+    #[test]
+    fn update_without_fields_is_rejected() {
+        let update_board = UpdateBoard {
+            name: None,
+            host: None,
+            allowed_members: None,
+            viewers: None,
+            is_public: None,
+        };
+        assert!(empty_update_response(&update_board.to_update_fields()).is_some());
+    }
+
+    fn update_members(host: Option<&str>, allowed_members: Option<&[&str]>) -> UpdateBoard {
+        UpdateBoard {
+            name: None,
+            host: host.map(str::to_string),
+            allowed_members: allowed_members
+                .map(|members| members.iter().map(|id| id.to_string()).collect()),
+            viewers: None,
+            is_public: None,
+        }
+    }
+
+    #[test]
+    fn removing_the_host_is_rejected() {
+        let board = board_with(&["host", "member"], &[]);
+        assert_eq!(
+            board.member_removal_error("host"),
+            Some("The host cannot be removed from the board")
+        );
+        assert_eq!(
+            board.member_removal_error("stranger"),
+            Some("Member not part of this board")
+        );
+        assert_eq!(board.member_removal_error("member"), None);
+    }
+
+    #[test]
+    fn member_updates_keep_the_host() {
+        let board = board_with(&["host", "member"], &[]);
+        // Removing a member, adding a member, handing over to a member and to a new user
+        assert!(update_members(None, Some(&["host"])).keeps_host(&board.host));
+        assert!(update_members(None, Some(&["host", "member", "new"])).keeps_host(&board.host));
+        assert!(update_members(Some("member"), Some(&["host", "member"])).keeps_host(&board.host));
+        assert!(update_members(Some("new"), None).keeps_host(&board.host));
+    }
+
+    #[test]
+    fn member_updates_without_the_host_are_rejected() {
+        let board = board_with(&["host", "member"], &[]);
+        assert!(!update_members(None, Some(&["member"])).keeps_host(&board.host));
+        assert!(!update_members(Some("new"), Some(&["host", "member"])).keeps_host(&board.host));
+    }
+
+    #[tokio::test]
+    async fn malformed_board_id_is_a_bad_request() {
+        // Never connects, the malformed ID is turned away before the query
+        let database_client = Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let error_response = Board::assert_member(
+            "not-an-id".to_string(),
+            "user".to_string(),
+            &database_client,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error_response.status(), StatusCode::BAD_REQUEST);
+    }
+}
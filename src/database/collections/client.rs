@@ -1,10 +1,11 @@
-use std::fmt::Display;
+use std::{env::var, fmt::Display, sync::OnceLock, time::Duration};
 
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
-use bson::serde_helpers::deserialize_hex_string_from_object_id;
+use bson::{serde_helpers::deserialize_hex_string_from_object_id, DateTime};
+use futures::TryStreamExt;
 use mongodb::{
     bson::doc,
     options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
@@ -14,12 +15,28 @@ use mongodb::{
 use serde::{Deserialize, Serialize};
 
 use crate::database::{
-    document::{Document, DocumentBase},
+    config::DATABASE_NAME,
+    document::{empty_update_response, Document, DocumentBase},
+    timestamp::{default_timestamp, serialize_timestamp},
     validator::Validator,
 };
 
 const CLIENT_COLLECTION_NAME: &str = "client";
 const CLIENT_DOCUMENT_NAME: &str = "Client";
+const DEFAULT_CLIENT_INACTIVITY_TTL_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+#[allow(non_snake_case)]
+pub fn CLIENT_INACTIVITY_TTL() -> Duration {
+    static CLIENT_INACTIVITY_TTL: OnceLock<Duration> = OnceLock::new();
+    *CLIENT_INACTIVITY_TTL.get_or_init(|| match var("CLIENT_INACTIVITY_TTL_SECONDS") {
+        Ok(client_inactivity_ttl) => Duration::from_secs(
+            client_inactivity_ttl
+                .parse()
+                .expect("Failed to parse `CLIENT_INACTIVITY_TTL_SECONDS` environment variable."),
+        ),
+        Err(_) => Duration::from_secs(DEFAULT_CLIENT_INACTIVITY_TTL_SECONDS),
+    })
+}
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum DeviceType {
@@ -63,6 +80,9 @@ pub struct Client {
     pub client_id: String,
     pub user_id: String,
     pub device_type: DeviceType,
+    // Clients that were not seen for longer than `CLIENT_INACTIVITY_TTL` are logged out
+    #[serde(default = "default_timestamp", serialize_with = "serialize_timestamp")]
+    pub last_seen: DateTime,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -71,6 +91,7 @@ pub struct CreateClient {
     pub client_id: String,
     pub user_id: String,
     pub device_type: DeviceType,
+    pub last_seen: DateTime,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -80,6 +101,19 @@ pub struct UpdateClient {
     pub device_type: Option<DeviceType>,
 }
 
+impl UpdateClient {
+    pub fn to_update_fields(&self) -> bson::Document {
+        let mut update_fields = doc! {};
+        if let Some(device_type) = &self.device_type {
+            update_fields.insert("deviceType", bson::to_bson(device_type).unwrap());
+        }
+        if let Some(client_id) = self.client_id.clone() {
+            update_fields.insert("clientId", client_id);
+        }
+        update_fields
+    }
+}
+
 impl Document<Client, CreateClient, UpdateClient> for Client {
     async fn create_collection(client: &mongodb::Client) -> Result<(), Response> {
         let create_collection_opts = Client::get_validation_options().ok();
@@ -123,13 +157,11 @@ impl Document<Client, CreateClient, UpdateClient> for Client {
         query_doc: bson::Document,
         update_document: UpdateClient,
     ) -> Result<UpdateResult, Response> {
-        let mut update_fields = doc! {};
-        if let Some(device_type) = update_document.device_type {
-            update_fields.insert("deviceType", device_type.to_string());
-        }
-        if let Some(client_id) = update_document.client_id {
-            update_fields.insert("clientId", client_id);
+        let mut update_fields = update_document.to_update_fields();
+        if let Some(error_response) = empty_update_response(&update_fields) {
+            return Err(error_response);
         }
+        update_fields.insert("lastSeen", DateTime::now());
         let update_doc = doc! {
             "$set": update_fields
         };
@@ -196,6 +228,54 @@ impl Client {
             Err(error_response) => Err(error_response),
         }
     }
+
+    pub async fn touch(user_id: &str, database_client: &mongodb::Client) {
+        let _ = database_client
+            .database(DATABASE_NAME())
+            .collection::<Client>(CLIENT_COLLECTION_NAME)
+            .update_one(
+                doc! { "userId": user_id },
+                doc! { "$set": doc! { "lastSeen": DateTime::now() } },
+                None,
+            )
+            .await;
+    }
+
+    pub async fn get_inactive(database_client: &mongodb::Client) -> Result<Vec<Client>, Response> {
+        let query_doc = doc! {
+            "lastSeen": doc! { "$lt": Client::inactive_before() },
+        };
+        match Client::get_multiple_documents(database_client, query_doc)
+            .await?
+            .try_collect::<Vec<Client>>()
+            .await
+        {
+            Ok(clients) => Ok(clients),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Clients could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    // Only deletes the Client if it was not seen again since it was found to be inactive
+    pub async fn delete_if_inactive(
+        client_id: &str,
+        database_client: &mongodb::Client,
+    ) -> Result<DeleteResult, Response> {
+        let query_doc = doc! {
+            "clientId": client_id,
+            "lastSeen": doc! { "$lt": Client::inactive_before() },
+        };
+        Client::delete_document(database_client, query_doc).await
+    }
+
+    fn inactive_before() -> DateTime {
+        DateTime::from_millis(
+            DateTime::now().timestamp_millis() - CLIENT_INACTIVITY_TTL().as_millis() as i64,
+        )
+    }
 }
 
 impl Validator for Client {
@@ -205,23 +285,27 @@ impl Validator for Client {
             "$jsonSchema": doc! {
                 "bsonType": "object",
                 "title": "Client Validation",
-                "required": vec! ["_id", "user_id", "device_type"],
+                "required": vec! ["_id", "clientId", "userId", "deviceType"],
                 "properties": doc! {
                     "_id": doc! {
-                        "bsonType": "string",
+                        "bsonType": "objectId",
                         "description": "ID of the client"
                     },
-                    "client_id": doc! {
+                    "clientId": doc! {
                         "bsonType": "string",
                         "description": "ID of the client device, created by the client"
                     },
-                    "user_id": doc! {
+                    "userId": doc! {
                         "bsonType": "string",
                         "description": "ID of the user this client is associated with"
                     },
-                    "device_type": doc! {
-                        "enum": vec!["Web", "Android", "IOS"],
+                    "deviceType": doc! {
+                        "enum": vec!["Web", "Android", "Ios", "Other"],
                         "description": "Type of the device associated with this client"
+                    },
+                    "lastSeen": doc! {
+                        "bsonType": "date",
+                        "description": "Time the client was last seen"
                     }
                 }
             }
@@ -236,3 +320,30 @@ impl Validator for Client {
         Ok(validation_opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    #[test]
+    fn created_client_satisfies_validator() {
+        let stored_client = bson::to_document(&CreateClient {
+            client_id: "client".to_string(),
+            user_id: "user".to_string(),
+            device_type: DeviceType::Web,
+            last_seen: DateTime::now(),
+        })
+        .unwrap();
+        assert_satisfies_validator::<Client>(&stored_client);
+    }
+
+    #[test]
+    fn update_without_fields_is_rejected() {
+        let update_client = UpdateClient {
+            client_id: None,
+            device_type: None,
+        };
+        assert!(empty_update_response(&update_client.to_update_fields()).is_some());
+    }
+}
@@ -1,34 +1,110 @@
-use axum::response::Response;
+use std::{collections::HashMap, env::var, str::FromStr, sync::OnceLock};
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use bson::{
     doc,
-    serde_helpers::{
-        deserialize_bson_datetime_from_rfc3339_string, deserialize_hex_string_from_object_id,
-        serialize_bson_datetime_as_rfc3339_string, serialize_hex_string_as_object_id,
-    },
-    DateTime,
+    oid::ObjectId,
+    serde_helpers::{deserialize_hex_string_from_object_id, serialize_hex_string_as_object_id},
+    Bson, DateTime, Regex,
 };
+use futures::TryStreamExt;
 use mongodb::{
-    options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
-    results::{DeleteResult, InsertOneResult, UpdateResult},
-    Client, Cursor,
+    options::{
+        AggregateOptions, CountOptions, CreateCollectionOptions, DistinctOptions, FindOneOptions,
+        FindOptions, IndexOptions, ValidationAction, ValidationLevel,
+    },
+    results::{DeleteResult, InsertManyResult, InsertOneResult, UpdateResult},
+    Client, Cursor, IndexModel,
 };
 use serde::{Deserialize, Serialize};
+use tracing::{error, info};
 
-use crate::database::{
-    document::{Document, DocumentBase},
-    validator::Validator,
+use crate::{
+    database::{
+        collections::{
+            board::{Board, BOARD_COLLECTION_NAME},
+            element_type::{ElementType, ELEMENT_TYPE_COLLECTION_NAME},
+        },
+        config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT},
+        document::{
+            empty_update_response, is_operation_timeout, read_error_response, Document,
+            DocumentBase,
+        },
+        timestamp::{
+            default_timestamp, deserialize_optional_timestamp, deserialize_timestamp,
+            serialize_optional_timestamp, serialize_timestamp,
+        },
+        validator::Validator,
+    },
+    utils::{
+        color::validate_color,
+        text_encryption::{decrypt_text, encrypt_text},
+    },
 };
 
-const ELEMENT_COLLECTION_NAME: &str = "element";
+pub const ELEMENT_COLLECTION_NAME: &str = "element";
 const ELEMENT_DOCUMENT_NAME: &str = "Element";
+const CREATED_BY_INDEX_NAME: &str = "board_created_by";
+const MAX_METADATA_SIZE: usize = 8192;
+const DEFAULT_MAX_TEXT_LENGTH: usize = 10000;
+const DEFAULT_MAX_ELEMENTS_PER_BOARD: u64 = 5000;
+const DEFAULT_ELEMENT_LIMIT_WARNING_PERCENTAGE: u64 = 80;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[allow(non_snake_case)]
+pub fn MAX_TEXT_LENGTH() -> usize {
+    static MAX_TEXT_LENGTH: OnceLock<usize> = OnceLock::new();
+    *MAX_TEXT_LENGTH.get_or_init(|| match var("ELEMENT_MAX_TEXT_LENGTH") {
+        Ok(max_text_length) => max_text_length
+            .parse()
+            .expect("Failed to parse `ELEMENT_MAX_TEXT_LENGTH` environment variable."),
+        Err(_) => DEFAULT_MAX_TEXT_LENGTH,
+    })
+}
+
+#[allow(non_snake_case)]
+pub fn MAX_ELEMENTS_PER_BOARD() -> u64 {
+    static MAX_ELEMENTS_PER_BOARD: OnceLock<u64> = OnceLock::new();
+    *MAX_ELEMENTS_PER_BOARD.get_or_init(|| match var("BOARD_MAX_ELEMENTS") {
+        Ok(max_elements) => max_elements
+            .parse()
+            .expect("Failed to parse `BOARD_MAX_ELEMENTS` environment variable."),
+        Err(_) => DEFAULT_MAX_ELEMENTS_PER_BOARD,
+    })
+}
+
+#[allow(non_snake_case)]
+fn ELEMENT_LIMIT_WARNING_PERCENTAGE() -> u64 {
+    static ELEMENT_LIMIT_WARNING_PERCENTAGE: OnceLock<u64> = OnceLock::new();
+    *ELEMENT_LIMIT_WARNING_PERCENTAGE.get_or_init(|| {
+        match var("BOARD_ELEMENT_WARNING_PERCENTAGE") {
+            Ok(percentage) => percentage
+                .parse::<u64>()
+                .expect("Failed to parse `BOARD_ELEMENT_WARNING_PERCENTAGE` environment variable."),
+            Err(_) => DEFAULT_ELEMENT_LIMIT_WARNING_PERCENTAGE,
+        }
+        .min(100)
+    })
+}
+
+fn limit_warning_threshold(element_limit: u64, percentage: u64) -> u64 {
+    (element_limit as u128 * percentage as u128).div_ceil(100) as u64
+}
+
+// Links two elements of the same board, the connector element is drawn between them and follows them
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
+pub struct ElementConnector {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", from = "StoredElement")]
 pub struct Element {
-    #[serde(
-        deserialize_with = "deserialize_hex_string_from_object_id",
-        rename = "_id"
-    )]
+    #[serde(rename = "_id")]
     pub _id: String,
     pub selected: bool,
     pub locked_by: Option<String>,
@@ -38,12 +114,80 @@ pub struct Element {
     pub scale_x: f32,
     pub scale_y: f32,
     pub z_index: i32,
-    #[serde(deserialize_with = "deserialize_bson_datetime_from_rfc3339_string")]
+    #[serde(serialize_with = "serialize_timestamp")]
     pub created_at: DateTime,
     pub text: String,
     pub element_type: String,
     pub board_id: String,
     pub color: String,
+    pub metadata: Option<serde_json::Value>,
+    pub asset_id: Option<String>,
+    // Empty for elements created before the creator was tracked
+    pub created_by: String,
+    // Missing for elements not changed since `updatedAt` was introduced
+    #[serde(serialize_with = "serialize_optional_timestamp")]
+    pub updated_at: Option<DateTime>,
+    // Only set for connector elements
+    pub connector: Option<ElementConnector>,
+}
+
+// An element as it is stored, the text is only decrypted once the board the element belongs to is known
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredElement {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        rename = "_id"
+    )]
+    _id: String,
+    selected: bool,
+    locked_by: Option<String>,
+    x: f32,
+    y: f32,
+    rotation: f32,
+    scale_x: f32,
+    scale_y: f32,
+    z_index: i32,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    created_at: DateTime,
+    text: String,
+    element_type: String,
+    board_id: String,
+    color: String,
+    metadata: Option<serde_json::Value>,
+    asset_id: Option<String>,
+    #[serde(default)]
+    created_by: String,
+    #[serde(default, deserialize_with = "deserialize_optional_timestamp")]
+    updated_at: Option<DateTime>,
+    #[serde(default)]
+    connector: Option<ElementConnector>,
+}
+
+impl From<StoredElement> for Element {
+    fn from(stored_element: StoredElement) -> Self {
+        Element {
+            text: decrypt_text(&stored_element.board_id, stored_element.text),
+            _id: stored_element._id,
+            selected: stored_element.selected,
+            locked_by: stored_element.locked_by,
+            x: stored_element.x,
+            y: stored_element.y,
+            rotation: stored_element.rotation,
+            scale_x: stored_element.scale_x,
+            scale_y: stored_element.scale_y,
+            z_index: stored_element.z_index,
+            created_at: stored_element.created_at,
+            element_type: stored_element.element_type,
+            board_id: stored_element.board_id,
+            color: stored_element.color,
+            metadata: stored_element.metadata,
+            asset_id: stored_element.asset_id,
+            created_by: stored_element.created_by,
+            updated_at: stored_element.updated_at,
+            connector: stored_element.connector,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -63,12 +207,16 @@ pub struct CreateElement {
     pub scale_x: f32,
     pub scale_y: f32,
     pub z_index: i32,
-    #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
     pub created_at: DateTime,
     pub text: String,
     pub element_type: String,
     pub board_id: String,
     pub color: String,
+    pub metadata: Option<serde_json::Value>,
+    pub asset_id: Option<String>,
+    pub created_by: String,
+    pub updated_at: DateTime,
+    pub connector: Option<ElementConnector>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -84,24 +232,88 @@ pub struct UpdateElement {
     pub z_index: Option<i32>,
     pub text: Option<String>,
     pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedElements {
+    pub missing_board: Vec<String>,
+    pub missing_element_type: Vec<String>,
+}
+
+impl UpdateElement {
+    // Connectors attached to the element have to be redrawn after these changes
+    pub fn changes_geometry(&self) -> bool {
+        self.x.is_some()
+            || self.y.is_some()
+            || self.rotation.is_some()
+            || self.scale_x.is_some()
+            || self.scale_y.is_some()
+    }
+
+    pub fn to_update_fields(&self) -> bson::Document {
+        let mut update_fields = doc! {};
+        if let Some(x) = self.x {
+            update_fields.insert("x", x);
+        };
+        if let Some(y) = self.y {
+            update_fields.insert("y", y);
+        };
+        if let Some(selected) = self.selected {
+            update_fields.insert("selected", selected);
+        };
+        if let Some(locked_by) = self.locked_by.clone() {
+            update_fields.insert("lockedBy", locked_by);
+        };
+        if let Some(rotation) = self.rotation {
+            update_fields.insert("rotation", rotation);
+        };
+        if let Some(scale_x) = self.scale_x {
+            update_fields.insert("scaleX", scale_x);
+        };
+        if let Some(scale_y) = self.scale_y {
+            update_fields.insert("scaleY", scale_y);
+        };
+        if let Some(z_index) = self.z_index {
+            update_fields.insert("zIndex", z_index);
+        };
+        if let Some(text) = self.text.clone() {
+            update_fields.insert("text", text);
+        };
+        if let Some(color) = self.color.clone() {
+            update_fields.insert("color", color);
+        };
+        if let Some(metadata) = self.metadata.clone() {
+            if let Ok(metadata) = bson::to_bson(&metadata) {
+                update_fields.insert("metadata", metadata);
+            }
+        };
+        update_fields
+    }
 }
 
 impl Document<Element, CreateElement, UpdateElement> for Element {
     async fn create_collection(client: &Client) -> Result<(), Response> {
         let create_collection_opts = Element::get_validation_options().ok();
+        Element::migrate_string_created_at(client).await;
         DocumentBase::create_collection(
             client,
             ELEMENT_COLLECTION_NAME,
             create_collection_opts,
             ELEMENT_DOCUMENT_NAME,
         )
-        .await
+        .await?;
+        Element::create_created_by_index(client).await;
+        Ok(())
     }
 
     async fn create_document(
         client: &Client,
-        insert_doc: CreateElement,
+        mut insert_doc: CreateElement,
     ) -> Result<InsertOneResult, Response> {
+        insert_doc.text =
+            Board::text_for_storage(&insert_doc.board_id, insert_doc.text, client).await?;
         DocumentBase::create_document::<CreateElement>(
             client,
             ELEMENT_COLLECTION_NAME,
@@ -127,39 +339,21 @@ impl Document<Element, CreateElement, UpdateElement> for Element {
     async fn update_document(
         client: &Client,
         query_doc: bson::Document,
-        update_document: UpdateElement,
+        mut update_document: UpdateElement,
     ) -> Result<UpdateResult, Response> {
-        let mut update_fields = doc! {};
-        if let Some(x) = update_document.x {
-            update_fields.insert("x", x);
-        };
-        if let Some(y) = update_document.y {
-            update_fields.insert("y", y);
-        };
-        if let Some(selected) = update_document.selected {
-            update_fields.insert("selected", selected);
-        };
-        if let Some(locked_by) = update_document.locked_by {
-            update_fields.insert("lockedBy", locked_by);
-        };
-        if let Some(rotation) = update_document.rotation {
-            update_fields.insert("rotation", rotation);
-        };
-        if let Some(scale_x) = update_document.scale_x {
-            update_fields.insert("scaleX", scale_x);
-        };
-        if let Some(scale_y) = update_document.scale_y {
-            update_fields.insert("scaleY", scale_y);
-        };
-        if let Some(z_index) = update_document.z_index {
-            update_fields.insert("zIndex", z_index);
-        };
-        if let Some(text) = update_document.text {
-            update_fields.insert("text", text);
-        };
-        if let Some(color) = update_document.color {
-            update_fields.insert("color", color);
-        };
+        if let Some(text) = update_document.text.take() {
+            update_document.text = match Element::get_document(client, query_doc.clone()).await? {
+                Some(element) => {
+                    Some(Board::text_for_storage(&element.board_id, text, client).await?)
+                }
+                None => Some(text),
+            };
+        }
+        let mut update_fields = update_document.to_update_fields();
+        if let Some(error_response) = empty_update_response(&update_fields) {
+            return Err(error_response);
+        }
+        update_fields.insert("updatedAt", DateTime::now());
         let update_doc = doc! {
             "$set": update_fields
         };
@@ -209,16 +403,953 @@ impl Document<Element, CreateElement, UpdateElement> for Element {
     }
 }
 
+impl Element {
+    // Elements used to store `createdAt` as an RFC 3339 string, they are converted to dates before the
+    // validator requiring a date is applied. Strings that cannot be parsed fall back to the unix epoch
+    async fn migrate_string_created_at(client: &Client) {
+        let migrate_pipeline = vec![doc! {
+            "$set": doc! {
+                "createdAt": doc! {
+                    "$dateFromString": doc! {
+                        "dateString": "$createdAt",
+                        "onError": default_timestamp(),
+                    }
+                }
+            }
+        }];
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .update_many(
+                doc! { "createdAt": doc! { "$type": "string" } },
+                migrate_pipeline,
+                None,
+            )
+            .await
+        {
+            Ok(result) if result.modified_count > 0 => info!(
+                "Converted createdAt of {} Elements to dates",
+                result.modified_count
+            ),
+            Ok(_) => {}
+            Err(err) => error!("createdAt of Elements could not be converted: {:?}", err),
+        }
+    }
+
+    async fn create_created_by_index(client: &Client) {
+        let index = IndexModel::builder()
+            .keys(doc! { "boardId": 1, "createdBy": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(CREATED_BY_INDEX_NAME.to_string())
+                    .build(),
+            )
+            .build();
+        if let Err(err) = client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .create_index(index, None)
+            .await
+        {
+            error!("Index on createdBy could not be created: {:?}", err);
+        }
+    }
+
+    pub async fn count_of_board(client: &Client, board_id: String) -> Result<u64, Response> {
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .count_documents(
+                doc! { "boardId": board_id },
+                CountOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(element_count) => Ok(element_count),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} counting", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Checked before inserting, so two creates racing each other may both pass
+    pub async fn violates_singleton(
+        client: &Client,
+        board_id: &str,
+        element_type: &str,
+    ) -> Result<bool, Response> {
+        if !ElementType::is_singleton(client, element_type).await? {
+            return Ok(false);
+        }
+        Ok(
+            Element::count_of_type_in_board(client, board_id.to_string(), element_type.to_string())
+                .await?
+                > 0,
+        )
+    }
+
+    pub async fn count_of_type_in_board(
+        client: &Client,
+        board_id: String,
+        element_type: String,
+    ) -> Result<u64, Response> {
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .count_documents(
+                doc! { "boardId": board_id, "elementType": element_type },
+                CountOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(element_count) => Ok(element_count),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} counting", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn get_extreme_z_index(
+        client: &Client,
+        board_id: String,
+        highest: bool,
+    ) -> Result<Option<i32>, Response> {
+        let find_options = FindOneOptions::builder()
+            .sort(doc! { "zIndex": if highest { -1 } else { 1 } })
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .find_one(doc! { "boardId": board_id }, find_options)
+            .await
+        {
+            Ok(element) => Ok(element.map(|element| element.z_index)),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} fetching", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn get_used_element_type_ids(client: &Client) -> Result<Vec<String>, Response> {
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .distinct(
+                "elementType",
+                doc! {},
+                DistinctOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(element_type_ids) => Ok(element_type_ids
+                .into_iter()
+                .filter_map(|element_type_id| element_type_id.as_str().map(str::to_string))
+                .collect()),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error during fetching of used Element Types",
+            )
+                .into_response()),
+        }
+    }
+
+    // Joins against boards and element types in the database so only the orphans leave the server
+    pub async fn get_orphaned(client: &Client) -> Result<OrphanedElements, Response> {
+        let pipeline = vec![
+            doc! { "$lookup": Element::existence_lookup(BOARD_COLLECTION_NAME, "$boardId", "board") },
+            doc! { "$lookup": Element::existence_lookup(ELEMENT_TYPE_COLLECTION_NAME, "$elementType", "type") },
+            doc! {
+                "$project": {
+                    "missingBoard": { "$eq": [{ "$size": "$board" }, 0] },
+                    "missingElementType": { "$eq": [{ "$size": "$type" }, 0] },
+                }
+            },
+            doc! { "$match": { "$or": [{ "missingBoard": true }, { "missingElementType": true }] } },
+        ];
+        let mut cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .aggregate(
+                pipeline,
+                AggregateOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Error during orphaned Element fetching",
+                )
+                    .into_response())
+            }
+        };
+        let mut orphaned_elements = OrphanedElements {
+            missing_board: Vec::new(),
+            missing_element_type: Vec::new(),
+        };
+        loop {
+            let orphan = match cursor.try_next().await {
+                Ok(Some(orphan)) => orphan,
+                Ok(None) => return Ok(orphaned_elements),
+                Err(_) => {
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Found orphaned Elements could not be retrieved",
+                    )
+                        .into_response())
+                }
+            };
+            let Ok(element_id) = orphan.get_object_id("_id") else {
+                continue;
+            };
+            if orphan.get_bool("missingBoard").unwrap_or(false) {
+                orphaned_elements.missing_board.push(element_id.to_hex());
+            }
+            if orphan.get_bool("missingElementType").unwrap_or(false) {
+                orphaned_elements
+                    .missing_element_type
+                    .push(element_id.to_hex());
+            }
+        }
+    }
+
+    // References are stored as hex strings, so they are converted before matching, invalid ones match nothing
+    fn existence_lookup(collection_name: &str, reference: &str, field: &str) -> bson::Document {
+        doc! {
+            "from": collection_name,
+            "let": {
+                "referenceId": {
+                    "$convert": { "input": reference, "to": "objectId", "onError": null, "onNull": null }
+                }
+            },
+            "pipeline": [
+                { "$match": { "$expr": { "$eq": ["$_id", "$$referenceId"] } } },
+                { "$project": { "_id": 1 } },
+            ],
+            "as": field,
+        }
+    }
+
+    pub async fn delete_by_ids(
+        client: &Client,
+        element_ids: &[String],
+    ) -> Result<DeleteResult, Response> {
+        let object_ids = element_ids
+            .iter()
+            .filter_map(|element_id| ObjectId::from_str(element_id).ok())
+            .collect::<Vec<ObjectId>>();
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .delete_many(doc! { "_id": { "$in": object_ids } }, None)
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} deletion", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Elements without `createdBy` never match, elements locked by anyone but the creator or the host are
+    // kept, so nobody loses an element while editing it
+    pub async fn delete_created_by(
+        client: &Client,
+        board_id: &str,
+        user_id: &str,
+        host_id: &str,
+    ) -> Result<Vec<String>, Response> {
+        let query_doc = doc! {
+            "boardId": board_id,
+            "createdBy": user_id,
+            "lockedBy": { "$in": [Bson::Null, user_id, host_id] },
+        };
+        let element_ids = Element::find_ids(client, query_doc.clone()).await?;
+        if element_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let delete_result = match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .delete_many(
+                doc! { "$and": [query_doc, { "_id": { "$in": &element_ids } }] },
+                None,
+            )
+            .await
+        {
+            Ok(delete_result) => delete_result,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} deletion", ELEMENT_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        // Elements locked in the meantime are still there and must not be reported as removed
+        let remaining_ids = if delete_result.deleted_count < element_ids.len() as u64 {
+            Element::find_ids(client, doc! { "_id": { "$in": &element_ids } }).await?
+        } else {
+            vec![]
+        };
+        Ok(element_ids
+            .into_iter()
+            .filter(|element_id| !remaining_ids.contains(element_id))
+            .map(|element_id| element_id.to_hex())
+            .collect())
+    }
+
+    // Both ends need to be distinct elements of the board the connector is created on
+    pub async fn connector_targets_exist(
+        client: &Client,
+        board_id: &str,
+        connector: &ElementConnector,
+    ) -> Result<bool, Response> {
+        if connector.from == connector.to {
+            return Ok(false);
+        }
+        let (Ok(from_id), Ok(to_id)) = (
+            ObjectId::from_str(&connector.from),
+            ObjectId::from_str(&connector.to),
+        ) else {
+            return Ok(false);
+        };
+        let target_ids = Element::find_ids(
+            client,
+            doc! { "boardId": board_id, "_id": { "$in": [from_id, to_id] } },
+        )
+        .await?;
+        Ok(target_ids.len() == 2)
+    }
+
+    fn connectors_of_query(board_id: &str, element_ids: &[String]) -> bson::Document {
+        doc! {
+            "boardId": board_id,
+            "$or": [
+                { "connector.from": { "$in": element_ids } },
+                { "connector.to": { "$in": element_ids } },
+            ],
+        }
+    }
+
+    pub async fn get_connectors_of(
+        client: &Client,
+        board_id: &str,
+        element_ids: &[String],
+    ) -> Result<Vec<Element>, Response> {
+        match Element::get_multiple_documents(
+            client,
+            Element::connectors_of_query(board_id, element_ids),
+        )
+        .await?
+        .try_collect::<Vec<Element>>()
+        .await
+        {
+            Ok(connectors) => Ok(connectors),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Elements could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    // Connectors cannot exist without both of their elements, so they are deleted with them
+    pub async fn delete_connectors_of(
+        client: &Client,
+        board_id: &str,
+        element_ids: &[String],
+    ) -> Result<Vec<String>, Response> {
+        let query_doc = Element::connectors_of_query(board_id, element_ids);
+        let connector_ids = Element::find_ids(client, query_doc.clone()).await?;
+        if connector_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .delete_many(
+                doc! { "$and": [query_doc, { "_id": { "$in": &connector_ids } }] },
+                None,
+            )
+            .await
+        {
+            Ok(_) => Ok(connector_ids
+                .into_iter()
+                .map(|connector_id| connector_id.to_hex())
+                .collect()),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} deletion", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    async fn find_ids(
+        client: &Client,
+        query_doc: bson::Document,
+    ) -> Result<Vec<ObjectId>, Response> {
+        let find_options = FindOptions::builder()
+            .projection(doc! { "_id": 1 })
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<bson::Document>(ELEMENT_COLLECTION_NAME)
+            .find(query_doc, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} fetching", ELEMENT_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        match cursor.try_collect::<Vec<bson::Document>>().await {
+            Ok(id_documents) => Ok(id_documents
+                .iter()
+                .filter_map(|id_document| id_document.get_object_id("_id").ok())
+                .collect()),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Elements could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    // All elements move or none does. Texts are stored again for the target board, as encrypted texts
+    // only decrypt on the board they were encrypted for, and connectors that would point back to the
+    // source board are dropped. Elements locked by someone else in the meantime abort the move
+    pub async fn move_to_board(
+        client: &Client,
+        elements: &[Element],
+        from_board_id: &str,
+        to_board_id: &str,
+        user_id: &str,
+    ) -> Result<(), Response> {
+        let transaction_error = || {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} move", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()
+        };
+        let encrypted = Board::is_encrypted(to_board_id, client).await?;
+        let moved_ids = elements
+            .iter()
+            .map(|element| element._id.as_str())
+            .collect::<Vec<&str>>();
+        let mut session = client
+            .start_session(None)
+            .await
+            .map_err(|_| transaction_error())?;
+        session
+            .start_transaction(None)
+            .await
+            .map_err(|_| transaction_error())?;
+        let collection = client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME);
+        for element in elements {
+            let text = match encrypted {
+                true => encrypt_text(to_board_id, &element.text).map_err(|error_message| {
+                    (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+                })?,
+                false => element.text.clone(),
+            };
+            let mut update_doc = doc! {
+                "$set": doc! { "boardId": to_board_id, "text": text, "updatedAt": DateTime::now() },
+            };
+            if element.connector.as_ref().is_some_and(|connector| {
+                !moved_ids.contains(&connector.from.as_str())
+                    || !moved_ids.contains(&connector.to.as_str())
+            }) {
+                update_doc.insert("$unset", doc! { "connector": "" });
+            }
+            let update_result = collection
+                .update_one_with_session(
+                    doc! {
+                        "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
+                        "boardId": from_board_id,
+                        "lockedBy": { "$in": [Bson::Null, user_id] },
+                    },
+                    update_doc,
+                    None,
+                    &mut session,
+                )
+                .await;
+            match update_result {
+                Ok(result) if result.matched_count == 1 => {}
+                Ok(_) => {
+                    let _ = session.abort_transaction().await;
+                    return Err((
+                        StatusCode::LOCKED,
+                        format!(
+                            "Element {} was locked or moved in the meantime",
+                            element._id
+                        ),
+                    )
+                        .into_response());
+                }
+                Err(_) => {
+                    let _ = session.abort_transaction().await;
+                    return Err(transaction_error());
+                }
+            }
+        }
+        session
+            .commit_transaction()
+            .await
+            .map_err(|_| transaction_error())
+    }
+
+    pub fn reaches_limit_warning(element_count: u64, element_limit: u64) -> bool {
+        element_count >= limit_warning_threshold(element_limit, ELEMENT_LIMIT_WARNING_PERCENTAGE())
+    }
+
+    // Only succeeds if nobody holds the lock at the time of the update, a competing lock wins otherwise
+    pub async fn try_reacquire_lock(
+        client: &Client,
+        element_id: &str,
+        user_id: String,
+    ) -> Result<bool, Response> {
+        let query_doc = doc! {
+            "_id": ObjectId::from_str(element_id).unwrap(),
+            "lockedBy": null,
+        };
+        let update_doc = doc! {
+            "$set": doc! { "lockedBy": user_id },
+        };
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .update_one(query_doc, update_doc, None)
+            .await
+        {
+            Ok(result) => Ok(result.matched_count > 0),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} lock", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn create_many_documents(
+        client: &Client,
+        mut insert_docs: Vec<CreateElement>,
+    ) -> Result<InsertManyResult, Response> {
+        let mut encrypted_boards = HashMap::new();
+        for insert_doc in insert_docs.iter_mut() {
+            let encrypted = match encrypted_boards.get(&insert_doc.board_id) {
+                Some(encrypted) => *encrypted,
+                None => {
+                    let encrypted = Board::is_encrypted(&insert_doc.board_id, client).await?;
+                    encrypted_boards.insert(insert_doc.board_id.clone(), encrypted);
+                    encrypted
+                }
+            };
+            if encrypted {
+                insert_doc.text = encrypt_text(&insert_doc.board_id, &insert_doc.text).map_err(
+                    |error_message| {
+                        (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+                    },
+                )?;
+            }
+        }
+        DocumentBase::create_many_documents::<CreateElement>(
+            client,
+            ELEMENT_COLLECTION_NAME,
+            insert_docs,
+            ELEMENT_DOCUMENT_NAME,
+        )
+        .await
+    }
+
+    // Stores every text of the board again as the board requires it, used when the encryption of the
+    // board is switched and to re-encrypt the texts after a rotation of the secret
+    pub async fn rewrite_texts_of_board(client: &Client, board_id: &str) -> Result<u64, Response> {
+        let elements = match Element::get_multiple_documents(client, doc! { "boardId": board_id })
+            .await?
+            .try_collect::<Vec<Element>>()
+            .await
+        {
+            Ok(elements) => elements,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Found Elements could not be retrieved",
+                )
+                    .into_response())
+            }
+        };
+        let encrypted = Board::is_encrypted(board_id, client).await?;
+        let mut rewritten_texts = 0;
+        for element in elements {
+            let text = match encrypted {
+                true => encrypt_text(board_id, &element.text).map_err(|error_message| {
+                    (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+                })?,
+                false => element.text,
+            };
+            let update_result = client
+                .database(DATABASE_NAME())
+                .collection::<Element>(ELEMENT_COLLECTION_NAME)
+                .update_one(
+                    doc! { "_id": ObjectId::from_str(element._id.as_str()).unwrap() },
+                    doc! { "$set": doc! { "text": text } },
+                    None,
+                )
+                .await;
+            match update_result {
+                Ok(result) => rewritten_texts += result.matched_count,
+                Err(_) => {
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Error during {} update", ELEMENT_DOCUMENT_NAME),
+                    )
+                        .into_response())
+                }
+            }
+        }
+        Ok(rewritten_texts)
+    }
+
+    pub async fn unlock_all_locked_by(
+        client: &Client,
+        board_id: String,
+        user_ids: &[String],
+    ) -> Result<Vec<String>, Response> {
+        let query_doc = doc! {
+            "boardId": board_id,
+            "lockedBy": doc! { "$in": user_ids },
+        };
+        let locked_elements = match Element::get_multiple_documents(client, query_doc.clone())
+            .await?
+            .try_collect::<Vec<Element>>()
+            .await
+        {
+            Ok(locked_elements) => locked_elements,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Found Elements could not be retrieved",
+                )
+                    .into_response())
+            }
+        };
+        if locked_elements.is_empty() {
+            return Ok(vec![]);
+        }
+        let update_doc = doc! {
+            "$set": doc! { "lockedBy": null },
+        };
+        match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .update_many(query_doc, update_doc, None)
+            .await
+        {
+            Ok(_) => Ok(locked_elements
+                .into_iter()
+                .map(|element| element._id)
+                .collect()),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} unlock", ELEMENT_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn get_existing_element(
+        element_id: String,
+        database_client: &Client,
+    ) -> Result<Element, Response> {
+        let object_id = match ObjectId::from_str(element_id.as_str()) {
+            Ok(object_id) => object_id,
+            Err(_) => {
+                return Err((StatusCode::BAD_REQUEST, "Element ID is invalid").into_response())
+            }
+        };
+        match Element::get_document(database_client, doc! { "_id": object_id }).await? {
+            Some(element) => Ok(element),
+            None => Err((
+                StatusCode::NOT_FOUND,
+                format!("No Element found with ID: {}", element_id),
+            )
+                .into_response()),
+        }
+    }
+
+    // Elements read from the database always carry the hex of a stored ObjectId, which is what the
+    // `_id` filter has to match, the hex string itself matches nothing
+    pub fn stored_id_query(&self) -> bson::Document {
+        doc! { "_id": ObjectId::from_str(self._id.as_str()).unwrap() }
+    }
+
+    // Edits look elements up on the board the caller was checked against, so a member of one board
+    // cannot reach the elements of another board by sending the ID of their own board
+    pub fn board_scoped_query(element_id: &str, board_id: &str) -> Result<bson::Document, String> {
+        match ObjectId::from_str(element_id) {
+            Ok(object_id) => Ok(doc! { "_id": object_id, "boardId": board_id }),
+            Err(_) => Err(format!("Element ID {} is invalid", element_id)),
+        }
+    }
+
+    pub fn board_scoped_ids_query(
+        element_ids: &[String],
+        board_id: &str,
+    ) -> Result<bson::Document, String> {
+        let mut object_ids = vec![];
+        for element_id in element_ids {
+            match ObjectId::from_str(element_id) {
+                Ok(object_id) => object_ids.push(object_id),
+                Err(_) => return Err(format!("Element ID {} is invalid", element_id)),
+            }
+        }
+        Ok(doc! { "_id": doc! { "$in": object_ids }, "boardId": board_id })
+    }
+
+    // Only `lockedBy` is projected, ids without an Element are missing from the result
+    pub async fn get_lock_owners(
+        client: &Client,
+        element_ids: &[ObjectId],
+    ) -> Result<Vec<(String, Option<String>)>, Response> {
+        let find_options = FindOptions::builder()
+            .projection(doc! { "lockedBy": 1 })
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<bson::Document>(ELEMENT_COLLECTION_NAME)
+            .find(doc! { "_id": { "$in": element_ids } }, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!(
+                        "Error during {} lock status fetching",
+                        ELEMENT_DOCUMENT_NAME
+                    ),
+                )
+                    .into_response())
+            }
+        };
+        match cursor.try_collect::<Vec<bson::Document>>().await {
+            Ok(lock_documents) => Ok(lock_documents
+                .iter()
+                .filter_map(|lock_document| {
+                    let element_id = lock_document.get_object_id("_id").ok()?;
+                    let locked_by = lock_document.get_str("lockedBy").ok().map(str::to_string);
+                    Some((element_id.to_hex(), locked_by))
+                })
+                .collect()),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Element lock statuses could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn set_asset_id(
+        client: &Client,
+        element_id: String,
+        asset_id: String,
+    ) -> Result<UpdateResult, Response> {
+        let query_doc = doc! {
+            "_id": ObjectId::from_str(element_id.as_str()).unwrap(),
+        };
+        let update_doc = doc! {
+            "$set": doc! { "assetId": asset_id, "updatedAt": DateTime::now() },
+        };
+        DocumentBase::update_document::<Element>(
+            client,
+            ELEMENT_COLLECTION_NAME,
+            query_doc,
+            update_doc,
+            ELEMENT_DOCUMENT_NAME,
+        )
+        .await
+    }
+
+    // Removed elements cannot show up here, clients reconciling deletions still need a full fetch.
+    // `createdAt` is only compared for elements without `updatedAt`.
+    pub async fn get_changed_since(
+        client: &Client,
+        board_id: String,
+        since: DateTime,
+        limit: i64,
+    ) -> Result<Vec<Element>, Response> {
+        let query_doc = doc! {
+            "boardId": board_id,
+            "$or": [
+                { "updatedAt": { "$gt": since } },
+                { "updatedAt": { "$exists": false }, "createdAt": { "$gt": since } },
+            ],
+        };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "updatedAt": 1, "_id": 1 })
+            .limit(limit)
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .find(query_doc, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} fetching", ELEMENT_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        match cursor.try_collect::<Vec<Element>>().await {
+            Ok(elements) => Ok(elements),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Elements could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn search_text_in_board(
+        client: &Client,
+        board_id: String,
+        search_query: &str,
+        skip: u64,
+        limit: i64,
+    ) -> Result<Vec<Element>, Response> {
+        let query_doc = doc! {
+            "boardId": board_id,
+            "text": Regex {
+                pattern: escape_regex(search_query),
+                options: "i".to_string(),
+            },
+        };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "createdAt": 1, "_id": 1 })
+            .skip(skip)
+            .limit(limit)
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<Element>(ELEMENT_COLLECTION_NAME)
+            .find(query_doc, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(err) => return Err(read_error_response(&err, ELEMENT_DOCUMENT_NAME, "search")),
+        };
+        match cursor.try_collect::<Vec<Element>>().await {
+            Ok(elements) => Ok(elements),
+            Err(err) if is_operation_timeout(&err) => {
+                Err(read_error_response(&err, ELEMENT_DOCUMENT_NAME, "search"))
+            }
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Elements could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub fn validate_element_fields(text: Option<&str>, color: Option<&str>) -> Result<(), String> {
+        if let Some(text) = text {
+            if text.chars().count() > MAX_TEXT_LENGTH() {
+                return Err(format!(
+                    "Element text exceeds the maximum length of {} characters",
+                    MAX_TEXT_LENGTH()
+                ));
+            }
+        }
+        if let Some(color) = color {
+            validate_color(color)?;
+        }
+        Ok(())
+    }
+
+    pub fn check_metadata(metadata: &Option<serde_json::Value>) -> Result<(), String> {
+        match metadata {
+            Some(serde_json::Value::Object(_)) => {
+                match serde_json::to_string(metadata).map(|serialized| serialized.len()) {
+                    Ok(size) if size <= MAX_METADATA_SIZE => Ok(()),
+                    _ => Err(format!(
+                        "Element metadata exceeds the maximum size of {} bytes",
+                        MAX_METADATA_SIZE
+                    )),
+                }
+            }
+            Some(serde_json::Value::Null) | None => Ok(()),
+            Some(_) => Err("Element metadata needs to be an object".to_string()),
+        }
+    }
+}
+
+// Clients add up rotations without wrapping them, so stored values are kept within [0, 360)
+pub fn normalize_rotation(rotation: f32) -> f32 {
+    let normalized = rotation.rem_euclid(360.0);
+    // rem_euclid rounds tiny negative values up to exactly 360.0
+    if normalized >= 360.0 {
+        0.0
+    } else {
+        normalized
+    }
+}
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        if "\\.+*?()|[]{}^$#-".contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
 impl Validator for Element {
     fn get_validation_options() -> Result<CreateCollectionOptions, Box<dyn std::error::Error>> {
         let validator = doc! {
             "$jsonSchema": doc! {
                 "bsonType": "object",
                 "title": "Element Validation",
-                "required": vec!["_id", "selected", "x_position", "y_position", "rotation", "scale", "z_index", "created_at", "text", "element_type", "board_id", "color"],
+                "required": vec!["_id", "selected", "x", "y", "rotation", "scaleX", "scaleY", "zIndex", "createdAt", "text", "elementType", "boardId", "color"],
                 "properties": doc! {
                     "_id": doc! {
-                        "bsonType": "string",
+                        "bsonType": "objectId",
                         "description": "ID fo the element",
                     },
                     "selected": doc! {
@@ -226,7 +1357,7 @@ impl Validator for Element {
                         "description": "Whether the element is selected",
                     },
                     "lockedBy": doc! {
-                        "bsonType": "string",
+                        "bsonType": vec!["string", "null"],
                         "description": "The User ID of the user currently locking the elemnent"
                     },
                     "x": doc! {
@@ -254,7 +1385,7 @@ impl Validator for Element {
                         "description": "The z-Index of the element"
                     },
                     "createdAt": doc! {
-                        "bsonType": "string",
+                        "bsonType": "date",
                         "description": "The timestamp of the creation of the element"
                     },
                     "text": doc! {
@@ -272,6 +1403,37 @@ impl Validator for Element {
                     "color": doc! {
                         "bsonType": "string",
                         "description": "The fill color of the element"
+                    },
+                    "metadata": doc! {
+                        "bsonType": vec!["object", "null"],
+                        "description": "App specific properties of the element"
+                    },
+                    "assetId": doc! {
+                        "bsonType": vec!["string", "null"],
+                        "description": "ID of the uploaded asset of the element"
+                    },
+                    "updatedAt": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the last change of the element"
+                    },
+                    "createdBy": doc! {
+                        "bsonType": "string",
+                        "description": "The User ID of the user who created the element"
+                    },
+                    "connector": doc! {
+                        "bsonType": vec!["object", "null"],
+                        "required": vec!["from", "to"],
+                        "properties": doc! {
+                            "from": doc! {
+                                "bsonType": "string",
+                                "description": "ID of the element the connector starts at"
+                            },
+                            "to": doc! {
+                                "bsonType": "string",
+                                "description": "ID of the element the connector ends at"
+                            }
+                        },
+                        "description": "The elements a connector element links"
                     }
                 }
             }
@@ -286,3 +1448,130 @@ impl Validator for Element {
         Ok(validation_opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    #[test]
+    fn board_scoped_query_filters_on_board() {
+        let element_id = ObjectId::new();
+        let query_doc = Element::board_scoped_query(&element_id.to_hex(), "board").unwrap();
+        assert_eq!(query_doc, doc! { "_id": element_id, "boardId": "board" });
+    }
+
+    #[test]
+    fn board_scoped_ids_query_filters_on_board() {
+        let element_ids = vec![ObjectId::new(), ObjectId::new()];
+        let query_doc = Element::board_scoped_ids_query(
+            &element_ids
+                .iter()
+                .map(|element_id| element_id.to_hex())
+                .collect::<Vec<String>>(),
+            "board",
+        )
+        .unwrap();
+        assert_eq!(
+            query_doc,
+            doc! { "_id": doc! { "$in": element_ids }, "boardId": "board" }
+        );
+    }
+
+    #[test]
+    fn board_scoped_queries_reject_invalid_ids() {
+        assert!(Element::board_scoped_query("not-an-id", "board").is_err());
+        assert!(Element::board_scoped_ids_query(
+            &[ObjectId::new().to_hex(), "not-an-id".to_string()],
+            "board"
+        )
+        .is_err());
+    }
+
+    fn create_element() -> CreateElement {
+        CreateElement {
+            _id: ObjectId::new().to_hex(),
+            selected: false,
+            locked_by: None,
+            x: 0.0,
+            y: 0.0,
+            rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            z_index: 0,
+            created_at: DateTime::now(),
+            text: String::new(),
+            element_type: "rectangle".to_string(),
+            board_id: "board".to_string(),
+            color: "#000000".to_string(),
+            metadata: None,
+            asset_id: None,
+            created_by: "user".to_string(),
+            updated_at: DateTime::now(),
+            connector: None,
+        }
+    }
+
+    #[test]
+    fn created_element_stores_created_at_as_date() {
+        let element = create_element();
+        let stored_element = bson::to_document(&element).unwrap();
+        assert_eq!(
+            stored_element.get("createdAt"),
+            Some(&Bson::DateTime(element.created_at))
+        );
+    }
+
+    #[test]
+    fn stored_element_reads_created_at_date() {
+        let element = create_element();
+        let stored_element: Element =
+            bson::from_document(bson::to_document(&element).unwrap()).unwrap();
+        assert_eq!(stored_element.created_at, element.created_at);
+    }
+
+    #[test]
+    fn limit_warning_threshold_rounds_up() {
+        assert_eq!(limit_warning_threshold(100, 80), 80);
+        assert_eq!(limit_warning_threshold(10, 85), 9);
+        assert_eq!(limit_warning_threshold(u64::MAX, 100), u64::MAX);
+    }
+
+    #[test]
+    fn created_element_satisfies_validator() {
+        let stored_element = bson::to_document(&create_element()).unwrap();
+        assert_satisfies_validator::<Element>(&stored_element);
+    }
+
+    #[test]
+    fn update_without_fields_is_rejected() {
+        let update_element = UpdateElement {
+            selected: None,
+            locked_by: None,
+            x: None,
+            y: None,
+            rotation: None,
+            scale_x: None,
+            scale_y: None,
+            z_index: None,
+            text: None,
+            color: None,
+            metadata: None,
+        };
+        assert!(empty_update_response(&update_element.to_update_fields()).is_some());
+    }
+
+    #[test]
+    fn stored_id_query_matches_the_stored_id() {
+        let stored_element = bson::to_document(&create_element()).unwrap();
+        let element: Element = bson::from_document(stored_element.clone()).unwrap();
+        assert_eq!(
+            element.stored_id_query().get("_id"),
+            stored_element.get("_id")
+        );
+        assert!(matches!(
+            element.stored_id_query().get("_id"),
+            Some(Bson::ObjectId(_))
+        ));
+    }
+}
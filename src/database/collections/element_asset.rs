@@ -0,0 +1,142 @@
+use std::{env::var, str::FromStr, sync::OnceLock};
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use bson::{doc, oid::ObjectId};
+use futures::{io::Cursor, TryStreamExt};
+use mongodb::{
+    gridfs::{FilesCollectionDocument, GridFsBucket},
+    options::{GridFsBucketOptions, GridFsFindOptions, GridFsUploadOptions},
+    Client,
+};
+use tracing::error;
+
+use crate::database::config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT};
+
+const ELEMENT_ASSET_BUCKET_NAME: &str = "element-asset";
+const DEFAULT_MAX_ASSET_SIZE: usize = 5 * 1024 * 1024;
+const ALLOWED_CONTENT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[allow(non_snake_case)]
+pub fn MAX_ASSET_SIZE() -> usize {
+    static MAX_ASSET_SIZE: OnceLock<usize> = OnceLock::new();
+    *MAX_ASSET_SIZE.get_or_init(|| match var("ELEMENT_ASSET_MAX_SIZE") {
+        Ok(max_asset_size) => max_asset_size
+            .parse()
+            .expect("Failed to parse `ELEMENT_ASSET_MAX_SIZE` environment variable."),
+        Err(_) => DEFAULT_MAX_ASSET_SIZE,
+    })
+}
+
+pub struct ElementAsset {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ElementAsset {
+    pub fn is_allowed_content_type(content_type: &str) -> bool {
+        ALLOWED_CONTENT_TYPES.contains(&content_type)
+    }
+
+    fn get_bucket(client: &Client) -> GridFsBucket {
+        client.database(DATABASE_NAME()).gridfs_bucket(
+            GridFsBucketOptions::builder()
+                .bucket_name(ELEMENT_ASSET_BUCKET_NAME.to_string())
+                .build(),
+        )
+    }
+
+    pub async fn upload(
+        client: &Client,
+        element_id: String,
+        content_type: String,
+        data: &[u8],
+    ) -> Result<String, Response> {
+        let upload_options = GridFsUploadOptions::builder()
+            .metadata(doc! {
+                "contentType": content_type,
+                "elementId": element_id.clone(),
+            })
+            .build();
+        match ElementAsset::get_bucket(client)
+            .upload_from_futures_0_3_reader(element_id, Cursor::new(data), upload_options)
+            .await
+        {
+            Ok(asset_id) => Ok(asset_id.to_hex()),
+            Err(error) => {
+                error!("Element Asset could not be uploaded: {}", error);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Error during Element Asset upload",
+                )
+                    .into_response())
+            }
+        }
+    }
+
+    pub async fn download(client: &Client, asset_id: String) -> Result<ElementAsset, Response> {
+        let object_id = match ObjectId::from_str(asset_id.as_str()) {
+            Ok(object_id) => object_id,
+            Err(_) => return Err((StatusCode::BAD_REQUEST, "Asset ID is invalid").into_response()),
+        };
+        let bucket = ElementAsset::get_bucket(client);
+        let file = match bucket
+            .find(
+                doc! { "_id": object_id },
+                GridFsFindOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(mut file_cursor) => match file_cursor.try_next().await {
+                Ok(Some(file)) => file,
+                Ok(None) => {
+                    return Err(
+                        (StatusCode::NOT_FOUND, "Element Asset does not exist").into_response()
+                    )
+                }
+                Err(_) => {
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Error during Element Asset fetching",
+                    )
+                        .into_response())
+                }
+            },
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Error during Element Asset fetching",
+                )
+                    .into_response())
+            }
+        };
+        let mut data = Cursor::new(Vec::new());
+        if let Err(error) = bucket
+            .download_to_futures_0_3_writer(object_id.into(), &mut data)
+            .await
+        {
+            error!("Element Asset could not be downloaded: {}", error);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error during Element Asset download",
+            )
+                .into_response());
+        }
+        Ok(ElementAsset {
+            content_type: ElementAsset::get_content_type(&file),
+            data: data.into_inner(),
+        })
+    }
+
+    fn get_content_type(file: &FilesCollectionDocument) -> String {
+        file.metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get_str("contentType").ok())
+            .unwrap_or("application/octet-stream")
+            .to_string()
+    }
+}
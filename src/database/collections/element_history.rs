@@ -0,0 +1,353 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use std::str::FromStr;
+
+use bson::{doc, oid::ObjectId, serde_helpers::deserialize_hex_string_from_object_id, DateTime};
+use futures::TryStreamExt;
+use mongodb::{
+    options::{CreateCollectionOptions, FindOptions, ValidationAction, ValidationLevel},
+    Client,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    database::{
+        collections::board::Board,
+        config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT},
+        document::DocumentBase,
+        timestamp::serialize_timestamp,
+        validator::Validator,
+    },
+    utils::text_encryption::{decrypt_text, encrypt_text},
+};
+
+const ELEMENT_HISTORY_COLLECTION_NAME: &str = "element-history";
+const ELEMENT_HISTORY_DOCUMENT_NAME: &str = "Element History";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ElementHistoryAction {
+    Created,
+    Updated,
+    Locked,
+    Unlocked,
+    Moved,
+    Removed,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", from = "StoredElementHistory")]
+pub struct ElementHistory {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub element_id: String,
+    pub board_id: String,
+    pub user_id: String,
+    pub action: ElementHistoryAction,
+    pub changes: Option<bson::Document>,
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub timestamp: DateTime,
+}
+
+// A history entry as it is stored, a changed text is decrypted with the board of the entry
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredElementHistory {
+    #[serde(
+        deserialize_with = "deserialize_hex_string_from_object_id",
+        rename = "_id"
+    )]
+    _id: String,
+    element_id: String,
+    board_id: String,
+    user_id: String,
+    action: ElementHistoryAction,
+    changes: Option<bson::Document>,
+    timestamp: DateTime,
+}
+
+impl From<StoredElementHistory> for ElementHistory {
+    fn from(stored_history: StoredElementHistory) -> Self {
+        let mut changes = stored_history.changes;
+        if let Some(changes) = changes.as_mut() {
+            if let Ok(text) = changes.get_str("text") {
+                let text = decrypt_text(&stored_history.board_id, text.to_string());
+                changes.insert("text", text);
+            }
+        }
+        ElementHistory {
+            _id: stored_history._id,
+            element_id: stored_history.element_id,
+            board_id: stored_history.board_id,
+            user_id: stored_history.user_id,
+            action: stored_history.action,
+            changes,
+            timestamp: stored_history.timestamp,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateElementHistory {
+    pub element_id: String,
+    pub board_id: String,
+    pub user_id: String,
+    pub action: ElementHistoryAction,
+    pub changes: Option<bson::Document>,
+    pub timestamp: DateTime,
+}
+
+impl ElementHistory {
+    pub async fn create_collection(client: &Client) -> Result<(), Response> {
+        let create_collection_opts = ElementHistory::get_validation_options().ok();
+        DocumentBase::create_collection(
+            client,
+            ELEMENT_HISTORY_COLLECTION_NAME,
+            create_collection_opts,
+            ELEMENT_HISTORY_DOCUMENT_NAME,
+        )
+        .await
+    }
+
+    pub async fn record(
+        client: &Client,
+        element_id: String,
+        board_id: String,
+        user_id: String,
+        action: ElementHistoryAction,
+        mut changes: Option<bson::Document>,
+    ) {
+        if let Some(changes) = changes.as_mut() {
+            if let Ok(text) = changes.get_str("text") {
+                match Board::text_for_storage(&board_id, text.to_string(), client).await {
+                    Ok(text) => {
+                        changes.insert("text", text);
+                    }
+                    Err(_) => {
+                        changes.remove("text");
+                    }
+                }
+            }
+        }
+        let create_result = DocumentBase::create_document::<CreateElementHistory>(
+            client,
+            ELEMENT_HISTORY_COLLECTION_NAME,
+            CreateElementHistory {
+                element_id: element_id.clone(),
+                board_id,
+                user_id,
+                action,
+                changes,
+                timestamp: DateTime::now(),
+            },
+            ELEMENT_HISTORY_DOCUMENT_NAME,
+        )
+        .await;
+        if create_result.is_err() {
+            error!(
+                "History entry for Element {} could not be stored",
+                element_id
+            );
+        }
+    }
+
+    pub async fn record_multiple(
+        client: &Client,
+        element_ids: &[String],
+        board_id: String,
+        user_id: String,
+        action: ElementHistoryAction,
+        changes: Option<bson::Document>,
+    ) {
+        for element_id in element_ids {
+            ElementHistory::record(
+                client,
+                element_id.clone(),
+                board_id.clone(),
+                user_id.clone(),
+                action.clone(),
+                changes.clone(),
+            )
+            .await;
+        }
+    }
+
+    pub async fn get_history_of_element(
+        client: &Client,
+        element_id: String,
+        limit: i64,
+    ) -> Result<Vec<ElementHistory>, Response> {
+        let find_options = FindOptions::builder()
+            .sort(doc! { "timestamp": 1, "_id": 1 })
+            .limit(limit)
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<ElementHistory>(ELEMENT_HISTORY_COLLECTION_NAME)
+            .find(doc! { "elementId": element_id }, find_options)
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} retrieval", ELEMENT_HISTORY_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        match cursor.try_collect::<Vec<ElementHistory>>().await {
+            Ok(history) => Ok(history),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found History entries could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn rewrite_texts_of_board(client: &Client, board_id: &str) -> Result<u64, Response> {
+        let cursor = match client
+            .database(DATABASE_NAME())
+            .collection::<ElementHistory>(ELEMENT_HISTORY_COLLECTION_NAME)
+            .find(
+                doc! { "boardId": board_id, "changes.text": doc! { "$exists": true } },
+                FindOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(cursor) => cursor,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} retrieval", ELEMENT_HISTORY_DOCUMENT_NAME),
+                )
+                    .into_response())
+            }
+        };
+        let history = match cursor.try_collect::<Vec<ElementHistory>>().await {
+            Ok(history) => history,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Found History entries could not be retrieved",
+                )
+                    .into_response())
+            }
+        };
+        let encrypted = Board::is_encrypted(board_id, client).await?;
+        let mut rewritten_texts = 0;
+        for entry in history {
+            let Some(text) = entry
+                .changes
+                .as_ref()
+                .and_then(|changes| changes.get_str("text").ok())
+            else {
+                continue;
+            };
+            let text = match encrypted {
+                true => encrypt_text(board_id, text).map_err(|error_message| {
+                    (StatusCode::INTERNAL_SERVER_ERROR, error_message).into_response()
+                })?,
+                false => text.to_string(),
+            };
+            let update_result = client
+                .database(DATABASE_NAME())
+                .collection::<ElementHistory>(ELEMENT_HISTORY_COLLECTION_NAME)
+                .update_one(
+                    doc! { "_id": ObjectId::from_str(entry._id.as_str()).unwrap() },
+                    doc! { "$set": doc! { "changes.text": text } },
+                    None,
+                )
+                .await;
+            match update_result {
+                Ok(result) => rewritten_texts += result.matched_count,
+                Err(_) => {
+                    return Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Error during {} update", ELEMENT_HISTORY_DOCUMENT_NAME),
+                    )
+                        .into_response())
+                }
+            }
+        }
+        Ok(rewritten_texts)
+    }
+}
+
+impl Validator for ElementHistory {
+    fn get_validation_options() -> Result<CreateCollectionOptions, Box<dyn std::error::Error>> {
+        let validator = doc! {
+            "$jsonSchema": doc! {
+                "bsonType": "object",
+                "title": "Element History Validation",
+                "required": vec!["_id", "elementId", "boardId", "userId", "action", "timestamp"],
+                "properties": doc! {
+                    "_id": doc! {
+                        "bsonType": "objectId",
+                        "description": "ID of the history entry",
+                    },
+                    "elementId": doc! {
+                        "bsonType": "string",
+                        "description": "The ID of the element this entry belongs to"
+                    },
+                    "boardId": doc! {
+                        "bsonType": "string",
+                        "description": "The ID of the board, the element is contained in"
+                    },
+                    "userId": doc! {
+                        "bsonType": "string",
+                        "description": "The ID of the user that performed the change"
+                    },
+                    "action": doc! {
+                        "enum": vec!["created", "updated", "locked", "unlocked", "moved", "removed"],
+                        "description": "The kind of change"
+                    },
+                    "changes": doc! {
+                        "bsonType": vec!["object", "null"],
+                        "description": "The changed fields of the element"
+                    },
+                    "timestamp": doc! {
+                        "bsonType": "date",
+                        "description": "The timestamp of the change"
+                    }
+                }
+            }
+        };
+
+        let validation_opts = CreateCollectionOptions::builder()
+            .validator(validator)
+            .validation_action(Some(ValidationAction::Error))
+            .validation_level(Some(ValidationLevel::Moderate))
+            .build();
+
+        Ok(validation_opts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    #[test]
+    fn created_element_history_satisfies_validator() {
+        let stored_history = bson::to_document(&CreateElementHistory {
+            element_id: ObjectId::new().to_hex(),
+            board_id: "board".to_string(),
+            user_id: "user".to_string(),
+            action: ElementHistoryAction::Updated,
+            changes: Some(doc! { "x": 1.0 }),
+            timestamp: DateTime::now(),
+        })
+        .unwrap();
+        assert_satisfies_validator::<ElementHistory>(&stored_history);
+    }
+}
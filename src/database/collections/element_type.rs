@@ -1,5 +1,11 @@
-use axum::response::Response;
-use bson::{doc, serde_helpers::deserialize_hex_string_from_object_id};
+use std::{env::var, str::FromStr, sync::OnceLock};
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use bson::{doc, oid::ObjectId, serde_helpers::deserialize_hex_string_from_object_id};
+use futures::TryStreamExt;
 use mongodb::{
     options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
     results::{DeleteResult, InsertOneResult, UpdateResult},
@@ -8,12 +14,42 @@ use mongodb::{
 use serde::{Deserialize, Serialize};
 
 use crate::database::{
-    document::{Document, DocumentBase},
+    collections::element::Element,
+    config::DATABASE_NAME,
+    document::{empty_update_response, Document, DocumentBase},
     validator::Validator,
 };
 
-const ELEMENT_TYPE_COLLECTION_NAME: &str = "element-type";
+pub const ELEMENT_TYPE_COLLECTION_NAME: &str = "element-type";
 const ELEMENT_TYPE_DOCUMENT_NAME: &str = "Element Type";
+const FALLBACK_ELEMENT_COLOR: &str = "black";
+const FALLBACK_ELEMENT_SCALE: f32 = 1.0;
+const SVG_PATH_COMMANDS: &str = "MmLlHhVvCcSsQqTtAaZz";
+
+// Hosts element type paths may point to as `https://<host>/...` URLs, empty allows none
+#[allow(non_snake_case)]
+fn ALLOWED_ASSET_HOSTS() -> &'static Vec<String> {
+    static ALLOWED_ASSET_HOSTS: OnceLock<Vec<String>> = OnceLock::new();
+    ALLOWED_ASSET_HOSTS.get_or_init(|| match var("ELEMENT_TYPE_ALLOWED_ASSET_HOSTS") {
+        Ok(allowed_asset_hosts) => allowed_asset_hosts
+            .split(',')
+            .map(|host| host.trim().to_lowercase())
+            .filter(|host| !host.is_empty())
+            .collect(),
+        Err(_) => Vec::new(),
+    })
+}
+
+// Used for fields a create request omits, fields the request sets always take precedence
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementTypeDefaults {
+    pub color: Option<String>,
+    pub scale_x: Option<f32>,
+    pub scale_y: Option<f32>,
+    pub z_index: Option<i32>,
+    pub text: Option<String>,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -25,6 +61,13 @@ pub struct ElementType {
     pub _id: String,
     pub name: String,
     pub path: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub defaults: Option<ElementTypeDefaults>,
+    // At most one element of a singleton type may exist per board
+    #[serde(default)]
+    pub singleton: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -32,6 +75,9 @@ pub struct ElementType {
 pub struct CreateElementType {
     pub name: String,
     pub path: String,
+    pub category: Option<String>,
+    pub defaults: Option<ElementTypeDefaults>,
+    pub singleton: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,6 +85,33 @@ pub struct CreateElementType {
 pub struct UpdateElementType {
     pub name: Option<String>,
     pub path: Option<String>,
+    pub category: Option<String>,
+    pub defaults: Option<ElementTypeDefaults>,
+    pub singleton: Option<bool>,
+}
+
+impl UpdateElementType {
+    pub fn to_update_fields(&self) -> bson::Document {
+        let mut update_fields = doc! {};
+        if let Some(name) = self.name.clone() {
+            update_fields.insert("name", name);
+        }
+        if let Some(path) = self.path.clone() {
+            update_fields.insert("path", path);
+        }
+        if let Some(category) = self.category.clone() {
+            update_fields.insert("category", category);
+        }
+        if let Some(singleton) = self.singleton {
+            update_fields.insert("singleton", singleton);
+        }
+        if let Some(defaults) = &self.defaults {
+            if let Ok(defaults) = bson::to_bson(defaults) {
+                update_fields.insert("defaults", defaults);
+            }
+        }
+        update_fields
+    }
 }
 
 impl Document<ElementType, CreateElementType, UpdateElementType> for ElementType {
@@ -84,12 +157,9 @@ impl Document<ElementType, CreateElementType, UpdateElementType> for ElementType
         query_doc: bson::Document,
         update_document: UpdateElementType,
     ) -> Result<UpdateResult, Response> {
-        let mut update_fields = doc! {};
-        if let Some(name) = update_document.name {
-            update_fields.insert("name", name);
-        }
-        if let Some(path) = update_document.path {
-            update_fields.insert("path", path);
+        let update_fields = update_document.to_update_fields();
+        if let Some(error_response) = empty_update_response(&update_fields) {
+            return Err(error_response);
         }
         let update_doc = doc! {
             "$set": update_fields
@@ -140,6 +210,171 @@ impl Document<ElementType, CreateElementType, UpdateElementType> for ElementType
     }
 }
 
+impl ElementTypeDefaults {
+    pub fn validate(&self) -> Result<(), String> {
+        Element::validate_element_fields(self.text.as_deref(), self.color.as_deref())
+    }
+
+    pub fn color(&self, explicit_color: Option<String>) -> String {
+        explicit_color
+            .or_else(|| self.color.clone())
+            .unwrap_or_else(|| FALLBACK_ELEMENT_COLOR.to_string())
+    }
+
+    pub fn scale_x(&self, explicit_scale_x: Option<f32>) -> f32 {
+        explicit_scale_x
+            .or(self.scale_x)
+            .unwrap_or(FALLBACK_ELEMENT_SCALE)
+    }
+
+    pub fn scale_y(&self, explicit_scale_y: Option<f32>) -> f32 {
+        explicit_scale_y
+            .or(self.scale_y)
+            .unwrap_or(FALLBACK_ELEMENT_SCALE)
+    }
+
+    // Without either z-index the element is stacked on top, see `Board::next_z_index`
+    pub fn z_index(&self, explicit_z_index: Option<i32>) -> Option<i32> {
+        explicit_z_index.or(self.z_index)
+    }
+
+    pub fn text(&self, explicit_text: Option<String>) -> String {
+        explicit_text
+            .or_else(|| self.text.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ElementType {
+    // Allowed paths are
+    // - SVG path data starting with a moveto, e.g. `M 10 10 H 110 V 60 H 10 Z`
+    // - relative asset paths made of letters, digits, `.`, `_`, `-` and `/`, e.g. `shapes/star.svg`,
+    //   without `..` segments
+    // - `https://` URLs whose host is listed in `ELEMENT_TYPE_ALLOWED_ASSET_HOSTS`
+    pub fn validate_path(path: &str) -> Result<(), String> {
+        let path = path.trim();
+        if path.is_empty() {
+            return Err("Element Type path must not be empty".to_string());
+        }
+        if ElementType::is_svg_path_data(path)
+            || ElementType::is_relative_asset_path(path)
+            || ElementType::is_allowed_asset_url(path)
+        {
+            Ok(())
+        } else {
+            Err("Element Type path needs to be SVG path data, a relative asset path or an https URL of an allowed host".to_string())
+        }
+    }
+
+    fn is_svg_path_data(path: &str) -> bool {
+        path.starts_with(['M', 'm'])
+            && path.chars().all(|character| {
+                SVG_PATH_COMMANDS.contains(character)
+                    || character.is_ascii_digit()
+                    || character.is_ascii_whitespace()
+                    || ",.-+eE".contains(character)
+            })
+    }
+
+    fn is_relative_asset_path(path: &str) -> bool {
+        !path.starts_with('/')
+            && path.split('/').all(|segment| {
+                !segment.is_empty()
+                    && segment != ".."
+                    && segment.chars().all(|character| {
+                        character.is_ascii_alphanumeric() || "._-".contains(character)
+                    })
+            })
+    }
+
+    fn is_allowed_asset_url(path: &str) -> bool {
+        let Some(rest) = path.strip_prefix("https://") else {
+            return false;
+        };
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        // Credentials in the authority could disguise the actual host
+        if authority.contains('@') {
+            return false;
+        }
+        let host = authority
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        ALLOWED_ASSET_HOSTS().contains(&host)
+    }
+
+    // Unknown element types have no defaults, every omitted field falls back to its fixed default then
+    pub async fn get_defaults(
+        client: &Client,
+        element_type_id: &str,
+    ) -> Result<ElementTypeDefaults, Response> {
+        let Ok(object_id) = ObjectId::from_str(element_type_id) else {
+            return Ok(ElementTypeDefaults::default());
+        };
+        Ok(ElementType::get_document(client, doc! { "_id": object_id })
+            .await?
+            .and_then(|element_type| element_type.defaults)
+            .unwrap_or_default())
+    }
+
+    // Types referenced by no element, the usage comes from a single distinct query over all elements
+    pub async fn is_singleton(client: &Client, element_type_id: &str) -> Result<bool, Response> {
+        let Ok(object_id) = ObjectId::from_str(element_type_id) else {
+            return Ok(false);
+        };
+        Ok(ElementType::get_document(client, doc! { "_id": object_id })
+            .await?
+            .is_some_and(|element_type| element_type.singleton))
+    }
+
+    pub async fn get_unused(client: &Client) -> Result<Vec<ElementType>, Response> {
+        let used_element_type_ids = Element::get_used_element_type_ids(client).await?;
+        let used_object_ids = used_element_type_ids
+            .iter()
+            .filter_map(|element_type_id| ObjectId::from_str(element_type_id).ok())
+            .collect::<Vec<ObjectId>>();
+        let query_doc = doc! {
+            "_id": doc! { "$nin": used_object_ids },
+        };
+        match ElementType::get_multiple_documents(client, query_doc)
+            .await?
+            .try_collect::<Vec<ElementType>>()
+            .await
+        {
+            Ok(element_types) => Ok(element_types),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Element Types could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn delete_by_ids(
+        client: &Client,
+        element_type_ids: &[String],
+    ) -> Result<DeleteResult, Response> {
+        let object_ids = element_type_ids
+            .iter()
+            .filter_map(|element_type_id| ObjectId::from_str(element_type_id).ok())
+            .collect::<Vec<ObjectId>>();
+        match client
+            .database(DATABASE_NAME())
+            .collection::<ElementType>(ELEMENT_TYPE_COLLECTION_NAME)
+            .delete_many(doc! { "_id": doc! { "$in": object_ids } }, None)
+            .await
+        {
+            Ok(result) => Ok(result),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} deletion", ELEMENT_TYPE_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+}
+
 impl Validator for ElementType {
     fn get_validation_options(
     ) -> Result<mongodb::options::CreateCollectionOptions, Box<dyn std::error::Error>> {
@@ -150,7 +385,7 @@ impl Validator for ElementType {
                 "required": vec!["_id", "name", "path"],
                 "properties": doc! {
                     "_id": doc! {
-                        "bsonType": "string",
+                        "bsonType": "objectId",
                         "description": "ID of the element type",
                     },
                     "name": doc! {
@@ -160,6 +395,25 @@ impl Validator for ElementType {
                     "path": doc! {
                         "bsonType": "string",
                         "description": "Path of the Element",
+                    },
+                    "category": doc! {
+                        "bsonType": vec!["string", "null"],
+                        "description": "Category the Element is grouped under in the palette",
+                    },
+                    "singleton": doc! {
+                        "bsonType": "bool",
+                        "description": "Whether a board may contain at most one Element of this type",
+                    },
+                    "defaults": doc! {
+                        "bsonType": vec!["object", "null"],
+                        "description": "Properties new Elements of this type get if their create request omits them",
+                        "properties": doc! {
+                            "color": doc! { "bsonType": vec!["string", "null"] },
+                            "scaleX": doc! { "bsonType": vec!["double", "null"] },
+                            "scaleY": doc! { "bsonType": vec!["double", "null"] },
+                            "zIndex": doc! { "bsonType": vec!["int", "null"] },
+                            "text": doc! { "bsonType": vec!["string", "null"] },
+                        }
                     }
                 }
             }
@@ -174,3 +428,48 @@ impl Validator for ElementType {
         Ok(validation_opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    #[test]
+    fn created_element_types_satisfy_validator() {
+        let plain_element_type = CreateElementType {
+            name: "rectangle".to_string(),
+            path: "/rectangle.svg".to_string(),
+            category: None,
+            defaults: None,
+            singleton: false,
+        };
+        let element_type_with_defaults = CreateElementType {
+            name: "sticky-note".to_string(),
+            path: "/sticky-note.svg".to_string(),
+            category: Some("notes".to_string()),
+            defaults: Some(ElementTypeDefaults {
+                color: Some("#ffff00".to_string()),
+                scale_x: Some(1.0),
+                scale_y: Some(1.0),
+                z_index: Some(0),
+                text: Some(String::new()),
+            }),
+            singleton: true,
+        };
+        for element_type in [plain_element_type, element_type_with_defaults] {
+            assert_satisfies_validator::<ElementType>(&bson::to_document(&element_type).unwrap());
+        }
+    }
+
+    #[test]
+    fn update_without_fields_is_rejected() {
+        let update_element_type = UpdateElementType {
+            name: None,
+            path: None,
+            category: None,
+            defaults: None,
+            singleton: None,
+        };
+        assert!(empty_update_response(&update_element_type.to_update_fields()).is_some());
+    }
+}
@@ -1,22 +1,53 @@
-use std::str::FromStr;
+use std::{env::var, str::FromStr, sync::OnceLock, time::Duration};
 
-use axum::response::Response;
-use bson::{oid::ObjectId, serde_helpers::deserialize_hex_string_from_object_id};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bson::{oid::ObjectId, serde_helpers::deserialize_hex_string_from_object_id, DateTime};
+use futures::TryStreamExt;
 use mongodb::{
     bson::doc,
-    options::{CreateCollectionOptions, ValidationAction, ValidationLevel},
+    options::{
+        AggregateOptions, CreateCollectionOptions, FindOneAndUpdateOptions, ReturnDocument,
+        UpdateOptions, ValidationAction, ValidationLevel,
+    },
     results::{DeleteResult, InsertOneResult, UpdateResult},
     Client, Cursor,
 };
+use ring::{
+    constant_time::verify_slices_are_equal,
+    digest,
+    rand::{SecureRandom, SystemRandom},
+};
 use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
 
 use crate::database::{
-    document::{Document, DocumentBase},
+    config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT},
+    document::{empty_update_response, Document, DocumentBase},
+    timestamp::{default_timestamp, serialize_timestamp},
     validator::Validator,
 };
 
 const USER_COLLECTION_NAME: &str = "user";
 const USER_DOCUMENT_NAME: &str = "User";
+const DEFAULT_GUEST_USER_TTL_SECONDS: u64 = 3600;
+const GUEST_TOKEN_LENGTH: usize = 32;
+
+#[allow(non_snake_case)]
+pub fn GUEST_USER_TTL() -> Duration {
+    static GUEST_USER_TTL: OnceLock<Duration> = OnceLock::new();
+    *GUEST_USER_TTL.get_or_init(|| match var("GUEST_USER_TTL_SECONDS") {
+        Ok(guest_user_ttl) => Duration::from_secs(
+            guest_user_ttl
+                .parse()
+                .expect("Failed to parse `GUEST_USER_TTL_SECONDS` environment variable."),
+        ),
+        Err(_) => Duration::from_secs(DEFAULT_GUEST_USER_TTL_SECONDS),
+    })
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -27,9 +58,19 @@ pub struct User {
     )]
     pub _id: String,
     pub name: String,
+    // Guests are stored without email and password and are removed once inactive for longer than
+    // `GUEST_USER_TTL`
+    #[serde(default)]
     pub email: String,
+    #[serde(default)]
     pub password: String,
     pub active_client: Option<String>,
+    #[serde(default)]
+    pub is_guest: bool,
+    #[serde(default = "default_timestamp", serialize_with = "serialize_timestamp")]
+    pub last_active_at: DateTime,
+    #[serde(default, skip_serializing)]
+    pub guest_token_hash: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -38,8 +79,14 @@ pub struct CreateUser {
     #[serde(rename = "_id")]
     pub _id: ObjectId,
     pub name: String,
-    pub email: String,
-    pub password: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    pub is_guest: bool,
+    pub last_active_at: DateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guest_token_hash: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -51,16 +98,38 @@ pub struct UpdateUser {
     pub active_client: Option<String>,
 }
 
+impl UpdateUser {
+    pub fn to_update_fields(&self) -> bson::Document {
+        let mut update_fields = doc! {};
+        if let Some(name) = self.name.clone() {
+            update_fields.insert("name", name);
+        }
+        if let Some(email) = self.email.clone() {
+            update_fields.insert("email", email);
+        }
+        if let Some(password) = self.password.clone() {
+            update_fields.insert("password", password);
+        }
+        if let Some(active_client) = self.active_client.clone() {
+            update_fields.insert("activeClient", active_client);
+        }
+        update_fields
+    }
+}
+
 impl Document<User, CreateUser, UpdateUser> for User {
     async fn create_collection(client: &Client) -> Result<(), Response> {
         let create_collection_opts = User::get_validation_options().ok();
         DocumentBase::create_collection(
             client,
-            "USER_COLLECTION_NAME",
+            USER_COLLECTION_NAME,
             create_collection_opts,
             USER_DOCUMENT_NAME,
         )
-        .await
+        .await?;
+        User::strip_guest_credentials(client).await;
+        User::normalize_stored_users(client).await;
+        Ok(())
     }
 
     async fn create_document(
@@ -94,18 +163,9 @@ impl Document<User, CreateUser, UpdateUser> for User {
         query_doc: bson::Document,
         update_document: UpdateUser,
     ) -> Result<UpdateResult, Response> {
-        let mut update_fields = doc! {};
-        if let Some(name) = update_document.name {
-            update_fields.insert("name", name);
-        }
-        if let Some(email) = update_document.email {
-            update_fields.insert("email", email);
-        }
-        if let Some(password) = update_document.password {
-            update_fields.insert("password", password);
-        }
-        if let Some(active_client) = update_document.active_client {
-            update_fields.insert("activeClient", active_client);
+        let update_fields = update_document.to_update_fields();
+        if let Some(error_response) = empty_update_response(&update_fields) {
+            return Err(error_response);
         }
         let update_doc = doc! {
             "$set": update_fields
@@ -153,6 +213,290 @@ impl Document<User, CreateUser, UpdateUser> for User {
 }
 
 impl User {
+    // Names and emails are normalized before they are stored or looked up, so surrounding
+    // whitespace or a capitalized email never leads to a second account or a failed login
+    pub fn normalize_name(name: &str) -> String {
+        name.trim().to_string()
+    }
+
+    pub fn normalize_email(email: &str) -> String {
+        email.trim().to_lowercase()
+    }
+
+    // Guests used to be stored with an empty email and password. Those guests have no token either,
+    // so the validator is bypassed, they cannot open streams anymore and expire as usual
+    async fn strip_guest_credentials(client: &Client) {
+        let update_options = UpdateOptions::builder()
+            .bypass_document_validation(true)
+            .build();
+        match client
+            .database(DATABASE_NAME())
+            .collection::<User>(USER_COLLECTION_NAME)
+            .update_many(
+                doc! { "isGuest": true, "$or": [{ "email": { "$exists": true } }, { "password": { "$exists": true } }] },
+                doc! { "$unset": doc! { "email": "", "password": "" } },
+                update_options,
+            )
+            .await
+        {
+            Ok(result) if result.modified_count > 0 => {
+                info!("Removed email and password of {} guests", result.modified_count)
+            }
+            Ok(_) => {}
+            Err(err) => error!("Email and password of guests could not be removed: {:?}", err),
+        }
+    }
+
+    // Guests act on public boards with a random token handed out once on creation, only its hash is
+    // stored
+    pub fn generate_guest_token() -> Option<String> {
+        let mut token = [0u8; GUEST_TOKEN_LENGTH];
+        SystemRandom::new()
+            .fill(&mut token)
+            .ok()
+            .map(|()| URL_SAFE_NO_PAD.encode(token))
+    }
+
+    pub fn hash_guest_token(token: &str) -> String {
+        URL_SAFE_NO_PAD.encode(digest::digest(&digest::SHA256, token.as_bytes()))
+    }
+
+    // Users that are no guests are not checked, they are identified by their ID
+    pub fn is_guest_token_valid(&self, token: Option<&str>) -> bool {
+        if !self.is_guest {
+            return true;
+        }
+        match (token, self.guest_token_hash.as_deref()) {
+            (Some(token), Some(guest_token_hash)) => verify_slices_are_equal(
+                User::hash_guest_token(token).as_bytes(),
+                guest_token_hash.as_bytes(),
+            )
+            .is_ok(),
+            _ => false,
+        }
+    }
+
+    // Users stored before normalization was introduced keep their raw name and email, so they are
+    // normalized once on startup, emails that only differed in case or whitespace are reported since
+    // a lookup by email returns just one of those accounts
+    async fn normalize_stored_users(client: &Client) {
+        let user_collection = client
+            .database(DATABASE_NAME())
+            .collection::<User>(USER_COLLECTION_NAME);
+        let normalize_pipeline = vec![doc! {
+            "$set": doc! {
+                "name": doc! { "$trim": doc! { "input": "$name" } },
+                "email": doc! { "$toLower": doc! { "$trim": doc! { "input": "$email" } } },
+            }
+        }];
+        match user_collection
+            .update_many(
+                doc! { "name": doc! { "$type": "string" }, "email": doc! { "$type": "string" } },
+                normalize_pipeline,
+                None,
+            )
+            .await
+        {
+            Ok(result) if result.modified_count > 0 => {
+                info!(
+                    "Normalized name and email of {} Users",
+                    result.modified_count
+                )
+            }
+            Ok(_) => {}
+            Err(err) => {
+                error!(
+                    "Stored User names and emails could not be normalized: {:?}",
+                    err
+                );
+                return;
+            }
+        }
+        let duplicates_pipeline = vec![
+            doc! { "$match": doc! { "email": doc! { "$type": "string", "$ne": "" } } },
+            doc! { "$group": doc! { "_id": "$email", "count": doc! { "$sum": 1 } } },
+            doc! { "$match": doc! { "count": doc! { "$gt": 1 } } },
+        ];
+        let duplicate_emails = match user_collection
+            .aggregate(
+                duplicates_pipeline,
+                AggregateOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await
+        {
+            Ok(cursor) => cursor.try_collect::<Vec<bson::Document>>().await,
+            Err(err) => Err(err),
+        };
+        match duplicate_emails {
+            Ok(duplicate_emails) => {
+                for duplicate_email in duplicate_emails {
+                    if let Ok(email) = duplicate_email.get_str("_id") {
+                        warn!("Multiple Users share the email {}", email);
+                    }
+                }
+            }
+            Err(err) => error!("Duplicate User emails could not be checked: {:?}", err),
+        }
+    }
+
+    pub async fn get_page(
+        database_client: &Client,
+        after: Option<ObjectId>,
+        limit: i64,
+    ) -> Result<Vec<User>, Response> {
+        DocumentBase::find_after_id::<User>(
+            database_client,
+            USER_COLLECTION_NAME,
+            after,
+            limit,
+            USER_DOCUMENT_NAME,
+        )
+        .await
+    }
+
+    pub async fn is_guest_user(user_id: &str, database_client: &Client) -> Result<bool, Response> {
+        let object_id = match ObjectId::from_str(user_id) {
+            Ok(object_id) => object_id,
+            Err(_) => return Ok(false),
+        };
+        match User::get_document(database_client, doc! { "_id": object_id }).await? {
+            Some(user) => Ok(user.is_guest),
+            None => Ok(false),
+        }
+    }
+
+    pub async fn get_expired_guests(database_client: &Client) -> Result<Vec<User>, Response> {
+        let expired_before = DateTime::from_millis(
+            DateTime::now().timestamp_millis() - GUEST_USER_TTL().as_millis() as i64,
+        );
+        let query_doc = doc! {
+            "isGuest": true,
+            "lastActiveAt": doc! { "$lt": expired_before },
+        };
+        match User::get_multiple_documents(database_client, query_doc)
+            .await?
+            .try_collect::<Vec<User>>()
+            .await
+        {
+            Ok(users) => Ok(users),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Users could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
+    pub async fn touch_guest(user_id: &str, database_client: &Client) {
+        let object_id = match ObjectId::from_str(user_id) {
+            Ok(object_id) => object_id,
+            Err(_) => return,
+        };
+        let _ = database_client
+            .database(DATABASE_NAME())
+            .collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": object_id, "isGuest": true },
+                doc! { "$set": doc! { "lastActiveAt": DateTime::now() } },
+                None,
+            )
+            .await;
+    }
+
+    // Returns the client that was active before, so callers can displace it if it differs
+    pub async fn set_active_client(
+        user_id: &str,
+        client_id: Option<String>,
+        database_client: &Client,
+    ) -> Result<Option<String>, Response> {
+        let object_id = match ObjectId::from_str(user_id) {
+            Ok(object_id) => object_id,
+            Err(_) => return Err((StatusCode::BAD_REQUEST, "User ID is invalid").into_response()),
+        };
+        let find_options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::Before)
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<User>(USER_COLLECTION_NAME)
+            .find_one_and_update(
+                doc! { "_id": object_id },
+                doc! { "$set": doc! { "activeClient": client_id } },
+                find_options,
+            )
+            .await
+        {
+            Ok(previous_user) => Ok(previous_user.and_then(|user| user.active_client)),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} update", USER_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Only clears the active client if it is still `client_id`, a client activated in the meantime is
+    // kept
+    pub async fn clear_active_client(
+        user_id: &str,
+        client_id: &str,
+        database_client: &Client,
+    ) -> Result<(), Response> {
+        let object_id = match ObjectId::from_str(user_id) {
+            Ok(object_id) => object_id,
+            Err(_) => return Err((StatusCode::BAD_REQUEST, "User ID is invalid").into_response()),
+        };
+        match database_client
+            .database(DATABASE_NAME())
+            .collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                doc! { "_id": object_id, "activeClient": client_id },
+                doc! { "$set": doc! { "activeClient": null } },
+                None,
+            )
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error during {} update", USER_DOCUMENT_NAME),
+            )
+                .into_response()),
+        }
+    }
+
+    // Ids that are invalid or belong to no user are skipped
+    pub async fn get_users_by_ids(
+        user_ids: &[String],
+        database_client: &Client,
+    ) -> Result<Vec<User>, Response> {
+        let object_ids = user_ids
+            .iter()
+            .filter_map(|user_id| ObjectId::from_str(user_id.as_str()).ok())
+            .collect::<Vec<ObjectId>>();
+        if object_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let query_doc = doc! {
+            "_id": doc! { "$in": object_ids },
+        };
+        match User::get_multiple_documents(database_client, query_doc)
+            .await?
+            .try_collect::<Vec<User>>()
+            .await
+        {
+            Ok(users) => Ok(users),
+            Err(_) => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Users could not be retrieved",
+            )
+                .into_response()),
+        }
+    }
+
     pub async fn get_existing_user(
         user_id: String,
         database_client: &Client,
@@ -177,10 +521,17 @@ impl Validator for User {
             "$jsonSchema": doc! {
                 "bsonType": "object",
                 "title": "User Validation",
-                "required": vec! ["_id", "name", "email", "password", "active_client"],
+                "required": vec! ["_id", "name"],
+                "anyOf": vec! [
+                    doc! { "required": vec! ["email", "password"] },
+                    doc! {
+                        "required": vec! ["isGuest", "guestTokenHash"],
+                        "properties": doc! { "isGuest": doc! { "enum": vec! [true] } }
+                    },
+                ],
                 "properties": doc! {
                     "_id": doc! {
-                        "bsonType": "string",
+                        "bsonType": "objectId",
                         "description": "ID of the User"
                     },
                     "name": doc! {
@@ -192,12 +543,24 @@ impl Validator for User {
                         "description": "Email of the user"
                     },
                     "password": doc! {
-                        "bsonType": vec! ["object"],
-                        "description": "Member array"
-                    },
-                    "active_client": doc! {
                         "bsonType": "string",
+                        "description": "Password of the user"
+                    },
+                    "activeClient": doc! {
+                        "bsonType": vec! ["string", "null"],
                         "description": "Current active client device ID"
+                    },
+                    "isGuest": doc! {
+                        "bsonType": "bool",
+                        "description": "Whether the user is a temporary guest"
+                    },
+                    "guestTokenHash": doc! {
+                        "bsonType": "string",
+                        "description": "SHA-256 hash of the token of a guest"
+                    },
+                    "lastActiveAt": doc! {
+                        "bsonType": "date",
+                        "description": "Last activity of the user, used to expire guests"
                     }
                 }
             }
@@ -212,3 +575,133 @@ impl Validator for User {
         Ok(validation_opts)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::validator::schema::assert_satisfies_validator;
+
+    #[test]
+    fn normalize_name_trims_whitespace() {
+        assert_eq!(User::normalize_name("  alice \t\n"), "alice");
+        assert_eq!(User::normalize_name("Alice Smith"), "Alice Smith");
+    }
+
+    #[test]
+    fn normalize_name_keeps_case() {
+        assert_ne!(User::normalize_name("Alice"), User::normalize_name("alice"));
+    }
+
+    #[test]
+    fn normalize_email_trims_and_lowercases() {
+        assert_eq!(User::normalize_email(" Alice@X.com "), "alice@x.com");
+        assert_eq!(
+            User::normalize_email("alice@x.com "),
+            User::normalize_email("ALICE@x.COM")
+        );
+    }
+
+    #[test]
+    fn normalize_email_is_idempotent() {
+        let normalized = User::normalize_email("\tBob@Example.org");
+        assert_eq!(User::normalize_email(&normalized), normalized);
+    }
+
+    fn guest(guest_token_hash: Option<String>) -> User {
+        User {
+            _id: ObjectId::new().to_hex(),
+            name: "Guest-abcdef".to_string(),
+            email: String::new(),
+            password: String::new(),
+            active_client: None,
+            is_guest: true,
+            last_active_at: DateTime::now(),
+            guest_token_hash,
+        }
+    }
+
+    #[test]
+    fn guest_token_is_checked_against_its_hash() {
+        let token = User::generate_guest_token().unwrap();
+        let user = guest(Some(User::hash_guest_token(&token)));
+        assert!(user.is_guest_token_valid(Some(&token)));
+        assert!(!user.is_guest_token_valid(Some("other token")));
+        assert!(!user.is_guest_token_valid(None));
+    }
+
+    #[test]
+    fn guest_without_stored_token_is_rejected() {
+        assert!(!guest(None).is_guest_token_valid(Some("token")));
+    }
+
+    #[test]
+    fn regular_users_need_no_guest_token() {
+        let user = User {
+            is_guest: false,
+            ..guest(None)
+        };
+        assert!(user.is_guest_token_valid(None));
+    }
+
+    #[test]
+    fn guests_are_stored_without_email_and_password() {
+        let created_guest = bson::to_document(&CreateUser {
+            _id: ObjectId::new(),
+            name: "Guest-abcdef".to_string(),
+            email: None,
+            password: None,
+            is_guest: true,
+            last_active_at: DateTime::now(),
+            guest_token_hash: Some(User::hash_guest_token("token")),
+        })
+        .unwrap();
+        assert!(!created_guest.contains_key("email"));
+        assert!(!created_guest.contains_key("password"));
+        let stored_guest: User = bson::from_document(created_guest).unwrap();
+        assert!(stored_guest.is_guest);
+        assert_eq!(stored_guest.email, "");
+    }
+
+    #[test]
+    fn collection_name_constant_is_used_for_create_and_crud() {
+        assert_eq!(USER_COLLECTION_NAME, "user");
+        // The collection was once created under the quoted name of the constant instead of its value
+        assert!(!include_str!("user.rs").contains(concat!("\"USER_", "COLLECTION_NAME\"")));
+    }
+
+    #[test]
+    fn created_users_satisfy_validator() {
+        let registered_user = CreateUser {
+            _id: ObjectId::new(),
+            name: "Alice".to_string(),
+            email: Some("alice@example.com".to_string()),
+            password: Some("hash".to_string()),
+            is_guest: false,
+            last_active_at: DateTime::now(),
+            guest_token_hash: None,
+        };
+        let guest = CreateUser {
+            _id: ObjectId::new(),
+            name: "Guest-abcdef".to_string(),
+            email: None,
+            password: None,
+            is_guest: true,
+            last_active_at: DateTime::now(),
+            guest_token_hash: Some(User::hash_guest_token("token")),
+        };
+        for user in [registered_user, guest] {
+            assert_satisfies_validator::<User>(&bson::to_document(&user).unwrap());
+        }
+    }
+
+    #[test]
+    fn update_without_fields_is_rejected() {
+        let update_user = UpdateUser {
+            name: None,
+            email: None,
+            password: None,
+            active_client: None,
+        };
+        assert!(empty_update_response(&update_user.to_update_fields()).is_some());
+    }
+}
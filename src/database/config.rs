@@ -1,6 +1,7 @@
-use mongodb::options::Compressor;
+use mongodb::options::{Acknowledgment, Compressor, ReadConcern, WriteConcern};
 use std::env::var;
 use std::{sync::OnceLock, time::Duration};
+use tracing::info;
 
 #[allow(non_snake_case)]
 pub fn DATABASE_NAME() -> &'static str {
@@ -8,12 +9,36 @@ pub fn DATABASE_NAME() -> &'static str {
     DATABASE_NAME.get_or_init(|| var("DATABASE_NAME").unwrap())
 }
 
+const DEFAULT_MONGO_OPERATION_TIMEOUT_MS: u64 = 10_000;
+
+// Server side `maxTimeMS` of a single query, independent of the request timeout. 0 keeps the driver
+// default of no limit. Finds, counts, aggregations and the find-and-modify writes are bounded, plain
+// updates, deletes and inserts are not since the driver offers no `maxTimeMS` for them
+#[allow(non_snake_case)]
+pub fn MONGO_OPERATION_TIMEOUT() -> Option<Duration> {
+    static MONGO_OPERATION_TIMEOUT: OnceLock<Option<Duration>> = OnceLock::new();
+    *MONGO_OPERATION_TIMEOUT.get_or_init(|| {
+        let timeout_ms = match var("MONGO_OPERATION_TIMEOUT_MS") {
+            Ok(timeout_ms) => timeout_ms
+                .parse()
+                .expect("Failed to parse `MONGO_OPERATION_TIMEOUT_MS` environment variable."),
+            Err(_) => DEFAULT_MONGO_OPERATION_TIMEOUT_MS,
+        };
+        match timeout_ms {
+            0 => None,
+            timeout_ms => Some(Duration::from_millis(timeout_ms)),
+        }
+    })
+}
+
 pub struct DatabaseConfig {
     pub uri: String,
     pub connection_timeout: Option<Duration>,
     pub min_pool_size: Option<u32>,
     pub max_pool_size: Option<u32>,
     pub compressors: Option<Vec<Compressor>>,
+    pub write_concern: Option<WriteConcern>,
+    pub read_concern: Option<ReadConcern>,
 }
 
 impl DatabaseConfig {
@@ -36,6 +61,38 @@ impl DatabaseConfig {
             .parse()
             .expect("Failed to parse `MONGO_MAX_POOL_SIZE` environment variable.");
 
+        if let Err(message) = Self::validate_pool(
+            mongo_connection_timeout,
+            mongo_min_pool_size,
+            mongo_max_pool_size,
+        ) {
+            panic!("{}", message);
+        }
+        info!(
+            "MongoDB pool configuration: min pool size {}, max pool size {}, connection timeout {}s",
+            mongo_min_pool_size, mongo_max_pool_size, mongo_connection_timeout
+        );
+
+        let write_concern = match var("MONGO_WRITE_CONCERN") {
+            Ok(write_concern) => Some(Self::parse_write_concern(write_concern)),
+            Err(_) => None,
+        };
+        let read_concern = match var("MONGO_READ_CONCERN") {
+            Ok(read_concern) => Some(Self::parse_read_concern(read_concern)),
+            Err(_) => None,
+        };
+        info!(
+            "MongoDB concerns: write concern {}, read concern {}",
+            match &write_concern {
+                Some(write_concern) => format!("{:?}", write_concern.w),
+                None => "driver default".to_string(),
+            },
+            match &read_concern {
+                Some(read_concern) => format!("{:?}", read_concern.level),
+                None => "driver default".to_string(),
+            }
+        );
+
         Self {
             uri: mongo_uri,
             connection_timeout: Some(Duration::from_secs(mongo_connection_timeout)),
@@ -50,6 +107,75 @@ impl DatabaseConfig {
                     level: Default::default(),
                 },
             ]),
+            write_concern,
+            read_concern,
+        }
+    }
+
+    fn validate_pool(
+        connection_timeout: u64,
+        min_pool_size: u32,
+        max_pool_size: u32,
+    ) -> Result<(), String> {
+        if connection_timeout == 0 {
+            return Err("`MONGO_CONNECTION_TIMEOUT` must be greater than 0 seconds.".to_string());
+        }
+        if max_pool_size == 0 {
+            return Err("`MONGO_MAX_POOL_SIZE` must be greater than 0.".to_string());
+        }
+        if min_pool_size > max_pool_size {
+            return Err(format!(
+                "`MONGO_MIN_POOL_SIZE` ({}) must not be greater than `MONGO_MAX_POOL_SIZE` ({}).",
+                min_pool_size, max_pool_size
+            ));
         }
+        Ok(())
+    }
+
+    fn parse_write_concern(write_concern: String) -> WriteConcern {
+        let acknowledgment = match write_concern.parse::<u32>() {
+            Ok(0) => panic!(
+                "`MONGO_WRITE_CONCERN` must not be 0, unacknowledged writes are not supported."
+            ),
+            Ok(nodes) => Acknowledgment::Nodes(nodes),
+            Err(_) => Acknowledgment::from(write_concern),
+        };
+        WriteConcern::builder().w(acknowledgment).build()
+    }
+
+    fn parse_read_concern(read_concern: String) -> ReadConcern {
+        match read_concern.as_str() {
+            "local" => ReadConcern::local(),
+            "majority" => ReadConcern::majority(),
+            "linearizable" => ReadConcern::linearizable(),
+            "available" => ReadConcern::available(),
+            "snapshot" => ReadConcern::snapshot(),
+            _ => panic!(
+                "Failed to parse `MONGO_READ_CONCERN` environment variable, expected one of local, majority, linearizable, available or snapshot."
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_pool_accepts_equal_pool_sizes() {
+        assert!(DatabaseConfig::validate_pool(1, 10, 10).is_ok());
+        assert!(DatabaseConfig::validate_pool(1, 0, 1).is_ok());
+    }
+
+    #[test]
+    fn validate_pool_rejects_min_above_max() {
+        let message = DatabaseConfig::validate_pool(1, 11, 10).unwrap_err();
+        assert!(message.contains("`MONGO_MIN_POOL_SIZE` (11)"));
+    }
+
+    #[test]
+    fn validate_pool_rejects_zero_values() {
+        assert!(DatabaseConfig::validate_pool(0, 1, 10).is_err());
+        assert!(DatabaseConfig::validate_pool(1, 0, 0).is_err());
     }
 }
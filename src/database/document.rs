@@ -2,19 +2,182 @@ use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
 };
+use bson::{doc, oid::ObjectId};
+use futures::TryStreamExt;
 use mongodb::{
-    options::CreateCollectionOptions,
-    results::{DeleteResult, InsertOneResult, UpdateResult},
+    error::{Error, ErrorKind, WriteFailure},
+    options::{CreateCollectionOptions, FindOneAndDeleteOptions, FindOneOptions, FindOptions},
+    results::{DeleteResult, InsertManyResult, InsertOneResult, UpdateResult},
     Client, Cursor,
 };
 use serde::{de::DeserializeOwned, Serialize};
-use tracing::error;
+use tracing::{error, info, warn};
 
-use super::config::DATABASE_NAME;
+use super::config::{DATABASE_NAME, MONGO_OPERATION_TIMEOUT};
 
 pub struct DocumentBase {}
 
+const NAMESPACE_EXISTS_ERROR_CODE: i32 = 48;
+
+const DUPLICATE_KEY_ERROR_CODE: i32 = 11000;
+
+fn duplicate_key_error_message(error: &Error) -> Option<&str> {
+    match *error.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref write_error))
+            if write_error.code == DUPLICATE_KEY_ERROR_CODE =>
+        {
+            Some(write_error.message.as_str())
+        }
+        ErrorKind::BulkWrite(ref bulk_write_failure) => bulk_write_failure
+            .write_errors
+            .as_ref()?
+            .iter()
+            .find(|write_error| write_error.code == DUPLICATE_KEY_ERROR_CODE)
+            .map(|write_error| write_error.message.as_str()),
+        _ => None,
+    }
+}
+
+// Only the field name is taken from the server message, e.g. `... dup key: { email: "..." }`,
+// the conflicting value is never echoed back
+fn duplicate_key_response(error_message: &str, document_name: &str) -> Response {
+    let field = error_message
+        .split_once("dup key: { ")
+        .and_then(|(_, dup_key)| dup_key.split_once(':'))
+        .map(|(field, _)| field.trim());
+    let message = match field {
+        Some(field) if !field.is_empty() => {
+            format!("{} with this `{}` already exists", document_name, field)
+        }
+        _ => format!("{} already exists", document_name),
+    };
+    (StatusCode::CONFLICT, message).into_response()
+}
+
+const DOCUMENT_VALIDATION_FAILURE_CODE: i32 = 121;
+
+// Some(details) if the write was rejected by the collection's `$jsonSchema` validator
+fn document_validation_failure(error: &Error) -> Option<Option<&bson::Document>> {
+    match *error.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref write_error))
+            if write_error.code == DOCUMENT_VALIDATION_FAILURE_CODE =>
+        {
+            Some(write_error.details.as_ref())
+        }
+        ErrorKind::BulkWrite(ref bulk_write_failure) => bulk_write_failure
+            .write_errors
+            .as_ref()?
+            .iter()
+            .find(|write_error| write_error.code == DOCUMENT_VALIDATION_FAILURE_CODE)
+            .map(|write_error| write_error.details.as_ref()),
+        _ => None,
+    }
+}
+
+// Collects the failing fields from `errInfo.details.schemaRulesNotSatisfied`, e.g.
+// `{ operatorName: "required", missingProperties: ["name"] }` or
+// `{ operatorName: "properties", propertiesNotSatisfied: [{ propertyName: "x", .. }] }`
+fn validation_failure_response(details: Option<&bson::Document>, document_name: &str) -> Response {
+    let mut problems = Vec::<String>::new();
+    let rules = details
+        .and_then(|details| details.get_document("details").ok())
+        .and_then(|details| details.get_array("schemaRulesNotSatisfied").ok());
+    for rule in rules
+        .into_iter()
+        .flatten()
+        .filter_map(|rule| rule.as_document())
+    {
+        if let Ok(missing_properties) = rule.get_array("missingProperties") {
+            problems.extend(
+                missing_properties
+                    .iter()
+                    .filter_map(|property| property.as_str())
+                    .map(|property| format!("`{}` is missing", property)),
+            );
+        }
+        if let Ok(properties) = rule.get_array("propertiesNotSatisfied") {
+            problems.extend(
+                properties
+                    .iter()
+                    .filter_map(|property| property.as_document())
+                    .filter_map(|property| property.get_str("propertyName").ok())
+                    .map(|property| format!("`{}` is invalid", property)),
+            );
+        }
+    }
+    let message = match problems.is_empty() {
+        true => format!("{} failed validation", document_name),
+        false => format!(
+            "{} failed validation: {}",
+            document_name,
+            problems.join(", ")
+        ),
+    };
+    (StatusCode::BAD_REQUEST, message).into_response()
+}
+
+// Client caused write failures get their own status, everything else stays a server error
+fn write_error_response(error: &Error, document_name: &str, action: &str) -> Response {
+    if let Some(error_message) = duplicate_key_error_message(error) {
+        return duplicate_key_response(error_message, document_name);
+    }
+    if let Some(details) = document_validation_failure(error) {
+        return validation_failure_response(details, document_name);
+    }
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Error during {} {}", document_name, action),
+    )
+        .into_response()
+}
+
+// MongoDB rejects an empty `$set`, so updates without a single field are turned away before reaching it
+pub fn empty_update_response(update_fields: &bson::Document) -> Option<Response> {
+    update_fields
+        .is_empty()
+        .then(|| (StatusCode::BAD_REQUEST, "No fields to update").into_response())
+}
+
+const MAX_TIME_MS_EXPIRED_ERROR_CODE: i32 = 50;
+
+// Raised by the server once an operation runs longer than `MONGO_OPERATION_TIMEOUT_MS`
+pub fn is_operation_timeout(error: &Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Command(ref command_error) if command_error.code == MAX_TIME_MS_EXPIRED_ERROR_CODE
+    )
+}
+
+// Timeouts are answered with 504 and logged on their own, so they can be alerted on separately
+pub fn read_error_response(error: &Error, document_name: &str, action: &str) -> Response {
+    if is_operation_timeout(error) {
+        warn!(
+            "{} {} exceeded the operation timeout",
+            document_name, action
+        );
+        return (
+            StatusCode::GATEWAY_TIMEOUT,
+            format!("{} {} timed out", document_name, action),
+        )
+            .into_response();
+    }
+    error!("{:?}", error);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Error during {} {}", document_name, action),
+    )
+        .into_response()
+}
+
+fn is_namespace_exists_error(error: &Error) -> bool {
+    matches!(
+        *error.kind,
+        ErrorKind::Command(ref command_error) if command_error.code == NAMESPACE_EXISTS_ERROR_CODE
+    )
+}
+
 impl DocumentBase {
+    // Safe to call on every boot, an existing collection gets the current validator applied instead
     pub async fn create_collection(
         client: &Client,
         collection_name: &str,
@@ -23,15 +186,73 @@ impl DocumentBase {
     ) -> Result<(), Response> {
         let result = client
             .database(DATABASE_NAME())
-            .create_collection(collection_name, create_collection_opts)
+            .create_collection(collection_name, create_collection_opts.clone())
             .await;
         match result {
+            Ok(_) => {
+                info!("Created {} collection", document_name);
+                Ok(())
+            }
+            Err(err) if is_namespace_exists_error(&err) => {
+                info!("{} collection already present", document_name);
+                DocumentBase::update_collection_validator(
+                    client,
+                    collection_name,
+                    create_collection_opts,
+                    document_name,
+                )
+                .await
+            }
+            Err(err) => {
+                error!("{:?}", err);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} collection creaton", document_name),
+                )
+                    .into_response())
+            }
+        }
+    }
+
+    async fn update_collection_validator(
+        client: &Client,
+        collection_name: &str,
+        create_collection_opts: Option<CreateCollectionOptions>,
+        document_name: &str,
+    ) -> Result<(), Response> {
+        let create_collection_opts = match create_collection_opts {
+            Some(create_collection_opts) => create_collection_opts,
+            None => return Ok(()),
+        };
+        let mut command = doc! {
+            "collMod": collection_name,
+        };
+        if let Some(validator) = create_collection_opts.validator {
+            command.insert("validator", validator);
+        }
+        if let Some(validation_level) = create_collection_opts.validation_level {
+            command.insert("validationLevel", bson::to_bson(&validation_level).unwrap());
+        }
+        if let Some(validation_action) = create_collection_opts.validation_action {
+            command.insert(
+                "validationAction",
+                bson::to_bson(&validation_action).unwrap(),
+            );
+        }
+        match client
+            .database(DATABASE_NAME())
+            .run_command(command, None)
+            .await
+        {
             Ok(_) => Ok(()),
-            Err(_) => Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error during {} collection creaton", document_name),
-            )
-                .into_response()),
+            Err(err) => {
+                error!("{:?}", err);
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error during {} validator update", document_name),
+                )
+                    .into_response())
+            }
         }
     }
 
@@ -51,11 +272,27 @@ impl DocumentBase {
             .await;
         match result {
             Ok(result) => Ok(result),
-            Err(_) => Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error during {} creation", document_name),
-            )
-                .into_response()),
+            Err(err) => Err(write_error_response(&err, document_name, "creation")),
+        }
+    }
+
+    pub async fn create_many_documents<CreateDocument>(
+        client: &Client,
+        collection_name: &str,
+        insert_docs: Vec<CreateDocument>,
+        document_name: &str,
+    ) -> Result<InsertManyResult, Response>
+    where
+        CreateDocument: Serialize,
+    {
+        let result = client
+            .database(DATABASE_NAME())
+            .collection::<CreateDocument>(collection_name)
+            .insert_many(insert_docs, None)
+            .await;
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Err(write_error_response(&err, document_name, "creation")),
         }
     }
 
@@ -83,6 +320,31 @@ impl DocumentBase {
         }
     }
 
+    pub async fn find_one_and_delete<BaseDocument>(
+        client: &Client,
+        collection_name: &str,
+        query_doc: bson::Document,
+        document_name: &str,
+    ) -> Result<Option<BaseDocument>, Response>
+    where
+        BaseDocument: DeserializeOwned + Serialize,
+    {
+        let result = client
+            .database(DATABASE_NAME())
+            .collection::<BaseDocument>(collection_name)
+            .find_one_and_delete(
+                query_doc,
+                FindOneAndDeleteOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
+            .await;
+        match result {
+            Ok(result) => Ok(result),
+            Err(err) => Err(read_error_response(&err, document_name, "deletion")),
+        }
+    }
+
     pub async fn update_document<BaseDocument>(
         client: &Client,
         collection_name: &str,
@@ -100,11 +362,7 @@ impl DocumentBase {
             .await;
         match result {
             Ok(result) => Ok(result),
-            Err(_) => Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error during {} update", document_name),
-            )
-                .into_response()),
+            Err(err) => Err(write_error_response(&err, document_name, "update")),
         }
     }
 
@@ -143,18 +401,16 @@ impl DocumentBase {
         let result = client
             .database(DATABASE_NAME())
             .collection::<BaseDocument>(collection_name)
-            .find_one(query_doc, None)
+            .find_one(
+                query_doc,
+                FindOneOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
             .await;
         match result {
             Ok(result) => Ok(result),
-            Err(err) => {
-                error!("{:?}", err);
-                Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Error during {} fetching", document_name),
-                )
-                    .into_response())
-            }
+            Err(err) => Err(read_error_response(&err, document_name, "fetching")),
         }
     }
 
@@ -170,15 +426,61 @@ impl DocumentBase {
         let result = client
             .database(DATABASE_NAME())
             .collection::<BaseDocument>(collection_name)
-            .find(query_doc, None)
+            .find(
+                query_doc,
+                FindOptions::builder()
+                    .max_time(MONGO_OPERATION_TIMEOUT())
+                    .build(),
+            )
             .await;
         match result {
             Ok(result) => Ok(result),
-            Err(_) => Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error during {} fetching", document_name),
-            )
-                .into_response()),
+            Err(err) => Err(read_error_response(&err, document_name, "fetching")),
+        }
+    }
+
+    // Pages are ordered by ascending `_id`, so a page only contains documents created after the
+    // cursor document and the `_id` of the last document is the cursor for the next page
+    pub async fn find_after_id<BaseDocument>(
+        client: &Client,
+        collection_name: &str,
+        after: Option<ObjectId>,
+        limit: i64,
+        document_name: &str,
+    ) -> Result<Vec<BaseDocument>, Response>
+    where
+        BaseDocument: DeserializeOwned + Unpin + Send + Sync,
+    {
+        let query_doc = match after {
+            Some(after) => doc! { "_id": doc! { "$gt": after } },
+            None => doc! {},
+        };
+        let find_options = FindOptions::builder()
+            .sort(doc! { "_id": 1 })
+            .limit(limit)
+            .max_time(MONGO_OPERATION_TIMEOUT())
+            .build();
+        let result = client
+            .database(DATABASE_NAME())
+            .collection::<BaseDocument>(collection_name)
+            .find(query_doc, find_options)
+            .await;
+        match result {
+            Ok(cursor) => match cursor.try_collect::<Vec<BaseDocument>>().await {
+                Ok(documents) => Ok(documents),
+                Err(err) if is_operation_timeout(&err) => {
+                    Err(read_error_response(&err, document_name, "fetching"))
+                }
+                Err(err) => {
+                    error!("{:?}", err);
+                    Err((
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Found {}s could not be retrieved", document_name),
+                    )
+                        .into_response())
+                }
+            },
+            Err(err) => Err(read_error_response(&err, document_name, "fetching")),
         }
     }
 }
@@ -209,3 +511,75 @@ pub trait Document<Base, Create, Update> {
         query_doc: bson::Document,
     ) -> Result<Cursor<Base>, Response>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn response_text(response: Response) -> String {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn validation_failure_response_lists_failing_fields() {
+        let details = doc! {
+            "details": {
+                "schemaRulesNotSatisfied": [
+                    { "operatorName": "required", "missingProperties": ["name"] },
+                    {
+                        "operatorName": "properties",
+                        "propertiesNotSatisfied": [{ "propertyName": "x", "details": [] }],
+                    },
+                ],
+            },
+        };
+        let response = validation_failure_response(Some(&details), "Element");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response_text(response).await,
+            "Element failed validation: `name` is missing, `x` is invalid"
+        );
+    }
+
+    #[tokio::test]
+    async fn validation_failure_response_without_details() {
+        let response = validation_failure_response(None, "Element");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response_text(response).await, "Element failed validation");
+    }
+
+    #[tokio::test]
+    async fn duplicate_key_response_names_only_the_field() {
+        let response = duplicate_key_response(
+            "E11000 duplicate key error collection: db.board index: host_name_unique dup key: { name: \"Board\" }",
+            "Board",
+        );
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            response_text(response).await,
+            "Board with this `name` already exists"
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_key_response_without_field() {
+        let response = duplicate_key_response("E11000 duplicate key error", "Board");
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(response_text(response).await, "Board already exists");
+    }
+
+    #[tokio::test]
+    async fn empty_update_response_rejects_empty_updates() {
+        let response = empty_update_response(&doc! {}).unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response_text(response).await, "No fields to update");
+    }
+
+    #[test]
+    fn empty_update_response_accepts_updates_with_fields() {
+        assert!(empty_update_response(&doc! { "name": "board" }).is_none());
+    }
+}
@@ -0,0 +1,39 @@
+use mongodb::Client;
+
+use super::{
+    collections::{
+        active_member::ActiveMember, board::Board, client::Client as ClientDocument,
+        element::Element, element_history::ElementHistory, element_type::ElementType, user::User,
+    },
+    document::Document,
+};
+
+pub async fn ensure_collections(database_client: &Client) -> Result<(), String> {
+    let results = [
+        ("User", User::create_collection(database_client).await),
+        (
+            "Client",
+            ClientDocument::create_collection(database_client).await,
+        ),
+        ("Board", Board::create_collection(database_client).await),
+        ("Element", Element::create_collection(database_client).await),
+        (
+            "Element History",
+            ElementHistory::create_collection(database_client).await,
+        ),
+        (
+            "Element Type",
+            ElementType::create_collection(database_client).await,
+        ),
+        (
+            "Active Member",
+            ActiveMember::create_collection(database_client).await,
+        ),
+    ];
+    for (document_name, result) in results {
+        if result.is_err() {
+            return Err(format!("Couldn't provision {} collection", document_name));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+// Shared serde helpers for timestamps. Timestamps are stored as BSON dates and sent to clients as
+// RFC 3339 strings, so read models serialize with `serialize_timestamp` while the structs that are
+// inserted keep the plain `DateTime`. Deserializing accepts both forms, so the same structs read
+// documents and client payloads.
+use bson::{Bson, DateTime};
+use serde::{
+    de::Error as DeserializeError, ser::Error as SerializeError, Deserialize, Deserializer,
+    Serializer,
+};
+
+// Documents stored before timestamps were introduced fall back to the unix epoch
+pub fn default_timestamp() -> DateTime {
+    DateTime::from_millis(0)
+}
+
+pub fn serialize_timestamp<S: Serializer>(
+    timestamp: &DateTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timestamp.try_to_rfc3339_string() {
+        Ok(timestamp) => serializer.serialize_str(&timestamp),
+        Err(error) => Err(S::Error::custom(format!(
+            "Cannot format timestamp {} as RFC 3339: {}",
+            timestamp, error
+        ))),
+    }
+}
+
+pub fn serialize_optional_timestamp<S: Serializer>(
+    timestamp: &Option<DateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(timestamp) => serialize_timestamp(timestamp, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+pub fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime, D::Error> {
+    timestamp_from_bson(Bson::deserialize(deserializer)?)
+}
+
+pub fn deserialize_optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime>, D::Error> {
+    match Bson::deserialize(deserializer)? {
+        Bson::Null | Bson::Undefined => Ok(None),
+        timestamp => timestamp_from_bson(timestamp).map(Some),
+    }
+}
+
+fn timestamp_from_bson<E: DeserializeError>(timestamp: Bson) -> Result<DateTime, E> {
+    match timestamp {
+        Bson::DateTime(timestamp) => Ok(timestamp),
+        Bson::String(timestamp) => DateTime::parse_rfc3339_str(&timestamp).map_err(|error| {
+            E::custom(format!(
+                "Timestamp {} is not a valid RFC 3339 string: {}",
+                timestamp, error
+            ))
+        }),
+        other => Err(E::custom(format!(
+            "Expected a date or an RFC 3339 string as timestamp, got {}",
+            other
+        ))),
+    }
+}
@@ -2,3 +2,117 @@ pub trait Validator {
     fn get_validation_options(
     ) -> Result<mongodb::options::CreateCollectionOptions, Box<dyn std::error::Error>>;
 }
+
+// Checks the part of `$jsonSchema` the validators use, so tests can assert that the documents the
+// server inserts satisfy the validator of their collection without a running MongoDB
+#[cfg(test)]
+pub mod schema {
+    use bson::{oid::ObjectId, Bson, Document};
+
+    use super::Validator;
+
+    pub fn assert_satisfies_validator<V: Validator>(document: &Document) {
+        let validator = V::get_validation_options()
+            .unwrap()
+            .validator
+            .expect("collection has a validator");
+        let schema = validator.get_document("$jsonSchema").unwrap();
+        // The driver generates the `_id` of inserted documents that lack one
+        let mut document = document.clone();
+        if !document.contains_key("_id") {
+            document.insert("_id", ObjectId::new());
+        }
+        let violations = violations(&Bson::Document(document.clone()), schema, "");
+        assert!(violations.is_empty(), "{:?} in {:?}", violations, document);
+    }
+
+    fn bson_type_name(value: &Bson) -> &'static str {
+        match value {
+            Bson::Double(_) => "double",
+            Bson::String(_) => "string",
+            Bson::Array(_) => "array",
+            Bson::Document(_) => "object",
+            Bson::Boolean(_) => "bool",
+            Bson::Null => "null",
+            Bson::Int32(_) => "int",
+            Bson::Int64(_) => "long",
+            Bson::ObjectId(_) => "objectId",
+            Bson::DateTime(_) => "date",
+            _ => "other",
+        }
+    }
+
+    fn violations(value: &Bson, schema: &Document, path: &str) -> Vec<String> {
+        let mut found = vec![];
+        match schema.get("bsonType") {
+            Some(Bson::String(bson_type)) if bson_type != bson_type_name(value) => {
+                found.push(format!("`{}` is no {}", path, bson_type));
+            }
+            Some(Bson::Array(bson_types))
+                if !bson_types
+                    .iter()
+                    .any(|bson_type| bson_type.as_str() == Some(bson_type_name(value))) =>
+            {
+                found.push(format!("`{}` is none of {:?}", path, bson_types));
+            }
+            _ => {}
+        }
+        if let Ok(allowed_values) = schema.get_array("enum") {
+            if !allowed_values.contains(value) {
+                found.push(format!("`{}` is not one of {:?}", path, allowed_values));
+            }
+        }
+        if let Ok(any_of) = schema.get_array("anyOf") {
+            if !any_of
+                .iter()
+                .filter_map(Bson::as_document)
+                .any(|schema| violations(value, schema, path).is_empty())
+            {
+                found.push(format!("`{}` satisfies no schema of anyOf", path));
+            }
+        }
+        if let Bson::Document(document) = value {
+            if let Ok(required) = schema.get_array("required") {
+                for field in required.iter().filter_map(Bson::as_str) {
+                    if !document.contains_key(field) {
+                        found.push(format!("`{}.{}` is missing", path, field));
+                    }
+                }
+            }
+            if let Ok(properties) = schema.get_document("properties") {
+                for (field, property_schema) in properties {
+                    if let (Some(field_value), Some(property_schema)) =
+                        (document.get(field), property_schema.as_document())
+                    {
+                        found.extend(violations(
+                            field_value,
+                            property_schema,
+                            &format!("{}.{}", path, field),
+                        ));
+                    }
+                }
+            }
+        }
+        if let (Bson::Array(items), Ok(items_schema)) = (value, schema.get_document("items")) {
+            for (index, item) in items.iter().enumerate() {
+                found.extend(violations(
+                    item,
+                    items_schema,
+                    &format!("{}[{}]", path, index),
+                ));
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn reports_missing_and_mistyped_fields() {
+        let schema = bson::doc! {
+            "bsonType": "object",
+            "required": ["name", "x"],
+            "properties": { "x": { "bsonType": "double" } },
+        };
+        let document = Bson::Document(bson::doc! { "x": "1" });
+        assert_eq!(violations(&document, &schema, "").len(), 2);
+    }
+}
@@ -1,6 +1,10 @@
 use std::process::exit;
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLock,
+};
 
+use database::setup::ensure_collections;
 use dotenvy::dotenv;
 use mongodb::bson::doc;
 use mongodb::{options::ClientOptions, Client};
@@ -8,6 +12,7 @@ use services::webtransport::context::active_member::ActiveMemberContext;
 use services::webtransport::context::board::BoardContext;
 use services::webtransport::context::client::ClientContext;
 use services::webtransport::context::element::ElementContext;
+use services::webtransport::session_limit::SessionLimit;
 use tokio::sync::Mutex;
 use tracing::{error, info};
 use utils::element_types::generate_elements;
@@ -17,12 +22,16 @@ use wtransport::Identity;
 mod database {
     pub mod config;
     pub mod document;
+    pub mod setup;
+    pub mod timestamp;
     pub mod validator;
     pub mod collections {
         pub mod active_member;
         pub mod board;
         pub mod client;
         pub mod element;
+        pub mod element_asset;
+        pub mod element_history;
         pub mod element_type;
         pub mod user;
     }
@@ -45,14 +54,25 @@ mod services {
             pub mod board;
             pub mod client;
             pub mod element;
+            pub mod event_buffer;
+            pub mod event_dedupe;
         }
+        pub mod datagram;
+        pub mod event_delivery;
+        pub mod message_rate_limit;
+        pub mod protocol_version;
         pub mod server;
+        pub mod session_limit;
     }
     pub mod rest {
+        pub mod admin_auth;
         pub mod server;
         pub mod endpoints {
             pub mod active_member;
+            pub mod admin;
             pub mod board;
+            pub mod capabilities;
+            pub mod certificate;
             pub mod client;
             pub mod element;
             pub mod element_type;
@@ -61,24 +81,40 @@ mod services {
         }
         pub mod payloads {
             pub mod active_member;
+            pub mod admin;
             pub mod board;
+            pub mod capabilities;
             pub mod client;
             pub mod element;
             pub mod element_type;
+            pub mod ping;
             pub mod user;
         }
     }
 }
 mod utils {
     pub mod check_request_body;
+    pub mod client_cleanup;
+    pub mod color;
+    pub mod demo_board;
     pub mod element_types;
     pub mod generate_certificate;
+    pub mod guest_cleanup;
+    pub mod inserted_object_id;
     pub mod logging;
+    pub mod serialize_or_log;
+    pub mod text_encryption;
 }
 use crate::database::config::DatabaseConfig;
 use crate::services::rest::server::RestServer;
 use crate::services::webtransport::server::WebTransportServer;
-use crate::utils::{generate_certificate::generate_certificate, logging::init_logging};
+use crate::utils::{
+    client_cleanup::start_client_cleanup,
+    demo_board::seed_demo_board,
+    generate_certificate::{certificate_needs_renewal, generate_certificate, CertificateConfig},
+    guest_cleanup::start_guest_cleanup,
+    logging::init_logging,
+};
 
 #[derive(Clone)]
 pub struct AppState {
@@ -87,6 +123,10 @@ pub struct AppState {
     element_context: Arc<Mutex<ElementContext>>,
     client_context: Arc<Mutex<ClientContext>>,
     active_member_context: Arc<Mutex<ActiveMemberContext>>,
+    session_limit: Arc<SessionLimit>,
+    certificate_hash: Arc<RwLock<String>>,
+    // Set once startup provisioning of collections and element types has completed
+    ready: Arc<AtomicBool>,
 }
 
 #[tokio::main]
@@ -95,12 +135,20 @@ async fn main() -> anyhow::Result<()> {
 
     init_logging();
 
+    let ready = Arc::new(AtomicBool::new(false));
+
     let database_config = DatabaseConfig::new();
     let mut client_options = ClientOptions::parse(database_config.uri).await.unwrap();
     client_options.connect_timeout = database_config.connection_timeout;
     client_options.max_pool_size = database_config.max_pool_size;
     client_options.min_pool_size = database_config.min_pool_size;
     client_options.compressors = database_config.compressors;
+    if database_config.write_concern.is_some() {
+        client_options.write_concern = database_config.write_concern;
+    }
+    if database_config.read_concern.is_some() {
+        client_options.read_concern = database_config.read_concern;
+    }
     let client = Client::with_options(client_options).unwrap();
 
     client
@@ -114,21 +162,27 @@ async fn main() -> anyhow::Result<()> {
         .await?;
     info!("master Database ready");
 
-    if !std::path::Path::new("certificates/key.pem").is_file() {
+    if certificate_needs_renewal().await {
         info!("Generiere Zeritifikat");
-        let _ = generate_certificate().await;
+        let _ = generate_certificate(&CertificateConfig::new()).await;
     }
     let identity = Identity::load_pemfiles(
         std::path::Path::new("certificates/cert.pem"),
         std::path::Path::new("certificates/key.pem"),
     )
     .await?;
-    info!(
-        "Certificate hash: {}",
-        identity.certificate_chain().as_slice()[0]
-            .hash()
-            .fmt(Sha256DigestFmt::BytesArray)
-    );
+    let certificate_hash = identity.certificate_chain().as_slice()[0]
+        .hash()
+        .fmt(Sha256DigestFmt::BytesArray);
+    info!("Certificate hash: {}", certificate_hash);
+
+    match ensure_collections(&client).await {
+        Ok(_) => {}
+        Err(error_message) => {
+            error!("Error during collection setup: {}", error_message);
+            exit(1);
+        }
+    };
 
     match generate_elements(&client).await {
         Ok(_) => {}
@@ -138,12 +192,26 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    if let Err(error_message) = seed_demo_board(&client).await {
+        error!("Error during demo seeding: {}", error_message);
+        exit(1);
+    }
+    ready.store(true, Ordering::Relaxed);
+
+    start_guest_cleanup(client.clone());
+
+    let client_context = Arc::new(Mutex::new(ClientContext::new()));
+    start_client_cleanup(client.clone(), client_context.clone());
+
     let state = AppState {
         database_client: client,
         board_context: Arc::new(Mutex::new(BoardContext::new())),
         element_context: Arc::new(Mutex::new(ElementContext::new())),
-        client_context: Arc::new(Mutex::new(ClientContext::new())),
+        client_context,
         active_member_context: Arc::new(Mutex::new(ActiveMemberContext::new())),
+        session_limit: Arc::new(SessionLimit::new()),
+        certificate_hash: Arc::new(RwLock::new(certificate_hash)),
+        ready,
     };
 
     let webtransport_server = WebTransportServer::new(state.clone(), identity)?;
@@ -0,0 +1,49 @@
+use std::{env::var, sync::OnceLock};
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+};
+use ring::constant_time::verify_slices_are_equal;
+
+const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+// Admin endpoints reject every request as long as no `ADMIN_TOKEN` is configured
+#[allow(non_snake_case)]
+pub fn ADMIN_TOKEN() -> Option<&'static str> {
+    static ADMIN_TOKEN: OnceLock<Option<String>> = OnceLock::new();
+    ADMIN_TOKEN
+        .get_or_init(|| var("ADMIN_TOKEN").ok().filter(|token| !token.is_empty()))
+        .as_deref()
+}
+
+// Extracted for every `/admin/*` route through a route layer, so admin handlers do not check the
+// token themselves
+pub struct AdminAuthorized;
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AdminAuthorized {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(admin_token) = ADMIN_TOKEN() else {
+            return Err((StatusCode::UNAUTHORIZED, "Admin endpoints are disabled").into_response());
+        };
+        let token = parts
+            .headers
+            .get(ADMIN_TOKEN_HEADER)
+            .map(|header| header.as_bytes())
+            .unwrap_or_default();
+        // Compared in constant time, so response times do not reveal how much of a guess matched
+        match verify_slices_are_equal(token, admin_token.as_bytes()) {
+            Ok(()) => Ok(AdminAuthorized),
+            Err(_) => Err((
+                StatusCode::UNAUTHORIZED,
+                "Admin token is missing or invalid",
+            )
+                .into_response()),
+        }
+    }
+}
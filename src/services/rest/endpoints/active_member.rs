@@ -7,7 +7,7 @@ use axum::{
     routing::{delete, get, post, put},
     Router,
 };
-use bson::{doc, oid::ObjectId};
+use bson::{doc, oid::ObjectId, DateTime};
 use futures::TryStreamExt;
 use tracing::info;
 
@@ -17,31 +17,46 @@ use crate::{
             active_member::{ActiveMember, CreateActiveMember, UpdateActiveMember},
             board::Board,
             element::{Element, UpdateElement},
+            element_history::{ElementHistory, ElementHistoryAction},
         },
         document::Document,
     },
     services::webtransport::{
-        context::active_member::{ActiveMemberEvent, ActiveMemberEventType},
-        messages::active_member::{
-            CreatedActiveMemberEventPayload, RemovedActiveMemberEventPayload,
-            UpdatedPositionEventPayload,
+        context::{
+            active_member::{ActiveMemberEvent, ActiveMemberEventType},
+            element::{ElementEvent, ElementEventType},
         },
+        messages::{
+            active_member::{
+                CreatedActiveMemberEventPayload, RemovedActiveMemberEventPayload,
+                UpdatedPositionEventPayload,
+            },
+            element::ElementUnlockedEventPayload,
+        },
+    },
+    utils::{
+        check_request_body::check_request_body, inserted_object_id::inserted_object_id_hex,
+        serialize_or_log::serialize_or_log,
     },
-    utils::check_request_body::check_request_body,
     AppState,
 };
 
 use super::super::payloads::active_member::{
-    ChangeActiveBoardPayload, CreateActiveMemberPayload, UpdatePostionPayload,
+    ChangeActiveBoardPayload, ClearActiveMembersPayload, CreateActiveMemberPayload,
+    UpdatePostionPayload, UserActiveMemberResponsePayload,
 };
 
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .route("/active-member", post(create_active_member))
         .route("/active-member/:id", get(get_active_member))
+        .route(
+            "/active-member/user/:userId",
+            get(get_active_member_of_user),
+        )
         .route(
             "/active-member/board/:boardId",
-            get(get_active_members_for_board),
+            get(get_active_members_for_board).delete(clear_active_members_of_board),
         )
         .route(
             "/active-member/:id/board/:boardId",
@@ -65,13 +80,14 @@ async fn create_active_member(
         Ok(success_body) => success_body,
         Err(error_response) => return error_response,
     };
-    let is_part_of_board =
-        match Board::get_existing_board(body.board_id.clone(), &database_client).await {
-            Ok(board) => board.allowed_members.contains(&body.user_id),
-            Err(error_response) => return error_response,
-        };
-    if !is_part_of_board {
-        return (StatusCode::FORBIDDEN, "User is not part of this board").into_response();
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
     }
     let query_doc = doc! {
         "userId": body.user_id.clone(),
@@ -84,6 +100,7 @@ async fn create_active_member(
         }
         Err(error_response) => return error_response,
     };
+    let created_at = DateTime::now();
     let create_active_member_result = ActiveMember::create_document(
         &database_client,
         CreateActiveMember {
@@ -91,28 +108,33 @@ async fn create_active_member(
             board_id: body.board_id.clone(),
             x: 0.0,
             y: 0.0,
+            created_at,
         },
     )
     .await;
     match create_active_member_result {
         Ok(result) => {
-            let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+            let inserted_id = match inserted_object_id_hex(&result) {
+                Ok(inserted_id) => inserted_id,
+                Err(error_response) => return error_response,
+            };
             info!("Created Active Member with ID: {}", inserted_id);
             let mut sub_context = active_member_context.lock().await;
-            sub_context
-                .emit_active_member_event(
-                    body.board_id.clone(),
-                    ActiveMemberEvent {
-                        event_type: ActiveMemberEventType::Created,
-                        body: serde_json::to_string(&CreatedActiveMemberEventPayload {
-                            _id: inserted_id.clone(),
-                            board_id: body.board_id.clone(),
-                            user_id: body.user_id.clone(),
-                        })
-                        .unwrap(),
-                    },
-                )
-                .await;
+            if let Ok(event_body) = serialize_or_log(&CreatedActiveMemberEventPayload {
+                _id: inserted_id.clone(),
+                board_id: body.board_id.clone(),
+                user_id: body.user_id.clone(),
+            }) {
+                sub_context
+                    .emit_active_member_event(
+                        body.board_id.clone(),
+                        ActiveMemberEvent {
+                            event_type: ActiveMemberEventType::Created,
+                            body: event_body,
+                        },
+                    )
+                    .await;
+            }
             drop(sub_context);
             (
                 StatusCode::OK,
@@ -122,6 +144,9 @@ async fn create_active_member(
                     board_id: body.board_id.clone(),
                     x: 0.0,
                     y: 0.0,
+                    created_at,
+                    position_updated_at: None,
+                    last_seen_at: None,
                 }),
             )
                 .into_response()
@@ -151,6 +176,40 @@ async fn get_active_member(
     }
 }
 
+async fn get_active_member_of_user(
+    Path(user_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let active_member = match ActiveMember::get_existing_active_member_by_user_id(
+        user_id,
+        &database_client,
+    )
+    .await
+    {
+        Ok(active_member) => active_member,
+        Err(error_response) => return error_response,
+    };
+    let board_name = match ObjectId::from_str(active_member.board_id.as_str()) {
+        Ok(board_object_id) => {
+            match Board::get_document(&database_client, doc! { "_id": board_object_id }).await {
+                Ok(board_option) => board_option.map(|board| board.name),
+                Err(error_response) => return error_response,
+            }
+        }
+        Err(_) => None,
+    };
+    (
+        StatusCode::OK,
+        Json(UserActiveMemberResponsePayload {
+            active_member,
+            board_name,
+        }),
+    )
+        .into_response()
+}
+
 async fn get_active_members_for_board(
     Path(board_id): Path<String>,
     State(AppState {
@@ -188,7 +247,7 @@ async fn get_active_members_for_board(
 }
 
 async fn delete_active_member(
-    Path((user_id, board_id)): Path<(String, String)>,
+    Path((user_id, _board_id)): Path<(String, String)>,
     State(AppState {
         database_client,
         active_member_context,
@@ -199,78 +258,188 @@ async fn delete_active_member(
        "userId": user_id.clone(),
     };
     let delete_active_member_result =
-        ActiveMember::delete_document(&database_client, query_doc).await;
+        ActiveMember::find_one_and_delete(&database_client, query_doc).await;
     match delete_active_member_result {
-        Ok(result) => {
-            info!("Deleted {} Active Members", result.deleted_count);
-            match result.deleted_count {
-                0 => (StatusCode::NOT_FOUND, "No Active Member found to delete").into_response(),
-                _ => {
-                    let query_doc = doc! {
-                        "lockedBy": user_id.clone(),
-                    };
-                    match Element::update_document(
-                        &database_client,
-                        query_doc,
-                        UpdateElement {
-                            x: None,
-                            y: None,
-                            scale_y: None,
-                            scale_x: None,
-                            text: None,
-                            color: None,
-                            z_index: None,
-                            selected: None,
-                            rotation: None,
-                            locked_by: Some(None),
-                        },
-                    )
-                    .await
-                    {
-                        Ok(_) => {}
-                        Err(error_response) => return error_response,
-                    };
-                    let mut sub_context = active_member_context.lock().await;
+        Ok(result) => match result {
+            None => (StatusCode::NOT_FOUND, "No Active Member found to delete").into_response(),
+            Some(removed_active_member) => {
+                info!("Deleted Active Member {}", removed_active_member._id);
+                let query_doc = doc! {
+                    "lockedBy": user_id.clone(),
+                };
+                match Element::update_document(
+                    &database_client,
+                    query_doc,
+                    UpdateElement {
+                        x: None,
+                        y: None,
+                        scale_y: None,
+                        scale_x: None,
+                        text: None,
+                        color: None,
+                        z_index: None,
+                        selected: None,
+                        rotation: None,
+                        locked_by: Some(None),
+                        metadata: None,
+                    },
+                )
+                .await
+                {
+                    Ok(_) => {}
+                    Err(error_response) => return error_response,
+                };
+                let mut sub_context = active_member_context.lock().await;
+                sub_context.clear_activity(user_id.clone()).await;
+                if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+                    user_id: user_id.clone(),
+                }) {
                     sub_context
                         .emit_active_member_event(
-                            board_id.clone(),
+                            removed_active_member.board_id,
                             ActiveMemberEvent {
                                 event_type: ActiveMemberEventType::Removed,
-                                body: serde_json::to_string(&RemovedActiveMemberEventPayload {
-                                    user_id: user_id.clone(),
-                                })
-                                .unwrap(),
+                                body: event_body,
                             },
                         )
                         .await;
-                    drop(sub_context);
-                    (StatusCode::OK, Json(format!("{}", result.deleted_count))).into_response()
                 }
+                drop(sub_context);
+                (StatusCode::OK, Json("1".to_string())).into_response()
             }
-        }
+        },
         Err(error_response) => error_response,
     }
 }
 
-async fn change_active_board(
+async fn clear_active_members_of_board(
+    Path(board_id): Path<String>,
     State(AppState {
         database_client,
+        element_context,
         active_member_context,
         ..
     }): State<AppState>,
-    payload: Result<Json<ChangeActiveBoardPayload>, JsonRejection>,
+    payload: Result<Json<ClearActiveMembersPayload>, JsonRejection>,
 ) -> Response {
     let body = match check_request_body(payload) {
         Ok(success_body) => success_body,
         Err(error_response) => return error_response,
     };
-    let is_part_of_board =
-        match Board::get_existing_board(body.new_board_id.clone(), &database_client).await {
-            Ok(board) => board.allowed_members.contains(&body.user_id),
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    if board.host != body.host_id {
+        return (
+            StatusCode::FORBIDDEN,
+            "Only the host can clear the active members",
+        )
+            .into_response();
+    }
+    let query_doc = doc! {
+        "boardId": board_id.clone(),
+    };
+    let user_ids = match ActiveMember::get_multiple_documents(&database_client, query_doc).await {
+        Ok(active_member_cursor) => match active_member_cursor
+            .try_collect::<Vec<ActiveMember>>()
+            .await
+        {
+            Ok(active_members) => active_members
+                .into_iter()
+                .map(|active_member| active_member.user_id)
+                .collect::<Vec<String>>(),
+            Err(_) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Found Active Members could not be retrieved",
+                )
+                    .into_response()
+            }
+        },
+        Err(error_response) => return error_response,
+    };
+    let deleted_count =
+        match ActiveMember::delete_all_of_board(board_id.clone(), &database_client).await {
+            Ok(result) => result.deleted_count,
+            Err(error_response) => return error_response,
+        };
+    info!(
+        "Deleted {} Active Members of Board {}",
+        deleted_count, board_id
+    );
+    let unlocked_ids =
+        match Element::unlock_all_locked_by(&database_client, board_id.clone(), &user_ids).await {
+            Ok(unlocked_ids) => unlocked_ids,
             Err(error_response) => return error_response,
         };
-    if !is_part_of_board {
-        return (StatusCode::FORBIDDEN, "User is not part of the new board").into_response();
+    ElementHistory::record_multiple(
+        &database_client,
+        &unlocked_ids,
+        board_id.clone(),
+        body.host_id.clone(),
+        ElementHistoryAction::Unlocked,
+        None,
+    )
+    .await;
+    let mut element_sub_context = element_context.lock().await;
+    for element_id in unlocked_ids.iter() {
+        if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+            _id: element_id.clone(),
+        }) {
+            element_sub_context
+                .emit_element_event(
+                    board_id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Unlocked,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(element_sub_context);
+    let mut sub_context = active_member_context.lock().await;
+    for user_id in user_ids.iter() {
+        sub_context.clear_activity(user_id.clone()).await;
+        if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+            user_id: user_id.clone(),
+        }) {
+            sub_context
+                .emit_active_member_event(
+                    board_id.clone(),
+                    ActiveMemberEvent {
+                        event_type: ActiveMemberEventType::Removed,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(sub_context);
+    (StatusCode::OK, Json(deleted_count)).into_response()
+}
+
+async fn change_active_board(
+    State(AppState {
+        database_client,
+        active_member_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<ChangeActiveBoardPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    if let Err(error_response) = Board::assert_member(
+        body.new_board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
     }
     let query_doc = doc! {
        "userId": body.user_id.clone(),
@@ -295,6 +464,7 @@ async fn change_active_board(
             board_id: Some(body.new_board_id.clone()),
             x: Some(0.0),
             y: Some(0.0),
+            position_updated_at: None,
         },
     )
     .await;
@@ -307,32 +477,35 @@ async fn change_active_board(
                     body.user_id.clone(),
                 );
                 let mut sub_context = active_member_context.lock().await;
-                sub_context
-                    .emit_active_member_event(
-                        old_board_id,
-                        ActiveMemberEvent {
-                            event_type: ActiveMemberEventType::Removed,
-                            body: serde_json::to_string(&RemovedActiveMemberEventPayload {
-                                user_id: body.user_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
-                sub_context
-                    .emit_active_member_event(
-                        body.new_board_id.clone(),
-                        ActiveMemberEvent {
-                            event_type: ActiveMemberEventType::Created,
-                            body: serde_json::to_string(&CreatedActiveMemberEventPayload {
-                                _id: current_active_member._id.clone(),
-                                user_id: body.user_id.clone(),
-                                board_id: body.new_board_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                sub_context.clear_activity(body.user_id.clone()).await;
+                if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+                    user_id: body.user_id.clone(),
+                }) {
+                    sub_context
+                        .emit_active_member_event(
+                            old_board_id,
+                            ActiveMemberEvent {
+                                event_type: ActiveMemberEventType::Removed,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                if let Ok(event_body) = serialize_or_log(&CreatedActiveMemberEventPayload {
+                    _id: current_active_member._id.clone(),
+                    user_id: body.user_id.clone(),
+                    board_id: body.new_board_id.clone(),
+                }) {
+                    sub_context
+                        .emit_active_member_event(
+                            body.new_board_id.clone(),
+                            ActiveMemberEvent {
+                                event_type: ActiveMemberEventType::Created,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
                 current_active_member
                     .board_id
@@ -366,6 +539,7 @@ async fn update_position(
             x: Some(body.x),
             y: Some(body.y),
             board_id: None,
+            position_updated_at: None,
         },
     )
     .await;
@@ -378,20 +552,21 @@ async fn update_position(
                     body.user_id.clone(),
                 );
                 let mut sub_context = active_member_context.lock().await;
-                sub_context
-                    .emit_active_member_event(
-                        body.board_id.clone(),
-                        ActiveMemberEvent {
-                            event_type: ActiveMemberEventType::PositionUpdated,
-                            body: serde_json::to_string(&UpdatedPositionEventPayload {
-                                user_id: body.user_id.clone(),
-                                x: body.x,
-                                y: body.y,
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&UpdatedPositionEventPayload {
+                    user_id: body.user_id.clone(),
+                    x: body.x,
+                    y: body.y,
+                }) {
+                    sub_context
+                        .emit_active_member_event(
+                            body.board_id.clone(),
+                            ActiveMemberEvent {
+                                event_type: ActiveMemberEventType::PositionUpdated,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
                 (StatusCode::OK, Json(body.user_id.clone())).into_response()
             }
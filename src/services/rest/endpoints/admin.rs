@@ -0,0 +1,441 @@
+use std::str::FromStr;
+
+use axum::{
+    extract::{rejection::JsonRejection, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use bson::oid::ObjectId;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    database::collections::{
+        active_member::ActiveMember, board::Board, element::Element, element_type::ElementType,
+        user::User,
+    },
+    services::{
+        rest::{
+            admin_auth::{AdminAuthorized, ADMIN_TOKEN},
+            payloads::{
+                admin::{
+                    BoardEncryptionResponsePayload, BroadcastPayload,
+                    DeletedOrphansResponsePayload, OffsetPageResponsePayload, PageResponsePayload,
+                    SetBoardEncryptionPayload, SetBoardTemplatePayload,
+                    UnusedElementTypesResponsePayload,
+                },
+                user::UserResponsePayload,
+            },
+        },
+        webtransport::{
+            context::{
+                active_member::{ActiveMemberEvent, ActiveMemberEventType},
+                base::broadcast_event,
+                board::{BoardEvent, BoardEventType},
+                client::{ClientEvent, ClientEventType},
+                element::{ElementEvent, ElementEventType},
+            },
+            messages::server::ServerAnnouncementEventPayload,
+        },
+    },
+    utils::{
+        check_request_body::check_request_body, serialize_or_log::serialize_or_log,
+        text_encryption::is_encryption_configured,
+    },
+    AppState,
+};
+
+const DEFAULT_PAGE_LIMIT: i64 = 100;
+const MAX_PAGE_LIMIT: i64 = 1000;
+
+pub fn get_routes() -> Router<AppState> {
+    if ADMIN_TOKEN().is_none() {
+        warn!("`ADMIN_TOKEN` is not set, all admin endpoints reject requests");
+    }
+    Router::new()
+        .route(
+            "/admin/orphans",
+            get(get_orphaned_elements).delete(delete_orphaned_elements),
+        )
+        .route("/admin/broadcast", post(broadcast_announcement))
+        .route(
+            "/admin/element-types/unused",
+            delete(delete_unused_element_types),
+        )
+        .route("/admin/users", get(get_users_page))
+        .route("/admin/boards", get(get_boards_page))
+        .route("/admin/boards/summary", get(get_board_summaries))
+        .route("/admin/active-boards", get(get_active_boards))
+        .route("/admin/boards/:id/encryption", put(set_board_encryption))
+        .route("/admin/boards/:id/template", put(set_board_template))
+        .route_layer(middleware::from_extractor::<AdminAuthorized>())
+}
+
+// Admin services ========================================
+
+async fn get_orphaned_elements(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    match Element::get_orphaned(&database_client).await {
+        Ok(orphaned_elements) => (StatusCode::OK, Json(orphaned_elements)).into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+async fn delete_orphaned_elements(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let orphaned_elements = match Element::get_orphaned(&database_client).await {
+        Ok(orphaned_elements) => orphaned_elements,
+        Err(error_response) => return error_response,
+    };
+    let mut element_ids = orphaned_elements.missing_board;
+    element_ids.extend(orphaned_elements.missing_element_type);
+    element_ids.sort();
+    element_ids.dedup();
+    if element_ids.is_empty() {
+        return (
+            StatusCode::OK,
+            Json(DeletedOrphansResponsePayload { deleted_count: 0 }),
+        )
+            .into_response();
+    }
+    match Element::delete_by_ids(&database_client, &element_ids).await {
+        Ok(result) => {
+            info!("Deleted {} orphaned Elements", result.deleted_count);
+            (
+                StatusCode::OK,
+                Json(DeletedOrphansResponsePayload {
+                    deleted_count: result.deleted_count,
+                }),
+            )
+                .into_response()
+        }
+        Err(error_response) => error_response,
+    }
+}
+
+async fn broadcast_announcement(
+    State(AppState {
+        board_context,
+        element_context,
+        client_context,
+        active_member_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<BroadcastPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    if body.message.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Announcement message is empty").into_response();
+    }
+    let announcement_id = ObjectId::new().to_hex();
+    let event_body = match serialize_or_log(&ServerAnnouncementEventPayload {
+        id: announcement_id.clone(),
+        level: body.level.clone(),
+        message: body.message.clone(),
+    }) {
+        Ok(event_body) => event_body,
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Announcement could not be serialized",
+            )
+                .into_response()
+        }
+    };
+    let mut sub_context = board_context.lock().await;
+    broadcast_event(
+        sub_context
+            .board_subjects
+            .values_mut()
+            .map(|subject| &mut subject.subject),
+        BoardEvent {
+            event_type: BoardEventType::Announcement,
+            body: event_body.clone(),
+        },
+    );
+    drop(sub_context);
+    let mut sub_context = element_context.lock().await;
+    broadcast_event(
+        sub_context
+            .board_element_subjects
+            .values_mut()
+            .map(|subject| &mut subject.subject),
+        ElementEvent {
+            event_type: ElementEventType::Announcement,
+            body: event_body.clone(),
+        },
+    );
+    drop(sub_context);
+    let mut sub_context = client_context.lock().await;
+    broadcast_event(
+        sub_context
+            .client_subjects
+            .values_mut()
+            .map(|subject| &mut subject.subject),
+        ClientEvent {
+            event_type: ClientEventType::Announcement,
+            body: event_body.clone(),
+        },
+    );
+    drop(sub_context);
+    let mut sub_context = active_member_context.lock().await;
+    broadcast_event(
+        sub_context
+            .board_active_member_subjects
+            .values_mut()
+            .map(|subject| &mut subject.subject),
+        ActiveMemberEvent {
+            event_type: ActiveMemberEventType::Announcement,
+            body: event_body,
+        },
+    );
+    drop(sub_context);
+    info!("Broadcasted announcement {}", announcement_id);
+    (StatusCode::OK, Json(announcement_id)).into_response()
+}
+
+#[derive(Deserialize)]
+struct PurgeQueryParams {
+    apply: Option<bool>,
+}
+
+// Only previews the unused Element Types unless `apply=true` is passed
+async fn delete_unused_element_types(
+    query_params: Query<PurgeQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let unused_element_types = match ElementType::get_unused(&database_client).await {
+        Ok(unused_element_types) => unused_element_types,
+        Err(error_response) => return error_response,
+    };
+    let apply = query_params.apply.unwrap_or(false);
+    if apply && !unused_element_types.is_empty() {
+        let element_type_ids = unused_element_types
+            .iter()
+            .map(|element_type| element_type._id.clone())
+            .collect::<Vec<String>>();
+        match ElementType::delete_by_ids(&database_client, &element_type_ids).await {
+            Ok(result) => info!("Deleted {} unused Element Types", result.deleted_count),
+            Err(error_response) => return error_response,
+        }
+    }
+    (
+        StatusCode::OK,
+        Json(UnusedElementTypesResponsePayload {
+            applied: apply,
+            element_types: unused_element_types,
+        }),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct PageQueryParams {
+    after: Option<String>,
+    limit: Option<i64>,
+}
+
+fn page_limit(query_params: &PageQueryParams) -> i64 {
+    query_params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT)
+}
+
+fn next_cursor(page_len: usize, limit: i64, last_id: Option<&String>) -> Option<String> {
+    match page_len as i64 == limit {
+        true => last_id.cloned(),
+        false => None,
+    }
+}
+
+async fn get_users_page(
+    query_params: Query<PageQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let after = match query_params
+        .after
+        .as_deref()
+        .map(ObjectId::from_str)
+        .transpose()
+    {
+        Ok(after) => after,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Cursor `after` is invalid").into_response(),
+    };
+    let limit = page_limit(&query_params);
+    match User::get_page(&database_client, after, limit).await {
+        Ok(users) => (
+            StatusCode::OK,
+            Json(PageResponsePayload {
+                next_cursor: next_cursor(users.len(), limit, users.last().map(|user| &user._id)),
+                items: users
+                    .into_iter()
+                    .map(|user| UserResponsePayload {
+                        id: user._id,
+                        name: user.name,
+                        email: user.email,
+                    })
+                    .collect(),
+            }),
+        )
+            .into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+async fn get_boards_page(
+    query_params: Query<PageQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let after = match query_params
+        .after
+        .as_deref()
+        .map(ObjectId::from_str)
+        .transpose()
+    {
+        Ok(after) => after,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Cursor `after` is invalid").into_response(),
+    };
+    let limit = page_limit(&query_params);
+    match Board::get_page(&database_client, after, limit).await {
+        Ok(boards) => (
+            StatusCode::OK,
+            Json(PageResponsePayload {
+                next_cursor: next_cursor(
+                    boards.len(),
+                    limit,
+                    boards.last().map(|board| &board._id),
+                ),
+                items: boards,
+            }),
+        )
+            .into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+#[derive(Deserialize)]
+struct OffsetPageQueryParams {
+    offset: Option<u64>,
+    limit: Option<i64>,
+}
+
+async fn get_board_summaries(
+    query_params: Query<OffsetPageQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let offset = query_params.offset.unwrap_or(0);
+    let limit = query_params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    match Board::get_summaries(&database_client, offset, limit).await {
+        Ok(board_summaries) => (
+            StatusCode::OK,
+            Json(OffsetPageResponsePayload {
+                next_offset: (board_summaries.len() as i64 == limit)
+                    .then_some(offset + limit as u64),
+                items: board_summaries,
+            }),
+        )
+            .into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+async fn get_active_boards(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    match ActiveMember::get_active_boards(&database_client).await {
+        Ok(active_boards) => (StatusCode::OK, Json(active_boards)).into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+// Switches the encryption of the element texts of a board and rewrites the stored texts, enabling it
+// again on an encrypted board re-encrypts the texts with the current secret after a rotation
+async fn set_board_encryption(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+    payload: Result<Json<SetBoardEncryptionPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    if body.encrypted && !is_encryption_configured() {
+        return (
+            StatusCode::CONFLICT,
+            "Element text encryption is not configured",
+        )
+            .into_response();
+    }
+    let (rewritten_elements, rewritten_history_entries) =
+        match Board::switch_encryption(&board_id, body.encrypted, &database_client).await {
+            Ok(rewritten) => rewritten,
+            Err(error_response) => return error_response,
+        };
+    info!(
+        "Set encryption of Board {} to {}, rewrote {} Elements and {} History entries",
+        board_id, body.encrypted, rewritten_elements, rewritten_history_entries
+    );
+    (
+        StatusCode::OK,
+        Json(BoardEncryptionResponsePayload {
+            encrypted: body.encrypted,
+            rewritten_elements,
+            rewritten_history_entries,
+        }),
+    )
+        .into_response()
+}
+
+async fn set_board_template(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+    payload: Result<Json<SetBoardTemplatePayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    match Board::set_template(&board_id, body.is_template, &database_client).await {
+        Ok(result) => match result.matched_count {
+            0 => (StatusCode::NOT_FOUND, "Board does not exist").into_response(),
+            _ => {
+                info!(
+                    "Set template flag of Board {} to {}",
+                    board_id, body.is_template
+                );
+                (StatusCode::OK, Json(body.is_template)).into_response()
+            }
+        },
+        Err(error_response) => error_response,
+    }
+}
@@ -1,45 +1,101 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use axum::{
-    extract::{rejection::JsonRejection, Path, State},
+    extract::{rejection::JsonRejection, Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Json, Router,
 };
-use bson::{doc, oid::ObjectId};
+use bson::{doc, oid::ObjectId, DateTime};
 use futures::TryStreamExt;
+use mongodb::Client;
+use serde::Deserialize;
 use tracing::{error, info};
 
 use crate::{
     database::{
         collections::{
-            board::{Board, CreateBoard, UpdateBoard},
-            element::Element,
+            active_member::ActiveMember,
+            board::{Board, BoardSettings, CreateBoard, UpdateBoard},
+            element::{
+                CreateElement, Element, ElementConnector, UpdateElement, MAX_ELEMENTS_PER_BOARD,
+            },
+            element_history::{ElementHistory, ElementHistoryAction},
+            user::User,
         },
         document::Document,
     },
     services::webtransport::{
-        context::board::{BoardEvent, BoardEventType},
-        messages::board::{MemberAddedEventPayload, MemberRemovedEventPayload},
+        context::{
+            active_member::{ActiveMemberEvent, ActiveMemberEventType},
+            board::{BoardEvent, BoardEventType},
+            client::{ClientEvent, ClientEventType},
+            element::{ElementEvent, ElementEventType},
+        },
+        messages::{
+            active_member::RemovedActiveMemberEventPayload,
+            board::BoardEmptiedEventPayload,
+            board::{
+                emit_near_limit_if_reached, MemberAddedEventPayload, MemberKickedEventPayload,
+                MemberRemovedEventPayload, ViewerAddedEventPayload, ViewerRemovedEventPayload,
+            },
+            client::ClientKickedPayload,
+            element::{
+                remove_connectors_of, ElementRemovedEventPayload, ElementUnlockedEventPayload,
+            },
+        },
+    },
+    utils::{
+        check_request_body::check_request_body, inserted_object_id::inserted_object_id_hex,
+        serialize_or_log::serialize_or_log, text_encryption::is_encryption_configured,
     },
-    utils::check_request_body::check_request_body,
     AppState,
 };
 
-use super::super::payloads::board::CreateBoardRequestPayload;
+use super::super::payloads::board::{
+    BoardMemberResponsePayload, BoardSettingsResponsePayload, CloneBoardPayload,
+    CreateBoardFromTemplatePayload, CreateBoardRequestPayload, DeletedElementsResponsePayload,
+    KickMemberPayload, UpdateBoardSettingsPayload, UpdateBoardVisibilityPayload,
+};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 50;
+const MAX_SEARCH_LIMIT: i64 = 200;
 
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .route("/board/:id", get(get_board))
         .route("/board/:id/elements", get(get_all_elements_of_board))
+        .route("/board/:id/elements/search", get(search_elements_of_board))
         .route("/board", post(create_board))
+        .route("/board/:boardId/members", get(get_board_members))
+        .route("/board/:boardId/visibility", put(update_board_visibility))
+        .route(
+            "/board/:boardId/settings",
+            get(get_board_settings).put(update_board_settings),
+        )
         .route("/board/:boardId/allowed-member/:userId", put(add_member))
         .route(
             "/board/:boardId/allowed-member/:userId",
             delete(remove_member),
         )
+        .route(
+            "/board/:boardId/viewer/:userId",
+            put(add_viewer).delete(remove_viewer),
+        )
+        .route("/board/:boardId/kick/:userId", post(kick_member))
+        .route(
+            "/board/:boardId/elements/by-user/:userId",
+            delete(delete_elements_created_by_user),
+        )
         .route("/boards/:userId", get(get_all_boards_with_user))
+        .route("/templates", get(get_templates))
+}
+
+pub fn get_long_running_routes() -> Router<AppState> {
+    Router::new()
+        .route("/board/:id/clone", post(clone_board))
+        .route("/board/from-template", post(create_board_from_template))
 }
 
 // Board services ============================================
@@ -54,18 +110,47 @@ async fn create_board(
         Ok(success_body) => success_body,
         Err(err_response) => return err_response,
     };
+    if let Err(message) = Board::validate_name(&body.name) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    match User::is_guest_user(&body.host, &database_client).await {
+        Ok(true) => {
+            return (StatusCode::FORBIDDEN, "Guests cannot create boards").into_response();
+        }
+        Ok(false) => {}
+        Err(error_response) => return error_response,
+    }
+    match Board::is_name_taken(&body.host, &body.name, None, &database_client).await {
+        Ok(true) => {
+            return (
+                StatusCode::CONFLICT,
+                "Host already has a board with this name",
+            )
+                .into_response();
+        }
+        Ok(false) => {}
+        Err(error_response) => return error_response,
+    }
     let create_board_result = Board::create_document(
         &database_client,
         CreateBoard {
             name: body.name.to_string(),
             host: body.host.to_string(),
             allowed_members: vec![body.host.to_string()],
+            viewers: vec![],
+            is_public: body.is_public,
+            settings: BoardSettings::default(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
         },
     )
     .await;
     match create_board_result {
         Ok(result) => {
-            let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+            let inserted_id = match inserted_object_id_hex(&result) {
+                Ok(inserted_id) => inserted_id,
+                Err(error_response) => return error_response,
+            };
             info!("Created Board with ID: {}", inserted_id);
             (StatusCode::OK, Json(inserted_id)).into_response()
         }
@@ -73,6 +158,238 @@ async fn create_board(
     }
 }
 
+async fn clone_board(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client,
+        board_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<CloneBoardPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(err_response) => return err_response,
+    };
+    if let Some(name) = &body.name {
+        if let Err(message) = Board::validate_name(name) {
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    }
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    if !board.allowed_members.contains(&body.user_id) {
+        return (StatusCode::FORBIDDEN, "User is not part of this board").into_response();
+    }
+    let board_name = body
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{} (Copy)", board.name));
+    match copy_board_for_user(&board, &body.user_id, board_name, &database_client).await {
+        Ok(new_board_id) => {
+            emit_near_limit_if_reached(&database_client, &board_context, &new_board_id).await;
+            info!("Cloned Board {} into Board {}", board._id, new_board_id);
+            (StatusCode::OK, Json(new_board_id)).into_response()
+        }
+        Err(error_response) => error_response,
+    }
+}
+
+// Private templates can only be used by their members, public ones by everyone
+async fn create_board_from_template(
+    State(AppState {
+        database_client,
+        board_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<CreateBoardFromTemplatePayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(err_response) => return err_response,
+    };
+    if let Some(name) = &body.name {
+        if let Err(message) = Board::validate_name(name) {
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    }
+    let template = match Board::get_existing_board(body.template_id.clone(), &database_client).await
+    {
+        Ok(template) => template,
+        Err(error_response) => return error_response,
+    };
+    if !template.is_template {
+        return (StatusCode::BAD_REQUEST, "Board is not a template").into_response();
+    }
+    if !template.is_public && !template.allowed_members.contains(&body.user_id) {
+        return (StatusCode::FORBIDDEN, "User is not part of this template").into_response();
+    }
+    let board_name = body.name.clone().unwrap_or_else(|| template.name.clone());
+    match copy_board_for_user(&template, &body.user_id, board_name, &database_client).await {
+        Ok(new_board_id) => {
+            emit_near_limit_if_reached(&database_client, &board_context, &new_board_id).await;
+            info!(
+                "Created Board {} from template {}",
+                new_board_id, template._id
+            );
+            (StatusCode::OK, Json(new_board_id)).into_response()
+        }
+        Err(error_response) => error_response,
+    }
+}
+
+async fn get_templates(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    match Board::get_public_templates(&database_client).await {
+        Ok(templates) => (StatusCode::OK, Json(templates)).into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+// Copies the board with all of its elements into a new private board hosted by the user, used for
+// clones and for boards created from templates
+async fn copy_board_for_user(
+    board: &Board,
+    user_id: &str,
+    board_name: String,
+    database_client: &Client,
+) -> Result<String, Response> {
+    match User::is_guest_user(user_id, database_client).await {
+        Ok(true) => {
+            return Err((StatusCode::FORBIDDEN, "Guests cannot create boards").into_response());
+        }
+        Ok(false) => {}
+        Err(error_response) => return Err(error_response),
+    }
+    let query_doc = doc! {
+        "boardId": board._id.clone(),
+    };
+    let elements = match Element::get_multiple_documents(database_client, query_doc).await {
+        Ok(element_cursor) => match element_cursor.try_collect::<Vec<Element>>().await {
+            Ok(retrieved_elements) => retrieved_elements,
+            Err(_) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Found Elements could not be retrieved",
+                )
+                    .into_response());
+            }
+        },
+        Err(error_response) => return Err(error_response),
+    };
+    if elements.len() as u64 > board.settings.element_limit() {
+        return Err((StatusCode::CONFLICT, "Board exceeds the element limit").into_response());
+    }
+    match Board::is_name_taken(user_id, &board_name, None, database_client).await {
+        Ok(true) => {
+            return Err((
+                StatusCode::CONFLICT,
+                "Host already has a board with this name",
+            )
+                .into_response());
+        }
+        Ok(false) => {}
+        Err(error_response) => return Err(error_response),
+    }
+    let create_board_result = Board::create_document(
+        database_client,
+        CreateBoard {
+            name: board_name,
+            host: user_id.to_string(),
+            allowed_members: vec![user_id.to_string()],
+            viewers: vec![],
+            is_public: false,
+            settings: board.settings.clone(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        },
+    )
+    .await;
+    let new_board_id = match create_board_result {
+        Ok(result) => match inserted_object_id_hex(&result) {
+            Ok(inserted_id) => inserted_id,
+            Err(error_response) => return Err(error_response),
+        },
+        Err(error_response) => return Err(error_response),
+    };
+    // Copies of an encrypted board keep their texts encrypted
+    if board.encrypted {
+        if let Err(error_response) =
+            Board::set_encrypted(&new_board_id, true, database_client).await
+        {
+            let query_doc = doc! {
+                "_id": ObjectId::from_str(new_board_id.as_str()).unwrap(),
+            };
+            let _ = Board::delete_document(database_client, query_doc).await;
+            return Err(error_response);
+        }
+    }
+    if !elements.is_empty() {
+        // Copies get new IDs up front, so connectors can point to the copied elements
+        let copied_ids_by_original = elements
+            .iter()
+            .map(|element| (element._id.clone(), ObjectId::new().to_hex()))
+            .collect::<HashMap<String, String>>();
+        let copied_elements = elements
+            .into_iter()
+            .map(|element| CreateElement {
+                _id: copied_ids_by_original[&element._id].clone(),
+                selected: false,
+                locked_by: None,
+                x: element.x,
+                y: element.y,
+                rotation: element.rotation,
+                scale_x: element.scale_x,
+                scale_y: element.scale_y,
+                z_index: element.z_index,
+                created_at: DateTime::now(),
+                text: element.text,
+                element_type: element.element_type,
+                board_id: new_board_id.clone(),
+                color: element.color,
+                metadata: element.metadata,
+                asset_id: element.asset_id,
+                created_by: user_id.to_string(),
+                updated_at: DateTime::now(),
+                connector: element.connector.and_then(|connector| {
+                    Some(ElementConnector {
+                        from: copied_ids_by_original.get(&connector.from)?.clone(),
+                        to: copied_ids_by_original.get(&connector.to)?.clone(),
+                    })
+                }),
+            })
+            .collect::<Vec<CreateElement>>();
+        let copied_ids = copied_elements
+            .iter()
+            .map(|element| element._id.clone())
+            .collect::<Vec<String>>();
+        if let Err(error_response) =
+            Element::create_many_documents(database_client, copied_elements).await
+        {
+            let query_doc = doc! {
+                "_id": ObjectId::from_str(new_board_id.as_str()).unwrap(),
+            };
+            let _ = Board::delete_document(database_client, query_doc).await;
+            return Err(error_response);
+        }
+        ElementHistory::record_multiple(
+            database_client,
+            &copied_ids,
+            new_board_id.clone(),
+            user_id.to_string(),
+            ElementHistoryAction::Created,
+            None,
+        )
+        .await;
+    }
+    Ok(new_board_id)
+}
+
 async fn get_board(
     Path(board_id): Path<String>,
     State(AppState {
@@ -112,6 +429,23 @@ async fn add_member(
             return error_response;
         }
     };
+    if !board.is_public || !board.settings.allow_guests {
+        match User::is_guest_user(&user_id, &database_client).await {
+            Ok(true) if !board.settings.allow_guests => {
+                return (
+                    StatusCode::FORBIDDEN,
+                    "Guests are not allowed on this board",
+                )
+                    .into_response();
+            }
+            Ok(true) => {
+                return (StatusCode::FORBIDDEN, "Guests can only join public boards")
+                    .into_response();
+            }
+            Ok(false) => {}
+            Err(error_response) => return error_response,
+        }
+    }
     match board.allowed_members.contains(&user_id) {
         true => {
             return (StatusCode::CONFLICT, "Member already part of this board").into_response();
@@ -130,6 +464,8 @@ async fn add_member(
             name: None,
             host: None,
             allowed_members: Some(current_allowed_members),
+            viewers: None,
+            is_public: None,
         },
     )
     .await;
@@ -142,20 +478,22 @@ async fn add_member(
                 .into_response(),
             _ => {
                 let mut sub_context = board_context.lock().await;
-                sub_context
-                    .emit_board_event(
-                        database_client.clone(),
-                        board._id,
-                        BoardEvent {
-                            event_type: BoardEventType::MemberAdded,
-                            body: serde_json::to_string(&MemberAddedEventPayload {
-                                user_id: user_id.to_string(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&MemberAddedEventPayload {
+                    user_id: user_id.to_string(),
+                }) {
+                    sub_context
+                        .emit_board_event(
+                            database_client.clone(),
+                            board._id,
+                            BoardEvent {
+                                event_type: BoardEventType::MemberAdded,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
+                User::touch_guest(&user_id, &database_client).await;
                 (StatusCode::OK, Json(user_id.clone())).into_response()
             }
         },
@@ -177,12 +515,9 @@ async fn remove_member(
             return error_response;
         }
     };
-    match board.allowed_members.contains(&user_id) {
-        false => {
-            return (StatusCode::CONFLICT, "Member not part of this board").into_response();
-        }
-        true => {}
-    };
+    if let Some(message) = board.member_removal_error(&user_id) {
+        return (StatusCode::CONFLICT, message).into_response();
+    }
     let mut current_allowed_members = board.allowed_members;
     let member_position = current_allowed_members
         .iter()
@@ -193,6 +528,8 @@ async fn remove_member(
         name: None,
         host: None,
         allowed_members: Some(current_allowed_members),
+        viewers: None,
+        is_public: None,
     };
     let query_doc = doc! {
         "_id": ObjectId::from_str(board_id.as_str()).unwrap(),
@@ -207,19 +544,20 @@ async fn remove_member(
                 .into_response(),
             _ => {
                 let mut sub_context = board_context.lock().await;
-                sub_context
-                    .emit_board_event(
-                        database_client.clone(),
-                        board._id,
-                        BoardEvent {
-                            event_type: BoardEventType::MemberRemoved,
-                            body: serde_json::to_string(&MemberRemovedEventPayload {
-                                user_id: user_id.to_string(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&MemberRemovedEventPayload {
+                    user_id: user_id.to_string(),
+                }) {
+                    sub_context
+                        .emit_board_event(
+                            database_client.clone(),
+                            board._id,
+                            BoardEvent {
+                                event_type: BoardEventType::MemberRemoved,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
                 (StatusCode::OK, Json(user_id.clone())).into_response()
             }
@@ -228,24 +566,621 @@ async fn remove_member(
     }
 }
 
-async fn get_all_boards_with_user(
-    Path(user_id): Path<String>,
+async fn add_viewer(
+    Path((board_id, user_id)): Path<(String, String)>,
     State(AppState {
-        database_client, ..
+        database_client,
+        board_context,
+        ..
+    }): State<AppState>,
+) -> Response {
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    if !board.is_public || !board.settings.allow_guests {
+        match User::is_guest_user(&user_id, &database_client).await {
+            Ok(true) if !board.settings.allow_guests => {
+                return (
+                    StatusCode::FORBIDDEN,
+                    "Guests are not allowed on this board",
+                )
+                    .into_response();
+            }
+            Ok(true) => {
+                return (StatusCode::FORBIDDEN, "Guests can only join public boards")
+                    .into_response();
+            }
+            Ok(false) => {}
+            Err(error_response) => return error_response,
+        }
+    }
+    if board.allowed_members.contains(&user_id) {
+        return (StatusCode::CONFLICT, "Member already part of this board").into_response();
+    }
+    if board.viewers.contains(&user_id) {
+        return (StatusCode::CONFLICT, "Viewer already part of this board").into_response();
+    }
+    let mut current_viewers = board.viewers;
+    current_viewers.push(user_id.clone());
+    let query_doc = doc! {
+        "_id": ObjectId::from_str(board_id.as_str()).unwrap(),
+    };
+    let result = Board::update_document(
+        &database_client,
+        query_doc,
+        UpdateBoard {
+            name: None,
+            host: None,
+            allowed_members: None,
+            viewers: Some(current_viewers),
+            is_public: None,
+        },
+    )
+    .await;
+    match result {
+        Ok(result) => match result.modified_count {
+            0 => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Viewer has not been added",
+            )
+                .into_response(),
+            _ => {
+                let mut sub_context = board_context.lock().await;
+                if let Ok(event_body) = serialize_or_log(&ViewerAddedEventPayload {
+                    user_id: user_id.to_string(),
+                }) {
+                    sub_context
+                        .emit_board_event(
+                            database_client.clone(),
+                            board._id,
+                            BoardEvent {
+                                event_type: BoardEventType::ViewerAdded,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(sub_context);
+                (StatusCode::OK, Json(user_id.clone())).into_response()
+            }
+        },
+        Err(error_response) => error_response,
+    }
+}
+
+async fn remove_viewer(
+    Path((board_id, user_id)): Path<(String, String)>,
+    State(AppState {
+        database_client,
+        board_context,
+        ..
+    }): State<AppState>,
+) -> Response {
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    if !board.viewers.contains(&user_id) {
+        return (StatusCode::CONFLICT, "Viewer not part of this board").into_response();
+    }
+    let current_viewers = board
+        .viewers
+        .into_iter()
+        .filter(|viewer_id| *viewer_id != user_id)
+        .collect::<Vec<String>>();
+    let query_doc = doc! {
+        "_id": ObjectId::from_str(board_id.as_str()).unwrap(),
+    };
+    let result = Board::update_document(
+        &database_client,
+        query_doc,
+        UpdateBoard {
+            name: None,
+            host: None,
+            allowed_members: None,
+            viewers: Some(current_viewers),
+            is_public: None,
+        },
+    )
+    .await;
+    match result {
+        Ok(result) => match result.modified_count {
+            0 => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Viewer has not been removed",
+            )
+                .into_response(),
+            _ => {
+                let mut sub_context = board_context.lock().await;
+                if let Ok(event_body) = serialize_or_log(&ViewerRemovedEventPayload {
+                    user_id: user_id.to_string(),
+                }) {
+                    sub_context
+                        .emit_board_event(
+                            database_client.clone(),
+                            board._id,
+                            BoardEvent {
+                                event_type: BoardEventType::ViewerRemoved,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(sub_context);
+                (StatusCode::OK, Json(user_id.clone())).into_response()
+            }
+        },
+        Err(error_response) => error_response,
+    }
+}
+
+async fn kick_member(
+    Path((board_id, user_id)): Path<(String, String)>,
+    State(AppState {
+        database_client,
+        board_context,
+        element_context,
+        client_context,
+        active_member_context,
+        ..
     }): State<AppState>,
+    payload: Result<Json<KickMemberPayload>, JsonRejection>,
 ) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    if board.host != body.host_id {
+        return (StatusCode::FORBIDDEN, "Only the host can kick members").into_response();
+    }
+    if !board.allowed_members.contains(&user_id) {
+        return (StatusCode::CONFLICT, "Member not part of this board").into_response();
+    }
     let query_doc = doc! {
-        "allowedMembers": doc!{ "$in": vec![user_id] }
-    };
-    let get_boards_result = Board::get_multiple_documents(&database_client, query_doc).await;
-    match get_boards_result {
-        Ok(board_cursor) => {
-            let all_boards = board_cursor.try_collect().await.unwrap_or_else(|_| vec![]);
-            match all_boards.len() {
-                0 => (StatusCode::NOT_FOUND, "User is not part of any board").into_response(),
-                _ => (StatusCode::OK, Json(all_boards)).into_response(),
+        "userId": user_id.clone(),
+        "boardId": board._id.clone(),
+    };
+    match ActiveMember::delete_document(&database_client, query_doc).await {
+        Ok(result) => {
+            if result.deleted_count == 0 {
+                return (StatusCode::NOT_FOUND, "Member is not active on this board")
+                    .into_response();
             }
         }
+        Err(error_response) => return error_response,
+    };
+    let query_doc = doc! {
+        "lockedBy": user_id.clone(),
+        "boardId": board._id.clone(),
+    };
+    let locked_elements = match Element::get_multiple_documents(&database_client, query_doc).await {
+        Ok(element_cursor) => match element_cursor.try_collect::<Vec<Element>>().await {
+            Ok(retrieved_elements) => retrieved_elements,
+            Err(_) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Found Elements could not be retrieved",
+                )
+                    .into_response();
+            }
+        },
+        Err(error_response) => return error_response,
+    };
+    let mut unlocked_ids: Vec<String> = vec![];
+    for element in locked_elements.iter() {
+        let query_doc = doc! {
+            "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
+        };
+        match Element::update_document(
+            &database_client,
+            query_doc,
+            UpdateElement {
+                selected: None,
+                locked_by: Some(None),
+                x: None,
+                y: None,
+                rotation: None,
+                scale_x: None,
+                scale_y: None,
+                z_index: None,
+                text: None,
+                color: None,
+                metadata: None,
+            },
+        )
+        .await
+        {
+            Ok(_) => unlocked_ids.push(element._id.clone()),
+            Err(error_response) => return error_response,
+        }
+    }
+    ElementHistory::record_multiple(
+        &database_client,
+        &unlocked_ids,
+        board._id.clone(),
+        body.host_id.clone(),
+        ElementHistoryAction::Unlocked,
+        None,
+    )
+    .await;
+    let mut element_sub_context = element_context.lock().await;
+    for element_id in unlocked_ids.iter() {
+        if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+            _id: element_id.clone(),
+        }) {
+            element_sub_context
+                .emit_element_event(
+                    board._id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Unlocked,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(element_sub_context);
+    let mut active_member_sub_context = active_member_context.lock().await;
+    active_member_sub_context
+        .clear_activity(user_id.clone())
+        .await;
+    if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+        user_id: user_id.clone(),
+    }) {
+        active_member_sub_context
+            .emit_active_member_event(
+                board._id.clone(),
+                ActiveMemberEvent {
+                    event_type: ActiveMemberEventType::Removed,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(active_member_sub_context);
+    let mut board_sub_context = board_context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&MemberKickedEventPayload {
+        user_id: user_id.clone(),
+    }) {
+        board_sub_context
+            .emit_board_event(
+                database_client.clone(),
+                board._id.clone(),
+                BoardEvent {
+                    event_type: BoardEventType::Kicked,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(board_sub_context);
+    let mut client_sub_context = client_context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&ClientKickedPayload {
+        user_id: user_id.clone(),
+        board_id: board._id.clone(),
+    }) {
+        client_sub_context
+            .emit_client_event(
+                database_client.clone(),
+                user_id.clone(),
+                ClientEvent {
+                    event_type: ClientEventType::Kicked,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(client_sub_context);
+    info!("Kicked Member {} from Board {}", user_id, board._id);
+    (StatusCode::OK, Json(user_id)).into_response()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteElementsByUserQueryParams {
+    host_id: String,
+}
+
+async fn delete_elements_created_by_user(
+    Path((board_id, user_id)): Path<(String, String)>,
+    query_params: Query<DeleteElementsByUserQueryParams>,
+    State(AppState {
+        database_client,
+        board_context,
+        element_context,
+        ..
+    }): State<AppState>,
+) -> Response {
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    if board.host != query_params.host_id {
+        return (
+            StatusCode::FORBIDDEN,
+            "Only the host can delete the elements of a user",
+        )
+            .into_response();
+    }
+    let deleted_ids = match Element::delete_created_by(
+        &database_client,
+        &board._id,
+        &user_id,
+        &query_params.host_id,
+    )
+    .await
+    {
+        Ok(deleted_ids) => deleted_ids,
+        Err(error_response) => return error_response,
+    };
+    info!(
+        "Deleted {} Elements created by {} on Board {}",
+        deleted_ids.len(),
+        user_id,
+        board._id
+    );
+    if deleted_ids.is_empty() {
+        return (
+            StatusCode::OK,
+            Json(DeletedElementsResponsePayload { deleted_count: 0 }),
+        )
+            .into_response();
+    }
+    ElementHistory::record_multiple(
+        &database_client,
+        &deleted_ids,
+        board._id.clone(),
+        query_params.host_id.clone(),
+        ElementHistoryAction::Removed,
+        None,
+    )
+    .await;
+    let mut element_sub_context = element_context.lock().await;
+    for element_id in deleted_ids.iter() {
+        if let Ok(event_body) = serialize_or_log(&ElementRemovedEventPayload {
+            _id: element_id.clone(),
+            user_id: query_params.host_id.clone(),
+        }) {
+            element_sub_context
+                .emit_element_event(
+                    board._id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Removed,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(element_sub_context);
+    remove_connectors_of(
+        &database_client,
+        &element_context,
+        &board._id,
+        &query_params.host_id,
+        &deleted_ids,
+    )
+    .await;
+    if let Ok(0) = Element::count_of_board(&database_client, board._id.clone()).await {
+        let mut board_sub_context = board_context.lock().await;
+        if let Ok(event_body) = serialize_or_log(&BoardEmptiedEventPayload {
+            user_id: query_params.host_id.clone(),
+        }) {
+            board_sub_context
+                .emit_board_event(
+                    database_client.clone(),
+                    board._id.clone(),
+                    BoardEvent {
+                        event_type: BoardEventType::Emptied,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+        drop(board_sub_context);
+    }
+    (
+        StatusCode::OK,
+        Json(DeletedElementsResponsePayload {
+            deleted_count: deleted_ids.len() as u64,
+        }),
+    )
+        .into_response()
+}
+
+async fn update_board_visibility(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+    payload: Result<Json<UpdateBoardVisibilityPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    if board.host != body.host_id {
+        return (
+            StatusCode::FORBIDDEN,
+            "Only the host can change the board visibility",
+        )
+            .into_response();
+    }
+    let query_doc = doc! {
+        "_id": ObjectId::from_str(board_id.as_str()).unwrap(),
+    };
+    match Board::update_document(
+        &database_client,
+        query_doc,
+        UpdateBoard {
+            name: None,
+            host: None,
+            allowed_members: None,
+            viewers: None,
+            is_public: Some(body.is_public),
+        },
+    )
+    .await
+    {
+        Ok(_) => {
+            info!(
+                "Board {} is now {}",
+                board._id,
+                if body.is_public { "public" } else { "private" }
+            );
+            (StatusCode::OK, Json(body.is_public)).into_response()
+        }
+        Err(error_response) => error_response,
+    }
+}
+
+async fn get_board_settings(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let board = match Board::get_existing_board(board_id, &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    (
+        StatusCode::OK,
+        Json(BoardSettingsResponsePayload {
+            allow_guests: board.settings.allow_guests,
+            max_elements: board.settings.max_elements,
+            encrypted: board.encrypted,
+        }),
+    )
+        .into_response()
+}
+
+async fn update_board_settings(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+    payload: Result<Json<UpdateBoardSettingsPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    let board = match Board::get_existing_board(board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    if board.host != body.host_id {
+        return (
+            StatusCode::FORBIDDEN,
+            "Only the host can change the board settings",
+        )
+            .into_response();
+    }
+    if body
+        .max_elements
+        .is_some_and(|max_elements| max_elements == 0 || max_elements > MAX_ELEMENTS_PER_BOARD())
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Max elements must be between 1 and {}",
+                MAX_ELEMENTS_PER_BOARD()
+            ),
+        )
+            .into_response();
+    }
+    if body.encrypted && !board.encrypted && !is_encryption_configured() {
+        return (
+            StatusCode::CONFLICT,
+            "Element text encryption is not configured",
+        )
+            .into_response();
+    }
+    let settings = BoardSettings {
+        allow_guests: body.allow_guests,
+        max_elements: body.max_elements,
+    };
+    if let Err(error_response) = Board::set_settings(&board_id, &settings, &database_client).await {
+        return error_response;
+    }
+    if body.encrypted != board.encrypted {
+        match Board::switch_encryption(&board_id, body.encrypted, &database_client).await {
+            Ok((rewritten_elements, rewritten_history_entries)) => info!(
+                "Set encryption of Board {} to {}, rewrote {} Elements and {} History entries",
+                board_id, body.encrypted, rewritten_elements, rewritten_history_entries
+            ),
+            Err(error_response) => return error_response,
+        }
+    }
+    info!("Updated settings of Board {}", board_id);
+    (
+        StatusCode::OK,
+        Json(BoardSettingsResponsePayload {
+            allow_guests: settings.allow_guests,
+            max_elements: settings.max_elements,
+            encrypted: body.encrypted,
+        }),
+    )
+        .into_response()
+}
+
+async fn get_board_members(
+    Path(board_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let board = match Board::get_existing_board(board_id, &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    match User::get_users_by_ids(&board.allowed_members, &database_client).await {
+        Ok(users) => (
+            StatusCode::OK,
+            Json(
+                users
+                    .into_iter()
+                    .map(|user| BoardMemberResponsePayload {
+                        is_host: user._id == board.host,
+                        id: user._id,
+                        name: user.name,
+                        email: user.email,
+                    })
+                    .collect::<Vec<BoardMemberResponsePayload>>(),
+            ),
+        )
+            .into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+async fn get_all_boards_with_user(
+    Path(user_id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    match Board::get_boards_of_user(user_id, &database_client).await {
+        Ok(all_boards) => match all_boards.len() {
+            0 => (StatusCode::NOT_FOUND, "User is not part of any board").into_response(),
+            _ => (StatusCode::OK, Json(all_boards)).into_response(),
+        },
         Err(error_response) => error_response,
     }
 }
@@ -278,3 +1213,38 @@ async fn get_all_elements_of_board(
         Err(error_response) => error_response,
     }
 }
+
+#[derive(Deserialize)]
+struct SearchElementsQueryParams {
+    q: String,
+    skip: Option<u64>,
+    limit: Option<i64>,
+}
+
+async fn search_elements_of_board(
+    Path(board_id): Path<String>,
+    query_params: Query<SearchElementsQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    if query_params.q.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Search query must not be empty").into_response();
+    }
+    let limit = query_params
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    match Element::search_text_in_board(
+        &database_client,
+        board_id,
+        query_params.q.trim(),
+        query_params.skip.unwrap_or(0),
+        limit,
+    )
+    .await
+    {
+        Ok(found_elements) => (StatusCode::OK, Json(found_elements)).into_response(),
+        Err(error_response) => error_response,
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+
+use crate::{
+    database::collections::{
+        element::{MAX_ELEMENTS_PER_BOARD, MAX_TEXT_LENGTH},
+        element_asset::MAX_ASSET_SIZE,
+    },
+    services::{
+        rest::payloads::capabilities::{
+            CapabilitiesResponsePayload, LimitCapabilities, TransportCapabilities,
+        },
+        webtransport::{
+            message_rate_limit::{MESSAGE_BURST, MESSAGE_RATE},
+            messages::{
+                active_member::ActiveMemberMessage, board::BoardMessage,
+                category::WebTransportMainCategoryHandler, element::ElementMessage,
+            },
+            protocol_version::{PROTOCOL_VERSION_MAJOR, PROTOCOL_VERSION_MINOR},
+            server::SWITCH_SUBJECT_MESSAGE_TYPE,
+        },
+    },
+    AppState,
+};
+
+pub fn get_routes() -> Router<AppState> {
+    Router::new().route("/capabilities", get(get_capabilities))
+}
+
+// Capabilities services ===========================================
+
+async fn get_capabilities() -> Response {
+    let mut message_categories = BTreeMap::new();
+    message_categories.insert("board", BoardMessage::SUBCATEGORIES.to_vec());
+    message_categories.insert("element", ElementMessage::SUBCATEGORIES.to_vec());
+    message_categories.insert("activemember", ActiveMemberMessage::SUBCATEGORIES.to_vec());
+    if let Some((category, subcategory)) = SWITCH_SUBJECT_MESSAGE_TYPE.split_once('_') {
+        message_categories.insert(category, vec![subcategory]);
+    }
+    (
+        StatusCode::OK,
+        Json(CapabilitiesResponsePayload {
+            protocol_version: PROTOCOL_VERSION_MAJOR,
+            protocol_minor_version: PROTOCOL_VERSION_MINOR,
+            message_categories,
+            event_categories: vec!["board", "client", "active_member", "element"],
+            transports: TransportCapabilities {
+                webtransport: true,
+                datagrams: true,
+                compression: false,
+                msgpack: false,
+                websocket_fallback: false,
+            },
+            limits: LimitCapabilities {
+                max_elements_per_board: MAX_ELEMENTS_PER_BOARD(),
+                max_text_length: MAX_TEXT_LENGTH(),
+                max_asset_size: MAX_ASSET_SIZE(),
+                max_messages_per_second: MESSAGE_RATE(),
+                message_burst: MESSAGE_BURST(),
+            },
+        }),
+    )
+        .into_response()
+}
@@ -0,0 +1,51 @@
+use std::{env::var, sync::OnceLock};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+
+use crate::AppState;
+
+// Self-signed certificates have to be pinned by WebTransport clients, production setups may hide the hash
+#[allow(non_snake_case)]
+fn CERTIFICATE_HASH_EXPOSED() -> bool {
+    static CERTIFICATE_HASH_EXPOSED: OnceLock<bool> = OnceLock::new();
+    *CERTIFICATE_HASH_EXPOSED.get_or_init(|| match var("CERTIFICATE_HASH_EXPOSED") {
+        Ok(certificate_hash_exposed) => certificate_hash_exposed
+            .parse()
+            .expect("Failed to parse `CERTIFICATE_HASH_EXPOSED` environment variable."),
+        Err(_) => true,
+    })
+}
+
+pub fn get_routes() -> Router<AppState> {
+    Router::new().route("/certificate-hash", get(get_certificate_hash))
+}
+
+// Certificate services ============================================
+
+// SHA-256 digest of the loaded certificate in the `BytesArray` format, e.g. `[12, 34, ...]`
+async fn get_certificate_hash(
+    State(AppState {
+        certificate_hash, ..
+    }): State<AppState>,
+) -> Response {
+    if !CERTIFICATE_HASH_EXPOSED() {
+        return (StatusCode::NOT_FOUND, "Certificate hash is not exposed").into_response();
+    }
+    let certificate_hash = match certificate_hash.read() {
+        Ok(certificate_hash) => certificate_hash.clone(),
+        Err(_) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Certificate hash could not be read",
+            )
+                .into_response()
+        }
+    };
+    (StatusCode::OK, Json(certificate_hash)).into_response()
+}
@@ -5,22 +5,30 @@ use axum::{
     routing::{delete, get, post},
     Router,
 };
-use bson::doc;
+use bson::{doc, DateTime, Uuid};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use tracing::{error, info};
 
 use crate::{
     database::{
-        collections::client::{Client, CreateClient, DeviceType, UpdateClient},
+        collections::{
+            client::{Client, CreateClient, DeviceType, UpdateClient},
+            user::User,
+        },
         document::Document,
     },
     services::{
         rest::payloads::client::{CreateOrUpdateClientResponsePayload, GetClientReponsePayload},
         webtransport::{
-            context::client::{ClientEvent, ClientEventType},
-            messages::client::ClientCreatedOrUpdatedPayload,
+            context::client::{ClientContext, ClientEvent, ClientEventType},
+            messages::client::{ClientCreatedOrUpdatedPayload, ClientDisplacedPayload},
         },
     },
-    utils::check_request_body::check_request_body,
+    utils::{
+        check_request_body::check_request_body, inserted_object_id::inserted_object_id_hex,
+        serialize_or_log::serialize_or_log,
+    },
     AppState,
 };
 
@@ -31,6 +39,57 @@ pub fn get_routes() -> Router<AppState> {
         .route("/client", post(create_or_update_client))
         .route("/client/:userId", get(get_client))
         .route("/client/:userId", delete(delete_client))
+        .route("/client/:userId/rotate", post(rotate_client))
+}
+
+// A user has a single active client, activating another one displaces the previous client. All devices
+// of a user share one subject, so the event carries the displaced client id and only the device with
+// that id logs out, `client_removed` stays reserved for removing the client of the user
+pub async fn activate_client(
+    database_client: &mongodb::Client,
+    client_context: &Arc<Mutex<ClientContext>>,
+    user_id: &str,
+    client_id: &str,
+) {
+    let previous_client_id = match User::set_active_client(
+        user_id,
+        Some(client_id.to_string()),
+        database_client,
+    )
+    .await
+    {
+        Ok(previous_client_id) => previous_client_id,
+        Err(_) => {
+            error!("Active Client of User with ID {} could not be set", user_id);
+            return;
+        }
+    };
+    let Some(previous_client_id) =
+        previous_client_id.filter(|previous_client_id| previous_client_id != client_id)
+    else {
+        return;
+    };
+    info!(
+        "Displaced previous Client {} of User with ID: {}",
+        previous_client_id, user_id
+    );
+    let Ok(event_body) = serialize_or_log(&ClientDisplacedPayload {
+        client_id: previous_client_id,
+    }) else {
+        return;
+    };
+    let mut sub_context = client_context.lock().await;
+    sub_context
+        .emit_client_event(
+            database_client.clone(),
+            user_id.to_string(),
+            ClientEvent {
+                event_type: ClientEventType::Displaced,
+                body: event_body,
+            },
+        )
+        .await;
+    drop(sub_context);
 }
 
 // Client services =================================================
@@ -93,22 +152,30 @@ async fn create_or_update_client(
                             "Updated Client with User ID: {}",
                             existing_client.user_id.clone()
                         );
+                        activate_client(
+                            &database_client,
+                            &client_context,
+                            &body.user_id,
+                            &body.client_id,
+                        )
+                        .await;
                         let mut sub_context = client_context.lock().await;
-                        sub_context
-                            .emit_client_event(
-                                database_client.clone(),
-                                existing_client.user_id.to_string(),
-                                ClientEvent {
-                                    event_type: ClientEventType::Changed,
-                                    body: serde_json::to_string(&ClientCreatedOrUpdatedPayload {
-                                        user_id: body.user_id.clone(),
-                                        device_type: body.device_type.clone(),
-                                        client_id: body.client_id.clone(),
-                                    })
-                                    .unwrap(),
-                                },
-                            )
-                            .await;
+                        if let Ok(event_body) = serialize_or_log(&ClientCreatedOrUpdatedPayload {
+                            user_id: body.user_id.clone(),
+                            device_type: body.device_type.clone(),
+                            client_id: body.client_id.clone(),
+                        }) {
+                            sub_context
+                                .emit_client_event(
+                                    database_client.clone(),
+                                    existing_client.user_id.to_string(),
+                                    ClientEvent {
+                                        event_type: ClientEventType::Changed,
+                                        body: event_body,
+                                    },
+                                )
+                                .await;
+                        }
                         drop(sub_context);
                         (
                             StatusCode::OK,
@@ -131,13 +198,24 @@ async fn create_or_update_client(
                     client_id: body.client_id.clone(),
                     user_id: body.user_id.clone(),
                     device_type: DeviceType::to_enum(body.device_type.clone()),
+                    last_seen: DateTime::now(),
                 },
             )
             .await;
             match create_client_result {
                 Ok(result) => {
-                    let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+                    let inserted_id = match inserted_object_id_hex(&result) {
+                        Ok(inserted_id) => inserted_id,
+                        Err(error_response) => return error_response,
+                    };
                     info!("Created new Client with ID: {}", inserted_id);
+                    activate_client(
+                        &database_client,
+                        &client_context,
+                        &body.user_id,
+                        &body.client_id,
+                    )
+                    .await;
                     (
                         StatusCode::OK,
                         Json(CreateOrUpdateClientResponsePayload {
@@ -212,6 +290,7 @@ async fn delete_client(
                 .into_response(),
             _ => {
                 info!("Deleted {} Clients", result.deleted_count);
+                let _ = User::set_active_client(&user_id, None, &database_client).await;
                 let mut sub_context = client_context.lock().await;
                 sub_context
                     .emit_client_event(
@@ -230,3 +309,77 @@ async fn delete_client(
         Err(error_response) => error_response,
     }
 }
+
+async fn rotate_client(
+    Path(user_id): Path<String>,
+    State(AppState {
+        database_client,
+        client_context,
+        ..
+    }): State<AppState>,
+) -> Response {
+    let existing_client = match Client::get_existing_client(user_id.clone(), &database_client).await
+    {
+        Ok(existing_client) => existing_client,
+        Err(error_response) => return error_response,
+    };
+    let new_client_id = Uuid::new().to_string();
+    let update_result = Client::update_document(
+        &database_client,
+        doc! {
+            "userId": user_id.clone(),
+        },
+        UpdateClient {
+            client_id: Some(new_client_id.clone()),
+            device_type: None,
+        },
+    )
+    .await;
+    match update_result {
+        Ok(result) => match result.modified_count {
+            0 => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Client couldn't be rotated",
+            )
+                .into_response(),
+            _ => {
+                info!("Rotated Client ID of User with ID: {}", user_id);
+                // Rotation keeps the same device active, so nothing is displaced
+                let _ = User::set_active_client(
+                    &user_id,
+                    Some(new_client_id.clone()),
+                    &database_client,
+                )
+                .await;
+                let mut sub_context = client_context.lock().await;
+                if let Ok(event_body) = serialize_or_log(&ClientCreatedOrUpdatedPayload {
+                    user_id: user_id.clone(),
+                    device_type: existing_client.device_type.to_string(),
+                    client_id: new_client_id.clone(),
+                }) {
+                    sub_context
+                        .emit_client_event(
+                            database_client.clone(),
+                            user_id.to_string(),
+                            ClientEvent {
+                                event_type: ClientEventType::Changed,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(sub_context);
+                (
+                    StatusCode::OK,
+                    Json(CreateOrUpdateClientResponsePayload {
+                        client_id: new_client_id,
+                        device_type: existing_client.device_type.to_string(),
+                        user_id,
+                    }),
+                )
+                    .into_response()
+            }
+        },
+        Err(error_response) => error_response,
+    }
+}
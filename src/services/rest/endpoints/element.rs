@@ -1,44 +1,71 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use axum::{
+    body::Bytes,
     extract::{rejection::JsonRejection, Json, Path, Query, State},
-    http::StatusCode,
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     routing::{delete, get, post, put},
     Router,
 };
-use bson::{doc, oid::ObjectId};
+use bson::{doc, oid::ObjectId, DateTime};
 use futures::TryStreamExt;
-use mongodb::results::UpdateResult;
+use mongodb::Client;
 use serde::Deserialize;
-use tracing::info;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
 
 use crate::{
     database::{
-        collections::element::{CreateElement, Element, UpdateElement},
+        collections::{
+            board::Board,
+            element::{normalize_rotation, CreateElement, Element, UpdateElement},
+            element_asset::{ElementAsset, MAX_ASSET_SIZE},
+            element_history::{ElementHistory, ElementHistoryAction},
+            element_type::ElementType,
+        },
         document::Document,
     },
     services::webtransport::{
-        context::element::{ElementEvent, ElementEventType},
-        messages::element::{
-            ElementCreatedEventPayload, ElementLockedEventPayload, ElementMovedEventPayload,
-            ElementRemovedEventPayload, ElementUnlockedEventPayload, UpdatedElementEventPayload,
+        context::{
+            board::{BoardEvent, BoardEventType},
+            element::{ElementContext, ElementEvent, ElementEventType},
+        },
+        messages::{
+            board::{emit_near_limit_if_reached, BoardEmptiedEventPayload},
+            element::{
+                emit_connector_updates, remove_connectors_of, sort_by_request_order,
+                AppliedUpdates, ElementCreatedEventPayload, ElementLockedEventPayload,
+                ElementMovedEventPayload, ElementRemovedEventPayload, ElementUnlockedEventPayload,
+                UpdatedElementEventPayload,
+            },
         },
     },
-    utils::check_request_body::check_request_body,
+    utils::{
+        check_request_body::check_request_body, inserted_object_id::inserted_object_id_hex,
+        serialize_or_log::serialize_or_log,
+    },
     AppState,
 };
 
 use super::super::payloads::element::{
-    CreateElementPayload, LockElementPayload, LockMultipleElementsPayload,
+    CreateElementPayload, ElementCreatedResponsePayload, ElementFieldsPayload,
+    ElementForceUnlockedResponsePayload, ElementLockStatusBatchPayload,
+    ElementLockStatusResponsePayload, ForceUnlockElementPayload, LockElementPayload,
+    LockMultipleElementsPayload, LockUpdateUnlockElementPayload, MoveElementsToBoardPayload,
     MoveMultipleElementsPayload, UnlockElementPayload, UnlockMultipleElementsPayload,
-    UpdateElementPayload,
+    UpdateElementPayload, UpdateMultipleElementsPayload,
 };
 
+const DEFAULT_HISTORY_LIMIT: i64 = 100;
+const MAX_HISTORY_LIMIT: i64 = 1000;
+const MAX_LOCK_STATUS_IDS: usize = 1000;
+
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .route("/element/single", post(create_element))
         .route("/element/single/:id", get(get_element))
+        .route("/element/single/:id/history", get(get_element_history))
         .route("/element/single", put(update_element))
         .route(
             "/element/single/:userId/:boardId/:elementId",
@@ -46,10 +73,42 @@ pub fn get_routes() -> Router<AppState> {
         )
         .route("/element/single/lock", put(lock_element))
         .route("/element/single/unlock", put(unlock_element))
+        .route(
+            "/element/single/:id/force-unlock",
+            put(force_unlock_element),
+        )
+        .route(
+            "/element/single/lock-update-unlock",
+            put(lock_update_unlock_element),
+        )
+        .route(
+            "/element/locked-by/:userId",
+            get(get_elements_locked_by_user),
+        )
+}
+
+pub fn get_asset_routes() -> Router<AppState> {
+    Router::new().route(
+        "/element/:id/asset",
+        post(upload_element_asset).get(get_element_asset),
+    )
+}
+
+pub fn get_batch_routes() -> Router<AppState> {
+    Router::new()
         .route("/element/multiple/unlock-all", put(unlock_all_for_user))
+        .route("/element/multiple", put(update_multiple_elements))
         .route("/element/multiple/move", put(move_multiple_elements))
+        .route(
+            "/element/multiple/move-to-board",
+            put(move_elements_to_board),
+        )
         .route("/element/multiple/lock", put(lock_multiple_elements))
         .route("/element/multiple/unlock", put(unlock_multiple_elements))
+        .route(
+            "/element/lock-status/batch",
+            post(get_lock_status_of_elements),
+        )
 }
 
 // Element services ==============================================
@@ -57,6 +116,7 @@ pub fn get_routes() -> Router<AppState> {
 async fn create_element(
     State(AppState {
         database_client,
+        board_context,
         element_context,
         ..
     }): State<AppState>,
@@ -66,57 +126,151 @@ async fn create_element(
         Ok(success_body) => success_body,
         Err(error_response) => return error_response,
     };
+    let defaults = match ElementType::get_defaults(&database_client, &body.element_type).await {
+        Ok(defaults) => defaults,
+        Err(error_response) => return error_response,
+    };
+    let text = defaults.text(body.text.clone());
+    let color = defaults.color(body.color.clone());
+    if let Err(message) = Element::validate_element_fields(Some(&text), Some(&color)) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(message) = Element::check_metadata(&body.metadata) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let element_count = match Element::count_of_board(&database_client, body.board_id.clone()).await
+    {
+        Ok(element_count) => element_count,
+        Err(error_response) => return error_response,
+    };
+    let element_limit = match Board::get_element_limit(&body.board_id, &database_client).await {
+        Ok(element_limit) => element_limit,
+        Err(error_response) => return error_response,
+    };
+    if element_count >= element_limit {
+        return (StatusCode::CONFLICT, "Board has reached its element limit").into_response();
+    }
+    match Element::violates_singleton(&database_client, &body.board_id, &body.element_type).await {
+        Ok(true) => {
+            return (
+                StatusCode::CONFLICT,
+                "Board already contains an Element of this singleton type",
+            )
+                .into_response();
+        }
+        Ok(false) => {}
+        Err(error_response) => return error_response,
+    }
+    if let Some(connector) = &body.connector {
+        match Element::connector_targets_exist(&database_client, &body.board_id, connector).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    "Connector must link two Elements of the same Board",
+                )
+                    .into_response();
+            }
+            Err(error_response) => return error_response,
+        }
+    }
+    let z_index = match defaults.z_index(body.z_index) {
+        Some(z_index) => z_index,
+        None => match Board::next_z_index(&body.board_id, &database_client).await {
+            Ok(z_index) => z_index,
+            Err(error_response) => return error_response,
+        },
+    };
     let create_element = CreateElement {
         _id: body._id.clone(),
         board_id: body.board_id.clone(),
         selected: body.selected,
         locked_by: body.locked_by.clone(),
-        rotation: body.rotation,
-        scale_x: body.scale_x,
-        scale_y: body.scale_y,
-        z_index: body.z_index,
+        rotation: normalize_rotation(body.rotation),
+        scale_x: defaults.scale_x(body.scale_x),
+        scale_y: defaults.scale_y(body.scale_y),
+        z_index,
         x: body.x,
         y: body.y,
         element_type: body.element_type.clone(),
-        text: body.text.clone(),
+        text,
         created_at: body.created_at,
-        color: body.color.clone(),
+        color,
+        metadata: body.metadata.clone(),
+        asset_id: None,
+        created_by: body.user_id.clone(),
+        updated_at: DateTime::now(),
+        connector: body.connector.clone(),
     };
     let create_element_result =
         Element::create_document(&database_client, create_element.clone()).await;
     match create_element_result {
         Ok(result) => {
-            let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+            let inserted_id = match inserted_object_id_hex(&result) {
+                Ok(inserted_id) => inserted_id,
+                Err(error_response) => return error_response,
+            };
             info!("Created Element with ID: {}", inserted_id);
+            ElementHistory::record(
+                &database_client,
+                inserted_id.clone(),
+                body.board_id.clone(),
+                body.user_id.clone(),
+                ElementHistoryAction::Created,
+                bson::to_document(&create_element).ok(),
+            )
+            .await;
             let mut sub_context = element_context.lock().await;
-            sub_context
-                .emit_element_event(
-                    body.board_id.clone(),
-                    ElementEvent {
-                        event_type: ElementEventType::Created,
-                        body: serde_json::to_string(&ElementCreatedEventPayload {
-                            _id: inserted_id.clone(),
-                            user_id: body.user_id.clone(),
-                            board_id: create_element.board_id,
-                            x: create_element.x,
-                            y: create_element.y,
-                            text: create_element.text,
-                            scale_x: create_element.scale_x,
-                            scale_y: create_element.scale_y,
-                            z_index: create_element.z_index,
-                            selected: create_element.selected,
-                            created_at: create_element.created_at,
-                            rotation: create_element.rotation,
-                            locked_by: create_element.locked_by,
-                            element_type: create_element.element_type,
-                            color: create_element.color,
-                        })
-                        .unwrap(),
-                    },
-                )
-                .await;
+            if let Ok(event_body) = serialize_or_log(&ElementCreatedEventPayload {
+                _id: inserted_id.clone(),
+                user_id: body.user_id.clone(),
+                board_id: create_element.board_id,
+                x: create_element.x,
+                y: create_element.y,
+                text: create_element.text,
+                scale_x: create_element.scale_x,
+                scale_y: create_element.scale_y,
+                z_index: create_element.z_index,
+                selected: create_element.selected,
+                created_at: create_element.created_at,
+                rotation: create_element.rotation,
+                locked_by: create_element.locked_by,
+                element_type: create_element.element_type,
+                color: create_element.color,
+                metadata: create_element.metadata,
+                asset_id: create_element.asset_id,
+                created_by: create_element.created_by,
+                connector: create_element.connector,
+            }) {
+                sub_context
+                    .emit_element_event(
+                        body.board_id.clone(),
+                        ElementEvent {
+                            event_type: ElementEventType::Created,
+                            body: event_body,
+                        },
+                    )
+                    .await;
+            }
             drop(sub_context);
-            (StatusCode::OK, Json(inserted_id)).into_response()
+            emit_near_limit_if_reached(&database_client, &board_context, &body.board_id).await;
+            (
+                StatusCode::OK,
+                Json(ElementCreatedResponsePayload {
+                    _id: inserted_id,
+                    z_index,
+                }),
+            )
+                .into_response()
         }
         Err(error_response) => error_response,
     }
@@ -141,16 +295,176 @@ async fn get_element(
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetQueryParams {
+    user_id: String,
+}
+
+async fn upload_element_asset(
+    Path(id): Path<String>,
+    query_params: Query<AssetQueryParams>,
+    State(AppState {
+        database_client,
+        element_context,
+        ..
+    }): State<AppState>,
+    headers: HeaderMap,
+    data: Bytes,
+) -> Response {
+    let content_type = match headers
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+    {
+        Some(content_type) if ElementAsset::is_allowed_content_type(content_type) => {
+            content_type.to_string()
+        }
+        _ => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Content type of the asset is not allowed",
+            )
+                .into_response()
+        }
+    };
+    if data.is_empty() {
+        return (StatusCode::BAD_REQUEST, "Asset must not be empty").into_response();
+    }
+    if data.len() > MAX_ASSET_SIZE() {
+        return (StatusCode::PAYLOAD_TOO_LARGE, "Asset is too large").into_response();
+    }
+    let element = match Element::get_existing_element(id.clone(), &database_client).await {
+        Ok(element) => element,
+        Err(error_response) => return error_response,
+    };
+    if let Err(error_response) = Board::assert_member(
+        element.board_id.clone(),
+        query_params.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let asset_id = match ElementAsset::upload(
+        &database_client,
+        element._id.clone(),
+        content_type,
+        &data,
+    )
+    .await
+    {
+        Ok(asset_id) => asset_id,
+        Err(error_response) => return error_response,
+    };
+    if let Err(error_response) =
+        Element::set_asset_id(&database_client, element._id.clone(), asset_id.clone()).await
+    {
+        return error_response;
+    }
+    info!("Uploaded Asset {} for Element {}", asset_id, element._id);
+    ElementHistory::record(
+        &database_client,
+        element._id.clone(),
+        element.board_id.clone(),
+        query_params.user_id.clone(),
+        ElementHistoryAction::Updated,
+        Some(doc! { "assetId": asset_id.clone() }),
+    )
+    .await;
+    let mut sub_context = element_context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+        _id: element._id.clone(),
+        user_id: query_params.user_id.clone(),
+        x: None,
+        y: None,
+        rotation: None,
+        scale_x: None,
+        scale_y: None,
+        z_index: None,
+        text: None,
+        color: None,
+        metadata: None,
+        asset_id: Some(asset_id.clone()),
+    }) {
+        sub_context
+            .emit_element_event(
+                element.board_id.clone(),
+                ElementEvent {
+                    event_type: ElementEventType::Updated,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(sub_context);
+    (StatusCode::OK, Json(asset_id)).into_response()
+}
+
+async fn get_element_asset(
+    Path(id): Path<String>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let element = match Element::get_existing_element(id, &database_client).await {
+        Ok(element) => element,
+        Err(error_response) => return error_response,
+    };
+    let asset_id = match element.asset_id {
+        Some(asset_id) => asset_id,
+        None => return (StatusCode::NOT_FOUND, "Element has no asset").into_response(),
+    };
+    match ElementAsset::download(&database_client, asset_id).await {
+        Ok(asset) => (
+            StatusCode::OK,
+            [(CONTENT_TYPE, asset.content_type)],
+            asset.data,
+        )
+            .into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQueryParams {
+    limit: Option<i64>,
+}
+
+async fn get_element_history(
+    Path(id): Path<String>,
+    query_params: Query<HistoryQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let limit = query_params
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+    match ElementHistory::get_history_of_element(&database_client, id, limit).await {
+        Ok(history) => (StatusCode::OK, Json(history)).into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
 async fn delete_element(
     Path((user_id, board_id, element_id)): Path<(String, String, String)>,
     State(AppState {
         database_client,
+        board_context,
         element_context,
         ..
     }): State<AppState>,
 ) -> Response {
-    let query_doc = doc! {
-        "_id": ObjectId::from_str(element_id.clone().as_str()).unwrap(),
+    if let Err(error_response) =
+        Board::assert_member(board_id.clone(), user_id.clone(), &database_client).await
+    {
+        return error_response;
+    }
+    let query_doc = match Element::board_scoped_query(&element_id, &board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let delete_element_result = Element::delete_document(&database_client, query_doc).await;
     match delete_element_result {
@@ -159,21 +473,59 @@ async fn delete_element(
             match result.deleted_count {
                 0 => (StatusCode::NOT_FOUND, "No Element found to delete").into_response(),
                 _ => {
+                    ElementHistory::record(
+                        &database_client,
+                        element_id.clone(),
+                        board_id.clone(),
+                        user_id.clone(),
+                        ElementHistoryAction::Removed,
+                        None,
+                    )
+                    .await;
                     let mut sub_context = element_context.lock().await;
-                    sub_context
-                        .emit_element_event(
-                            board_id,
-                            ElementEvent {
-                                event_type: ElementEventType::Removed,
-                                body: serde_json::to_string(&ElementRemovedEventPayload {
-                                    _id: element_id.to_string(),
-                                    user_id,
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&ElementRemovedEventPayload {
+                        _id: element_id.to_string(),
+                        user_id: user_id.clone(),
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Removed,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(sub_context);
+                    remove_connectors_of(
+                        &database_client,
+                        &element_context,
+                        &board_id,
+                        &user_id,
+                        std::slice::from_ref(&element_id),
+                    )
+                    .await;
+                    // Only a deletion that actually removed an element can bring the board to zero
+                    if let Ok(0) = Element::count_of_board(&database_client, board_id.clone()).await
+                    {
+                        let mut sub_context = board_context.lock().await;
+                        if let Ok(event_body) =
+                            serialize_or_log(&BoardEmptiedEventPayload { user_id })
+                        {
+                            sub_context
+                                .emit_board_event(
+                                    database_client.clone(),
+                                    board_id,
+                                    BoardEvent {
+                                        event_type: BoardEventType::Emptied,
+                                        body: event_body,
+                                    },
+                                )
+                                .await;
+                        }
+                        drop(sub_context);
+                    }
                     (StatusCode::OK, Json(format!("{}", result.deleted_count))).into_response()
                 }
             }
@@ -196,8 +548,18 @@ async fn lock_element(
             return error_response;
         }
     };
-    let query_doc = doc! {
-        "_id": ObjectId::from_str(body._id.as_str()).unwrap()
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
     match found_element_result {
@@ -239,6 +601,7 @@ async fn lock_element(
             z_index: None,
             text: None,
             color: None,
+            metadata: None,
         },
     )
     .await;
@@ -247,20 +610,30 @@ async fn lock_element(
             0 => (StatusCode::NOT_FOUND, "No Element found to update").into_response(),
             _ => {
                 info!("Updated Element with ID: {}", body.user_id.clone());
+                ElementHistory::record(
+                    &database_client,
+                    body._id.clone(),
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Locked,
+                    None,
+                )
+                .await;
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        body.board_id.clone(),
-                        ElementEvent {
-                            event_type: ElementEventType::Locked,
-                            body: serde_json::to_string(&ElementLockedEventPayload {
-                                _id: body._id.clone(),
-                                user_id: body.user_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&ElementLockedEventPayload {
+                    _id: body._id.clone(),
+                    user_id: body.user_id.clone(),
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.clone(),
+                            ElementEvent {
+                                event_type: ElementEventType::Locked,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
                 (StatusCode::OK, Json(body.user_id.clone())).into_response()
             }
@@ -273,6 +646,7 @@ async fn unlock_element(
     State(AppState {
         database_client,
         element_context,
+        active_member_context,
         ..
     }): State<AppState>,
     payload: Result<Json<UnlockElementPayload>, JsonRejection>,
@@ -283,8 +657,9 @@ async fn unlock_element(
             return error_response;
         }
     };
-    let query_doc = doc! {
-        "_id": ObjectId::from_str(body._id.as_str()).unwrap()
+    let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
     match found_element_result {
@@ -329,6 +704,7 @@ async fn unlock_element(
             z_index: None,
             text: None,
             color: None,
+            metadata: None,
         },
     )
     .await;
@@ -337,20 +713,38 @@ async fn unlock_element(
             0 => (StatusCode::NOT_FOUND, "No Element found to update").into_response(),
             _ => {
                 info!("Updated Element with ID: {}", body.user_id.clone(),);
+                ElementHistory::record(
+                    &database_client,
+                    body._id.clone(),
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Unlocked,
+                    None,
+                )
+                .await;
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        body.board_id.clone(),
-                        ElementEvent {
-                            event_type: ElementEventType::Unlocked,
-                            body: serde_json::to_string(&ElementUnlockedEventPayload {
-                                _id: body._id.clone(),
-                            })
-                            .unwrap(),
-                        },
+                if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+                    _id: body._id.clone(),
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.clone(),
+                            ElementEvent {
+                                event_type: ElementEventType::Unlocked,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(sub_context);
+                let mut active_member_sub_context = active_member_context.lock().await;
+                active_member_sub_context
+                    .clear_activity_for_elements(
+                        body.user_id.clone(),
+                        std::slice::from_ref(&body._id),
                     )
                     .await;
-                drop(sub_context);
+                drop(active_member_sub_context);
                 (StatusCode::OK, Json(body.user_id.clone())).into_response()
             }
         },
@@ -358,6 +752,121 @@ async fn unlock_element(
     }
 }
 
+// Escape hatch for locks whose holder is gone, the host takes the lock away from whoever holds it
+async fn force_unlock_element(
+    Path(element_id): Path<String>,
+    State(AppState {
+        database_client,
+        element_context,
+        active_member_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<ForceUnlockElementPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    let element = match Element::get_existing_element(element_id.clone(), &database_client).await {
+        Ok(element) => element,
+        Err(error_response) => return error_response,
+    };
+    let board = match Board::get_existing_board(element.board_id.clone(), &database_client).await {
+        Ok(board) => board,
+        Err(error_response) => return error_response,
+    };
+    if board.host != body.host_id {
+        return (
+            StatusCode::FORBIDDEN,
+            "Only the host can force unlock elements",
+        )
+            .into_response();
+    }
+    let Some(displaced_user_id) = element.locked_by else {
+        return (StatusCode::NO_CONTENT, "Element already unlocked").into_response();
+    };
+    // Only releases the lock that was checked, a lock taken in the meantime is left alone
+    let query_doc = doc! {
+        "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
+        "lockedBy": displaced_user_id.clone(),
+    };
+    let update_result = Element::update_document(
+        &database_client,
+        query_doc,
+        UpdateElement {
+            selected: None,
+            locked_by: Some(None),
+            x: None,
+            y: None,
+            rotation: None,
+            scale_x: None,
+            scale_y: None,
+            z_index: None,
+            text: None,
+            color: None,
+            metadata: None,
+        },
+    )
+    .await;
+    match update_result {
+        Ok(result) if result.matched_count == 0 => {
+            return (
+                StatusCode::CONFLICT,
+                "Lock of the Element changed in the meantime",
+            )
+                .into_response();
+        }
+        Ok(_) => {}
+        Err(error_response) => return error_response,
+    }
+    warn!(
+        "Host {} force unlocked Element {} on Board {}, displacing lock holder {}",
+        body.host_id, element._id, board._id, displaced_user_id
+    );
+    ElementHistory::record(
+        &database_client,
+        element._id.clone(),
+        board._id.clone(),
+        body.host_id.clone(),
+        ElementHistoryAction::Unlocked,
+        Some(doc! { "displacedUserId": displaced_user_id.clone() }),
+    )
+    .await;
+    let mut sub_context = element_context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+        _id: element._id.clone(),
+    }) {
+        sub_context
+            .emit_element_event(
+                board._id.clone(),
+                ElementEvent {
+                    event_type: ElementEventType::Unlocked,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(sub_context);
+    let mut active_member_sub_context = active_member_context.lock().await;
+    active_member_sub_context
+        .clear_activity_for_elements(
+            displaced_user_id.clone(),
+            std::slice::from_ref(&element._id),
+        )
+        .await;
+    drop(active_member_sub_context);
+    (
+        StatusCode::OK,
+        Json(ElementForceUnlockedResponsePayload {
+            _id: element._id,
+            displaced_user_id,
+        }),
+    )
+        .into_response()
+}
+
 async fn lock_multiple_elements(
     State(AppState {
         database_client,
@@ -372,12 +881,22 @@ async fn lock_multiple_elements(
             return error_response;
         }
     };
-    let query_doc = doc! {
-        "_id": doc! { "$in": body.ids.iter().map(|id| ObjectId::from_str(id.as_str()).unwrap()).collect::<Vec<ObjectId>>() }
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let query_doc = match Element::board_scoped_ids_query(&body.ids, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let found_element_result =
         Element::get_multiple_documents(&database_client, query_doc.clone()).await;
-    let found_elements = match found_element_result {
+    let mut found_elements = match found_element_result {
         Ok(element_cursor) => {
             let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
             match retrieved_elements {
@@ -407,12 +926,11 @@ async fn lock_multiple_elements(
     {
         return (StatusCode::LOCKED, "Some Element is locked by another user").into_response();
     }
-    let mut updated_document_results: Vec<UpdateResult> = vec![];
+    sort_by_request_order(&mut found_elements, &body.ids);
+    let mut applied_updates = AppliedUpdates::default();
     for element in found_elements.iter() {
-        let query_doc = doc! {
-            "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
-        };
-        match Element::update_document(
+        let query_doc = element.stored_id_query();
+        let modified_count = Element::update_document(
             &database_client,
             query_doc,
             UpdateElement {
@@ -426,46 +944,61 @@ async fn lock_multiple_elements(
                 z_index: None,
                 text: None,
                 color: None,
+                metadata: None,
             },
         )
         .await
-        {
-            Ok(update_result) => match update_result.modified_count {
-                0 => {
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Lock of Element with ID {} failed", element._id),
-                    )
-                        .into_response()
-                }
-                _ => {
-                    updated_document_results.push(update_result);
-                }
-            },
-            Err(error_response) => return error_response,
+        .map(|update_result| update_result.modified_count);
+        if !applied_updates.record(&element._id, modified_count, || {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Lock of Element with ID {} failed", element._id),
+            )
+                .into_response()
+        }) {
+            break;
         }
     }
-    match updated_document_results.len() {
-        0 => (StatusCode::NOT_FOUND, "No Element found to update").into_response(),
+    let AppliedUpdates {
+        updated_ids,
+        failure: failure_response,
+    } = applied_updates;
+    match updated_ids.len() {
+        0 => failure_response.unwrap_or_else(|| {
+            (StatusCode::NOT_FOUND, "No Element found to update").into_response()
+        }),
         number => {
             info!("Updateded {} Elements", number);
-            for element_id in body.ids.iter() {
+            ElementHistory::record_multiple(
+                &database_client,
+                &updated_ids,
+                body.board_id.clone(),
+                body.user_id.clone(),
+                ElementHistoryAction::Locked,
+                None,
+            )
+            .await;
+            for element_id in updated_ids.iter() {
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        body.board_id.to_string(),
-                        ElementEvent {
-                            event_type: ElementEventType::Locked,
-                            body: serde_json::to_string(&ElementLockedEventPayload {
-                                _id: element_id.clone(),
-                                user_id: body.user_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&ElementLockedEventPayload {
+                    _id: element_id.clone(),
+                    user_id: body.user_id.clone(),
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.to_string(),
+                            ElementEvent {
+                                event_type: ElementEventType::Locked,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
             }
+            if let Some(failure_response) = failure_response {
+                return failure_response;
+            }
             (StatusCode::OK, Json(format!("{}", number))).into_response()
         }
     }
@@ -475,6 +1008,7 @@ async fn unlock_multiple_elements(
     State(AppState {
         database_client,
         element_context,
+        active_member_context,
         ..
     }): State<AppState>,
     payload: Result<Json<UnlockMultipleElementsPayload>, JsonRejection>,
@@ -485,12 +1019,13 @@ async fn unlock_multiple_elements(
             return error_response;
         }
     };
-    let query_doc = doc! {
-        "_id": doc! { "$in": body.ids.iter().map(|id| ObjectId::from_str(id.as_str()).unwrap()).collect::<Vec<ObjectId>>() }
+    let query_doc = match Element::board_scoped_ids_query(&body.ids, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let found_element_result =
         Element::get_multiple_documents(&database_client, query_doc.clone()).await;
-    let found_elements = match found_element_result {
+    let mut found_elements = match found_element_result {
         Ok(element_cursor) => {
             let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
             match retrieved_elements {
@@ -520,12 +1055,11 @@ async fn unlock_multiple_elements(
     {
         return (StatusCode::LOCKED, "Some Element is locked by another user").into_response();
     }
-    let mut updated_document_results: Vec<UpdateResult> = vec![];
+    sort_by_request_order(&mut found_elements, &body.ids);
+    let mut applied_updates = AppliedUpdates::default();
     for element in found_elements.iter() {
-        let query_doc = doc! {
-            "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
-        };
-        match Element::update_document(
+        let query_doc = element.stored_id_query();
+        let modified_count = Element::update_document(
             &database_client,
             query_doc,
             UpdateElement {
@@ -539,50 +1073,150 @@ async fn unlock_multiple_elements(
                 z_index: None,
                 text: None,
                 color: None,
+                metadata: None,
             },
         )
         .await
-        {
-            Ok(update_result) => match update_result.modified_count {
-                0 => {
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Unlock of Element with ID {} failed", element._id),
-                    )
-                        .into_response()
-                }
-                _ => {
-                    updated_document_results.push(update_result);
-                }
-            },
-            Err(error_response) => return error_response,
+        .map(|update_result| update_result.modified_count);
+        if !applied_updates.record(&element._id, modified_count, || {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Unlock of Element with ID {} failed", element._id),
+            )
+                .into_response()
+        }) {
+            break;
         }
     }
-    match updated_document_results.len() {
-        0 => (StatusCode::NOT_FOUND, "No Element found to update").into_response(),
+    let AppliedUpdates {
+        updated_ids,
+        failure: failure_response,
+    } = applied_updates;
+    match updated_ids.len() {
+        0 => failure_response.unwrap_or_else(|| {
+            (StatusCode::NOT_FOUND, "No Element found to update").into_response()
+        }),
         number => {
             info!("Updateded {} Elements", number);
-            for element_id in body.ids.iter() {
+            ElementHistory::record_multiple(
+                &database_client,
+                &updated_ids,
+                body.board_id.clone(),
+                body.user_id.clone(),
+                ElementHistoryAction::Unlocked,
+                None,
+            )
+            .await;
+            for element_id in updated_ids.iter() {
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        body.board_id.to_string(),
-                        ElementEvent {
-                            event_type: ElementEventType::Unlocked,
-                            body: serde_json::to_string(&ElementUnlockedEventPayload {
-                                _id: element_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+                    _id: element_id.clone(),
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.to_string(),
+                            ElementEvent {
+                                event_type: ElementEventType::Unlocked,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
             }
+            let mut active_member_sub_context = active_member_context.lock().await;
+            active_member_sub_context
+                .clear_activity_for_elements(body.user_id.clone(), &updated_ids)
+                .await;
+            drop(active_member_sub_context);
+            if let Some(failure_response) = failure_response {
+                return failure_response;
+            }
             (StatusCode::OK, Json(format!("{}", number))).into_response()
         }
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockedByQueryParams {
+    board_id: Option<String>,
+}
+
+async fn get_elements_locked_by_user(
+    Path(user_id): Path<String>,
+    query_params: Query<LockedByQueryParams>,
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let mut query_doc = doc! {
+        "lockedBy": user_id
+    };
+    if let Some(board_id) = query_params.board_id.clone() {
+        query_doc.insert("boardId", board_id);
+    }
+    match Element::get_multiple_documents(&database_client, query_doc).await {
+        Ok(element_cursor) => match element_cursor.try_collect::<Vec<Element>>().await {
+            Ok(retrieved_elements) => (StatusCode::OK, Json(retrieved_elements)).into_response(),
+            Err(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Found Elements could not be retrieved",
+            )
+                .into_response(),
+        },
+        Err(error_response) => error_response,
+    }
+}
+
+async fn get_lock_status_of_elements(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+    payload: Result<Json<ElementLockStatusBatchPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    if body.ids.len() > MAX_LOCK_STATUS_IDS {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "At most {} Element IDs can be requested at once",
+                MAX_LOCK_STATUS_IDS
+            ),
+        )
+            .into_response();
+    }
+    let element_ids = match body
+        .ids
+        .iter()
+        .map(|id| ObjectId::from_str(id.as_str()))
+        .collect::<Result<Vec<ObjectId>, _>>()
+    {
+        Ok(element_ids) => element_ids,
+        Err(_) => return (StatusCode::BAD_REQUEST, "Element ID is invalid").into_response(),
+    };
+    match Element::get_lock_owners(&database_client, &element_ids).await {
+        Ok(lock_owners) => (
+            StatusCode::OK,
+            Json(
+                lock_owners
+                    .into_iter()
+                    .map(|(element_id, locked_by)| ElementLockStatusResponsePayload {
+                        _id: element_id,
+                        locked: locked_by.is_some(),
+                        locked_by,
+                    })
+                    .collect::<Vec<ElementLockStatusResponsePayload>>(),
+            ),
+        )
+            .into_response(),
+        Err(error_response) => error_response,
+    }
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct UnlockAllQueryParams {
@@ -595,6 +1229,7 @@ async fn unlock_all_for_user(
     State(AppState {
         database_client,
         element_context,
+        active_member_context,
         ..
     }): State<AppState>,
 ) -> Response {
@@ -635,6 +1270,7 @@ async fn unlock_all_for_user(
             x: None,
             y: None,
             locked_by: Some(None),
+            metadata: None,
         },
     )
     .await
@@ -644,22 +1280,37 @@ async fn unlock_all_for_user(
                 .iter()
                 .map(|element| element._id.clone())
                 .collect::<Vec<String>>();
+            ElementHistory::record_multiple(
+                &database_client,
+                &ids,
+                query_params.board_id.clone(),
+                query_params.user_id.clone(),
+                ElementHistoryAction::Unlocked,
+                None,
+            )
+            .await;
             for id in ids.iter() {
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        query_params.board_id.to_string(),
-                        ElementEvent {
-                            event_type: ElementEventType::Unlocked,
-                            body: serde_json::to_string(&ElementUnlockedEventPayload {
-                                _id: id.to_string(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+                    _id: id.to_string(),
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            query_params.board_id.to_string(),
+                            ElementEvent {
+                                event_type: ElementEventType::Unlocked,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
             }
+            let mut active_member_sub_context = active_member_context.lock().await;
+            active_member_sub_context
+                .clear_activity_for_elements(query_params.user_id.clone(), &ids)
+                .await;
+            drop(active_member_sub_context);
             (StatusCode::OK, Json(ids)).into_response()
         }
         Err(error_response) => error_response,
@@ -680,8 +1331,26 @@ async fn update_element(
             return error_response;
         }
     };
-    let query_doc = doc! {
-        "_id": ObjectId::from_str(body._id.as_str()).unwrap(),
+    if let Err(message) =
+        Element::validate_element_fields(body.text.as_deref(), body.color.as_deref())
+    {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(message) = Element::check_metadata(&body.metadata) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
     match found_element_result {
@@ -697,11 +1366,60 @@ async fn update_element(
                     }
                 }
                 None => {
-                    return (
-                        StatusCode::PRECONDITION_REQUIRED,
-                        "Element needs to be locked first",
+                    if !body.reacquire_lock {
+                        return (
+                            StatusCode::PRECONDITION_REQUIRED,
+                            "Element needs to be locked first",
+                        )
+                            .into_response();
+                    }
+                    match Element::try_reacquire_lock(
+                        &database_client,
+                        body._id.as_str(),
+                        body.user_id.clone(),
                     )
-                        .into_response()
+                    .await
+                    {
+                        Ok(true) => {
+                            info!(
+                                "Reacquired lock on Element {} for User {}",
+                                body._id, body.user_id
+                            );
+                            ElementHistory::record(
+                                &database_client,
+                                body._id.clone(),
+                                body.board_id.clone(),
+                                body.user_id.clone(),
+                                ElementHistoryAction::Locked,
+                                None,
+                            )
+                            .await;
+                            let mut sub_context = element_context.lock().await;
+                            if let Ok(event_body) = serialize_or_log(&ElementLockedEventPayload {
+                                _id: body._id.clone(),
+                                user_id: body.user_id.clone(),
+                            }) {
+                                sub_context
+                                    .emit_element_event(
+                                        body.board_id.clone(),
+                                        ElementEvent {
+                                            event_type: ElementEventType::Locked,
+                                            body: event_body,
+                                        },
+                                    )
+                                    .await;
+                            }
+                            drop(sub_context);
+                        }
+                        Ok(false) => {
+                            return (
+                                StatusCode::LOCKED,
+                                "Element currently locked by someone else",
+                            )
+                                .into_response();
+                        }
+                        Err(error_response) => return error_response,
+                    }
                 }
             },
             None => {
@@ -716,51 +1434,71 @@ async fn update_element(
             return error_response;
         }
     };
-    let update_result = Element::update_document(
-        &database_client,
-        query_doc,
-        UpdateElement {
-            selected: None,
-            locked_by: None,
-            x: body.x,
-            y: body.y,
-            rotation: body.rotation,
-            scale_x: body.scale_x,
-            scale_y: body.scale_y,
-            z_index: body.z_index,
-            text: body.text.clone(),
-            color: body.color.clone(),
-        },
-    )
-    .await;
+    let update_element = UpdateElement {
+        selected: None,
+        locked_by: None,
+        x: body.x,
+        y: body.y,
+        rotation: body.rotation.map(normalize_rotation),
+        scale_x: body.scale_x,
+        scale_y: body.scale_y,
+        z_index: body.z_index,
+        text: body.text.clone(),
+        color: body.color.clone(),
+        metadata: body.metadata.clone(),
+    };
+    let changes = update_element.to_update_fields();
+    let changes_geometry = update_element.changes_geometry();
+    let update_result = Element::update_document(&database_client, query_doc, update_element).await;
     match update_result {
         Ok(result) => match result.modified_count {
             0 => (StatusCode::NOT_FOUND, "No Element found to update").into_response(),
             _ => {
                 info!("Updated Element with ID: {}", body._id.clone());
+                ElementHistory::record(
+                    &database_client,
+                    body._id.clone(),
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Updated,
+                    Some(changes),
+                )
+                .await;
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        body.board_id.clone(),
-                        ElementEvent {
-                            event_type: ElementEventType::Updated,
-                            body: serde_json::to_string(&UpdatedElementEventPayload {
-                                _id: body._id.clone(),
-                                user_id: body.user_id.clone(),
-                                text: body.text.clone(),
-                                z_index: body.z_index,
-                                scale_x: body.scale_x,
-                                scale_y: body.scale_y,
-                                rotation: body.rotation,
-                                x: body.x,
-                                y: body.y,
-                                color: body.color.clone(),
-                            })
-                            .unwrap(),
-                        },
+                if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+                    _id: body._id.clone(),
+                    user_id: body.user_id.clone(),
+                    text: body.text.clone(),
+                    z_index: body.z_index,
+                    scale_x: body.scale_x,
+                    scale_y: body.scale_y,
+                    rotation: body.rotation.map(normalize_rotation),
+                    x: body.x,
+                    y: body.y,
+                    color: body.color.clone(),
+                    metadata: body.metadata.clone(),
+                    asset_id: None,
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.clone(),
+                            ElementEvent {
+                                event_type: ElementEventType::Updated,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(sub_context);
+                if changes_geometry {
+                    emit_connector_updates(
+                        &database_client,
+                        &element_context,
+                        &body.board_id,
+                        std::slice::from_ref(&body._id),
                     )
                     .await;
-                drop(sub_context);
+                }
                 (StatusCode::OK, Json(body._id.clone())).into_response()
             }
         },
@@ -768,6 +1506,289 @@ async fn update_element(
     }
 }
 
+// Quick edits in a single round trip, the lock is only taken if it is free or already held by the user
+async fn release_acquired_lock(
+    database_client: &Client,
+    element_context: &Arc<Mutex<ElementContext>>,
+    body: &LockUpdateUnlockElementPayload,
+) {
+    let mut query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(_) => return,
+    };
+    query_doc.insert("lockedBy", body.user_id.clone());
+    let release_element = UpdateElement {
+        selected: None,
+        locked_by: Some(None),
+        x: None,
+        y: None,
+        rotation: None,
+        scale_x: None,
+        scale_y: None,
+        z_index: None,
+        text: None,
+        color: None,
+        metadata: None,
+    };
+    match Element::update_document(database_client, query_doc, release_element).await {
+        Ok(result) if result.matched_count > 0 => {}
+        _ => {
+            warn!("Lock of Element with ID {} could not be released", body._id);
+            return;
+        }
+    }
+    ElementHistory::record(
+        database_client,
+        body._id.clone(),
+        body.board_id.clone(),
+        body.user_id.clone(),
+        ElementHistoryAction::Unlocked,
+        None,
+    )
+    .await;
+    let mut sub_context = element_context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+        _id: body._id.clone(),
+    }) {
+        sub_context
+            .emit_element_event(
+                body.board_id.clone(),
+                ElementEvent {
+                    event_type: ElementEventType::Unlocked,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(sub_context);
+}
+
+async fn lock_update_unlock_element(
+    State(AppState {
+        database_client,
+        element_context,
+        active_member_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<LockUpdateUnlockElementPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    if let Err(message) =
+        Element::validate_element_fields(body.text.as_deref(), body.color.as_deref())
+    {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(message) = Element::check_metadata(&body.metadata) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let element = match Element::get_existing_element(body._id.clone(), &database_client).await {
+        Ok(element) => element,
+        Err(error_response) => return error_response,
+    };
+    if element.board_id != body.board_id {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("No Element found with ID: {}", body._id),
+        )
+            .into_response();
+    }
+    let mut lock_acquired = false;
+    match element.locked_by {
+        Some(locked_by) if locked_by != body.user_id => {
+            return (
+                StatusCode::LOCKED,
+                "Element currently locked by someone else",
+            )
+                .into_response();
+        }
+        Some(_) => {}
+        None => {
+            match Element::try_reacquire_lock(
+                &database_client,
+                body._id.as_str(),
+                body.user_id.clone(),
+            )
+            .await
+            {
+                Ok(true) => {
+                    lock_acquired = true;
+                    ElementHistory::record(
+                        &database_client,
+                        body._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Locked,
+                        None,
+                    )
+                    .await;
+                    let mut sub_context = element_context.lock().await;
+                    if let Ok(event_body) = serialize_or_log(&ElementLockedEventPayload {
+                        _id: body._id.clone(),
+                        user_id: body.user_id.clone(),
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                body.board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Locked,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
+                    drop(sub_context);
+                }
+                Ok(false) => {
+                    return (
+                        StatusCode::LOCKED,
+                        "Element currently locked by someone else",
+                    )
+                        .into_response();
+                }
+                Err(error_response) => return error_response,
+            }
+        }
+    }
+    let update_element = UpdateElement {
+        selected: None,
+        locked_by: None,
+        x: body.x,
+        y: body.y,
+        rotation: body.rotation.map(normalize_rotation),
+        scale_x: body.scale_x,
+        scale_y: body.scale_y,
+        z_index: body.z_index,
+        text: body.text.clone(),
+        color: body.color.clone(),
+        metadata: body.metadata.clone(),
+    };
+    let changes = update_element.to_update_fields();
+    let changes_geometry = update_element.changes_geometry();
+    // The update and the release are a single write, guarded by the lock taken above
+    let update_element = UpdateElement {
+        locked_by: match body.keep_locked {
+            true => None,
+            false => Some(None),
+        },
+        ..update_element
+    };
+    let mut query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    query_doc.insert("lockedBy", body.user_id.clone());
+    let update_error =
+        match Element::update_document(&database_client, query_doc, update_element).await {
+            Ok(result) if result.matched_count == 0 => Some(
+                (
+                    StatusCode::LOCKED,
+                    "Element currently locked by someone else",
+                )
+                    .into_response(),
+            ),
+            Ok(_) => None,
+            Err(error_response) => Some(error_response),
+        };
+    if let Some(error_response) = update_error {
+        // A lock taken by this request must not outlive the failed update
+        if lock_acquired {
+            release_acquired_lock(&database_client, &element_context, &body).await;
+        }
+        return error_response;
+    }
+    info!("Updated Element with ID: {}", body._id.clone());
+    ElementHistory::record(
+        &database_client,
+        body._id.clone(),
+        body.board_id.clone(),
+        body.user_id.clone(),
+        ElementHistoryAction::Updated,
+        Some(changes),
+    )
+    .await;
+    let mut sub_context = element_context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+        _id: body._id.clone(),
+        user_id: body.user_id.clone(),
+        text: body.text.clone(),
+        z_index: body.z_index,
+        scale_x: body.scale_x,
+        scale_y: body.scale_y,
+        rotation: body.rotation.map(normalize_rotation),
+        x: body.x,
+        y: body.y,
+        color: body.color.clone(),
+        metadata: body.metadata.clone(),
+        asset_id: None,
+    }) {
+        sub_context
+            .emit_element_event(
+                body.board_id.clone(),
+                ElementEvent {
+                    event_type: ElementEventType::Updated,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(sub_context);
+    if changes_geometry {
+        emit_connector_updates(
+            &database_client,
+            &element_context,
+            &body.board_id,
+            std::slice::from_ref(&body._id),
+        )
+        .await;
+    }
+    if !body.keep_locked {
+        ElementHistory::record(
+            &database_client,
+            body._id.clone(),
+            body.board_id.clone(),
+            body.user_id.clone(),
+            ElementHistoryAction::Unlocked,
+            None,
+        )
+        .await;
+        let mut sub_context = element_context.lock().await;
+        if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+            _id: body._id.clone(),
+        }) {
+            sub_context
+                .emit_element_event(
+                    body.board_id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Unlocked,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+        drop(sub_context);
+        let mut active_member_sub_context = active_member_context.lock().await;
+        active_member_sub_context
+            .clear_activity_for_elements(body.user_id.clone(), std::slice::from_ref(&body._id))
+            .await;
+        drop(active_member_sub_context);
+    }
+    (StatusCode::OK, Json(body._id.clone())).into_response()
+}
+
 async fn move_multiple_elements(
     State(AppState {
         database_client,
@@ -782,12 +1803,22 @@ async fn move_multiple_elements(
             return error_response;
         }
     };
-    let query_doc = doc! {
-        "_id": doc! { "$in": body.ids.iter().map(|id| ObjectId::from_str(id.as_str()).unwrap()).collect::<Vec<ObjectId>>() }
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let query_doc = match Element::board_scoped_ids_query(&body.ids, &body.board_id) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
     };
     let found_element_result =
         Element::get_multiple_documents(&database_client, query_doc.clone()).await;
-    let found_elements = match found_element_result {
+    let mut found_elements = match found_element_result {
         Ok(element_cursor) => {
             let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
             match retrieved_elements {
@@ -817,12 +1848,11 @@ async fn move_multiple_elements(
     {
         return (StatusCode::LOCKED, "Some Element is locked by another user").into_response();
     }
-    let mut updated_document_results: Vec<UpdateResult> = vec![];
+    sort_by_request_order(&mut found_elements, &body.ids);
+    let mut applied_updates = AppliedUpdates::default();
     for element in found_elements.iter() {
-        let query_doc = doc! {
-            "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
-        };
-        match Element::update_document(
+        let query_doc = element.stored_id_query();
+        let modified_count = Element::update_document(
             &database_client,
             query_doc,
             UpdateElement {
@@ -836,49 +1866,569 @@ async fn move_multiple_elements(
                 z_index: None,
                 text: None,
                 color: None,
+                metadata: None,
             },
         )
         .await
+        .map(|update_result| update_result.modified_count);
+        if !applied_updates.record(&element._id, modified_count, || {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Move of Element with ID {} failed", element._id),
+            )
+                .into_response()
+        }) {
+            break;
+        }
+    }
+    let AppliedUpdates {
+        updated_ids,
+        failure: failure_response,
+    } = applied_updates;
+    match updated_ids.len() {
+        0 => failure_response.unwrap_or_else(|| {
+            (StatusCode::NOT_FOUND, "No Element found to update").into_response()
+        }),
+        number => {
+            info!("Updateded {} Elements", number);
+            ElementHistory::record_multiple(
+                &database_client,
+                &updated_ids,
+                body.board_id.clone(),
+                body.user_id.clone(),
+                ElementHistoryAction::Moved,
+                Some(doc! {
+                    "xOffset": body.x_offset,
+                    "yOffset": body.y_offset,
+                }),
+            )
+            .await;
+            for element_id in updated_ids.iter() {
+                let mut sub_context = element_context.lock().await;
+                if let Ok(event_body) = serialize_or_log(&ElementMovedEventPayload {
+                    user_id: body.user_id.clone(),
+                    _id: element_id.to_string(),
+                    x_offset: body.x_offset,
+                    y_offset: body.y_offset,
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.to_string(),
+                            ElementEvent {
+                                event_type: ElementEventType::Moved,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(sub_context);
+            }
+            emit_connector_updates(
+                &database_client,
+                &element_context,
+                &body.board_id,
+                &updated_ids,
+            )
+            .await;
+            if let Some(failure_response) = failure_response {
+                return failure_response;
+            }
+            (StatusCode::OK, Json(format!("{}", number))).into_response()
+        }
+    }
+}
+
+async fn move_elements_to_board(
+    State(AppState {
+        database_client,
+        board_context,
+        element_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<MoveElementsToBoardPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    if body.ids.is_empty() {
+        return (StatusCode::BAD_REQUEST, "No Element IDs provided").into_response();
+    }
+    if body.from_board_id == body.to_board_id {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Source and target Board must be different",
+        )
+            .into_response();
+    }
+    for board_id in [&body.from_board_id, &body.to_board_id] {
+        if let Err(error_response) =
+            Board::assert_member(board_id.clone(), body.user_id.clone(), &database_client).await
         {
-            Ok(update_result) => match update_result.modified_count {
-                0 => {
+            return error_response;
+        }
+    }
+    let mut object_ids = vec![];
+    for id in body.ids.iter() {
+        match ObjectId::from_str(id.as_str()) {
+            Ok(object_id) => object_ids.push(object_id),
+            Err(_) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    format!("Element ID {} is invalid", id),
+                )
+                    .into_response()
+            }
+        }
+    }
+    let query_doc = doc! {
+        "_id": doc! { "$in": object_ids }
+    };
+    let found_elements =
+        match Element::get_multiple_documents(&database_client, query_doc.clone()).await {
+            Ok(element_cursor) => match element_cursor.try_collect::<Vec<Element>>().await {
+                Ok(retrieved_elements) => retrieved_elements,
+                Err(_) => {
                     return (
                         StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Move of Element with ID {} failed", element._id),
+                        "Found Elements could not be retrieved",
                     )
-                        .into_response()
+                        .into_response();
+                }
+            },
+            Err(error_response) => {
+                return error_response;
+            }
+        };
+    if let Some(missing_id) = body
+        .ids
+        .iter()
+        .find(|id| !found_elements.iter().any(|element| element._id == **id))
+    {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("No Element found with ID: {}", missing_id),
+        )
+            .into_response();
+    }
+    if let Some(foreign_element) = found_elements
+        .iter()
+        .find(|element| element.board_id != body.from_board_id)
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Element {} does not belong to the Board {}",
+                foreign_element._id, body.from_board_id
+            ),
+        )
+            .into_response();
+    }
+    if found_elements
+        .iter()
+        .any(|element| match &element.locked_by {
+            Some(locked_by) => *locked_by != body.user_id,
+            None => false,
+        })
+    {
+        return (StatusCode::LOCKED, "Some Element is locked by another user").into_response();
+    }
+    let element_count =
+        match Element::count_of_board(&database_client, body.to_board_id.clone()).await {
+            Ok(element_count) => element_count,
+            Err(error_response) => return error_response,
+        };
+    let element_limit = match Board::get_element_limit(&body.to_board_id, &database_client).await {
+        Ok(element_limit) => element_limit,
+        Err(error_response) => return error_response,
+    };
+    if element_count + found_elements.len() as u64 > element_limit {
+        return (
+            StatusCode::CONFLICT,
+            "Target Board would exceed its element limit",
+        )
+            .into_response();
+    }
+    for element in found_elements.iter() {
+        match Element::violates_singleton(
+            &database_client,
+            &body.to_board_id,
+            &element.element_type,
+        )
+        .await
+        {
+            Ok(true) => {
+                return (
+                    StatusCode::CONFLICT,
+                    "Target Board already contains an Element of this singleton type",
+                )
+                    .into_response();
+            }
+            Ok(false) => {}
+            Err(error_response) => return error_response,
+        }
+    }
+    if let Err(error_response) = Element::move_to_board(
+        &database_client,
+        &found_elements,
+        &body.from_board_id,
+        &body.to_board_id,
+        &body.user_id,
+    )
+    .await
+    {
+        return error_response;
+    }
+    info!(
+        "Moved {} Elements from Board {} to Board {}",
+        body.ids.len(),
+        body.from_board_id,
+        body.to_board_id
+    );
+    ElementHistory::record_multiple(
+        &database_client,
+        &body.ids,
+        body.to_board_id.clone(),
+        body.user_id.clone(),
+        ElementHistoryAction::Moved,
+        Some(doc! {
+            "fromBoardId": body.from_board_id.clone(),
+            "toBoardId": body.to_board_id.clone(),
+        }),
+    )
+    .await;
+    // Fetched again, texts and connectors may have changed on the way
+    let moved_elements = match Element::get_multiple_documents(&database_client, query_doc).await {
+        Ok(element_cursor) => element_cursor
+            .try_collect::<Vec<Element>>()
+            .await
+            .unwrap_or_default(),
+        Err(_) => vec![],
+    };
+    let mut sub_context = element_context.lock().await;
+    for element_id in body.ids.iter() {
+        if let Ok(event_body) = serialize_or_log(&ElementRemovedEventPayload {
+            _id: element_id.clone(),
+            user_id: body.user_id.clone(),
+        }) {
+            sub_context
+                .emit_element_event(
+                    body.from_board_id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Removed,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    for element in moved_elements {
+        if let Ok(event_body) = serialize_or_log(&ElementCreatedEventPayload {
+            _id: element._id,
+            user_id: body.user_id.clone(),
+            board_id: element.board_id,
+            x: element.x,
+            y: element.y,
+            text: element.text,
+            scale_x: element.scale_x,
+            scale_y: element.scale_y,
+            z_index: element.z_index,
+            selected: element.selected,
+            created_at: element.created_at,
+            rotation: element.rotation,
+            locked_by: element.locked_by,
+            element_type: element.element_type,
+            color: element.color,
+            metadata: element.metadata,
+            asset_id: element.asset_id,
+            created_by: element.created_by,
+            connector: element.connector,
+        }) {
+            sub_context
+                .emit_element_event(
+                    body.to_board_id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Created,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(sub_context);
+    emit_near_limit_if_reached(&database_client, &board_context, &body.to_board_id).await;
+    remove_connectors_of(
+        &database_client,
+        &element_context,
+        &body.from_board_id,
+        &body.user_id,
+        &body.ids,
+    )
+    .await;
+    if let Ok(0) = Element::count_of_board(&database_client, body.from_board_id.clone()).await {
+        let mut sub_context = board_context.lock().await;
+        if let Ok(event_body) = serialize_or_log(&BoardEmptiedEventPayload {
+            user_id: body.user_id.clone(),
+        }) {
+            sub_context
+                .emit_board_event(
+                    database_client.clone(),
+                    body.from_board_id.clone(),
+                    BoardEvent {
+                        event_type: BoardEventType::Emptied,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+        drop(sub_context);
+    }
+    (StatusCode::OK, Json(format!("{}", body.ids.len()))).into_response()
+}
+
+/// Builds the update for the fields of a single entry of a multi element update, fields that were
+/// not sent are left untouched
+fn fields_update(fields: &ElementFieldsPayload) -> UpdateElement {
+    UpdateElement {
+        selected: None,
+        locked_by: None,
+        x: None,
+        y: None,
+        rotation: fields.rotation.map(normalize_rotation),
+        scale_x: fields.scale_x,
+        scale_y: fields.scale_y,
+        z_index: fields.z_index,
+        text: fields.text.clone(),
+        color: fields.color.clone(),
+        metadata: None,
+    }
+}
+
+async fn update_multiple_elements(
+    State(AppState {
+        database_client,
+        element_context,
+        ..
+    }): State<AppState>,
+    payload: Result<Json<UpdateMultipleElementsPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    for update in body.updates.iter() {
+        if let Err(message) = Element::validate_element_fields(
+            update.fields.text.as_deref(),
+            update.fields.color.as_deref(),
+        ) {
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    }
+    if let Err(error_response) = Board::assert_member(
+        body.board_id.clone(),
+        body.user_id.clone(),
+        &database_client,
+    )
+    .await
+    {
+        return error_response;
+    }
+    let query_doc = match Element::board_scoped_ids_query(
+        &body
+            .updates
+            .iter()
+            .map(|update| update._id.clone())
+            .collect::<Vec<String>>(),
+        &body.board_id,
+    ) {
+        Ok(query_doc) => query_doc,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    let found_element_result =
+        Element::get_multiple_documents(&database_client, query_doc.clone()).await;
+    let found_elements = match found_element_result {
+        Ok(element_cursor) => {
+            let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
+            match retrieved_elements {
+                Ok(retrieved_elements) => match retrieved_elements.len() {
+                    0 => return (StatusCode::NOT_FOUND, "No Elements found").into_response(),
+                    _ => retrieved_elements,
+                },
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Found Elements could not be retrieved",
+                    )
+                        .into_response();
+                }
+            }
+        }
+        Err(error_response) => {
+            return error_response;
+        }
+    };
+    if let Some(missing_update) = body.updates.iter().find(|update| {
+        !found_elements
+            .iter()
+            .any(|element| element._id == update._id)
+    }) {
+        return (
+            StatusCode::NOT_FOUND,
+            format!("No Element found with ID: {}", missing_update._id),
+        )
+            .into_response();
+    }
+    if found_elements
+        .iter()
+        .any(|element| match &element.locked_by {
+            Some(locked_by) => *locked_by != body.user_id,
+            None => false,
+        })
+    {
+        return (StatusCode::LOCKED, "Some Element is locked by another user").into_response();
+    }
+    // Events only cover the updates that were actually applied, a failed update stops the remaining
+    // ones but the earlier updates are still announced
+    let mut applied_updates = vec![];
+    let mut reshaped_ids: Vec<String> = vec![];
+    let mut failure_response: Option<Response> = None;
+    for update in body.updates.iter() {
+        let query_doc = match Element::board_scoped_query(&update._id, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                failure_response = Some((StatusCode::BAD_REQUEST, message).into_response());
+                break;
+            }
+        };
+        let update_element = fields_update(&update.fields);
+        let changes = update_element.to_update_fields();
+        let changes_geometry = update_element.changes_geometry();
+        match Element::update_document(&database_client, query_doc, update_element).await {
+            Ok(update_result) => match update_result.matched_count {
+                0 => {
+                    failure_response = Some(
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("Update of Element with ID {} failed", update._id),
+                        )
+                            .into_response(),
+                    );
+                    break;
                 }
                 _ => {
-                    updated_document_results.push(update_result);
+                    ElementHistory::record(
+                        &database_client,
+                        update._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Updated,
+                        Some(changes),
+                    )
+                    .await;
+                    if changes_geometry {
+                        reshaped_ids.push(update._id.clone());
+                    }
+                    applied_updates.push(update);
                 }
             },
-            Err(error_response) => return error_response,
+            Err(error_response) => {
+                failure_response = Some(error_response);
+                break;
+            }
         }
     }
-    match updated_document_results.len() {
-        0 => (StatusCode::NOT_FOUND, "No Element found to update").into_response(),
+    match applied_updates.len() {
+        0 => failure_response.unwrap_or_else(|| {
+            (StatusCode::NOT_FOUND, "No Element found to update").into_response()
+        }),
         number => {
             info!("Updateded {} Elements", number);
-            for element_id in body.ids.iter() {
+            for update in applied_updates.iter() {
                 let mut sub_context = element_context.lock().await;
-                sub_context
-                    .emit_element_event(
-                        body.board_id.to_string(),
-                        ElementEvent {
-                            event_type: ElementEventType::Moved,
-                            body: serde_json::to_string(&ElementMovedEventPayload {
-                                user_id: body.user_id.clone(),
-                                _id: element_id.to_string(),
-                                x_offset: body.x_offset,
-                                y_offset: body.y_offset,
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+                    _id: update._id.clone(),
+                    user_id: body.user_id.clone(),
+                    text: update.fields.text.clone(),
+                    z_index: update.fields.z_index,
+                    scale_x: update.fields.scale_x,
+                    scale_y: update.fields.scale_y,
+                    rotation: update.fields.rotation.map(normalize_rotation),
+                    x: None,
+                    y: None,
+                    color: update.fields.color.clone(),
+                    metadata: None,
+                    asset_id: None,
+                }) {
+                    sub_context
+                        .emit_element_event(
+                            body.board_id.to_string(),
+                            ElementEvent {
+                                event_type: ElementEventType::Updated,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
             }
+            emit_connector_updates(
+                &database_client,
+                &element_context,
+                &body.board_id,
+                &reshaped_ids,
+            )
+            .await;
+            if let Some(failure_response) = failure_response {
+                return failure_response;
+            }
             (StatusCode::OK, Json(format!("{}", number))).into_response()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(
+        text: Option<&str>,
+        z_index: Option<i32>,
+        rotation: Option<f32>,
+    ) -> ElementFieldsPayload {
+        ElementFieldsPayload {
+            rotation,
+            scale_x: None,
+            scale_y: None,
+            z_index,
+            text: text.map(str::to_string),
+            color: None,
+        }
+    }
+
+    #[test]
+    fn fields_update_only_sets_sent_fields() {
+        let selection = [
+            fields(Some("note"), None, None),
+            fields(None, Some(3), None),
+            fields(None, None, Some(-90.0)),
+        ];
+        let update_fields = selection
+            .iter()
+            .map(|entry| fields_update(entry).to_update_fields())
+            .collect::<Vec<bson::Document>>();
+        assert_eq!(update_fields[0], doc! { "text": "note" });
+        assert_eq!(update_fields[1], doc! { "zIndex": 3 });
+        assert_eq!(update_fields[2], doc! { "rotation": 270.0_f32 });
+    }
+
+    #[test]
+    fn fields_update_without_fields_is_empty() {
+        let update_element = fields_update(&fields(None, None, None));
+        assert!(update_element.to_update_fields().is_empty());
+        assert!(!update_element.changes_geometry());
+    }
+}
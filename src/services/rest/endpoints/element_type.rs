@@ -1,4 +1,4 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr};
 
 use axum::{
     extract::{rejection::JsonRejection, Json, Path, State},
@@ -16,17 +16,20 @@ use crate::{
         collections::element_type::{CreateElementType, ElementType},
         document::Document,
     },
-    utils::check_request_body::check_request_body,
+    utils::{check_request_body::check_request_body, inserted_object_id::inserted_object_id_hex},
     AppState,
 };
 
 use super::super::payloads::element_type::CreateElementTypePayload;
 
+const UNCATEGORIZED_ELEMENT_TYPE_GROUP: &str = "Other";
+
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .route("/element-type", post(create_element_type))
         .route("/element-type/:id", get(get_element_type))
         .route("/element-types", get(get_all_element_types))
+        .route("/element-types/grouped", get(get_grouped_element_types))
 }
 
 // Element type services ========================================
@@ -41,17 +44,31 @@ async fn create_element_type(
         Ok(success_body) => success_body,
         Err(error_response) => return error_response,
     };
+    if let Err(message) = ElementType::validate_path(&body.path) {
+        return (StatusCode::BAD_REQUEST, message).into_response();
+    }
+    if let Some(defaults) = &body.defaults {
+        if let Err(message) = defaults.validate() {
+            return (StatusCode::BAD_REQUEST, message).into_response();
+        }
+    }
     let create_element_type_result = ElementType::create_document(
         &database_client,
         CreateElementType {
             name: body.name.clone(),
             path: body.path.clone(),
+            category: body.category.clone(),
+            defaults: body.defaults.clone(),
+            singleton: body.singleton,
         },
     )
     .await;
     match create_element_type_result {
         Ok(result) => {
-            let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+            let inserted_id = match inserted_object_id_hex(&result) {
+                Ok(inserted_id) => inserted_id,
+                Err(error_response) => return error_response,
+            };
             info!("Created Element Type with ID: {}", inserted_id);
             (StatusCode::OK, Json(inserted_id)).into_response()
         }
@@ -107,3 +124,42 @@ async fn get_all_element_types(
         _ => (StatusCode::OK, Json(element_types)).into_response(),
     }
 }
+
+async fn get_grouped_element_types(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let query_doc = doc! {};
+    let element_types = match ElementType::get_multiple_documents(&database_client, query_doc).await
+    {
+        Ok(element_type_cursor) => {
+            match element_type_cursor.try_collect::<Vec<ElementType>>().await {
+                Ok(retrieved_element_types) => retrieved_element_types,
+                Err(_) => {
+                    return (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "Found Element Types could not be retrieved",
+                    )
+                        .into_response()
+                }
+            }
+        }
+        Err(error_response) => return error_response,
+    };
+    if element_types.is_empty() {
+        return (StatusCode::NOT_FOUND, "No Element Types found").into_response();
+    }
+    let mut grouped_element_types = BTreeMap::<String, Vec<ElementType>>::new();
+    for element_type in element_types {
+        let category = element_type
+            .category
+            .clone()
+            .unwrap_or_else(|| UNCATEGORIZED_ELEMENT_TYPE_GROUP.to_string());
+        grouped_element_types
+            .entry(category)
+            .or_default()
+            .push(element_type);
+    }
+    (StatusCode::OK, Json(grouped_element_types)).into_response()
+}
@@ -1,16 +1,91 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
 use axum::{
+    extract::State,
     http::StatusCode,
     response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
+use bson::doc;
+use tokio::time::timeout;
+use tracing::warn;
+
+use crate::{
+    services::{
+        rest::payloads::ping::MetricsResponsePayload,
+        webtransport::event_delivery::{
+            dropped_event_count, element_event_fanout, MAX_CONCURRENT_EVENT_WRITES,
+        },
+    },
+    AppState,
+};
 
-use crate::AppState;
+const READINESS_PING_TIMEOUT: Duration = Duration::from_secs(2);
 
 pub fn get_routes() -> Router<AppState> {
-    Router::new().route("/ping", get(ping))
+    Router::new()
+        .route("/ping", get(ping))
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
 }
 
 pub async fn ping() -> Response {
     (StatusCode::OK, Json("Health Check OK")).into_response()
 }
+
+// Liveness only tells whether the process still answers, so it never touches the database
+pub async fn livez() -> Response {
+    (StatusCode::OK, Json("Alive")).into_response()
+}
+
+pub async fn readyz(
+    State(AppState {
+        database_client,
+        ready,
+        ..
+    }): State<AppState>,
+) -> Response {
+    if !ready.load(Ordering::Relaxed) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Startup provisioning not completed",
+        )
+            .into_response();
+    }
+    let ping_result = timeout(
+        READINESS_PING_TIMEOUT,
+        database_client
+            .database("admin")
+            .run_command(doc! { "ping": 1 }, None),
+    )
+    .await;
+    match ping_result {
+        Ok(Ok(_)) => (StatusCode::OK, Json("Ready")).into_response(),
+        Ok(Err(error)) => {
+            warn!("Readiness check failed, MongoDB ping failed: {}", error);
+            (StatusCode::SERVICE_UNAVAILABLE, "MongoDB is not reachable").into_response()
+        }
+        Err(_) => {
+            warn!("Readiness check failed, MongoDB ping timed out");
+            (StatusCode::SERVICE_UNAVAILABLE, "MongoDB is not reachable").into_response()
+        }
+    }
+}
+pub async fn metrics(State(AppState { session_limit, .. }): State<AppState>) -> Response {
+    let element_event_fanout = element_event_fanout();
+    (
+        StatusCode::OK,
+        Json(MetricsResponsePayload {
+            active_webtransport_sessions: session_limit.active_sessions(),
+            max_webtransport_sessions: session_limit.max_sessions(),
+            dropped_webtransport_events: dropped_event_count(),
+            emitted_element_events: element_event_fanout.events,
+            element_event_fanout_total: element_event_fanout.total_fanout,
+            element_event_fanout_max: element_event_fanout.max_fanout,
+            max_concurrent_event_writes: MAX_CONCURRENT_EVENT_WRITES(),
+        }),
+    )
+        .into_response()
+}
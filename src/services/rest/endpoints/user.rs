@@ -8,7 +8,7 @@ use axum::{
     response::{IntoResponse, Response},
     routing::{delete, get, post, Router},
 };
-use bson::{doc, oid::ObjectId};
+use bson::{doc, oid::ObjectId, DateTime};
 
 use crate::{
     database::{
@@ -20,23 +20,31 @@ use crate::{
     },
     services::{
         rest::payloads::user::{
-            CreateUserResponsePayload, LoginUserPayload, LoginUserResponsePayload,
+            CreateUserResponsePayload, GetUsersBatchPayload, GuestUserResponsePayload,
+            LoginUserPayload, LoginUserResponsePayload, UserResponsePayload,
         },
         webtransport::{
             context::client::{ClientEvent, ClientEventType},
             messages::client::ClientCreatedOrUpdatedPayload,
         },
     },
-    utils::check_request_body::check_request_body,
+    utils::{
+        check_request_body::check_request_body, inserted_object_id::inserted_object_id_hex,
+        serialize_or_log::serialize_or_log,
+    },
     AppState,
 };
 
-use super::super::payloads::user::CreateUserPayload;
+use super::{super::payloads::user::CreateUserPayload, client::activate_client};
+
+const MAX_BATCH_USER_IDS: usize = 100;
 
 pub fn get_routes() -> Router<AppState> {
     Router::new()
         .route("/user/:id", get(get_user))
+        .route("/users/batch", post(get_users_batch))
         .route("/register", post(create_user))
+        .route("/guest", post(create_guest_user))
         .route("/user", get(get_user_by_email_or_name))
         .route("/login", post(login))
         .route("/logout/:userId", delete(logout))
@@ -51,7 +59,11 @@ async fn create_user(
     payload: Result<Json<CreateUserPayload>, JsonRejection>,
 ) -> Response {
     let body = match check_request_body(payload) {
-        Ok(success_body) => success_body,
+        Ok(Json(success_body)) => CreateUserPayload {
+            name: User::normalize_name(&success_body.name),
+            email: User::normalize_email(&success_body.email),
+            ..success_body
+        },
         Err(error_response) => return error_response,
     };
     if body.name.is_empty() {
@@ -85,19 +97,25 @@ async fn create_user(
     let created_user = CreateUser {
         _id: ObjectId::new(),
         name: body.name.to_string(),
-        email: body.email.to_string(),
-        password: body.password.to_string(),
+        email: Some(body.email.to_string()),
+        password: Some(body.password.to_string()),
+        is_guest: false,
+        last_active_at: DateTime::now(),
+        guest_token_hash: None,
     };
     let create_user_result = User::create_document(&database_client, created_user.clone()).await;
     match create_user_result {
         Ok(result) => {
-            let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+            let inserted_id = match inserted_object_id_hex(&result) {
+                Ok(inserted_id) => inserted_id,
+                Err(error_response) => return error_response,
+            };
             info!("Created user with ID: {}", inserted_id);
             (
                 StatusCode::OK,
                 Json(CreateUserResponsePayload {
                     id: created_user._id.to_hex(),
-                    email: created_user.email,
+                    email: created_user.email.unwrap_or_default(),
                     name: created_user.name,
                 }),
             )
@@ -107,6 +125,47 @@ async fn create_user(
     }
 }
 
+async fn create_guest_user(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+) -> Response {
+    let Some(guest_token) = User::generate_guest_token() else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Guest token could not be generated",
+        )
+            .into_response();
+    };
+    let guest_id = ObjectId::new();
+    let guest_id_hex = guest_id.to_hex();
+    let created_guest = CreateUser {
+        _id: guest_id,
+        name: format!("Guest-{}", &guest_id_hex[guest_id_hex.len() - 6..]),
+        email: None,
+        password: None,
+        is_guest: true,
+        last_active_at: DateTime::now(),
+        guest_token_hash: Some(User::hash_guest_token(&guest_token)),
+    };
+    match User::create_document(&database_client, created_guest.clone()).await {
+        Ok(_) => {
+            info!("Created guest user with ID: {}", guest_id_hex);
+            (
+                StatusCode::OK,
+                Json(GuestUserResponsePayload {
+                    id: guest_id_hex,
+                    name: created_guest.name,
+                    is_guest: true,
+                    token: guest_token,
+                }),
+            )
+                .into_response()
+        }
+        Err(error_response) => error_response,
+    }
+}
+
 async fn get_user(
     Path(user_id): Path<String>,
     State(AppState {
@@ -132,6 +191,52 @@ async fn get_user(
     }
 }
 
+async fn get_users_batch(
+    State(AppState {
+        database_client, ..
+    }): State<AppState>,
+    payload: Result<Json<GetUsersBatchPayload>, JsonRejection>,
+) -> Response {
+    let body = match check_request_body(payload) {
+        Ok(success_body) => success_body,
+        Err(error_response) => return error_response,
+    };
+    if body.ids.len() > MAX_BATCH_USER_IDS {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!(
+                "At most {} user ids can be requested at once",
+                MAX_BATCH_USER_IDS
+            ),
+        )
+            .into_response();
+    }
+    match User::get_users_by_ids(&body.ids, &database_client).await {
+        Ok(users) => {
+            info!(
+                "Fetched {} of {} requested Users",
+                users.len(),
+                body.ids.len()
+            );
+            (
+                StatusCode::OK,
+                Json(
+                    users
+                        .into_iter()
+                        .map(|user| UserResponsePayload {
+                            id: user._id,
+                            name: user.name,
+                            email: user.email,
+                        })
+                        .collect::<Vec<UserResponsePayload>>(),
+                ),
+            )
+                .into_response()
+        }
+        Err(error_response) => error_response,
+    }
+}
+
 async fn get_user_by_email_or_name(
     Query(query_params): Query<HashMap<String, String>>,
     State(AppState {
@@ -142,7 +247,7 @@ async fn get_user_by_email_or_name(
     if query_params.contains_key("name") {
         search_by_name = true;
     }
-    if !search_by_name && query_params.contains_key("email") {
+    if !search_by_name && !query_params.contains_key("email") {
         return (
             StatusCode::BAD_REQUEST,
             "Query param \"email\" needed at least",
@@ -151,7 +256,7 @@ async fn get_user_by_email_or_name(
     }
     if search_by_name {
         let query_doc = doc! {
-            "name": query_params.get("name").unwrap().clone()
+            "name": User::normalize_name(query_params.get("name").unwrap())
         };
         let found_users_result = User::get_multiple_documents(&database_client, query_doc).await;
         match found_users_result {
@@ -169,7 +274,7 @@ async fn get_user_by_email_or_name(
         }
     } else {
         let query_doc = doc! {
-            "email": query_params.get("email").unwrap().clone()
+            "email": User::normalize_email(query_params.get("email").unwrap())
         };
         let found_user = User::get_document(&database_client, query_doc).await;
         match found_user {
@@ -202,17 +307,20 @@ async fn login(
             .into_response();
     }
     let device_type = DeviceType::to_enum(body.device_type.clone());
-    let query_doc = match body.name.clone() {
+    let query_doc = match body.name.as_deref() {
         Some(name) => doc! {
-            "name": name,
+            "name": User::normalize_name(name),
         },
         None => doc! {
-            "email": body.email.clone(),
+            "email": body.email.as_deref().map(User::normalize_email),
         },
     };
     let existing_user = User::get_document(&database_client, query_doc).await;
     let user = match existing_user {
         Ok(user_option) => match user_option {
+            Some(user) if user.is_guest => {
+                return (StatusCode::FORBIDDEN, "Guests cannot log in").into_response();
+            }
             Some(user) => match user.password == body.password {
                 false => {
                     return (
@@ -239,28 +347,37 @@ async fn login(
                 client_id: body.client_id.clone(),
                 user_id: user._id.clone(),
                 device_type,
+                last_seen: DateTime::now(),
             },
         )
         .await
         {
             Ok(_) => {
                 info!("Updated Client with User ID: {}", user._id.clone());
+                activate_client(
+                    &database_client,
+                    &client_context,
+                    &user._id,
+                    &body.client_id,
+                )
+                .await;
                 let mut sub_context = client_context.lock().await;
-                sub_context
-                    .emit_client_event(
-                        database_client.clone(),
-                        user._id.to_string(),
-                        ClientEvent {
-                            event_type: ClientEventType::Changed,
-                            body: serde_json::to_string(&ClientCreatedOrUpdatedPayload {
-                                user_id: user._id.clone(),
-                                device_type: body.device_type.clone(),
-                                client_id: body.client_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&ClientCreatedOrUpdatedPayload {
+                    user_id: user._id.clone(),
+                    device_type: body.device_type.clone(),
+                    client_id: body.client_id.clone(),
+                }) {
+                    sub_context
+                        .emit_client_event(
+                            database_client.clone(),
+                            user._id.to_string(),
+                            ClientEvent {
+                                event_type: ClientEventType::Changed,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
                 (
                     StatusCode::OK,
@@ -290,7 +407,10 @@ async fn logout(
     match Client::delete_document(&database_client, query_doc).await {
         Ok(delete_result) => match delete_result.deleted_count {
             0 => (StatusCode::INTERNAL_SERVER_ERROR, "User not logged in").into_response(),
-            _ => (StatusCode::OK, Json(user_id.clone())).into_response(),
+            _ => {
+                let _ = User::set_active_client(&user_id, None, &database_client).await;
+                (StatusCode::OK, Json(user_id.clone())).into_response()
+            }
         },
         Err(error_response) => error_response,
     }
@@ -1,4 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::database::collections::active_member::ActiveMember;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,3 +24,18 @@ pub struct UpdatePostionPayload {
     pub x: f32,
     pub y: f32,
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearActiveMembersPayload {
+    pub host_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserActiveMemberResponsePayload {
+    #[serde(flatten)]
+    pub active_member: ActiveMember,
+    // None if the board was deleted while the user was still active on it
+    pub board_name: Option<String>,
+}
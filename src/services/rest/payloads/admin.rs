@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::collections::element_type::ElementType,
+    services::webtransport::messages::server::AnnouncementLevel,
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedOrphansResponsePayload {
+    pub deleted_count: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BroadcastPayload {
+    pub message: String,
+    pub level: AnnouncementLevel,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnusedElementTypesResponsePayload {
+    pub applied: bool,
+    pub element_types: Vec<ElementType>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageResponsePayload<Item> {
+    pub items: Vec<Item>,
+    // Pass as `after` to fetch the next page, missing once the last page was reached
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OffsetPageResponsePayload<Item> {
+    pub items: Vec<Item>,
+    // Pass as `offset` to fetch the next page, missing once the last page was reached
+    pub next_offset: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetBoardEncryptionPayload {
+    pub encrypted: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetBoardTemplatePayload {
+    pub is_template: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardEncryptionResponsePayload {
+    pub encrypted: bool,
+    pub rewritten_elements: u64,
+    pub rewritten_history_entries: u64,
+}
@@ -1,8 +1,70 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateBoardRequestPayload {
     pub name: String,
     pub host: String,
+    #[serde(default)]
+    pub is_public: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KickMemberPayload {
+    pub host_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBoardVisibilityPayload {
+    pub host_id: String,
+    pub is_public: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBoardSettingsPayload {
+    pub host_id: String,
+    pub allow_guests: bool,
+    pub max_elements: Option<u64>,
+    pub encrypted: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardSettingsResponsePayload {
+    pub allow_guests: bool,
+    pub max_elements: Option<u64>,
+    pub encrypted: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloneBoardPayload {
+    pub user_id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBoardFromTemplatePayload {
+    pub template_id: String,
+    pub user_id: String,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardMemberResponsePayload {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub is_host: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedElementsResponsePayload {
+    pub deleted_count: u64,
 }
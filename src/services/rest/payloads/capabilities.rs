@@ -0,0 +1,38 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilitiesResponsePayload {
+    // Major version of the WebTransport protocol, sent as `<major>.<minor>` in the init message
+    pub protocol_version: u32,
+    pub protocol_minor_version: u32,
+    // Subcategories per message category, sent as `<category>_<subcategory>`
+    pub message_categories: BTreeMap<&'static str, Vec<&'static str>>,
+    pub event_categories: Vec<&'static str>,
+    pub transports: TransportCapabilities,
+    pub limits: LimitCapabilities,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportCapabilities {
+    pub webtransport: bool,
+    // Only used if the WebTransport session of the client supports datagrams as well
+    pub datagrams: bool,
+    pub compression: bool,
+    pub msgpack: bool,
+    pub websocket_fallback: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitCapabilities {
+    pub max_elements_per_board: u64,
+    pub max_text_length: usize,
+    pub max_asset_size: usize,
+    // Sustained WebTransport messages per second and connection, 0 if unlimited
+    pub max_messages_per_second: u32,
+    pub message_burst: u32,
+}
@@ -1,5 +1,7 @@
-use bson::{serde_helpers::deserialize_bson_datetime_from_rfc3339_string, DateTime};
-use serde::Deserialize;
+use bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::database::{collections::element::ElementConnector, timestamp::deserialize_timestamp};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -12,15 +14,19 @@ pub struct CreateElementPayload {
     pub x: f32,
     pub y: f32,
     pub rotation: f32,
-    pub scale_x: f32,
-    pub scale_y: f32,
-    pub z_index: i32,
-    #[serde(deserialize_with = "deserialize_bson_datetime_from_rfc3339_string")]
+    // Omitted fields are taken from the defaults of the element type
+    pub scale_x: Option<f32>,
+    pub scale_y: Option<f32>,
+    pub z_index: Option<i32>,
+    #[serde(deserialize_with = "deserialize_timestamp")]
     pub created_at: DateTime,
-    pub text: String,
+    pub text: Option<String>,
     pub element_type: String,
     pub board_id: String,
-    pub color: String,
+    pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub connector: Option<ElementConnector>,
 }
 
 #[derive(Deserialize)]
@@ -32,6 +38,20 @@ pub struct LockElementPayload {
     pub board_id: String,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForceUnlockElementPayload {
+    pub host_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementForceUnlockedResponsePayload {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub displaced_user_id: String,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UnlockElementPayload {
@@ -72,6 +92,41 @@ pub struct UpdateElementPayload {
     pub z_index: Option<i32>,
     pub text: Option<String>,
     pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    // Locks an unlocked element for the user before updating it instead of rejecting the update.
+    // Another user locking the element in between still wins and the update is rejected.
+    #[serde(default)]
+    pub reacquire_lock: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockUpdateUnlockElementPayload {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub user_id: String,
+    pub board_id: String,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+    pub rotation: Option<f32>,
+    pub scale_x: Option<f32>,
+    pub scale_y: Option<f32>,
+    pub z_index: Option<i32>,
+    pub text: Option<String>,
+    pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    // Skips the release so the user can keep editing the element afterwards
+    #[serde(default)]
+    pub keep_locked: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveElementsToBoardPayload {
+    pub ids: Vec<String>,
+    pub from_board_id: String,
+    pub to_board_id: String,
+    pub user_id: String,
 }
 
 #[derive(Deserialize)]
@@ -83,3 +138,54 @@ pub struct MoveMultipleElementsPayload {
     pub x_offset: f32,
     pub y_offset: f32,
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMultipleElementsPayload {
+    pub user_id: String,
+    pub board_id: String,
+    pub updates: Vec<ElementFieldsUpdatePayload>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementFieldsUpdatePayload {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub fields: ElementFieldsPayload,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementFieldsPayload {
+    pub rotation: Option<f32>,
+    pub scale_x: Option<f32>,
+    pub scale_y: Option<f32>,
+    pub z_index: Option<i32>,
+    pub text: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementLockStatusBatchPayload {
+    pub ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementLockStatusResponsePayload {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub locked: bool,
+    pub locked_by: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementCreatedResponsePayload {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    // Assigned by the server if the request and the element type left it open
+    pub z_index: i32,
+}
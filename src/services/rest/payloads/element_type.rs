@@ -1,8 +1,14 @@
 use serde::Deserialize;
 
+use crate::database::collections::element_type::ElementTypeDefaults;
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateElementTypePayload {
     pub name: String,
     pub path: String,
+    pub category: Option<String>,
+    pub defaults: Option<ElementTypeDefaults>,
+    #[serde(default)]
+    pub singleton: bool,
 }
@@ -0,0 +1,15 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsResponsePayload {
+    pub active_webtransport_sessions: usize,
+    pub max_webtransport_sessions: usize,
+    pub dropped_webtransport_events: u64,
+    pub emitted_element_events: u64,
+    // Subscribers reached per element event, the average is the total divided by the emitted events
+    pub element_event_fanout_total: u64,
+    pub element_event_fanout_max: u64,
+    // 0 if concurrent event writes are unlimited
+    pub max_concurrent_event_writes: usize,
+}
@@ -16,6 +16,16 @@ pub struct CreateUserResponsePayload {
     pub email: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuestUserResponsePayload {
+    pub id: String,
+    pub name: String,
+    pub is_guest: bool,
+    // Only returned here, guests send it as `guestToken` when opening WebTransport streams
+    pub token: String,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LoginUserPayload {
@@ -33,3 +43,17 @@ pub struct LoginUserResponsePayload {
     pub name: String,
     pub email: String,
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUsersBatchPayload {
+    pub ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserResponsePayload {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+}
@@ -1,13 +1,93 @@
-use std::net::{Ipv4Addr, SocketAddr};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
 
 use crate::{
-    services::rest::endpoints::{active_member, board, client, element, element_type, ping, user},
+    database::collections::element_asset::MAX_ASSET_SIZE,
+    services::rest::endpoints::{
+        active_member, admin, board, capabilities, certificate, client, element, element_type,
+        ping, user,
+    },
     AppState,
 };
 use anyhow::Context;
-use axum::{serve::Serve, Router};
-use tower_http::cors::CorsLayer;
-use tracing::info;
+use axum::{
+    body::{to_bytes, Body},
+    error_handling::HandleErrorLayer,
+    extract::{DefaultBodyLimit, Request},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    serve::Serve,
+    BoxError, Router,
+};
+use tower::{timeout::TimeoutLayer, ServiceBuilder};
+use tower_http::{
+    cors::CorsLayer,
+    limit::RequestBodyLimitLayer,
+    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
+};
+use tracing::{error, info, warn, Level};
+
+const PRETTY_QUERY_PARAM: &str = "pretty";
+const PRETTY_HEADER: &str = "x-pretty";
+
+pub struct BodyLimitConfig {
+    pub default_limit: usize,
+    pub batch_limit: usize,
+}
+
+impl BodyLimitConfig {
+    pub fn new() -> Self {
+        let default_limit: usize = std::env::var("REST_MAX_BODY_SIZE")
+            .expect("Failed to load `REST_MAX_BODY_SIZE` environment variable.")
+            .parse()
+            .expect("Failed to parse `REST_MAX_BODY_SIZE` environment variable.");
+
+        let batch_limit: usize = std::env::var("REST_MAX_BATCH_BODY_SIZE")
+            .expect("Failed to load `REST_MAX_BATCH_BODY_SIZE` environment variable.")
+            .parse()
+            .expect("Failed to parse `REST_MAX_BATCH_BODY_SIZE` environment variable.");
+
+        Self {
+            default_limit,
+            batch_limit,
+        }
+    }
+}
+
+const DEFAULT_REQUEST_TIMEOUT_SECONDS: u64 = 30;
+const DEFAULT_LONG_RUNNING_REQUEST_TIMEOUT_SECONDS: u64 = 120;
+
+pub struct TimeoutConfig {
+    pub default_timeout: Duration,
+    pub long_running_timeout: Duration,
+}
+
+impl TimeoutConfig {
+    pub fn new() -> Self {
+        let default_timeout = match std::env::var("REST_REQUEST_TIMEOUT_SECONDS") {
+            Ok(timeout) => timeout
+                .parse()
+                .expect("Failed to parse `REST_REQUEST_TIMEOUT_SECONDS` environment variable."),
+            Err(_) => DEFAULT_REQUEST_TIMEOUT_SECONDS,
+        };
+
+        let long_running_timeout = match std::env::var("REST_LONG_RUNNING_REQUEST_TIMEOUT_SECONDS")
+        {
+            Ok(timeout) => timeout.parse().expect(
+                "Failed to parse `REST_LONG_RUNNING_REQUEST_TIMEOUT_SECONDS` environment variable.",
+            ),
+            Err(_) => DEFAULT_LONG_RUNNING_REQUEST_TIMEOUT_SECONDS,
+        };
+
+        Self {
+            default_timeout: Duration::from_secs(default_timeout),
+            long_running_timeout: Duration::from_secs(long_running_timeout),
+        }
+    }
+}
 
 pub struct RestServer {
     serve: Serve<Router, Router>,
@@ -50,6 +130,8 @@ impl RestServer {
     }
 
     fn build_router(state: AppState) -> Router {
+        let body_limit_config = BodyLimitConfig::new();
+        let timeout_config = TimeoutConfig::new();
         Router::<AppState>::new()
             .merge(ping::get_routes())
             .merge(user::get_routes())
@@ -58,7 +140,152 @@ impl RestServer {
             .merge(element::get_routes())
             .merge(element_type::get_routes())
             .merge(client::get_routes())
+            .merge(admin::get_routes())
+            .merge(certificate::get_routes())
+            .merge(capabilities::get_routes())
+            .layer(RequestBodyLimitLayer::new(body_limit_config.default_limit))
+            .merge(
+                element::get_batch_routes()
+                    .layer(RequestBodyLimitLayer::new(body_limit_config.batch_limit)),
+            )
+            .layer(middleware::from_fn(Self::require_json_content_type))
+            // Assets are uploaded as raw image bytes
+            .merge(element::get_asset_routes().layer(RequestBodyLimitLayer::new(MAX_ASSET_SIZE())))
+            .layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(Self::handle_timeout))
+                    .layer(TimeoutLayer::new(timeout_config.default_timeout)),
+            )
+            .merge(
+                board::get_long_running_routes()
+                    .layer(middleware::from_fn(Self::require_json_content_type))
+                    .layer(RequestBodyLimitLayer::new(body_limit_config.default_limit))
+                    .layer(
+                        ServiceBuilder::new()
+                            .layer(HandleErrorLayer::new(Self::handle_timeout))
+                            .layer(TimeoutLayer::new(timeout_config.long_running_timeout)),
+                    ),
+            )
+            .layer(DefaultBodyLimit::disable())
             .with_state(state)
+            .layer(middleware::from_fn(Self::pretty_print_json))
+            // Outside of the timeouts, so requests cut off with a 504 are still logged
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                    .on_response(DefaultOnResponse::new().level(Level::INFO)),
+            )
             .layer(CorsLayer::permissive())
     }
+
+    // Rejects bodies that are not JSON before a handler tries to parse them. Requests without a
+    // body, like GET and DELETE requests or a POST to `/guest`, pass unchecked
+    async fn require_json_content_type(request: Request, next: Next) -> Response {
+        if !matches!(
+            *request.method(),
+            Method::POST | Method::PUT | Method::PATCH
+        ) || !Self::has_body(&request)
+        {
+            return next.run(request).await;
+        }
+        let is_json = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.split(';').next())
+            .map(|media_type| media_type.trim().to_ascii_lowercase())
+            .is_some_and(|media_type| {
+                media_type == "application/json"
+                    || (media_type.starts_with("application/") && media_type.ends_with("+json"))
+            });
+        if !is_json {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Request Body must be sent with `Content-Type: application/json`",
+            )
+                .into_response();
+        }
+        next.run(request).await
+    }
+
+    // Debugging aid, responses stay compact unless `?pretty=true` or `X-Pretty: true` is sent. Applied
+    // to every JSON response here, so handlers keep using axum's `Json`
+    async fn pretty_print_json(request: Request, next: Next) -> Response {
+        if !Self::wants_pretty_json(&request) {
+            return next.run(request).await;
+        }
+        let response = next.run(request).await;
+        let is_json = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.starts_with("application/json"));
+        if !is_json {
+            return response;
+        }
+        let (mut parts, body) = response.into_parts();
+        let bytes = match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                error!("Response body could not be read for pretty printing");
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Response could not be pretty printed",
+                )
+                    .into_response();
+            }
+        };
+        // Keys come out sorted, as `serde_json::Value` keeps objects in a BTreeMap
+        let pretty_body = match serde_json::from_slice::<serde_json::Value>(&bytes)
+            .and_then(|value| serde_json::to_string_pretty(&value))
+        {
+            Ok(pretty_body) => pretty_body,
+            Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+        };
+        parts.headers.remove(header::CONTENT_LENGTH);
+        parts.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        Response::from_parts(parts, Body::from(pretty_body))
+    }
+
+    fn wants_pretty_json(request: &Request) -> bool {
+        let pretty_query = request.uri().query().is_some_and(|query| {
+            query.split('&').any(|pair| match pair.split_once('=') {
+                Some((key, value)) => key == PRETTY_QUERY_PARAM && value == "true",
+                None => false,
+            })
+        });
+        let pretty_header = request
+            .headers()
+            .get(PRETTY_HEADER)
+            .and_then(|pretty| pretty.to_str().ok())
+            .is_some_and(|pretty| pretty.eq_ignore_ascii_case("true"));
+        pretty_query || pretty_header
+    }
+
+    fn has_body(request: &Request) -> bool {
+        let headers = request.headers();
+        if headers.contains_key(header::TRANSFER_ENCODING) {
+            return true;
+        }
+        headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|content_length| content_length.to_str().ok())
+            .and_then(|content_length| content_length.parse::<u64>().ok())
+            .is_some_and(|content_length| content_length > 0)
+    }
+
+    async fn handle_timeout(error: BoxError) -> Response {
+        if error.is::<tower::timeout::error::Elapsed>() {
+            warn!("Request exceeded its timeout");
+            return (StatusCode::GATEWAY_TIMEOUT, "Request timed out").into_response();
+        }
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Unhandled internal error: {}", error),
+        )
+            .into_response()
+    }
 }
@@ -1,16 +1,67 @@
-use crate::services::webtransport::context::base::Subject;
+use crate::{
+    services::webtransport::{
+        context::{base::Subject, event_buffer::EventBuffer, event_dedupe::EventDeduplicator},
+        event_delivery::DeliveryPolicy,
+        messages::active_member::ActivityChangedEventPayload,
+    },
+    utils::serialize_or_log::serialize_or_log,
+};
 use log::info;
-use rxrust::observer::Observer;
-use std::collections::HashMap;
+use rxrust::{observer::Observer, subject::SubjectSize};
+use std::{
+    collections::HashMap,
+    env::var,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_ACTIVE_MEMBER_REMOVAL_GRACE_SECONDS: u64 = 5;
+const DEFAULT_ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE_SECONDS: u64 = 60;
+
+#[allow(non_snake_case)]
+pub fn ACTIVE_MEMBER_REMOVAL_GRACE_PERIOD() -> Duration {
+    static ACTIVE_MEMBER_REMOVAL_GRACE_PERIOD: OnceLock<Duration> = OnceLock::new();
+    *ACTIVE_MEMBER_REMOVAL_GRACE_PERIOD.get_or_init(|| {
+        match var("ACTIVE_MEMBER_REMOVAL_GRACE_SECONDS") {
+            Ok(grace_seconds) => Duration::from_secs(grace_seconds.parse().expect(
+                "Failed to parse `ACTIVE_MEMBER_REMOVAL_GRACE_SECONDS` environment variable.",
+            )),
+            Err(_) => Duration::from_secs(DEFAULT_ACTIVE_MEMBER_REMOVAL_GRACE_SECONDS),
+        }
+    })
+}
+
+// Keepalives within this window after the last stored one are answered without a database write.
+// Keep it well below `CLIENT_INACTIVITY_TTL_SECONDS`, a keepalive also counts as the Client being seen
+#[allow(non_snake_case)]
+pub fn ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE() -> Duration {
+    static ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE: OnceLock<Duration> = OnceLock::new();
+    *ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE.get_or_init(|| {
+        match var("ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE_SECONDS") {
+            Ok(debounce_seconds) => Duration::from_secs(debounce_seconds.parse().expect(
+                "Failed to parse `ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE_SECONDS` environment variable.",
+            )),
+            Err(_) => Duration::from_secs(DEFAULT_ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE_SECONDS),
+        }
+    })
+}
 
 pub struct ActiveMemberContext {
     pub board_active_member_subjects: HashMap<String, ActiveMemberSubject>,
+    pub member_activities: HashMap<String, MemberActivity>,
+    pending_removals: HashMap<String, u64>,
+    next_removal_id: u64,
+    stored_keepalives: HashMap<String, (String, Instant)>,
 }
 
 impl ActiveMemberContext {
     pub fn new() -> Self {
         Self {
             board_active_member_subjects: HashMap::new(),
+            member_activities: HashMap::new(),
+            pending_removals: HashMap::new(),
+            next_removal_id: 0,
+            stored_keepalives: HashMap::new(),
         }
     }
 
@@ -28,10 +79,23 @@ impl ActiveMemberContext {
             .clone()
     }
 
+    // Announcements reach every subscriber of this context and are not kept for replay
+    pub fn cleanup_event_buffers(&mut self) {
+        self.board_active_member_subjects.retain(|_, subject| {
+            if !subject.subject.is_empty() {
+                return true;
+            }
+            subject.event_buffer.prune();
+            !subject.event_buffer.is_empty()
+        });
+    }
+
     fn create_subject(board_id: String) -> ActiveMemberSubject {
         ActiveMemberSubject {
             board_id,
             subject: Subject::default(),
+            event_buffer: EventBuffer::new(),
+            event_deduplicator: EventDeduplicator::new(),
         }
     }
 
@@ -49,14 +113,131 @@ impl ActiveMemberContext {
                 board_id,
                 event.clone().body
             );
+            if subject.event_deduplicator.is_duplicate(
+                event.event_type.to_string(),
+                &event.body,
+                event.event_type.is_dedupable(),
+            ) {
+                return;
+            }
+            if matches!(
+                event.event_type.delivery_policy(),
+                DeliveryPolicy::QueueWhenFull
+            ) {
+                subject.event_buffer.push(event.clone());
+            }
             subject.subject.next(event);
         }
     }
+
+    pub async fn set_activity(&mut self, user_id: String, board_id: String, element_id: String) {
+        if let Some(previous_activity) = self.member_activities.insert(
+            user_id.clone(),
+            MemberActivity {
+                board_id: board_id.clone(),
+                element_id: element_id.clone(),
+            },
+        ) {
+            if previous_activity.board_id != board_id {
+                self.emit_activity_changed_event(previous_activity.board_id, user_id.clone(), None)
+                    .await;
+            }
+        }
+        self.emit_activity_changed_event(board_id, user_id, Some(element_id))
+            .await;
+    }
+
+    pub async fn clear_activity(&mut self, user_id: String) -> Option<MemberActivity> {
+        let activity = self.member_activities.remove(&user_id)?;
+        self.emit_activity_changed_event(activity.board_id.clone(), user_id, None)
+            .await;
+        Some(activity)
+    }
+
+    pub async fn clear_activity_for_elements(
+        &mut self,
+        user_id: String,
+        element_ids: &[String],
+    ) -> Option<MemberActivity> {
+        match self.member_activities.get(&user_id) {
+            Some(activity) if element_ids.contains(&activity.element_id) => {
+                self.clear_activity(user_id).await
+            }
+            _ => None,
+        }
+    }
+
+    // A newer removal for the same user replaces the previous one
+    pub fn schedule_removal(&mut self, user_id: String) -> u64 {
+        self.next_removal_id += 1;
+        self.pending_removals.insert(user_id, self.next_removal_id);
+        self.next_removal_id
+    }
+
+    pub fn cancel_pending_removal(&mut self, user_id: &str) -> bool {
+        self.pending_removals.remove(user_id).is_some()
+    }
+
+    pub fn take_pending_removal(&mut self, user_id: &str, removal_id: u64) -> bool {
+        match self.pending_removals.get(user_id) {
+            Some(pending_removal_id) if *pending_removal_id == removal_id => {
+                self.pending_removals.remove(user_id);
+                self.stored_keepalives.remove(user_id);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Switching boards stores the next keepalive right away, the debounce is per board
+    pub fn is_keepalive_debounced(&self, user_id: &str, board_id: &str) -> bool {
+        match self.stored_keepalives.get(user_id) {
+            Some((stored_board_id, stored_at)) => {
+                stored_board_id == board_id
+                    && stored_at.elapsed() < ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE()
+            }
+            None => false,
+        }
+    }
+
+    pub fn keepalive_stored(&mut self, user_id: String, board_id: String) {
+        self.stored_keepalives
+            .insert(user_id, (board_id, Instant::now()));
+    }
+
+    async fn emit_activity_changed_event(
+        &mut self,
+        board_id: String,
+        user_id: String,
+        element_id: Option<String>,
+    ) {
+        if let Ok(event_body) = serialize_or_log(&ActivityChangedEventPayload {
+            user_id,
+            element_id,
+        }) {
+            self.emit_active_member_event(
+                board_id,
+                ActiveMemberEvent {
+                    event_type: ActiveMemberEventType::ActivityChanged,
+                    body: event_body,
+                },
+            )
+            .await;
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MemberActivity {
+    pub board_id: String,
+    pub element_id: String,
 }
 
 pub struct ActiveMemberSubject {
     pub board_id: String,
     pub subject: Subject<ActiveMemberEvent>,
+    pub event_buffer: EventBuffer<ActiveMemberEvent>,
+    pub event_deduplicator: EventDeduplicator,
 }
 
 #[derive(Clone)]
@@ -64,6 +245,8 @@ pub enum ActiveMemberEventType {
     Created,
     Removed,
     PositionUpdated,
+    ActivityChanged,
+    Announcement,
 }
 
 impl ToString for ActiveMemberEventType {
@@ -72,10 +255,28 @@ impl ToString for ActiveMemberEventType {
             ActiveMemberEventType::Created => "activemember_created".to_string(),
             ActiveMemberEventType::Removed => "activemember_removed".to_string(),
             ActiveMemberEventType::PositionUpdated => "activemember_positionupdated".to_string(),
+            ActiveMemberEventType::ActivityChanged => "activemember_activitychanged".to_string(),
+            ActiveMemberEventType::Announcement => "server_announcement".to_string(),
         }
     }
 }
 
+impl ActiveMemberEventType {
+    pub fn delivery_policy(&self) -> DeliveryPolicy {
+        match self {
+            ActiveMemberEventType::Created => DeliveryPolicy::QueueWhenFull,
+            ActiveMemberEventType::Removed => DeliveryPolicy::QueueWhenFull,
+            ActiveMemberEventType::PositionUpdated => DeliveryPolicy::DropWhenFull,
+            ActiveMemberEventType::ActivityChanged => DeliveryPolicy::QueueWhenFull,
+            ActiveMemberEventType::Announcement => DeliveryPolicy::DropWhenFull,
+        }
+    }
+
+    pub fn is_dedupable(&self) -> bool {
+        matches!(self, ActiveMemberEventType::PositionUpdated)
+    }
+}
+
 #[derive(Clone)]
 pub struct ActiveMemberEvent {
     pub event_type: ActiveMemberEventType,
@@ -1,7 +1,8 @@
 use std::convert::Infallible;
 
-use rxrust::subject::SubjectThreads;
+use rxrust::{observer::Observer, subject::SubjectThreads};
 
+#[derive(Clone, Copy)]
 pub enum EventCategory {
     Board,
     Client,
@@ -23,3 +24,14 @@ impl EventCategory {
 }
 
 pub type Subject<T> = SubjectThreads<T, Infallible>;
+
+// Server wide events like announcements go to every subject of a context, whichever board or client
+// it belongs to
+pub fn broadcast_event<'a, T: Clone + 'a>(
+    subjects: impl Iterator<Item = &'a mut Subject<T>>,
+    event: T,
+) {
+    for subject in subjects {
+        subject.next(event.clone());
+    }
+}
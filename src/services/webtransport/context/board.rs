@@ -1,7 +1,10 @@
 use crate::services::webtransport::context::base::Subject;
+use crate::services::webtransport::context::event_buffer::EventBuffer;
+use crate::services::webtransport::event_delivery::DeliveryPolicy;
 use log::info;
 use mongodb::Client;
 use rxrust::observer::Observer;
+use rxrust::subject::SubjectSize;
 use std::collections::HashMap;
 
 use crate::database::collections::board::Board;
@@ -31,10 +34,22 @@ impl BoardContext {
             .clone()
     }
 
+    // Announcements reach every subscriber of this context and are not kept for replay
+    pub fn cleanup_event_buffers(&mut self) {
+        self.board_subjects.retain(|_, subject| {
+            if !subject.subject.is_empty() {
+                return true;
+            }
+            subject.event_buffer.prune();
+            !subject.event_buffer.is_empty()
+        });
+    }
+
     fn create_subject(board_id: String) -> BoardSubject {
         BoardSubject {
             board_id,
             subject: Subject::default(),
+            event_buffer: EventBuffer::new(),
         }
     }
 
@@ -58,6 +73,12 @@ impl BoardContext {
                     board_id,
                     event.clone().body
                 );
+                if matches!(
+                    event.event_type.delivery_policy(),
+                    DeliveryPolicy::QueueWhenFull
+                ) {
+                    subject.event_buffer.push(event.clone());
+                }
                 subject.subject.next(event);
             }
         }
@@ -67,12 +88,21 @@ impl BoardContext {
 pub struct BoardSubject {
     pub board_id: String,
     pub subject: Subject<BoardEvent>,
+    pub event_buffer: EventBuffer<BoardEvent>,
 }
 
 #[derive(Clone)]
 pub enum BoardEventType {
     MemberAdded,
     MemberRemoved,
+    Kicked,
+    ViewerAdded,
+    ViewerRemoved,
+    NearLimit,
+    Renamed,
+    // Advisory hint for empty-state prompts, clients must not rely on it to know the board's contents
+    Emptied,
+    Announcement,
 }
 
 impl ToString for BoardEventType {
@@ -80,6 +110,29 @@ impl ToString for BoardEventType {
         match self {
             BoardEventType::MemberAdded => "board_memberadded".to_string(),
             BoardEventType::MemberRemoved => "board_memberremoved".to_string(),
+            BoardEventType::Kicked => "board_kicked".to_string(),
+            BoardEventType::ViewerAdded => "board_vieweradded".to_string(),
+            BoardEventType::ViewerRemoved => "board_viewerremoved".to_string(),
+            BoardEventType::NearLimit => "board_nearlimit".to_string(),
+            BoardEventType::Renamed => "board_renamed".to_string(),
+            BoardEventType::Emptied => "board_emptied".to_string(),
+            BoardEventType::Announcement => "server_announcement".to_string(),
+        }
+    }
+}
+
+impl BoardEventType {
+    pub fn delivery_policy(&self) -> DeliveryPolicy {
+        match self {
+            BoardEventType::MemberAdded => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::MemberRemoved => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::Kicked => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::ViewerAdded => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::ViewerRemoved => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::NearLimit => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::Renamed => DeliveryPolicy::QueueWhenFull,
+            BoardEventType::Emptied => DeliveryPolicy::DropWhenFull,
+            BoardEventType::Announcement => DeliveryPolicy::DropWhenFull,
         }
     }
 }
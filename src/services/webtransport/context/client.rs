@@ -1,4 +1,5 @@
 use crate::services::webtransport::context::base::Subject;
+use crate::services::webtransport::event_delivery::DeliveryPolicy;
 use log::info;
 use rxrust::observer::Observer;
 use std::collections::HashMap;
@@ -30,6 +31,7 @@ impl ClientContext {
             .clone()
     }
 
+    // Announcements reach every subscriber of this context and are not kept for replay
     fn create_subject(client_id: String) -> ClientSubject {
         ClientSubject {
             client_id,
@@ -71,14 +73,33 @@ pub struct ClientSubject {
 #[derive(Clone)]
 pub enum ClientEventType {
     Deleted,
+    // Another device of the user became the active client
+    Displaced,
     Changed,
+    Kicked,
+    Announcement,
 }
 
 impl ToString for ClientEventType {
     fn to_string(&self) -> String {
         match self {
             ClientEventType::Deleted => "client_removed".to_string(),
+            ClientEventType::Displaced => "client_displaced".to_string(),
             ClientEventType::Changed => "client_changed".to_string(),
+            ClientEventType::Kicked => "client_kicked".to_string(),
+            ClientEventType::Announcement => "server_announcement".to_string(),
+        }
+    }
+}
+
+impl ClientEventType {
+    pub fn delivery_policy(&self) -> DeliveryPolicy {
+        match self {
+            ClientEventType::Deleted => DeliveryPolicy::QueueWhenFull,
+            ClientEventType::Displaced => DeliveryPolicy::QueueWhenFull,
+            ClientEventType::Changed => DeliveryPolicy::QueueWhenFull,
+            ClientEventType::Kicked => DeliveryPolicy::QueueWhenFull,
+            ClientEventType::Announcement => DeliveryPolicy::DropWhenFull,
         }
     }
 }
@@ -88,3 +109,17 @@ pub struct ClientEvent {
     pub event_type: ClientEventType,
     pub body: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displacement_is_not_a_removal() {
+        assert_eq!(ClientEventType::Displaced.to_string(), "client_displaced");
+        assert_ne!(
+            ClientEventType::Displaced.to_string(),
+            ClientEventType::Deleted.to_string()
+        );
+    }
+}
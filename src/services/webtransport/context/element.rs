@@ -1,6 +1,10 @@
 use crate::services::webtransport::context::base::Subject;
+use crate::services::webtransport::context::event_buffer::EventBuffer;
+use crate::services::webtransport::context::event_dedupe::EventDeduplicator;
+use crate::services::webtransport::event_delivery::{record_element_event_fanout, DeliveryPolicy};
 use log::info;
 use rxrust::observer::Observer;
+use rxrust::subject::SubjectSize;
 use std::collections::HashMap;
 
 pub struct ElementContext {
@@ -28,10 +32,23 @@ impl ElementContext {
             .clone()
     }
 
+    // Announcements reach every subscriber of this context and are not kept for replay
+    pub fn cleanup_event_buffers(&mut self) {
+        self.board_element_subjects.retain(|_, subject| {
+            if !subject.subject.is_empty() {
+                return true;
+            }
+            subject.event_buffer.prune();
+            !subject.event_buffer.is_empty()
+        });
+    }
+
     fn create_subject(board_id: String) -> ElementSubject {
         ElementSubject {
             board_id,
             subject: Subject::default(),
+            event_buffer: EventBuffer::new(),
+            event_deduplicator: EventDeduplicator::new(),
         }
     }
 
@@ -49,6 +66,20 @@ impl ElementContext {
                 board_id,
                 event.clone().body
             );
+            if subject.event_deduplicator.is_duplicate(
+                event.event_type.to_string(),
+                &event.body,
+                event.event_type.is_dedupable(),
+            ) {
+                return;
+            }
+            if matches!(
+                event.event_type.delivery_policy(),
+                DeliveryPolicy::QueueWhenFull
+            ) {
+                subject.event_buffer.push(event.clone());
+            }
+            record_element_event_fanout(subject.subject.len());
             subject.subject.next(event);
         }
     }
@@ -57,6 +88,8 @@ impl ElementContext {
 pub struct ElementSubject {
     pub board_id: String,
     pub subject: Subject<ElementEvent>,
+    pub event_buffer: EventBuffer<ElementEvent>,
+    pub event_deduplicator: EventDeduplicator,
 }
 
 #[derive(Clone)]
@@ -67,6 +100,8 @@ pub enum ElementEventType {
     Locked,
     Unlocked,
     Updated,
+    ConnectorUpdated,
+    Announcement,
 }
 
 impl ToString for ElementEventType {
@@ -78,8 +113,31 @@ impl ToString for ElementEventType {
             ElementEventType::Locked => "element_locked".to_string(),
             ElementEventType::Unlocked => "element_unlocked".to_string(),
             ElementEventType::Updated => "element_updated".to_string(),
+            ElementEventType::ConnectorUpdated => "element_connectorupdated".to_string(),
+            ElementEventType::Announcement => "server_announcement".to_string(),
+        }
+    }
+}
+
+impl ElementEventType {
+    pub fn delivery_policy(&self) -> DeliveryPolicy {
+        match self {
+            ElementEventType::Created => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::Removed => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::Moved => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::Locked => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::Unlocked => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::Updated => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::ConnectorUpdated => DeliveryPolicy::QueueWhenFull,
+            ElementEventType::Announcement => DeliveryPolicy::DropWhenFull,
         }
     }
+
+    // Only repeated updates are suppressed, create, remove, lock and move events change the structure
+    // of the board and are always delivered
+    pub fn is_dedupable(&self) -> bool {
+        matches!(self, ElementEventType::Updated)
+    }
 }
 
 #[derive(Clone)]
@@ -87,3 +145,24 @@ pub struct ElementEvent {
     pub event_type: ElementEventType,
     pub body: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_updates_are_dedupable() {
+        assert!(ElementEventType::Updated.is_dedupable());
+        for event_type in [
+            ElementEventType::Created,
+            ElementEventType::Removed,
+            ElementEventType::Moved,
+            ElementEventType::Locked,
+            ElementEventType::Unlocked,
+            ElementEventType::ConnectorUpdated,
+            ElementEventType::Announcement,
+        ] {
+            assert!(!event_type.is_dedupable());
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::{collections::VecDeque, env::var, sync::OnceLock, time::Duration};
+
+use bson::DateTime;
+
+const DEFAULT_EVENT_BUFFER_SIZE: usize = 500;
+const DEFAULT_EVENT_BUFFER_WINDOW_SECONDS: u64 = 30;
+
+#[allow(non_snake_case)]
+fn EVENT_BUFFER_SIZE() -> usize {
+    static EVENT_BUFFER_SIZE: OnceLock<usize> = OnceLock::new();
+    *EVENT_BUFFER_SIZE.get_or_init(|| match var("EVENT_BUFFER_SIZE") {
+        Ok(event_buffer_size) => event_buffer_size
+            .parse()
+            .expect("Failed to parse `EVENT_BUFFER_SIZE` environment variable."),
+        Err(_) => DEFAULT_EVENT_BUFFER_SIZE,
+    })
+}
+
+#[allow(non_snake_case)]
+pub fn EVENT_BUFFER_WINDOW() -> Duration {
+    static EVENT_BUFFER_WINDOW: OnceLock<Duration> = OnceLock::new();
+    *EVENT_BUFFER_WINDOW.get_or_init(|| match var("EVENT_BUFFER_WINDOW_SECONDS") {
+        Ok(event_buffer_window) => Duration::from_secs(
+            event_buffer_window
+                .parse()
+                .expect("Failed to parse `EVENT_BUFFER_WINDOW_SECONDS` environment variable."),
+        ),
+        Err(_) => Duration::from_secs(DEFAULT_EVENT_BUFFER_WINDOW_SECONDS),
+    })
+}
+
+// Keeps the most recent events of a subject so clients reconnecting within the window can replay them
+pub struct EventBuffer<Event> {
+    events: VecDeque<(DateTime, Event)>,
+}
+
+impl<Event: Clone> EventBuffer<Event> {
+    pub fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        self.events.push_back((DateTime::now(), event));
+        while self.events.len() > EVENT_BUFFER_SIZE() {
+            self.events.pop_front();
+        }
+        self.prune();
+    }
+
+    pub fn events_since(&mut self, since: DateTime) -> Vec<Event> {
+        self.prune();
+        self.events
+            .iter()
+            .filter(|(emitted_at, _)| *emitted_at > since)
+            .map(|(_, event)| event.clone())
+            .collect()
+    }
+
+    pub fn prune(&mut self) {
+        let window_start = DateTime::from_millis(
+            DateTime::now().timestamp_millis() - EVENT_BUFFER_WINDOW().as_millis() as i64,
+        );
+        while self
+            .events
+            .front()
+            .is_some_and(|(emitted_at, _)| *emitted_at < window_start)
+        {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+}
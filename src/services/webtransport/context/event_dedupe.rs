@@ -0,0 +1,60 @@
+use std::{env::var, sync::OnceLock, time::Duration, time::Instant};
+
+#[allow(non_snake_case)]
+fn EVENT_DEDUPE_WINDOW() -> Duration {
+    static EVENT_DEDUPE_WINDOW: OnceLock<Duration> = OnceLock::new();
+    *EVENT_DEDUPE_WINDOW.get_or_init(|| match var("EVENT_DEDUPE_WINDOW_MILLIS") {
+        Ok(event_dedupe_window) => Duration::from_millis(
+            event_dedupe_window
+                .parse()
+                .expect("Failed to parse `EVENT_DEDUPE_WINDOW_MILLIS` environment variable."),
+        ),
+        Err(_) => Duration::ZERO,
+    })
+}
+
+// Comma separated event types, e.g. `element_moved,activemember_positionupdated`
+#[allow(non_snake_case)]
+fn EVENT_DEDUPE_TYPES() -> &'static Vec<String> {
+    static EVENT_DEDUPE_TYPES: OnceLock<Vec<String>> = OnceLock::new();
+    EVENT_DEDUPE_TYPES.get_or_init(|| match var("EVENT_DEDUPE_TYPES") {
+        Ok(event_dedupe_types) => event_dedupe_types
+            .split(',')
+            .map(|event_type| event_type.trim().to_string())
+            .filter(|event_type| !event_type.is_empty())
+            .collect(),
+        Err(_) => Vec::new(),
+    })
+}
+
+// Suppresses an event that is identical to the previous event of the same subject within the window.
+// Only event types that are enabled by `EVENT_DEDUPE_TYPES` and marked as dedupable by their context
+// are considered, so structural events like create, remove or lock always get through.
+pub struct EventDeduplicator {
+    last_event: Option<(Instant, String, String)>,
+}
+
+impl EventDeduplicator {
+    pub fn new() -> Self {
+        Self { last_event: None }
+    }
+
+    pub fn is_duplicate(&mut self, event_type: String, body: &str, dedupable: bool) -> bool {
+        let now = Instant::now();
+        let is_duplicate = dedupable
+            && !EVENT_DEDUPE_WINDOW().is_zero()
+            && EVENT_DEDUPE_TYPES().contains(&event_type)
+            && self
+                .last_event
+                .as_ref()
+                .is_some_and(|(emitted_at, last_event_type, last_body)| {
+                    *last_event_type == event_type
+                        && last_body == body
+                        && now.duration_since(*emitted_at) <= EVENT_DEDUPE_WINDOW()
+                });
+        if !is_duplicate {
+            self.last_event = Some((now, event_type, body.to_string()));
+        }
+        is_duplicate
+    }
+}
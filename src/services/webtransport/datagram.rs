@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use mongodb::Client;
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+use wtransport::{Connection, VarInt};
+
+use super::{
+    context::active_member::ActiveMemberContext,
+    message_rate_limit::{MessageRateLimiter, RateLimitDecision},
+    messages::{
+        active_member::UpdatePositionMessage, base::WebTransportBaseMessageHandler,
+        server::ServerMessage,
+    },
+    server::RATE_LIMIT_CLOSE_CODE,
+};
+
+// Cursor positions are exchanged as UTF-8 JSON datagrams when the session supports them.
+// Incoming: the body of an `updateposition` message, e.g. {"userId":"..","boardId":"..","x":1.0,"y":2.0}.
+// They are only accepted once an active member stream initialized, for its user and board.
+// Outgoing: the same ServerMessage event the stream delivers, e.g.
+// {"messageType":"activemember_positionupdated","status":"OK","body":"{\"userId\":\"..\",\"x\":1.0,\"y\":2.0}"}.
+// Datagrams are unreliable and get no response, a lost position is superseded by the next one.
+pub fn supports_datagrams(connection: &Connection) -> bool {
+    connection.max_datagram_size().is_some()
+}
+
+// The initialized active member stream the datagrams of a connection act for, datagrams moving
+// another user or targeting another board are dropped
+pub struct DatagramBinding {
+    pub user_id: String,
+    pub board_id: String,
+}
+
+impl DatagramBinding {
+    pub fn accepts(&self, message: &Value) -> bool {
+        message.get("userId").and_then(Value::as_str) == Some(self.user_id.as_str())
+            && message.get("boardId").and_then(Value::as_str) == Some(self.board_id.as_str())
+    }
+}
+
+pub async fn handle_datagrams(
+    connection: Arc<Connection>,
+    database_client: Client,
+    active_member_context: Arc<Mutex<ActiveMemberContext>>,
+    binding: DatagramBinding,
+    // Shared with the streams of the connection, so datagrams count against the same limit
+    message_rate_limiter: Arc<Mutex<MessageRateLimiter>>,
+) {
+    info!(
+        "Listening for cursor position datagrams of User {} on Board {}",
+        binding.user_id, binding.board_id
+    );
+    loop {
+        let datagram = match connection.receive_datagram().await {
+            Ok(datagram) => datagram,
+            Err(_) => {
+                info!("Datagram channel closed");
+                return;
+            }
+        };
+        let rate_limit_decision = message_rate_limiter.lock().await.check();
+        match rate_limit_decision {
+            RateLimitDecision::Allowed => {}
+            RateLimitDecision::Throttled => {
+                warn!("Message rate of connection exceeded, position datagram is dropped");
+                continue;
+            }
+            RateLimitDecision::Exceeded => {
+                error!("Connection closed after repeatedly exceeding the message rate");
+                connection.close(
+                    VarInt::from_u32(RATE_LIMIT_CLOSE_CODE),
+                    b"message rate exceeded",
+                );
+                return;
+            }
+        }
+        let message = match serde_json::from_slice::<Value>(&datagram.payload()) {
+            Ok(message) => message,
+            Err(_) => {
+                warn!("Received datagram is not valid JSON");
+                continue;
+            }
+        };
+        if !binding.accepts(&message) {
+            warn!("Dropped position datagram for another user or board");
+            continue;
+        }
+        if let Err(error_message) = UpdatePositionMessage::handle_message(
+            message,
+            database_client.clone(),
+            active_member_context.clone(),
+        )
+        .await
+        {
+            warn!("Position datagram rejected: {}", error_message.body);
+        }
+    }
+}
+
+// Returns false if the message could not be sent as a datagram and has to go over the stream
+pub fn send_datagram(connection: &Connection, message: &ServerMessage) -> bool {
+    let payload = serde_json::to_string(message).unwrap();
+    match connection.max_datagram_size() {
+        Some(max_datagram_size) if payload.len() <= max_datagram_size => {
+            connection.send_datagram(payload).is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_only_accepts_its_own_user_and_board() {
+        let binding = DatagramBinding {
+            user_id: "user".to_string(),
+            board_id: "board".to_string(),
+        };
+        let position = |user_id: &str, board_id: &str| serde_json::json!({ "userId": user_id, "boardId": board_id, "x": 1.0, "y": 2.0 });
+        assert!(binding.accepts(&position("user", "board")));
+        assert!(!binding.accepts(&position("other", "board")));
+        assert!(!binding.accepts(&position("user", "other")));
+        assert!(!binding.accepts(&serde_json::json!({ "x": 1.0, "y": 2.0 })));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    env::var,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    Mutex, Semaphore,
+};
+use tracing::warn;
+use wtransport::{Connection, SendStream};
+
+use super::{messages::server::ServerMessage, server::WebTransportServer};
+
+const EVENT_BUFFER_SIZE: usize = 256;
+// Hard bound for `QueueWhenFull` events, so a subscriber that stopped reading cannot grow its queue forever
+const MAX_QUEUED_EVENTS: usize = 4 * EVENT_BUFFER_SIZE;
+
+static DROPPED_EVENT_COUNT: AtomicU64 = AtomicU64::new(0);
+static ELEMENT_EVENT_COUNT: AtomicU64 = AtomicU64::new(0);
+static ELEMENT_EVENT_FANOUT_TOTAL: AtomicU64 = AtomicU64::new(0);
+static ELEMENT_EVENT_FANOUT_MAX: AtomicU64 = AtomicU64::new(0);
+
+pub fn dropped_event_count() -> u64 {
+    DROPPED_EVENT_COUNT.load(Ordering::Relaxed)
+}
+
+pub struct FanoutMetrics {
+    pub events: u64,
+    pub total_fanout: u64,
+    pub max_fanout: u64,
+}
+
+// Subscribers reached by an emitted element event, every one of them costs a stream write
+pub fn record_element_event_fanout(subscribers: usize) {
+    ELEMENT_EVENT_COUNT.fetch_add(1, Ordering::Relaxed);
+    ELEMENT_EVENT_FANOUT_TOTAL.fetch_add(subscribers as u64, Ordering::Relaxed);
+    ELEMENT_EVENT_FANOUT_MAX.fetch_max(subscribers as u64, Ordering::Relaxed);
+}
+
+pub fn element_event_fanout() -> FanoutMetrics {
+    FanoutMetrics {
+        events: ELEMENT_EVENT_COUNT.load(Ordering::Relaxed),
+        total_fanout: ELEMENT_EVENT_FANOUT_TOTAL.load(Ordering::Relaxed),
+        max_fanout: ELEMENT_EVENT_FANOUT_MAX.load(Ordering::Relaxed),
+    }
+}
+
+// Event writes running at once over all subscribers, 0 leaves them unlimited. Once the cap is hit,
+// delivery tasks wait for a running write to finish. Their events queue up for the subscriber
+// meanwhile, and once `EVENT_BUFFER_SIZE` events are queued the `DeliveryPolicy` of the event decides:
+// `DropWhenFull` events are dropped, `QueueWhenFull` events are still queued up to
+// `MAX_QUEUED_EVENTS`. Dropped events show up in `/metrics`
+#[allow(non_snake_case)]
+pub fn MAX_CONCURRENT_EVENT_WRITES() -> usize {
+    static MAX_CONCURRENT_EVENT_WRITES: OnceLock<usize> = OnceLock::new();
+    *MAX_CONCURRENT_EVENT_WRITES.get_or_init(|| {
+        match var("WEBTRANSPORT_MAX_CONCURRENT_EVENT_WRITES") {
+            Ok(max_concurrent_event_writes) => max_concurrent_event_writes.parse().expect(
+                "Failed to parse `WEBTRANSPORT_MAX_CONCURRENT_EVENT_WRITES` environment variable.",
+            ),
+            Err(_) => 0,
+        }
+    })
+}
+
+fn event_write_permits() -> Option<&'static Semaphore> {
+    static EVENT_WRITE_PERMITS: OnceLock<Option<Semaphore>> = OnceLock::new();
+    EVENT_WRITE_PERMITS
+        .get_or_init(|| match MAX_CONCURRENT_EVENT_WRITES() {
+            0 => None,
+            max_concurrent_event_writes => Some(Semaphore::new(max_concurrent_event_writes)),
+        })
+        .as_ref()
+}
+
+// How an event is handled when the subscriber's delivery buffer is full:
+// `DropWhenFull` discards the event, `QueueWhenFull` queues it beyond the buffer up to
+// `MAX_QUEUED_EVENTS`. Events are emitted while the context mutex is held, so delivering never
+// waits for the subscriber, the writes happen in the subscriber's own task
+pub enum DeliveryPolicy {
+    DropWhenFull,
+    QueueWhenFull,
+}
+
+impl DeliveryPolicy {
+    fn queue_limit(&self) -> usize {
+        match self {
+            DeliveryPolicy::DropWhenFull => EVENT_BUFFER_SIZE,
+            DeliveryPolicy::QueueWhenFull => MAX_QUEUED_EVENTS,
+        }
+    }
+}
+
+pub struct EventDelivery {
+    sender: UnboundedSender<ServerMessage>,
+    queued: Arc<AtomicUsize>,
+}
+
+impl EventDelivery {
+    // Stops delivering once a write fails, later events are discarded as the channel is closed
+    pub fn start(send_stream: Arc<Mutex<SendStream>>, connection: Arc<Connection>) -> Self {
+        let (sender, mut receiver) = unbounded_channel::<ServerMessage>();
+        let queued = Arc::new(AtomicUsize::new(0));
+        let writer_queued = queued.clone();
+        tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                writer_queued.fetch_sub(1, Ordering::Relaxed);
+                // The semaphore is never closed, so acquiring only fails if the cap is disabled
+                let _write_permit = match event_write_permits() {
+                    Some(event_write_permits) => event_write_permits.acquire().await.ok(),
+                    None => None,
+                };
+                if WebTransportServer::send_message_to_stream(
+                    send_stream.lock().await,
+                    &connection,
+                    message,
+                )
+                .await
+                .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Self { sender, queued }
+    }
+
+    pub fn deliver(&self, message: ServerMessage, policy: DeliveryPolicy) {
+        if self.queued.load(Ordering::Relaxed) >= policy.queue_limit() {
+            DROPPED_EVENT_COUNT.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "Subscriber is falling behind, dropped event: {}",
+                message.message_type
+            );
+            return;
+        }
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        // Fails once the delivery task stopped after a failed write, the event is discarded then
+        if self.sender.send(message).is_err() {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{env::var, sync::OnceLock, time::Instant};
+
+const DEFAULT_MESSAGE_RATE: u32 = 20;
+const DEFAULT_MESSAGE_BURST: u32 = 200;
+const DEFAULT_MAX_RATE_VIOLATIONS: u32 = 100;
+
+// Messages per second a connection may send on average, 0 disables the limit
+#[allow(non_snake_case)]
+pub fn MESSAGE_RATE() -> u32 {
+    static MESSAGE_RATE: OnceLock<u32> = OnceLock::new();
+    *MESSAGE_RATE.get_or_init(|| match var("WEBTRANSPORT_MESSAGE_RATE") {
+        Ok(message_rate) => message_rate
+            .parse()
+            .expect("Failed to parse `WEBTRANSPORT_MESSAGE_RATE` environment variable."),
+        Err(_) => DEFAULT_MESSAGE_RATE,
+    })
+}
+
+// Messages a connection may send at once, e.g. when pasting many elements
+#[allow(non_snake_case)]
+pub fn MESSAGE_BURST() -> u32 {
+    static MESSAGE_BURST: OnceLock<u32> = OnceLock::new();
+    *MESSAGE_BURST.get_or_init(|| match var("WEBTRANSPORT_MESSAGE_BURST") {
+        Ok(message_burst) => message_burst
+            .parse()
+            .expect("Failed to parse `WEBTRANSPORT_MESSAGE_BURST` environment variable."),
+        Err(_) => DEFAULT_MESSAGE_BURST,
+    })
+}
+
+// Throttled messages in a row after which the connection is closed, 0 never closes it
+#[allow(non_snake_case)]
+fn MAX_RATE_VIOLATIONS() -> u32 {
+    static MAX_RATE_VIOLATIONS: OnceLock<u32> = OnceLock::new();
+    *MAX_RATE_VIOLATIONS.get_or_init(|| match var("WEBTRANSPORT_MAX_RATE_VIOLATIONS") {
+        Ok(max_rate_violations) => max_rate_violations
+            .parse()
+            .expect("Failed to parse `WEBTRANSPORT_MAX_RATE_VIOLATIONS` environment variable."),
+        Err(_) => DEFAULT_MAX_RATE_VIOLATIONS,
+    })
+}
+
+pub enum RateLimitDecision {
+    Allowed,
+    Throttled,
+    Exceeded,
+}
+
+// Token bucket shared by all streams of a connection, so opening more streams does not raise the limit
+pub struct MessageRateLimiter {
+    tokens: f64,
+    last_refill: Instant,
+    violations: u32,
+}
+
+impl MessageRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            tokens: MESSAGE_BURST() as f64,
+            last_refill: Instant::now(),
+            violations: 0,
+        }
+    }
+
+    pub fn check(&mut self) -> RateLimitDecision {
+        if MESSAGE_RATE() == 0 {
+            return RateLimitDecision::Allowed;
+        }
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            self.violations = 0;
+            return RateLimitDecision::Allowed;
+        }
+        self.violations += 1;
+        if MAX_RATE_VIOLATIONS() > 0 && self.violations >= MAX_RATE_VIOLATIONS() {
+            RateLimitDecision::Exceeded
+        } else {
+            RateLimitDecision::Throttled
+        }
+    }
+
+    // Time until the next message would be accepted again
+    pub fn retry_after_millis(&self) -> u64 {
+        if MESSAGE_RATE() == 0 {
+            return 0;
+        }
+        ((1.0 - self.tokens).max(0.0) / MESSAGE_RATE() as f64 * 1000.0).ceil() as u64
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed_seconds = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed_seconds * MESSAGE_RATE() as f64).min(MESSAGE_BURST() as f64);
+        self.last_refill = now;
+    }
+}
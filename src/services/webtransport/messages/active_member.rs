@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use bson::doc;
+use bson::{doc, DateTime};
 use mongodb::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -8,12 +8,16 @@ use tokio::sync::Mutex;
 
 use crate::{
     database::{
-        collections::active_member::{ActiveMember, CreateActiveMember, UpdateActiveMember},
+        collections::{
+            active_member::{ActiveMember, CreateActiveMember, UpdateActiveMember},
+            client::Client as ClientDocument,
+        },
         document::Document,
     },
     services::webtransport::context::active_member::{
         ActiveMemberContext, ActiveMemberEvent, ActiveMemberEventType,
     },
+    utils::serialize_or_log::serialize_or_log,
 };
 
 use super::{
@@ -21,9 +25,22 @@ use super::{
     server::ServerMessage,
 };
 
+const MAX_BUFFERED_POSITIONS: usize = 50;
+
 pub struct ActiveMemberMessage {}
 
 impl WebTransportMainCategoryHandler<ActiveMemberContext> for ActiveMemberMessage {
+    const SUBCATEGORIES: &'static [&'static str] = &[
+        "createactivemember",
+        "removeactivemember",
+        "changeactiveboard",
+        "updateposition",
+        "updatepositions",
+        "setactivity",
+        "clearactivity",
+        "keepalive",
+    ];
+
     async fn handle_with_corresponding_message(
         message_subcategory: &str,
         message: Value,
@@ -43,6 +60,18 @@ impl WebTransportMainCategoryHandler<ActiveMemberContext> for ActiveMemberMessag
             "updateposition" => {
                 UpdatePositionMessage::handle_message(message, database_client, context).await
             }
+            "updatepositions" => {
+                UpdatePositionsMessage::handle_message(message, database_client, context).await
+            }
+            "setactivity" => {
+                SetActivityMessage::handle_message(message, database_client, context).await
+            }
+            "clearactivity" => {
+                ClearActivityMessage::handle_message(message, database_client, context).await
+            }
+            "keepalive" => {
+                KeepAliveMessage::handle_message(message, database_client, context).await
+            }
             _ => Err(ServerMessage::error_response(
                 "unknownactivemembercategory".to_string(),
                 "Active Member has no such subcategory".to_string(),
@@ -98,27 +127,37 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for CreateActiveMemberM
                 board_id: body.board_id.clone(),
                 x: 0.0,
                 y: 0.0,
+                created_at: DateTime::now(),
             },
         )
         .await;
         match create_active_member_result {
             Ok(result) => {
-                let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
+                let inserted_id = match result.inserted_id.as_object_id() {
+                    Some(inserted_id) => inserted_id.to_hex(),
+                    None => {
+                        return Err(ServerMessage::error_response(
+                            "createactivemember".to_string(),
+                            "Inserted ID is not an ObjectId".to_string(),
+                        ))
+                    }
+                };
                 let mut sub_context = context.lock().await;
-                sub_context
-                    .emit_active_member_event(
-                        body.board_id.clone(),
-                        ActiveMemberEvent {
-                            event_type: ActiveMemberEventType::Created,
-                            body: serde_json::to_string(&CreatedActiveMemberEventPayload {
-                                _id: inserted_id.clone(),
-                                board_id: body.board_id.clone(),
-                                user_id: body.user_id.clone(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&CreatedActiveMemberEventPayload {
+                    _id: inserted_id.clone(),
+                    board_id: body.board_id.clone(),
+                    user_id: body.user_id.clone(),
+                }) {
+                    sub_context
+                        .emit_active_member_event(
+                            body.board_id.clone(),
+                            ActiveMemberEvent {
+                                event_type: ActiveMemberEventType::Created,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(sub_context);
                 Ok(ServerMessage::ok_response(
                     "createactivemember".to_string(),
@@ -148,7 +187,6 @@ pub struct RemovedActiveMemberEventPayload {
 #[serde(rename_all = "camelCase")]
 pub struct RemoveActiveMemberMessage {
     pub user_id: String,
-    pub board_id: String,
 }
 
 #[derive(Serialize)]
@@ -176,31 +214,33 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for RemoveActiveMemberM
            "userId": body.user_id.clone(),
         };
         let delete_active_member_result =
-            ActiveMember::delete_document(&database_client, query_doc).await;
+            ActiveMember::find_one_and_delete(&database_client, query_doc).await;
         match delete_active_member_result {
-            Ok(result) => match result.deleted_count {
-                0 => Err(ServerMessage::error_response(
+            Ok(result) => match result {
+                None => Err(ServerMessage::error_response(
                     "removeactivemember".to_string(),
                     "No Active Member found to delete".to_string(),
                 )),
-                _ => {
+                Some(removed_active_member) => {
                     let mut sub_context = context.lock().await;
-                    sub_context
-                        .emit_active_member_event(
-                            body.board_id.clone(),
-                            ActiveMemberEvent {
-                                event_type: ActiveMemberEventType::Removed,
-                                body: serde_json::to_string(&RemovedActiveMemberEventPayload {
-                                    user_id: body.user_id,
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    sub_context.clear_activity(body.user_id.clone()).await;
+                    if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+                        user_id: body.user_id,
+                    }) {
+                        sub_context
+                            .emit_active_member_event(
+                                removed_active_member.board_id,
+                                ActiveMemberEvent {
+                                    event_type: ActiveMemberEventType::Removed,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(sub_context);
                     Ok(ServerMessage::ok_response(
                         "removeactivemember".to_string(),
-                        format!("{}", result.deleted_count),
+                        "1".to_string(),
                     ))
                 }
             },
@@ -272,6 +312,7 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for ChangeActiveBoardMe
                 board_id: Some(body.new_board_id.clone()),
                 x: Some(0.0),
                 y: Some(0.0),
+                position_updated_at: None,
             },
         )
         .await;
@@ -283,32 +324,35 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for ChangeActiveBoardMe
                 )),
                 _ => {
                     let mut sub_context = context.lock().await;
-                    sub_context
-                        .emit_active_member_event(
-                            active_member.board_id,
-                            ActiveMemberEvent {
-                                event_type: ActiveMemberEventType::Removed,
-                                body: serde_json::to_string(&RemovedActiveMemberEventPayload {
-                                    user_id: body.user_id.clone(),
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
-                    sub_context
-                        .emit_active_member_event(
-                            body.new_board_id.clone(),
-                            ActiveMemberEvent {
-                                event_type: ActiveMemberEventType::Created,
-                                body: serde_json::to_string(&CreatedActiveMemberEventPayload {
-                                    _id: active_member._id,
-                                    user_id: body.user_id.clone(),
-                                    board_id: body.new_board_id.clone(),
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    sub_context.clear_activity(body.user_id.clone()).await;
+                    if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+                        user_id: body.user_id.clone(),
+                    }) {
+                        sub_context
+                            .emit_active_member_event(
+                                active_member.board_id,
+                                ActiveMemberEvent {
+                                    event_type: ActiveMemberEventType::Removed,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
+                    if let Ok(event_body) = serialize_or_log(&CreatedActiveMemberEventPayload {
+                        _id: active_member._id,
+                        user_id: body.user_id.clone(),
+                        board_id: body.new_board_id.clone(),
+                    }) {
+                        sub_context
+                            .emit_active_member_event(
+                                body.new_board_id.clone(),
+                                ActiveMemberEvent {
+                                    event_type: ActiveMemberEventType::Created,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(sub_context);
                     Ok(ServerMessage::ok_response(
                         "changeactiveboard".to_string(),
@@ -378,6 +422,7 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for UpdatePositionMessa
                 x: Some(body.x),
                 y: Some(body.y),
                 board_id: None,
+                position_updated_at: None,
             },
         )
         .await;
@@ -389,20 +434,21 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for UpdatePositionMessa
                 )),
                 _ => {
                     let mut sub_context = context.lock().await;
-                    sub_context
-                        .emit_active_member_event(
-                            body.board_id.clone(),
-                            ActiveMemberEvent {
-                                event_type: ActiveMemberEventType::PositionUpdated,
-                                body: serde_json::to_string(&UpdatedPositionEventPayload {
-                                    user_id: body.user_id.clone(),
-                                    x: body.x,
-                                    y: body.y,
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&UpdatedPositionEventPayload {
+                        user_id: body.user_id.clone(),
+                        x: body.x,
+                        y: body.y,
+                    }) {
+                        sub_context
+                            .emit_active_member_event(
+                                body.board_id.clone(),
+                                ActiveMemberEvent {
+                                    event_type: ActiveMemberEventType::PositionUpdated,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(sub_context);
                     Ok(ServerMessage::ok_response(
                         "updateposition".to_string(),
@@ -422,3 +468,374 @@ impl WebTransportBaseMessageHandler<ActiveMemberContext> for UpdatePositionMessa
         }
     }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BufferedPosition {
+    pub x: f32,
+    pub y: f32,
+    // RFC 3339 timestamp of the moment the client recorded the position
+    pub timestamp: String,
+}
+
+// Flushes cursor moves a client buffered while offline or reconnecting, only the latest position
+// that is newer than the stored one is applied and emitted
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePositionsMessage {
+    pub user_id: String,
+    pub board_id: String,
+    pub positions: Vec<BufferedPosition>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatedPositionsMessage {
+    pub user_id: String,
+    pub x: f32,
+    pub y: f32,
+    pub applied: bool,
+}
+
+impl WebTransportBaseMessageHandler<ActiveMemberContext> for UpdatePositionsMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ActiveMemberContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<UpdatePositionsMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "updatepositions".to_string(),
+                    "Update Positions Message is invalid".to_string(),
+                ))
+            }
+        };
+        if body.positions.is_empty() || body.positions.len() > MAX_BUFFERED_POSITIONS {
+            return Err(ServerMessage::error_response(
+                "updatepositions".to_string(),
+                format!(
+                    "Between 1 and {} positions can be updated at once",
+                    MAX_BUFFERED_POSITIONS
+                ),
+            ));
+        }
+        let mut positions = Vec::new();
+        for position in body.positions {
+            match DateTime::parse_rfc3339_str(position.timestamp.as_str()) {
+                Ok(timestamp) => positions.push((timestamp, position.x, position.y)),
+                Err(_) => {
+                    return Err(ServerMessage::error_response(
+                        "updatepositions".to_string(),
+                        "Position `timestamp` is not a valid RFC 3339 timestamp".to_string(),
+                    ))
+                }
+            }
+        }
+        let active_member = match ActiveMember::get_existing_active_member_by_user_id(
+            body.user_id.clone(),
+            &database_client,
+        )
+        .await
+        {
+            Ok(active_member) => active_member,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "updatepositions".to_string(),
+                    "Error during fetching of active member".to_string(),
+                ))
+            }
+        };
+        if active_member.board_id != body.board_id {
+            return Err(ServerMessage::error_response(
+                "updatepositions".to_string(),
+                "Active member is not working on this board".to_string(),
+            ));
+        }
+        let latest_position = positions
+            .into_iter()
+            .filter(|(timestamp, _, _)| {
+                active_member
+                    .position_updated_at
+                    .is_none_or(|position_updated_at| *timestamp > position_updated_at)
+            })
+            .max_by_key(|(timestamp, _, _)| *timestamp);
+        let Some((timestamp, x, y)) = latest_position else {
+            return Ok(ServerMessage::ok_response(
+                "updatepositions".to_string(),
+                serde_json::to_string(&UpdatedPositionsMessage {
+                    user_id: body.user_id,
+                    x: active_member.x,
+                    y: active_member.y,
+                    applied: false,
+                })
+                .unwrap(),
+            ));
+        };
+        // Guards against a newer position that was stored since the active member was fetched
+        let query_doc = doc! {
+            "userId": body.user_id.clone(),
+            "$or": vec![
+                doc! { "positionUpdatedAt": doc! { "$exists": false } },
+                doc! { "positionUpdatedAt": doc! { "$lt": timestamp } },
+            ],
+        };
+        let update_result = ActiveMember::update_document(
+            &database_client,
+            query_doc,
+            UpdateActiveMember {
+                x: Some(x),
+                y: Some(y),
+                board_id: None,
+                position_updated_at: Some(timestamp),
+            },
+        )
+        .await;
+        match update_result {
+            Ok(result) => {
+                let applied = result.matched_count > 0;
+                if applied {
+                    let mut sub_context = context.lock().await;
+                    if let Ok(event_body) = serialize_or_log(&UpdatedPositionEventPayload {
+                        user_id: body.user_id.clone(),
+                        x,
+                        y,
+                    }) {
+                        sub_context
+                            .emit_active_member_event(
+                                body.board_id.clone(),
+                                ActiveMemberEvent {
+                                    event_type: ActiveMemberEventType::PositionUpdated,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
+                    drop(sub_context);
+                }
+                Ok(ServerMessage::ok_response(
+                    "updatepositions".to_string(),
+                    serde_json::to_string(&UpdatedPositionsMessage {
+                        user_id: body.user_id,
+                        x,
+                        y,
+                        applied,
+                    })
+                    .unwrap(),
+                ))
+            }
+            Err(_) => Err(ServerMessage::error_response(
+                "updatepositions".to_string(),
+                "Error during updating of position of active member".to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityChangedEventPayload {
+    pub user_id: String,
+    pub element_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetActivityMessage {
+    pub user_id: String,
+    pub board_id: String,
+    pub element_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivitySetMessage {
+    pub user_id: String,
+    pub element_id: String,
+}
+
+impl WebTransportBaseMessageHandler<ActiveMemberContext> for SetActivityMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ActiveMemberContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<SetActivityMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "setactivity".to_string(),
+                    "Set Activity Message is invalid".to_string(),
+                ))
+            }
+        };
+        match ActiveMember::get_existing_active_member_by_user_id(
+            body.user_id.clone(),
+            &database_client,
+        )
+        .await
+        {
+            Ok(active_member) => {
+                if active_member.board_id != body.board_id {
+                    return Err(ServerMessage::error_response(
+                        "setactivity".to_string(),
+                        "Active member is not working on this board".to_string(),
+                    ));
+                }
+            }
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "setactivity".to_string(),
+                    "Error during fetching of active member".to_string(),
+                ))
+            }
+        };
+        let mut sub_context = context.lock().await;
+        sub_context
+            .set_activity(
+                body.user_id.clone(),
+                body.board_id.clone(),
+                body.element_id.clone(),
+            )
+            .await;
+        drop(sub_context);
+        Ok(ServerMessage::ok_response(
+            "setactivity".to_string(),
+            serde_json::to_string(&ActivitySetMessage {
+                user_id: body.user_id,
+                element_id: body.element_id,
+            })
+            .unwrap(),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClearActivityMessage {
+    pub user_id: String,
+    pub board_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityClearedMessage {
+    pub user_id: String,
+}
+
+impl WebTransportBaseMessageHandler<ActiveMemberContext> for ClearActivityMessage {
+    async fn handle_message(
+        message: Value,
+        _database_client: Client,
+        context: Arc<Mutex<ActiveMemberContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<ClearActivityMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "clearactivity".to_string(),
+                    "Clear Activity Message is invalid".to_string(),
+                ))
+            }
+        };
+        let mut sub_context = context.lock().await;
+        let cleared_activity = match sub_context.member_activities.get(&body.user_id) {
+            Some(activity) if activity.board_id == body.board_id => {
+                sub_context.clear_activity(body.user_id.clone()).await
+            }
+            _ => None,
+        };
+        drop(sub_context);
+        match cleared_activity {
+            Some(_) => Ok(ServerMessage::ok_response(
+                "clearactivity".to_string(),
+                serde_json::to_string(&ActivityClearedMessage {
+                    user_id: body.user_id,
+                })
+                .unwrap(),
+            )),
+            None => Err(ServerMessage::error_response(
+                "clearactivity".to_string(),
+                "No activity found to clear".to_string(),
+            )),
+        }
+    }
+}
+
+// Keeps the presence of a member warm without a human present, e.g. on boards used as dashboards.
+// Nothing is broadcast and the position is left alone. Besides `lastSeenAt` of the Active Member,
+// the Client counts as seen, so it is not logged out once `CLIENT_INACTIVITY_TTL_SECONDS` passed.
+// Keepalives within `ACTIVE_MEMBER_KEEPALIVE_DEBOUNCE_SECONDS` of the last stored one skip the database
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeepAliveMessage {
+    pub user_id: String,
+    pub board_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeptAliveMessage {
+    pub user_id: String,
+    // False if the keepalive fell into the debounce window and nothing was written
+    pub stored: bool,
+}
+
+impl WebTransportBaseMessageHandler<ActiveMemberContext> for KeepAliveMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ActiveMemberContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<KeepAliveMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "keepalive".to_string(),
+                    "Keep Alive Message is invalid".to_string(),
+                ))
+            }
+        };
+        let sub_context = context.lock().await;
+        let debounced = sub_context.is_keepalive_debounced(&body.user_id, &body.board_id);
+        drop(sub_context);
+        if debounced {
+            return Ok(ServerMessage::ok_response(
+                "keepalive".to_string(),
+                serde_json::to_string(&KeptAliveMessage {
+                    user_id: body.user_id,
+                    stored: false,
+                })
+                .unwrap(),
+            ));
+        }
+        match ActiveMember::keep_alive(&body.user_id, &body.board_id, &database_client).await {
+            Ok(result) if result.matched_count == 0 => {
+                return Err(ServerMessage::error_response(
+                    "keepalive".to_string(),
+                    "No Active Member found to keep alive".to_string(),
+                ));
+            }
+            Ok(_) => {}
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "keepalive".to_string(),
+                    "Active Member could not be kept alive".to_string(),
+                ));
+            }
+        }
+        ClientDocument::touch(&body.user_id, &database_client).await;
+        let mut sub_context = context.lock().await;
+        sub_context.keepalive_stored(body.user_id.clone(), body.board_id);
+        drop(sub_context);
+        Ok(ServerMessage::ok_response(
+            "keepalive".to_string(),
+            serde_json::to_string(&KeptAliveMessage {
+                user_id: body.user_id,
+                stored: true,
+            })
+            .unwrap(),
+        ))
+    }
+}
@@ -1,12 +1,16 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
+use bson::{doc, oid::ObjectId};
 use mongodb::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::Mutex;
 
 use crate::{
-    database::collections::board::Board,
+    database::{
+        collections::board::{Board, UpdateBoard},
+        document::Document,
+    },
     services::webtransport::{
         context::board::{BoardContext, BoardEvent, BoardEventType},
         messages::{
@@ -14,6 +18,7 @@ use crate::{
             server::ServerMessage,
         },
     },
+    utils::serialize_or_log::serialize_or_log,
 };
 
 use super::server::ErrorResponseBody;
@@ -21,6 +26,9 @@ use super::server::ErrorResponseBody;
 pub struct BoardMessage {}
 
 impl WebTransportMainCategoryHandler<BoardContext> for BoardMessage {
+    const SUBCATEGORIES: &'static [&'static str] =
+        &["memberadd", "memberremove", "rename", "getboard"];
+
     async fn handle_with_corresponding_message(
         message_subcategory: &str,
         message: Value,
@@ -34,6 +42,8 @@ impl WebTransportMainCategoryHandler<BoardContext> for BoardMessage {
             "memberremove" => {
                 MemberRemoveMessage::handle_message(message, database_client, context).await
             }
+            "rename" => RenameMessage::handle_message(message, database_client, context).await,
+            "getboard" => GetBoardMessage::handle_message(message, database_client, context).await,
             _ => Err(ServerMessage::error_response(
                 "unknownboardcategory".to_string(),
                 "Board has no such subcategory".to_string(),
@@ -111,19 +121,20 @@ impl WebTransportBaseMessageHandler<BoardContext> for MemberAddMessage {
         {
             Ok(_) => {
                 let mut context_guard = context.lock().await;
-                context_guard
-                    .emit_board_event(
-                        database_client.clone(),
-                        body.board_id,
-                        BoardEvent {
-                            event_type: BoardEventType::MemberAdded,
-                            body: serde_json::to_string(&MemberAddedEventPayload {
-                                user_id: body.user_id.to_string(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&MemberAddedEventPayload {
+                    user_id: body.user_id.to_string(),
+                }) {
+                    context_guard
+                        .emit_board_event(
+                            database_client.clone(),
+                            body.board_id,
+                            BoardEvent {
+                                event_type: BoardEventType::MemberAdded,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(context_guard);
                 Ok(ServerMessage::ok_response(
                     "memberadd".to_string(),
@@ -151,6 +162,67 @@ pub struct MemberRemovedEventPayload {
     pub user_id: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberKickedEventPayload {
+    pub user_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewerAddedEventPayload {
+    pub user_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewerRemovedEventPayload {
+    pub user_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardNearLimitEventPayload {
+    pub element_count: u64,
+    pub element_limit: u64,
+}
+
+// Called after elements were added to a board, warns at most once per crossing of the threshold
+pub async fn emit_near_limit_if_reached(
+    database_client: &Client,
+    context: &Arc<Mutex<BoardContext>>,
+    board_id: &str,
+) {
+    let (element_count, element_limit) =
+        match Board::claim_limit_warning(board_id, database_client).await {
+            Ok(Some(limit_warning)) => limit_warning,
+            Ok(None) | Err(_) => return,
+        };
+    let mut sub_context = context.lock().await;
+    if let Ok(event_body) = serialize_or_log(&BoardNearLimitEventPayload {
+        element_count,
+        element_limit,
+    }) {
+        sub_context
+            .emit_board_event(
+                database_client.clone(),
+                board_id.to_string(),
+                BoardEvent {
+                    event_type: BoardEventType::NearLimit,
+                    body: event_body,
+                },
+            )
+            .await;
+    }
+    drop(sub_context);
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardEmptiedEventPayload {
+    pub user_id: String,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MemberRemoveMessage {
@@ -192,19 +264,16 @@ impl WebTransportBaseMessageHandler<BoardContext> for MemberRemoveMessage {
                 ));
             }
         };
-        match board.allowed_members.contains(&body.user_id) {
-            false => {
-                return Err(ServerMessage::error_response(
-                    "memberremove".to_string(),
-                    serde_json::to_string(&ErrorResponseBody {
-                        message: "Member not part of this board".to_string(),
-                        body: body.user_id,
-                    })
-                    .unwrap(),
-                ));
-            }
-            true => {}
-        };
+        if let Some(message) = board.member_removal_error(&body.user_id) {
+            return Err(ServerMessage::error_response(
+                "memberremove".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: message.to_string(),
+                    body: body.user_id,
+                })
+                .unwrap(),
+            ));
+        }
         match Board::remove_member(
             body.board_id.clone(),
             body.user_id.clone(),
@@ -214,19 +283,20 @@ impl WebTransportBaseMessageHandler<BoardContext> for MemberRemoveMessage {
         {
             Ok(_) => {
                 let mut context_guard = context.lock().await;
-                context_guard
-                    .emit_board_event(
-                        database_client.clone(),
-                        body.board_id,
-                        BoardEvent {
-                            event_type: BoardEventType::MemberRemoved,
-                            body: serde_json::to_string(&MemberRemovedEventPayload {
-                                user_id: body.user_id.to_string(),
-                            })
-                            .unwrap(),
-                        },
-                    )
-                    .await;
+                if let Ok(event_body) = serialize_or_log(&MemberRemovedEventPayload {
+                    user_id: body.user_id.to_string(),
+                }) {
+                    context_guard
+                        .emit_board_event(
+                            database_client.clone(),
+                            body.board_id,
+                            BoardEvent {
+                                event_type: BoardEventType::MemberRemoved,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(context_guard);
                 Ok(ServerMessage::ok_response(
                     "memberremove".to_string(),
@@ -247,3 +317,227 @@ impl WebTransportBaseMessageHandler<BoardContext> for MemberRemoveMessage {
         }
     }
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardRenamedEventPayload {
+    pub user_id: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameMessage {
+    pub user_id: String,
+    pub board_id: String,
+    pub name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedMessage {
+    board_id: String,
+    name: String,
+}
+
+impl WebTransportBaseMessageHandler<BoardContext> for RenameMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<BoardContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<RenameMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "rename".to_string(),
+                    "Rename Message is invalid".to_string(),
+                ))
+            }
+        };
+        if let Err(message) = Board::validate_name(&body.name) {
+            return Err(ServerMessage::error_response(
+                "rename".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: body.board_id,
+                })
+                .unwrap(),
+            ));
+        }
+        let board = match Board::get_existing_board(body.board_id.clone(), &database_client).await {
+            Ok(board) => board,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "rename".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Board does not exist".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ));
+            }
+        };
+        if !board.allowed_members.contains(&body.user_id) {
+            return Err(ServerMessage::error_response(
+                "rename".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "User is not part of this board".to_string(),
+                    body: body.board_id,
+                })
+                .unwrap(),
+            ));
+        }
+        match Board::is_name_taken(
+            &board.host,
+            &body.name,
+            Some(board._id.as_str()),
+            &database_client,
+        )
+        .await
+        {
+            Ok(false) => {}
+            Ok(true) => {
+                return Err(ServerMessage::error_response(
+                    "rename".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Host already has a board with this name".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ));
+            }
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "rename".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during Board name check".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ));
+            }
+        }
+        let query_doc = doc! {
+            "_id": ObjectId::from_str(board._id.as_str()).unwrap(),
+        };
+        let update_result = Board::update_document(
+            &database_client,
+            query_doc,
+            UpdateBoard {
+                name: Some(body.name.clone()),
+                host: None,
+                allowed_members: None,
+                viewers: None,
+                is_public: None,
+            },
+        )
+        .await;
+        match update_result {
+            Ok(_) => {
+                let mut context_guard = context.lock().await;
+                if let Ok(event_body) = serialize_or_log(&BoardRenamedEventPayload {
+                    user_id: body.user_id,
+                    name: body.name.clone(),
+                }) {
+                    context_guard
+                        .emit_board_event(
+                            database_client.clone(),
+                            board._id.clone(),
+                            BoardEvent {
+                                event_type: BoardEventType::Renamed,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+                drop(context_guard);
+                Ok(ServerMessage::ok_response(
+                    "rename".to_string(),
+                    serde_json::to_string(&RenamedMessage {
+                        board_id: board._id,
+                        name: body.name,
+                    })
+                    .unwrap(),
+                ))
+            }
+            Err(_) => Err(ServerMessage::error_response(
+                "rename".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "Board could not be renamed".to_string(),
+                    body: body.board_id,
+                })
+                .unwrap(),
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBoardMessage {
+    pub user_id: String,
+    pub board_id: String,
+}
+
+impl WebTransportBaseMessageHandler<BoardContext> for GetBoardMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        _context: Arc<Mutex<BoardContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<GetBoardMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "getboard".to_string(),
+                    "Get Board Message is invalid".to_string(),
+                ))
+            }
+        };
+        // The board may have been deleted mid-session, so a missing board is an error response
+        let board = match ObjectId::from_str(body.board_id.as_str()) {
+            Ok(board_object_id) => {
+                Board::get_document(&database_client, doc! { "_id": board_object_id }).await
+            }
+            Err(_) => Ok(None),
+        };
+        let board = match board {
+            Ok(Some(board)) => board,
+            Ok(None) => {
+                return Err(ServerMessage::error_response(
+                    "getboard".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Board does not exist".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ))
+            }
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "getboard".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Board could not be fetched".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        if !board.can_view(&body.user_id) {
+            return Err(ServerMessage::error_response(
+                "getboard".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "User is not part of this board".to_string(),
+                    body: body.board_id,
+                })
+                .unwrap(),
+            ));
+        }
+        Ok(ServerMessage::ok_response(
+            "getboard".to_string(),
+            serde_json::to_string(&board).unwrap(),
+        ))
+    }
+}
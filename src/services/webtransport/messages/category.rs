@@ -24,6 +24,14 @@ impl WebTransportMessageMainCategory {
 }
 
 pub trait WebTransportMainCategoryHandler<Context> {
+    // Every subcategory handled by `handle_with_corresponding_message`, messages with any other
+    // subcategory are rejected before they reach the handler and clients read it from `/capabilities`
+    const SUBCATEGORIES: &'static [&'static str];
+
+    fn supports_subcategory(message_subcategory: &str) -> bool {
+        Self::SUBCATEGORIES.contains(&message_subcategory)
+    }
+
     async fn handle_with_corresponding_message(
         message_subcategory: &str,
         message: Value,
@@ -7,3 +7,30 @@ pub struct ClientCreatedOrUpdatedPayload {
     pub user_id: String,
     pub device_type: String,
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientDisplacedPayload {
+    pub client_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientKickedPayload {
+    pub user_id: String,
+    pub board_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displaced_payload_names_the_client() {
+        let payload = serde_json::to_value(ClientDisplacedPayload {
+            client_id: "client".to_string(),
+        })
+        .unwrap();
+        assert_eq!(payload, serde_json::json!({ "clientId": "client" }));
+    }
+}
@@ -1,25 +1,28 @@
 use std::{str::FromStr, sync::Arc};
 
-use bson::{
-    doc,
-    oid::ObjectId,
-    serde_helpers::{
-        deserialize_bson_datetime_from_rfc3339_string, serialize_bson_datetime_as_rfc3339_string,
-    },
-    DateTime,
-};
+use bson::{doc, oid::ObjectId, DateTime};
 use futures::TryStreamExt;
-use mongodb::{results::UpdateResult, Client};
+use mongodb::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::sync::Mutex;
+use tracing::error;
 
 use crate::{
     database::{
-        collections::element::{CreateElement, Element, UpdateElement},
+        collections::{
+            board::Board,
+            element::{
+                normalize_rotation, CreateElement, Element, ElementConnector, UpdateElement,
+            },
+            element_history::{ElementHistory, ElementHistoryAction},
+            element_type::ElementType,
+        },
         document::Document,
+        timestamp::{deserialize_timestamp, serialize_timestamp},
     },
     services::webtransport::context::element::{ElementContext, ElementEvent, ElementEventType},
+    utils::serialize_or_log::serialize_or_log,
 };
 
 use super::{
@@ -28,9 +31,29 @@ use super::{
     server::{ErrorResponseBody, ServerMessage},
 };
 
+const MAX_CHANGES_SINCE_ELEMENTS: usize = 500;
+
 pub struct ElementMessage {}
 
 impl WebTransportMainCategoryHandler<ElementContext> for ElementMessage {
+    const SUBCATEGORIES: &'static [&'static str] = &[
+        "createelement",
+        "removeelement",
+        "lockelement",
+        "unlockelement",
+        "lockelements",
+        "unlockelements",
+        "unlockmine",
+        "updateelement",
+        "moveelements",
+        "setpositions",
+        "updatetext",
+        "getlocks",
+        "changessince",
+        "bringtofront",
+        "sendtoback",
+    ];
+
     async fn handle_with_corresponding_message(
         message_subcategory: &str,
         message: Value,
@@ -56,12 +79,45 @@ impl WebTransportMainCategoryHandler<ElementContext> for ElementMessage {
             "unlockelements" => {
                 UnlockElementsMessage::handle_message(message, database_client, context).await
             }
+            "unlockmine" => {
+                UnlockMineMessage::handle_message(message, database_client, context).await
+            }
             "updateelement" => {
                 UpdateElementMessage::handle_message(message, database_client, context).await
             }
             "moveelements" => {
                 MoveElementsMessage::handle_message(message, database_client, context).await
             }
+            "setpositions" => {
+                SetPositionsMessage::handle_message(message, database_client, context).await
+            }
+            "updatetext" => {
+                UpdateTextMessage::handle_message(message, database_client, context).await
+            }
+            "getlocks" => GetLocksMessage::handle_message(message, database_client, context).await,
+            "changessince" => {
+                ChangesSinceMessage::handle_message(message, database_client, context).await
+            }
+            "bringtofront" => {
+                ReorderElementMessage::handle_reorder(
+                    message,
+                    database_client,
+                    context,
+                    "bringtofront",
+                    true,
+                )
+                .await
+            }
+            "sendtoback" => {
+                ReorderElementMessage::handle_reorder(
+                    message,
+                    database_client,
+                    context,
+                    "sendtoback",
+                    false,
+                )
+                .await
+            }
             _ => Err(ServerMessage::error_response(
                 "unknownelementcategory".to_string(),
                 "Element has no such subcategory".to_string(),
@@ -84,12 +140,213 @@ pub struct ElementCreatedEventPayload {
     pub scale_x: f32,
     pub scale_y: f32,
     pub z_index: i32,
-    #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
+    #[serde(serialize_with = "serialize_timestamp")]
     pub created_at: DateTime,
     pub text: String,
     pub element_type: String,
     pub board_id: String,
     pub color: String,
+    pub metadata: Option<serde_json::Value>,
+    pub asset_id: Option<String>,
+    pub created_by: String,
+    pub connector: Option<ElementConnector>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectorUpdatedEventPayload {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub from: String,
+    pub to: String,
+}
+
+// The board ID comes from the client, a malformed one is treated like a missing board
+async fn find_board(board_id: &str, database_client: &Client) -> Option<Board> {
+    let board_object_id = ObjectId::from_str(board_id).ok()?;
+    Board::get_document(database_client, doc! { "_id": board_object_id })
+        .await
+        .ok()
+        .flatten()
+}
+
+// Only members may create, change, lock or remove elements, viewers and users outside of the board
+// are turned away. Releasing a lock stays possible, so a member demoted to viewer can still let go.
+async fn assert_editor(
+    subcategory: &str,
+    board_id: &str,
+    user_id: &str,
+    database_client: &Client,
+) -> Result<(), ServerMessage> {
+    let message = match find_board(board_id, database_client).await {
+        Some(board) if board.can_edit(user_id) => return Ok(()),
+        Some(_) => "User is not allowed to edit this board".to_string(),
+        None => format!("No Board found with ID: {}", board_id),
+    };
+    Err(ServerMessage::error_response(
+        subcategory.to_string(),
+        serde_json::to_string(&ErrorResponseBody {
+            message,
+            body: board_id.to_string(),
+        })
+        .unwrap(),
+    ))
+}
+
+// Reading elements requires the same access as following the board, members and viewers
+async fn assert_viewer(
+    subcategory: &str,
+    board_id: &str,
+    user_id: &str,
+    database_client: &Client,
+) -> Result<(), ServerMessage> {
+    let message = match find_board(board_id, database_client).await {
+        Some(board) if board.can_view(&user_id.to_string()) => return Ok(()),
+        Some(_) => "User is not part of this board".to_string(),
+        None => format!("No Board found with ID: {}", board_id),
+    };
+    Err(ServerMessage::error_response(
+        subcategory.to_string(),
+        serde_json::to_string(&ErrorResponseBody {
+            message,
+            body: board_id.to_string(),
+        })
+        .unwrap(),
+    ))
+}
+
+// The database returns found elements in its own order, multi element events follow the request
+pub fn sort_by_request_order(elements: &mut [Element], ids: &[String]) {
+    elements.sort_by_key(|element| ids.iter().position(|id| *id == element._id));
+}
+
+// Collects the outcome of multi element updates, events and history only cover the elements that
+// were actually changed. The first failed update stops the remaining ones, the earlier updates are
+// still announced before the failure is returned
+pub struct AppliedUpdates<E> {
+    pub updated_ids: Vec<String>,
+    pub failure: Option<E>,
+}
+
+impl<E> Default for AppliedUpdates<E> {
+    fn default() -> Self {
+        Self {
+            updated_ids: vec![],
+            failure: None,
+        }
+    }
+}
+
+impl<E> AppliedUpdates<E> {
+    // Returns whether the remaining updates should still be applied
+    pub fn record(
+        &mut self,
+        element_id: &str,
+        modified_count: Result<u64, E>,
+        unmodified_failure: impl FnOnce() -> E,
+    ) -> bool {
+        match modified_count {
+            Ok(0) => self.failure = Some(unmodified_failure()),
+            Ok(_) => self.updated_ids.push(element_id.to_string()),
+            Err(failure) => self.failure = Some(failure),
+        }
+        self.failure.is_none()
+    }
+}
+
+// Connectors are drawn between the elements they link, so clients redraw them once one of these moved
+pub async fn emit_connector_updates(
+    database_client: &Client,
+    context: &Arc<Mutex<ElementContext>>,
+    board_id: &str,
+    moved_ids: &[String],
+) {
+    let connectors = match Element::get_connectors_of(database_client, board_id, moved_ids).await {
+        Ok(connectors) => connectors,
+        Err(_) => {
+            error!(
+                "Connectors of moved Elements on Board {} could not be fetched",
+                board_id
+            );
+            return;
+        }
+    };
+    if connectors.is_empty() {
+        return;
+    }
+    let mut sub_context = context.lock().await;
+    for connector in connectors {
+        let Some(ElementConnector { from, to }) = connector.connector else {
+            continue;
+        };
+        if let Ok(event_body) = serialize_or_log(&ConnectorUpdatedEventPayload {
+            _id: connector._id,
+            from,
+            to,
+        }) {
+            sub_context
+                .emit_element_event(
+                    board_id.to_string(),
+                    ElementEvent {
+                        event_type: ElementEventType::ConnectorUpdated,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(sub_context);
+}
+
+// Connectors of removed elements are removed as well, clients learn about it like about any removal
+pub async fn remove_connectors_of(
+    database_client: &Client,
+    context: &Arc<Mutex<ElementContext>>,
+    board_id: &str,
+    user_id: &str,
+    removed_ids: &[String],
+) {
+    let connector_ids =
+        match Element::delete_connectors_of(database_client, board_id, removed_ids).await {
+            Ok(connector_ids) => connector_ids,
+            Err(_) => {
+                error!(
+                    "Connectors of removed Elements on Board {} could not be deleted",
+                    board_id
+                );
+                return;
+            }
+        };
+    if connector_ids.is_empty() {
+        return;
+    }
+    ElementHistory::record_multiple(
+        database_client,
+        &connector_ids,
+        board_id.to_string(),
+        user_id.to_string(),
+        ElementHistoryAction::Removed,
+        None,
+    )
+    .await;
+    let mut sub_context = context.lock().await;
+    for connector_id in connector_ids {
+        if let Ok(event_body) = serialize_or_log(&ElementRemovedEventPayload {
+            _id: connector_id,
+            user_id: user_id.to_string(),
+        }) {
+            sub_context
+                .emit_element_event(
+                    board_id.to_string(),
+                    ElementEvent {
+                        event_type: ElementEventType::Removed,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+    }
+    drop(sub_context);
 }
 
 #[derive(Deserialize)]
@@ -103,15 +360,19 @@ pub struct CreateElementMessage {
     pub x: f32,
     pub y: f32,
     pub rotation: f32,
-    pub scale_x: f32,
-    pub scale_y: f32,
-    pub z_index: i32,
-    #[serde(deserialize_with = "deserialize_bson_datetime_from_rfc3339_string")]
+    // Omitted fields are taken from the defaults of the element type
+    pub scale_x: Option<f32>,
+    pub scale_y: Option<f32>,
+    pub z_index: Option<i32>,
+    #[serde(deserialize_with = "deserialize_timestamp")]
     pub created_at: DateTime,
-    pub text: String,
+    pub text: Option<String>,
     pub element_type: String,
     pub board_id: String,
-    pub color: String,
+    pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub connector: Option<ElementConnector>,
 }
 
 #[derive(Serialize)]
@@ -128,12 +389,13 @@ pub struct ElementCreatedMessage {
     pub scale_x: f32,
     pub scale_y: f32,
     pub z_index: i32,
-    #[serde(serialize_with = "serialize_bson_datetime_as_rfc3339_string")]
+    #[serde(serialize_with = "serialize_timestamp")]
     pub created_at: DateTime,
     pub text: String,
     pub element_type: String,
     pub board_id: String,
     pub color: String,
+    pub connector: Option<ElementConnector>,
 }
 
 impl WebTransportBaseMessageHandler<ElementContext> for CreateElementMessage {
@@ -151,52 +413,179 @@ impl WebTransportBaseMessageHandler<ElementContext> for CreateElementMessage {
                 ));
             }
         };
+        assert_editor(
+            "createelement",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let defaults = match ElementType::get_defaults(&database_client, &body.element_type).await {
+            Ok(defaults) => defaults,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "createelement".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during Element Type defaults fetching".to_string(),
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        let text = defaults.text(body.text.clone());
+        let color = defaults.color(body.color.clone());
+        if let Err(message) = Element::validate_element_fields(Some(&text), Some(&color)) {
+            return Err(ServerMessage::error_response(
+                "createelement".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: body._id,
+                })
+                .unwrap(),
+            ));
+        }
+        if let Err(message) = Element::check_metadata(&body.metadata) {
+            return Err(ServerMessage::error_response(
+                "createelement".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: body._id,
+                })
+                .unwrap(),
+            ));
+        }
+        let singleton_message =
+            match Element::violates_singleton(&database_client, &body.board_id, &body.element_type)
+                .await
+            {
+                Ok(false) => None,
+                Ok(true) => Some("Board already contains an Element of this singleton type"),
+                Err(_) => Some("Error during Element Type singleton check"),
+            };
+        if let Some(message) = singleton_message {
+            return Err(ServerMessage::error_response(
+                "createelement".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: message.to_string(),
+                    body: body._id,
+                })
+                .unwrap(),
+            ));
+        }
+        if let Some(connector) = &body.connector {
+            let connector_message =
+                match Element::connector_targets_exist(&database_client, &body.board_id, connector)
+                    .await
+                {
+                    Ok(true) => None,
+                    Ok(false) => Some("Connector must link two Elements of the same Board"),
+                    Err(_) => Some("Error during Connector check"),
+                };
+            if let Some(message) = connector_message {
+                return Err(ServerMessage::error_response(
+                    "createelement".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: message.to_string(),
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ));
+            }
+        }
+        let z_index = match defaults.z_index(body.z_index) {
+            Some(z_index) => z_index,
+            None => match Board::next_z_index(&body.board_id, &database_client).await {
+                Ok(z_index) => z_index,
+                Err(_) => {
+                    return Err(ServerMessage::error_response(
+                        "createelement".to_string(),
+                        serde_json::to_string(&ErrorResponseBody {
+                            message: "Error during z-index assignment".to_string(),
+                            body: body._id,
+                        })
+                        .unwrap(),
+                    ))
+                }
+            },
+        };
         let create_element = CreateElement {
             _id: body._id.clone(),
             board_id: body.board_id.clone(),
             selected: body.selected,
             locked_by: body.locked_by,
-            rotation: body.rotation,
-            scale_x: body.scale_x,
-            scale_y: body.scale_y,
-            z_index: body.z_index,
+            rotation: normalize_rotation(body.rotation),
+            scale_x: defaults.scale_x(body.scale_x),
+            scale_y: defaults.scale_y(body.scale_y),
+            z_index,
             x: body.x,
             y: body.y,
             element_type: body.element_type.clone(),
-            text: body.text.clone(),
+            text,
             created_at: body.created_at,
-            color: body.color,
+            color,
+            metadata: body.metadata.clone(),
+            asset_id: None,
+            created_by: body.user_id.clone(),
+            updated_at: DateTime::now(),
+            connector: body.connector.clone(),
         };
         match Element::create_document(&database_client, create_element.clone()).await {
             Ok(result) => {
-                let inserted_id = result.inserted_id.as_object_id().unwrap().to_hex();
-                let mut context_guard = context.lock().await;
-                context_guard
-                    .emit_element_event(
-                        body.board_id,
-                        ElementEvent {
-                            event_type: ElementEventType::Created,
-                            body: serde_json::to_string(&ElementCreatedEventPayload {
-                                _id: inserted_id.clone(),
-                                user_id: body.user_id.clone(),
-                                selected: create_element.selected,
-                                locked_by: create_element.locked_by.clone(),
-                                x: create_element.x,
-                                y: create_element.y,
-                                rotation: create_element.rotation,
-                                scale_x: create_element.scale_x,
-                                scale_y: create_element.scale_y,
-                                z_index: create_element.z_index,
-                                created_at: create_element.created_at,
-                                text: create_element.text.clone(),
-                                element_type: create_element.element_type.clone(),
-                                board_id: create_element.board_id.clone(),
-                                color: create_element.color.clone(),
+                let inserted_id = match result.inserted_id.as_object_id() {
+                    Some(inserted_id) => inserted_id.to_hex(),
+                    None => {
+                        return Err(ServerMessage::error_response(
+                            "createelement".to_string(),
+                            serde_json::to_string(&ErrorResponseBody {
+                                message: "Inserted ID is not an ObjectId".to_string(),
+                                body: body._id.clone(),
                             })
                             .unwrap(),
-                        },
-                    )
-                    .await;
+                        ))
+                    }
+                };
+                ElementHistory::record(
+                    &database_client,
+                    inserted_id.clone(),
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Created,
+                    bson::to_document(&create_element).ok(),
+                )
+                .await;
+                let mut context_guard = context.lock().await;
+                if let Ok(event_body) = serialize_or_log(&ElementCreatedEventPayload {
+                    _id: inserted_id.clone(),
+                    user_id: body.user_id.clone(),
+                    selected: create_element.selected,
+                    locked_by: create_element.locked_by.clone(),
+                    x: create_element.x,
+                    y: create_element.y,
+                    rotation: create_element.rotation,
+                    scale_x: create_element.scale_x,
+                    scale_y: create_element.scale_y,
+                    z_index: create_element.z_index,
+                    created_at: create_element.created_at,
+                    text: create_element.text.clone(),
+                    element_type: create_element.element_type.clone(),
+                    board_id: create_element.board_id.clone(),
+                    color: create_element.color.clone(),
+                    metadata: create_element.metadata,
+                    asset_id: create_element.asset_id,
+                    created_by: create_element.created_by,
+                    connector: create_element.connector.clone(),
+                }) {
+                    context_guard
+                        .emit_element_event(
+                            body.board_id,
+                            ElementEvent {
+                                event_type: ElementEventType::Created,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
                 drop(context_guard);
                 Ok(ServerMessage::ok_response(
                     "createelement".to_string(),
@@ -216,6 +605,7 @@ impl WebTransportBaseMessageHandler<ElementContext> for CreateElementMessage {
                         element_type: create_element.element_type,
                         board_id: create_element.board_id,
                         color: create_element.color,
+                        connector: create_element.connector,
                     })
                     .unwrap(),
                 ))
@@ -271,9 +661,28 @@ impl WebTransportBaseMessageHandler<ElementContext> for RemoveElementMessage {
                 ))
             }
         };
+        assert_editor(
+            "removeelement",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
         match Element::delete_document(
             &database_client,
-            doc! { "_id": ObjectId::from_str(body._id.as_str()).unwrap() },
+            match Element::board_scoped_query(&body._id, &body.board_id) {
+                Ok(query_doc) => query_doc,
+                Err(message) => {
+                    return Err(ServerMessage::error_response(
+                        "removeelement".to_string(),
+                        serde_json::to_string(&ErrorResponseBody {
+                            message,
+                            body: body._id,
+                        })
+                        .unwrap(),
+                    ))
+                }
+            },
         )
         .await
         {
@@ -287,21 +696,39 @@ impl WebTransportBaseMessageHandler<ElementContext> for RemoveElementMessage {
                     .unwrap(),
                 )),
                 _ => {
+                    ElementHistory::record(
+                        &database_client,
+                        body._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Removed,
+                        None,
+                    )
+                    .await;
                     let mut context_guard = context.lock().await;
-                    context_guard
-                        .emit_element_event(
-                            body.board_id.clone(),
-                            ElementEvent {
-                                event_type: ElementEventType::Removed,
-                                body: serde_json::to_string(&ElementRemovedEventPayload {
-                                    _id: body._id.clone(),
-                                    user_id: body.user_id.clone(),
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&ElementRemovedEventPayload {
+                        _id: body._id.clone(),
+                        user_id: body.user_id.clone(),
+                    }) {
+                        context_guard
+                            .emit_element_event(
+                                body.board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Removed,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(context_guard);
+                    remove_connectors_of(
+                        &database_client,
+                        &context,
+                        &body.board_id,
+                        &body.user_id,
+                        std::slice::from_ref(&body._id),
+                    )
+                    .await;
                     Ok(ServerMessage::ok_response(
                         "removeelement".to_string(),
                         serde_json::to_string(&ElementRemovedMessage { _id: body._id }).unwrap(),
@@ -360,8 +787,25 @@ impl WebTransportBaseMessageHandler<ElementContext> for LockElementMessage {
                 ))
             }
         };
-        let query_doc = doc! {
-            "_id": ObjectId::from_str(body._id.as_str()).unwrap()
+        assert_editor(
+            "lockelement",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "lockelement".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ))
+            }
         };
         let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
         match found_element_result {
@@ -425,6 +869,7 @@ impl WebTransportBaseMessageHandler<ElementContext> for LockElementMessage {
                 z_index: None,
                 text: None,
                 color: None,
+                metadata: None,
             },
         )
         .await;
@@ -439,20 +884,30 @@ impl WebTransportBaseMessageHandler<ElementContext> for LockElementMessage {
                     .unwrap(),
                 )),
                 _ => {
+                    ElementHistory::record(
+                        &database_client,
+                        body._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Locked,
+                        None,
+                    )
+                    .await;
                     let mut context_guard = context.lock().await;
-                    context_guard
-                        .emit_element_event(
-                            body.board_id.clone(),
-                            ElementEvent {
-                                event_type: ElementEventType::Locked,
-                                body: serde_json::to_string(&ElementLockedEventPayload {
-                                    _id: body._id.clone(),
-                                    user_id: body.user_id.clone(),
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&ElementLockedEventPayload {
+                        _id: body._id.clone(),
+                        user_id: body.user_id.clone(),
+                    }) {
+                        context_guard
+                            .emit_element_event(
+                                body.board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Locked,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(context_guard);
                     Ok(ServerMessage::ok_response(
                         "lockelement".to_string(),
@@ -514,8 +969,18 @@ impl WebTransportBaseMessageHandler<ElementContext> for UnlockElementMessage {
                 ))
             }
         };
-        let query_doc = doc! {
-            "_id": ObjectId::from_str(body._id.as_str()).unwrap()
+        let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "unlockelement".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ))
+            }
         };
         let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
         match found_element_result {
@@ -580,6 +1045,7 @@ impl WebTransportBaseMessageHandler<ElementContext> for UnlockElementMessage {
                 z_index: None,
                 text: None,
                 color: None,
+                metadata: None,
             },
         )
         .await;
@@ -594,19 +1060,29 @@ impl WebTransportBaseMessageHandler<ElementContext> for UnlockElementMessage {
                     .unwrap(),
                 )),
                 _ => {
+                    ElementHistory::record(
+                        &database_client,
+                        body._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Unlocked,
+                        None,
+                    )
+                    .await;
                     let mut context_guard = context.lock().await;
-                    context_guard
-                        .emit_element_event(
-                            body.board_id.clone(),
-                            ElementEvent {
-                                event_type: ElementEventType::Unlocked,
-                                body: serde_json::to_string(&ElementUnlockedEventPayload {
-                                    _id: body._id.clone(),
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+                        _id: body._id.clone(),
+                    }) {
+                        context_guard
+                            .emit_element_event(
+                                body.board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Unlocked,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(context_guard);
                     Ok(ServerMessage::ok_response(
                         "unlockelement".to_string(),
@@ -656,12 +1132,29 @@ impl WebTransportBaseMessageHandler<ElementContext> for LockElementsMessage {
                 ))
             }
         };
-        let query_doc = doc! {
-            "_id": doc! { "$in": body.ids.iter().map(|id| ObjectId::from_str(id.as_str()).unwrap()).collect::<Vec<ObjectId>>() }
+        assert_editor(
+            "lockelements",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let query_doc = match Element::board_scoped_ids_query(&body.ids, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "lockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                ))
+            }
         };
         let found_element_result =
             Element::get_multiple_documents(&database_client, query_doc.clone()).await;
-        let found_elements = match found_element_result {
+        let mut found_elements = match found_element_result {
             Ok(element_cursor) => {
                 let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
                 match retrieved_elements {
@@ -717,12 +1210,11 @@ impl WebTransportBaseMessageHandler<ElementContext> for LockElementsMessage {
                 .unwrap(),
             ));
         }
-        let mut updated_document_results: Vec<UpdateResult> = vec![];
+        sort_by_request_order(&mut found_elements, &body.ids);
+        let mut applied_updates = AppliedUpdates::default();
         for element in found_elements.iter() {
-            let query_doc = doc! {
-                "_id": element._id.clone(),
-            };
-            match Element::update_document(
+            let query_doc = element.stored_id_query();
+            let modified_count = Element::update_document(
                 &database_client,
                 query_doc,
                 UpdateElement {
@@ -736,68 +1228,84 @@ impl WebTransportBaseMessageHandler<ElementContext> for LockElementsMessage {
                     z_index: None,
                     text: None,
                     color: None,
+                    metadata: None,
                 },
             )
             .await
-            {
-                Ok(update_result) => match update_result.modified_count {
-                    0 => {
-                        return Err(ServerMessage::error_response(
-                            "lockelements".to_string(),
-                            serde_json::to_string(&ErrorResponseBody {
-                                message: format!("Lock of Element with ID {} failed", element._id),
-                                body: serde_json::to_string(&body.ids).unwrap(),
-                            })
-                            .unwrap(),
-                        ))
-                    }
-                    _ => {
-                        updated_document_results.push(update_result);
-                    }
-                },
-                Err(_) => {
-                    return Err(ServerMessage::error_response(
-                        "lockelements".to_string(),
-                        serde_json::to_string(&ErrorResponseBody {
-                            message: "Error during locking of elements".to_string(),
-                            body: serde_json::to_string(&body.ids).unwrap(),
-                        })
-                        .unwrap(),
-                    ))
-                }
+            .map(|update_result| update_result.modified_count)
+            .map_err(|_| {
+                ServerMessage::error_response(
+                    "lockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during locking of elements".to_string(),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            });
+            if !applied_updates.record(&element._id, modified_count, || {
+                ServerMessage::error_response(
+                    "lockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: format!("Lock of Element with ID {} failed", element._id),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            }) {
+                break;
             }
         }
-        match updated_document_results.len() {
-            0 => Err(ServerMessage::error_response(
-                "lockelements".to_string(),
-                serde_json::to_string(&ErrorResponseBody {
-                    message: "No Element found to lock".to_string(),
-                    body: serde_json::to_string(&body.ids).unwrap(),
-                })
-                .unwrap(),
-            )),
+        let AppliedUpdates {
+            updated_ids,
+            failure: failure_response,
+        } = applied_updates;
+        match updated_ids.len() {
+            0 => Err(failure_response.unwrap_or_else(|| {
+                ServerMessage::error_response(
+                    "lockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "No Element found to lock".to_string(),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            })),
             _ => {
-                for element_id in body.ids.iter() {
+                ElementHistory::record_multiple(
+                    &database_client,
+                    &updated_ids,
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Locked,
+                    None,
+                )
+                .await;
+                for element_id in updated_ids.iter() {
                     let mut sub_context = context.lock().await;
-                    sub_context
-                        .emit_element_event(
-                            body.board_id.to_string(),
-                            ElementEvent {
-                                event_type: ElementEventType::Locked,
-                                body: serde_json::to_string(&ElementLockedEventPayload {
-                                    _id: element_id.clone(),
-                                    user_id: body.user_id.clone(),
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&ElementLockedEventPayload {
+                        _id: element_id.clone(),
+                        user_id: body.user_id.clone(),
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                body.board_id.to_string(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Locked,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(sub_context);
                 }
+                if let Some(failure_response) = failure_response {
+                    return Err(failure_response);
+                }
                 Ok(ServerMessage::ok_response(
                     "lockelements".to_string(),
                     serde_json::to_string(&ElementsLockedMessage {
-                        ids: body.ids,
+                        ids: updated_ids,
                         user_id: body.user_id,
                     })
                     .unwrap(),
@@ -836,12 +1344,22 @@ impl WebTransportBaseMessageHandler<ElementContext> for UnlockElementsMessage {
                 ))
             }
         };
-        let query_doc = doc! {
-            "_id": doc! { "$in": body.ids.iter().map(|id| ObjectId::from_str(id.as_str()).unwrap()).collect::<Vec<ObjectId>>() }
+        let query_doc = match Element::board_scoped_ids_query(&body.ids, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "unlockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                ))
+            }
         };
         let found_element_result =
             Element::get_multiple_documents(&database_client, query_doc.clone()).await;
-        let found_elements = match found_element_result {
+        let mut found_elements = match found_element_result {
             Ok(element_cursor) => {
                 let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
                 match retrieved_elements {
@@ -897,12 +1415,11 @@ impl WebTransportBaseMessageHandler<ElementContext> for UnlockElementsMessage {
                 .unwrap(),
             ));
         }
-        let mut updated_document_results: Vec<UpdateResult> = vec![];
+        sort_by_request_order(&mut found_elements, &body.ids);
+        let mut applied_updates = AppliedUpdates::default();
         for element in found_elements.iter() {
-            let query_doc = doc! {
-                "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
-            };
-            match Element::update_document(
+            let query_doc = element.stored_id_query();
+            let modified_count = Element::update_document(
                 &database_client,
                 query_doc,
                 UpdateElement {
@@ -916,72 +1433,162 @@ impl WebTransportBaseMessageHandler<ElementContext> for UnlockElementsMessage {
                     z_index: None,
                     text: None,
                     color: None,
+                    metadata: None,
                 },
             )
             .await
-            {
-                Ok(update_result) => match update_result.modified_count {
-                    0 => {
-                        return Err(ServerMessage::error_response(
-                            "unlockelements".to_string(),
-                            serde_json::to_string(&ErrorResponseBody {
-                                message: format!(
-                                    "Unlock of Element with ID {} failed",
-                                    element._id
-                                ),
-                                body: serde_json::to_string(&body.ids).unwrap(),
-                            })
-                            .unwrap(),
-                        ));
-                    }
-                    _ => {
-                        updated_document_results.push(update_result);
-                    }
-                },
-                Err(_) => {
-                    return Err(ServerMessage::error_response(
-                        "unlockelements".to_string(),
-                        serde_json::to_string(&ErrorResponseBody {
-                            message: "Error during unlocking of elements".to_string(),
-                            body: serde_json::to_string(&body.ids).unwrap(),
-                        })
-                        .unwrap(),
-                    ))
-                }
+            .map(|update_result| update_result.modified_count)
+            .map_err(|_| {
+                ServerMessage::error_response(
+                    "unlockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during unlocking of elements".to_string(),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            });
+            if !applied_updates.record(&element._id, modified_count, || {
+                ServerMessage::error_response(
+                    "unlockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: format!("Unlock of Element with ID {} failed", element._id),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            }) {
+                break;
             }
         }
-        match updated_document_results.len() {
-            0 => Err(ServerMessage::error_response(
-                "unlockelements".to_string(),
-                serde_json::to_string(&ErrorResponseBody {
-                    message: "No Element found to unlock".to_string(),
-                    body: serde_json::to_string(&body.ids).unwrap(),
-                })
-                .unwrap(),
-            )),
+        let AppliedUpdates {
+            updated_ids,
+            failure: failure_response,
+        } = applied_updates;
+        match updated_ids.len() {
+            0 => Err(failure_response.unwrap_or_else(|| {
+                ServerMessage::error_response(
+                    "unlockelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "No Element found to unlock".to_string(),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            })),
             _ => {
-                for element_id in body.ids.iter() {
+                ElementHistory::record_multiple(
+                    &database_client,
+                    &updated_ids,
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Unlocked,
+                    None,
+                )
+                .await;
+                for element_id in updated_ids.iter() {
                     let mut sub_context = context.lock().await;
+                    if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+                        _id: element_id.clone(),
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                body.board_id.to_string(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Unlocked,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
+                    drop(sub_context);
+                }
+                if let Some(failure_response) = failure_response {
+                    return Err(failure_response);
+                }
+                Ok(ServerMessage::ok_response(
+                    "unlockelements".to_string(),
+                    serde_json::to_string(&ElementsUnlockedMessage { ids: updated_ids }).unwrap(),
+                ))
+            }
+        }
+    }
+}
+
+// Releases every lock the user holds on the board, for clients that lost track of their locks
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockMineMessage {
+    pub user_id: String,
+    pub board_id: String,
+}
+
+impl WebTransportBaseMessageHandler<ElementContext> for UnlockMineMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ElementContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<UnlockMineMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "unlockmine".to_string(),
+                    "Unlock Mine Message is invalid".to_string(),
+                ))
+            }
+        };
+        let unlocked_ids = match Element::unlock_all_locked_by(
+            &database_client,
+            body.board_id.clone(),
+            std::slice::from_ref(&body.user_id),
+        )
+        .await
+        {
+            Ok(unlocked_ids) => unlocked_ids,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "unlockmine".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during unlocking of elements".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        if !unlocked_ids.is_empty() {
+            ElementHistory::record_multiple(
+                &database_client,
+                &unlocked_ids,
+                body.board_id.clone(),
+                body.user_id.clone(),
+                ElementHistoryAction::Unlocked,
+                None,
+            )
+            .await;
+            let mut sub_context = context.lock().await;
+            for element_id in unlocked_ids.iter() {
+                if let Ok(event_body) = serialize_or_log(&ElementUnlockedEventPayload {
+                    _id: element_id.clone(),
+                }) {
                     sub_context
                         .emit_element_event(
-                            body.board_id.to_string(),
+                            body.board_id.clone(),
                             ElementEvent {
                                 event_type: ElementEventType::Unlocked,
-                                body: serde_json::to_string(&ElementUnlockedEventPayload {
-                                    _id: element_id.clone(),
-                                })
-                                .unwrap(),
+                                body: event_body,
                             },
                         )
                         .await;
-                    drop(sub_context);
                 }
-                Ok(ServerMessage::ok_response(
-                    "unlockelements".to_string(),
-                    serde_json::to_string(&ElementsUnlockedMessage { ids: body.ids }).unwrap(),
-                ))
             }
+            drop(sub_context);
         }
+        Ok(ServerMessage::ok_response(
+            "unlockmine".to_string(),
+            serde_json::to_string(&ElementsUnlockedMessage { ids: unlocked_ids }).unwrap(),
+        ))
     }
 }
 
@@ -999,6 +1606,8 @@ pub struct UpdatedElementEventPayload {
     pub z_index: Option<i32>,
     pub text: Option<String>,
     pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub asset_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -1016,6 +1625,7 @@ pub struct UpdateElementMessage {
     pub z_index: Option<i32>,
     pub text: Option<String>,
     pub color: Option<String>,
+    pub metadata: Option<serde_json::Value>,
 }
 
 #[derive(Serialize)]
@@ -1039,8 +1649,47 @@ impl WebTransportBaseMessageHandler<ElementContext> for UpdateElementMessage {
                 ))
             }
         };
-        let query_doc = doc! {
-            "_id": ObjectId::from_str(body._id.as_str()).unwrap(),
+        assert_editor(
+            "updateelement",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        if let Err(message) =
+            Element::validate_element_fields(body.text.as_deref(), body.color.as_deref())
+        {
+            return Err(ServerMessage::error_response(
+                "updateelement".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: body._id,
+                })
+                .unwrap(),
+            ));
+        }
+        if let Err(message) = Element::check_metadata(&body.metadata) {
+            return Err(ServerMessage::error_response(
+                "updateelement".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: body._id,
+                })
+                .unwrap(),
+            ));
+        }
+        let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "updateelement".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ))
+            }
         };
         let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
         match found_element_result {
@@ -1099,23 +1748,23 @@ impl WebTransportBaseMessageHandler<ElementContext> for UpdateElementMessage {
                 ));
             }
         };
-        let update_result = Element::update_document(
-            &database_client,
-            query_doc,
-            UpdateElement {
-                selected: None,
-                locked_by: None,
-                x: body.x,
-                y: body.y,
-                rotation: body.rotation,
-                scale_x: body.scale_x,
-                scale_y: body.scale_y,
-                z_index: body.z_index,
-                text: body.text.clone(),
-                color: body.color.clone(),
-            },
-        )
-        .await;
+        let update_element = UpdateElement {
+            selected: None,
+            locked_by: None,
+            x: body.x,
+            y: body.y,
+            rotation: body.rotation.map(normalize_rotation),
+            scale_x: body.scale_x,
+            scale_y: body.scale_y,
+            z_index: body.z_index,
+            text: body.text.clone(),
+            color: body.color.clone(),
+            metadata: body.metadata.clone(),
+        };
+        let changes = update_element.to_update_fields();
+        let changes_geometry = update_element.changes_geometry();
+        let update_result =
+            Element::update_document(&database_client, query_doc, update_element).await;
         match update_result {
             Ok(result) => match result.modified_count {
                 0 => Err(ServerMessage::error_response(
@@ -1128,29 +1777,50 @@ impl WebTransportBaseMessageHandler<ElementContext> for UpdateElementMessage {
                     .unwrap(),
                 )),
                 _ => {
+                    ElementHistory::record(
+                        &database_client,
+                        body._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Updated,
+                        Some(changes),
+                    )
+                    .await;
                     let mut sub_context = context.lock().await;
-                    sub_context
-                        .emit_element_event(
-                            body.board_id.clone(),
-                            ElementEvent {
-                                event_type: ElementEventType::Updated,
-                                body: serde_json::to_string(&UpdatedElementEventPayload {
-                                    user_id: body.user_id.clone(),
-                                    _id: body._id.clone(),
-                                    text: body.text.clone(),
-                                    z_index: body.z_index,
-                                    scale_x: body.scale_x,
-                                    scale_y: body.scale_y,
-                                    rotation: body.rotation,
-                                    x: body.x,
-                                    y: body.y,
-                                    color: body.color,
-                                })
-                                .unwrap(),
-                            },
+                    if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+                        user_id: body.user_id.clone(),
+                        _id: body._id.clone(),
+                        text: body.text.clone(),
+                        z_index: body.z_index,
+                        scale_x: body.scale_x,
+                        scale_y: body.scale_y,
+                        rotation: body.rotation.map(normalize_rotation),
+                        x: body.x,
+                        y: body.y,
+                        color: body.color,
+                        metadata: body.metadata,
+                        asset_id: None,
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                body.board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Updated,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
+                    drop(sub_context);
+                    if changes_geometry {
+                        emit_connector_updates(
+                            &database_client,
+                            &context,
+                            &body.board_id,
+                            std::slice::from_ref(&body._id),
                         )
                         .await;
-                    drop(sub_context);
+                    }
                     Ok(ServerMessage::ok_response(
                         "updateelement".to_string(),
                         serde_json::to_string(&ElementUpdatedMessage { id: body._id }).unwrap(),
@@ -1210,12 +1880,29 @@ impl WebTransportBaseMessageHandler<ElementContext> for MoveElementsMessage {
                 ))
             }
         };
-        let query_doc = doc! {
-            "_id": doc! { "$in": body.ids.iter().map(|id| ObjectId::from_str(id.as_str()).unwrap()).collect::<Vec<ObjectId>>() }
+        assert_editor(
+            "moveelements",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let query_doc = match Element::board_scoped_ids_query(&body.ids, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "moveelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                ))
+            }
         };
         let found_element_result =
             Element::get_multiple_documents(&database_client, query_doc.clone()).await;
-        let found_elements = match found_element_result {
+        let mut found_elements = match found_element_result {
             Ok(element_cursor) => {
                 let retrieved_elements = element_cursor.try_collect::<Vec<Element>>().await;
                 match retrieved_elements {
@@ -1271,12 +1958,11 @@ impl WebTransportBaseMessageHandler<ElementContext> for MoveElementsMessage {
                 .unwrap(),
             ));
         }
-        let mut updated_document_results: Vec<UpdateResult> = vec![];
+        sort_by_request_order(&mut found_elements, &body.ids);
+        let mut applied_updates = AppliedUpdates::default();
         for element in found_elements.iter() {
-            let query_doc = doc! {
-                "_id": ObjectId::from_str(element._id.as_str()).unwrap(),
-            };
-            match Element::update_document(
+            let query_doc = element.stored_id_query();
+            let modified_count = Element::update_document(
                 &database_client,
                 query_doc,
                 UpdateElement {
@@ -1290,71 +1976,847 @@ impl WebTransportBaseMessageHandler<ElementContext> for MoveElementsMessage {
                     z_index: None,
                     text: None,
                     color: None,
+                    metadata: None,
                 },
             )
             .await
-            {
-                Ok(update_result) => match update_result.modified_count {
-                    0 => {
-                        return Err(ServerMessage::error_response(
-                            "moveelements".to_string(),
-                            serde_json::to_string(&ErrorResponseBody {
-                                message: format!("Move of Element with ID {} failed", element._id),
-                                body: serde_json::to_string(&body.ids).unwrap(),
-                            })
-                            .unwrap(),
-                        ));
-                    }
-                    _ => {
-                        updated_document_results.push(update_result);
-                    }
-                },
-                Err(_) => {
-                    return Err(ServerMessage::error_response(
-                        "moveelements".to_string(),
-                        serde_json::to_string(&ErrorResponseBody {
-                            message: "Error during moving of Elements".to_string(),
-                            body: serde_json::to_string(&body.ids).unwrap(),
-                        })
-                        .unwrap(),
-                    ));
-                }
+            .map(|update_result| update_result.modified_count)
+            .map_err(|_| {
+                ServerMessage::error_response(
+                    "moveelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during moving of Elements".to_string(),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            });
+            if !applied_updates.record(&element._id, modified_count, || {
+                ServerMessage::error_response(
+                    "moveelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: format!("Move of Element with ID {} failed", element._id),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            }) {
+                break;
             }
         }
-        match updated_document_results.len() {
-            0 => Err(ServerMessage::error_response(
-                "moveelements".to_string(),
-                serde_json::to_string(&ErrorResponseBody {
-                    message: "No Element found to update".to_string(),
-                    body: serde_json::to_string(&body.ids).unwrap(),
-                })
-                .unwrap(),
-            )),
+        let AppliedUpdates {
+            updated_ids,
+            failure: failure_response,
+        } = applied_updates;
+        match updated_ids.len() {
+            0 => Err(failure_response.unwrap_or_else(|| {
+                ServerMessage::error_response(
+                    "moveelements".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "No Element found to update".to_string(),
+                        body: serde_json::to_string(&body.ids).unwrap(),
+                    })
+                    .unwrap(),
+                )
+            })),
             _ => {
-                for element_id in body.ids.iter() {
+                ElementHistory::record_multiple(
+                    &database_client,
+                    &updated_ids,
+                    body.board_id.clone(),
+                    body.user_id.clone(),
+                    ElementHistoryAction::Moved,
+                    Some(doc! { "xOffset": body.x_offset, "yOffset": body.y_offset }),
+                )
+                .await;
+                for element_id in updated_ids.iter() {
                     let mut sub_context = context.lock().await;
-                    sub_context
-                        .emit_element_event(
-                            body.board_id.to_string(),
-                            ElementEvent {
-                                event_type: ElementEventType::Moved,
-                                body: serde_json::to_string(&ElementMovedEventPayload {
-                                    _id: element_id.to_string(),
-                                    user_id: body.user_id.clone(),
-                                    x_offset: body.x_offset,
-                                    y_offset: body.y_offset,
-                                })
-                                .unwrap(),
-                            },
-                        )
-                        .await;
+                    if let Ok(event_body) = serialize_or_log(&ElementMovedEventPayload {
+                        _id: element_id.to_string(),
+                        user_id: body.user_id.clone(),
+                        x_offset: body.x_offset,
+                        y_offset: body.y_offset,
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                body.board_id.to_string(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Moved,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
                     drop(sub_context);
                 }
+                emit_connector_updates(&database_client, &context, &body.board_id, &updated_ids)
+                    .await;
+                if let Some(failure_response) = failure_response {
+                    return Err(failure_response);
+                }
                 Ok(ServerMessage::ok_response(
                     "moveelements".to_string(),
-                    serde_json::to_string(&ElementsMovedMessage { ids: body.ids }).unwrap(),
+                    serde_json::to_string(&ElementsMovedMessage { ids: updated_ids }).unwrap(),
                 ))
             }
         }
     }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementPosition {
+    pub id: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+// `moveelements` applies offsets and suits dragging, where every message carries a new delta.
+// Programmatic arranges should use `setpositions`: absolute targets make a retried message idempotent.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPositionsMessage {
+    pub positions: Vec<ElementPosition>,
+    pub user_id: String,
+    pub board_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementPositionsSetMessage {
+    pub ids: Vec<String>,
+}
+
+impl WebTransportBaseMessageHandler<ElementContext> for SetPositionsMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ElementContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<SetPositionsMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "setpositions".to_string(),
+                    "Set Positions Message is invalid".to_string(),
+                ))
+            }
+        };
+        assert_editor(
+            "setpositions",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let ids = body
+            .positions
+            .iter()
+            .map(|position| position.id.clone())
+            .collect::<Vec<String>>();
+        let object_ids = match ids
+            .iter()
+            .map(|id| ObjectId::from_str(id.as_str()))
+            .collect::<Result<Vec<ObjectId>, _>>()
+        {
+            Ok(object_ids) => object_ids,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "setpositions".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Some Element ID is invalid".to_string(),
+                        body: serde_json::to_string(&ids).unwrap(),
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        let query_doc = doc! {
+            "_id": doc! { "$in": object_ids },
+            "boardId": body.board_id.clone(),
+        };
+        let found_elements =
+            match Element::get_multiple_documents(&database_client, query_doc).await {
+                Ok(element_cursor) => match element_cursor.try_collect::<Vec<Element>>().await {
+                    Ok(retrieved_elements) => retrieved_elements,
+                    Err(_) => {
+                        return Err(ServerMessage::error_response(
+                            "setpositions".to_string(),
+                            serde_json::to_string(&ErrorResponseBody {
+                                message: "Found Elements could not be retrieved".to_string(),
+                                body: serde_json::to_string(&ids).unwrap(),
+                            })
+                            .unwrap(),
+                        ));
+                    }
+                },
+                Err(_) => {
+                    return Err(ServerMessage::error_response(
+                        "setpositions".to_string(),
+                        serde_json::to_string(&ErrorResponseBody {
+                            message: "Error during fetching of Elements".to_string(),
+                            body: serde_json::to_string(&ids).unwrap(),
+                        })
+                        .unwrap(),
+                    ));
+                }
+            };
+        if found_elements.len() != ids.len() {
+            return Err(ServerMessage::error_response(
+                "setpositions".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "Some Element does not exist on this board".to_string(),
+                    body: serde_json::to_string(&ids).unwrap(),
+                })
+                .unwrap(),
+            ));
+        }
+        if found_elements
+            .iter()
+            .any(|element| match &element.locked_by {
+                Some(locked_by) => *locked_by != body.user_id,
+                None => false,
+            })
+        {
+            return Err(ServerMessage::error_response(
+                "setpositions".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "Some Element is locked by someone else".to_string(),
+                    body: serde_json::to_string(&ids).unwrap(),
+                })
+                .unwrap(),
+            ));
+        }
+        for position in body.positions.iter() {
+            let query_doc = doc! {
+                "_id": ObjectId::from_str(position.id.as_str()).unwrap(),
+            };
+            // A retry writes the same values again, so an unmodified element is not an error
+            if Element::update_document(
+                &database_client,
+                query_doc,
+                UpdateElement {
+                    selected: None,
+                    locked_by: None,
+                    x: Some(position.x),
+                    y: Some(position.y),
+                    rotation: None,
+                    scale_x: None,
+                    scale_y: None,
+                    z_index: None,
+                    text: None,
+                    color: None,
+                    metadata: None,
+                },
+            )
+            .await
+            .is_err()
+            {
+                return Err(ServerMessage::error_response(
+                    "setpositions".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: format!("Positioning of Element with ID {} failed", position.id),
+                        body: serde_json::to_string(&ids).unwrap(),
+                    })
+                    .unwrap(),
+                ));
+            }
+            ElementHistory::record(
+                &database_client,
+                position.id.clone(),
+                body.board_id.clone(),
+                body.user_id.clone(),
+                ElementHistoryAction::Moved,
+                Some(doc! { "x": position.x, "y": position.y }),
+            )
+            .await;
+            let mut sub_context = context.lock().await;
+            if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+                _id: position.id.clone(),
+                user_id: body.user_id.clone(),
+                x: Some(position.x),
+                y: Some(position.y),
+                rotation: None,
+                scale_x: None,
+                scale_y: None,
+                z_index: None,
+                text: None,
+                color: None,
+                metadata: None,
+                asset_id: None,
+            }) {
+                sub_context
+                    .emit_element_event(
+                        body.board_id.clone(),
+                        ElementEvent {
+                            event_type: ElementEventType::Updated,
+                            body: event_body,
+                        },
+                    )
+                    .await;
+            }
+            drop(sub_context);
+        }
+        emit_connector_updates(&database_client, &context, &body.board_id, &ids).await;
+        Ok(ServerMessage::ok_response(
+            "setpositions".to_string(),
+            serde_json::to_string(&ElementPositionsSetMessage { ids }).unwrap(),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTextMessage {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub user_id: String,
+    pub board_id: String,
+    pub text: String,
+}
+
+impl WebTransportBaseMessageHandler<ElementContext> for UpdateTextMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ElementContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<UpdateTextMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "updatetext".to_string(),
+                    "Update Text Message is invalid".to_string(),
+                ))
+            }
+        };
+        assert_editor(
+            "updatetext",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        if let Err(message) = Element::validate_element_fields(Some(&body.text), None) {
+            return Err(ServerMessage::error_response(
+                "updatetext".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: body._id,
+                })
+                .unwrap(),
+            ));
+        }
+        let query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => {
+                return Err(ServerMessage::error_response(
+                    "updatetext".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message,
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        let found_element_result = Element::get_document(&database_client, query_doc.clone()).await;
+        match found_element_result {
+            Ok(element) => match element {
+                Some(element) => match element.locked_by {
+                    Some(locked_by) => {
+                        if locked_by != body.user_id {
+                            return Err(ServerMessage::error_response(
+                                "updatetext".to_string(),
+                                serde_json::to_string(&ErrorResponseBody {
+                                    message: "Element currently locked by someone else".to_string(),
+                                    body: body._id,
+                                })
+                                .unwrap(),
+                            ));
+                        }
+                    }
+                    None => {
+                        return Err(ServerMessage::error_response(
+                            "updatetext".to_string(),
+                            serde_json::to_string(&ErrorResponseBody {
+                                message: "Element needs to be locked first".to_string(),
+                                body: body._id,
+                            })
+                            .unwrap(),
+                        ));
+                    }
+                },
+                None => {
+                    return Err(ServerMessage::error_response(
+                        "updatetext".to_string(),
+                        serde_json::to_string(&ErrorResponseBody {
+                            message: "Element not found".to_string(),
+                            body: body._id,
+                        })
+                        .unwrap(),
+                    ));
+                }
+            },
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "updatetext".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during Element fetching".to_string(),
+                        body: body._id,
+                    })
+                    .unwrap(),
+                ));
+            }
+        };
+        let update_result = Element::update_document(
+            &database_client,
+            query_doc,
+            UpdateElement {
+                selected: None,
+                locked_by: None,
+                x: None,
+                y: None,
+                rotation: None,
+                scale_x: None,
+                scale_y: None,
+                z_index: None,
+                text: Some(body.text.clone()),
+                color: None,
+                metadata: None,
+            },
+        )
+        .await;
+        match update_result {
+            Ok(result) => match result.matched_count {
+                0 => Err(ServerMessage::error_response(
+                    "updatetext".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "No Element found to update".to_string(),
+                        body: body._id,
+                    })
+                    .unwrap(),
+                )),
+                _ => {
+                    ElementHistory::record(
+                        &database_client,
+                        body._id.clone(),
+                        body.board_id.clone(),
+                        body.user_id.clone(),
+                        ElementHistoryAction::Updated,
+                        Some(doc! { "text": body.text.clone() }),
+                    )
+                    .await;
+                    let mut sub_context = context.lock().await;
+                    if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+                        user_id: body.user_id.clone(),
+                        _id: body._id.clone(),
+                        text: Some(body.text),
+                        z_index: None,
+                        scale_x: None,
+                        scale_y: None,
+                        rotation: None,
+                        x: None,
+                        y: None,
+                        color: None,
+                        metadata: None,
+                        asset_id: None,
+                    }) {
+                        sub_context
+                            .emit_element_event(
+                                body.board_id.clone(),
+                                ElementEvent {
+                                    event_type: ElementEventType::Updated,
+                                    body: event_body,
+                                },
+                            )
+                            .await;
+                    }
+                    drop(sub_context);
+                    Ok(ServerMessage::ok_response(
+                        "updatetext".to_string(),
+                        serde_json::to_string(&ElementUpdatedMessage { id: body._id }).unwrap(),
+                    ))
+                }
+            },
+            Err(_) => Err(ServerMessage::error_response(
+                "updatetext".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "Could not update Element text".to_string(),
+                    body: body._id,
+                })
+                .unwrap(),
+            )),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLocksMessage {
+    pub board_id: String,
+    pub user_id: String,
+}
+
+impl WebTransportBaseMessageHandler<ElementContext> for GetLocksMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        _context: Arc<Mutex<ElementContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<GetLocksMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "getlocks".to_string(),
+                    "Get Locks Message is invalid".to_string(),
+                ))
+            }
+        };
+        assert_viewer("getlocks", &body.board_id, &body.user_id, &database_client).await?;
+        let query_doc = doc! {
+            "boardId": body.board_id.clone(),
+            "lockedBy": doc! { "$ne": null },
+        };
+        let locked_elements =
+            match Element::get_multiple_documents(&database_client, query_doc).await {
+                Ok(element_cursor) => match element_cursor.try_collect::<Vec<Element>>().await {
+                    Ok(retrieved_elements) => retrieved_elements,
+                    Err(_) => {
+                        return Err(ServerMessage::error_response(
+                            "getlocks".to_string(),
+                            serde_json::to_string(&ErrorResponseBody {
+                                message: "Found Elements could not be retrieved".to_string(),
+                                body: body.board_id,
+                            })
+                            .unwrap(),
+                        ))
+                    }
+                },
+                Err(_) => {
+                    return Err(ServerMessage::error_response(
+                        "getlocks".to_string(),
+                        serde_json::to_string(&ErrorResponseBody {
+                            message: "Error during fetching of locked Elements".to_string(),
+                            body: body.board_id,
+                        })
+                        .unwrap(),
+                    ))
+                }
+            };
+        let locks = locked_elements
+            .into_iter()
+            .filter_map(|element| {
+                element.locked_by.map(|locked_by| ElementLockMessage {
+                    element_id: element._id,
+                    locked_by,
+                })
+            })
+            .collect::<Vec<ElementLockMessage>>();
+        Ok(ServerMessage::ok_response(
+            "getlocks".to_string(),
+            serde_json::to_string(&locks).unwrap(),
+        ))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementLockMessage {
+    pub element_id: String,
+    pub locked_by: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesSinceMessage {
+    pub board_id: String,
+    pub user_id: String,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub since: DateTime,
+}
+
+// `truncated` means there are more changes than `MAX_CHANGES_SINCE_ELEMENTS`, the client should fetch
+// the whole board instead
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangesSinceResponseMessage {
+    pub board_id: String,
+    pub elements: Vec<Element>,
+    pub truncated: bool,
+}
+
+impl WebTransportBaseMessageHandler<ElementContext> for ChangesSinceMessage {
+    async fn handle_message(
+        message: Value,
+        database_client: Client,
+        _context: Arc<Mutex<ElementContext>>,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<ChangesSinceMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "changessince".to_string(),
+                    "Changes Since Message is invalid".to_string(),
+                ))
+            }
+        };
+        assert_viewer(
+            "changessince",
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let mut elements = match Element::get_changed_since(
+            &database_client,
+            body.board_id.clone(),
+            body.since,
+            MAX_CHANGES_SINCE_ELEMENTS as i64 + 1,
+        )
+        .await
+        {
+            Ok(elements) => elements,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "changessince".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Error during fetching of changed Elements".to_string(),
+                        body: body.board_id,
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        let truncated = elements.len() > MAX_CHANGES_SINCE_ELEMENTS;
+        elements.truncate(MAX_CHANGES_SINCE_ELEMENTS);
+        Ok(ServerMessage::ok_response(
+            "changessince".to_string(),
+            serde_json::to_string(&ChangesSinceResponseMessage {
+                board_id: body.board_id,
+                elements,
+                truncated,
+            })
+            .unwrap(),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderElementMessage {
+    #[serde(rename = "_id")]
+    pub _id: String,
+    pub user_id: String,
+    pub board_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementReorderedMessage {
+    pub id: String,
+    pub z_index: i32,
+}
+
+impl ReorderElementMessage {
+    // The z-index is claimed from the counter of the board in one atomic update, so concurrent
+    // reorders never assign the same z-index and no context lock is held during database I/O
+    pub async fn handle_reorder(
+        message: Value,
+        database_client: Client,
+        context: Arc<Mutex<ElementContext>>,
+        message_type: &str,
+        to_front: bool,
+    ) -> Result<ServerMessage, ServerMessage> {
+        let body = match serde_json::from_value::<ReorderElementMessage>(message) {
+            Ok(parsed_message) => parsed_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    message_type.to_string(),
+                    "Reorder Element Message is invalid".to_string(),
+                ))
+            }
+        };
+        assert_editor(
+            message_type,
+            &body.board_id,
+            &body.user_id,
+            &database_client,
+        )
+        .await?;
+        let reorder_error = |message: String, element_id: String| {
+            ServerMessage::error_response(
+                message_type.to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message,
+                    body: serde_json::to_string(&ElementUpdatedMessage { id: element_id }).unwrap(),
+                })
+                .unwrap(),
+            )
+        };
+        let z_index = match to_front {
+            true => Board::next_z_index(&body.board_id, &database_client).await,
+            false => Board::previous_z_index(&body.board_id, &database_client).await,
+        };
+        let z_index = match z_index {
+            Ok(z_index) => z_index,
+            Err(_) => {
+                return Err(reorder_error(
+                    "Error during z-index assignment".to_string(),
+                    body._id,
+                ))
+            }
+        };
+        let update_element = UpdateElement {
+            selected: None,
+            locked_by: None,
+            x: None,
+            y: None,
+            rotation: None,
+            scale_x: None,
+            scale_y: None,
+            z_index: Some(z_index),
+            text: None,
+            color: None,
+            metadata: None,
+        };
+        let changes = update_element.to_update_fields();
+        // Only applies while the caller holds the lock, a lock taken or released in the meantime
+        // makes the update miss instead of overriding it
+        let mut query_doc = match Element::board_scoped_query(&body._id, &body.board_id) {
+            Ok(query_doc) => query_doc,
+            Err(message) => return Err(reorder_error(message, body._id)),
+        };
+        query_doc.insert("lockedBy", body.user_id.clone());
+        match Element::update_document(&database_client, query_doc, update_element).await {
+            Ok(update_result) if update_result.matched_count > 0 => {}
+            Ok(_) => {
+                let message =
+                    match Element::get_existing_element(body._id.clone(), &database_client).await {
+                        Ok(element) if element.board_id != body.board_id => {
+                            "Element is not part of this board".to_string()
+                        }
+                        Ok(element) => match element.locked_by {
+                            Some(_) => "Element currently locked by someone else".to_string(),
+                            None => "Element needs to be locked first".to_string(),
+                        },
+                        Err(_) => format!("No Element found with ID: {}", body._id),
+                    };
+                return Err(reorder_error(message, body._id));
+            }
+            Err(_) => {
+                return Err(reorder_error(
+                    "Could not update Element".to_string(),
+                    body._id,
+                ))
+            }
+        }
+        ElementHistory::record(
+            &database_client,
+            body._id.clone(),
+            body.board_id.clone(),
+            body.user_id.clone(),
+            ElementHistoryAction::Updated,
+            Some(changes),
+        )
+        .await;
+        if let Ok(event_body) = serialize_or_log(&UpdatedElementEventPayload {
+            user_id: body.user_id,
+            _id: body._id.clone(),
+            text: None,
+            z_index: Some(z_index),
+            scale_x: None,
+            scale_y: None,
+            rotation: None,
+            x: None,
+            y: None,
+            color: None,
+            metadata: None,
+            asset_id: None,
+        }) {
+            let mut sub_context = context.lock().await;
+            sub_context
+                .emit_element_event(
+                    body.board_id.clone(),
+                    ElementEvent {
+                        event_type: ElementEventType::Updated,
+                        body: event_body,
+                    },
+                )
+                .await;
+            drop(sub_context);
+        }
+        Ok(ServerMessage::ok_response(
+            message_type.to_string(),
+            serde_json::to_string(&ElementReorderedMessage {
+                id: body._id,
+                z_index,
+            })
+            .unwrap(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_updated_elements_are_announced() {
+        let mut applied_updates = AppliedUpdates::default();
+        // The bogus ID matches no element, so its update modifies nothing
+        for (element_id, modified_count) in [("a", Ok(1)), ("b", Ok(1)), ("bogus", Ok(0))] {
+            if !applied_updates.record(element_id, modified_count, || "failed") {
+                break;
+            }
+        }
+        assert_eq!(applied_updates.updated_ids, vec!["a", "b"]);
+        assert_eq!(applied_updates.failure, Some("failed"));
+    }
+
+    #[test]
+    fn failed_update_stops_the_remaining_ones() {
+        let mut applied_updates = AppliedUpdates::default();
+        for (element_id, modified_count) in [("a", Ok(1)), ("b", Err("error")), ("c", Ok(1))] {
+            if !applied_updates.record(element_id, modified_count, || "failed") {
+                break;
+            }
+        }
+        assert_eq!(applied_updates.updated_ids, vec!["a"]);
+        assert_eq!(applied_updates.failure, Some("error"));
+    }
+
+    #[test]
+    fn changes_since_requires_the_user() {
+        let message = serde_json::json!({
+            "boardId": "board",
+            "since": "2024-01-01T00:00:00Z",
+        });
+        assert!(serde_json::from_value::<ChangesSinceMessage>(message.clone()).is_err());
+        let mut message = message;
+        message["userId"] = "user".into();
+        assert!(serde_json::from_value::<ChangesSinceMessage>(message).is_ok());
+    }
+
+    #[test]
+    fn get_locks_requires_the_user() {
+        let message = serde_json::json!({ "boardId": "board" });
+        assert!(serde_json::from_value::<GetLocksMessage>(message).is_err());
+        let message = serde_json::json!({ "boardId": "board", "userId": "user" });
+        assert!(serde_json::from_value::<GetLocksMessage>(message).is_ok());
+    }
+
+    #[tokio::test]
+    async fn malformed_board_id_is_no_board() {
+        // Never connects, the malformed ID is turned away before the query
+        let database_client = Client::with_uri_str("mongodb://localhost:27017")
+            .await
+            .unwrap();
+        let error_message = assert_editor("updateelement", "not-an-id", "user", &database_client)
+            .await
+            .unwrap_err();
+        assert!(error_message
+            .body
+            .contains("No Board found with ID: not-an-id"));
+    }
+}
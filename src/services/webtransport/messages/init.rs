@@ -1,4 +1,6 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::services::webtransport::protocol_version::ProtocolVersion;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -6,4 +8,66 @@ pub struct InitMessage {
     pub message_type: String,
     pub event_category: String,
     pub context_id: String,
+    pub user_id: Option<String>,
+    // RFC 3339 timestamp, buffered events emitted after it are sent before live events
+    pub replay_since: Option<String>,
+    // `<major>.<minor>`, clients without it are treated as speaking version 1.0 and get the plain
+    // `initialized` body
+    pub protocol_version: Option<String>,
+    // Required for guests, the token returned by `POST /guest`
+    pub guest_token: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitializedMessage {
+    pub protocol_version: String,
+}
+
+impl InitializedMessage {
+    // Clients without a version still expect the plain body the server answered with before
+    pub fn body_for(protocol_version: Option<ProtocolVersion>) -> String {
+        match protocol_version {
+            Some(_) => serde_json::to_string(&InitializedMessage {
+                protocol_version: ProtocolVersion::SERVER.to_string(),
+            })
+            .unwrap(),
+            None => "initialized".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchSubjectMessage {
+    pub board_id: String,
+    // Sends the board document on board streams and its elements on element streams
+    #[serde(default)]
+    pub include_snapshot: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubjectSwitchedMessage {
+    pub board_id: String,
+    // Lets clients hide their editing UI, the element handlers check edit permission per message
+    pub read_only: bool,
+    pub snapshot: Option<serde_json::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_clients_get_the_plain_initialized_body() {
+        assert_eq!(InitializedMessage::body_for(None), "initialized");
+    }
+
+    #[test]
+    fn versioned_clients_get_the_server_version() {
+        let body = InitializedMessage::body_for(ProtocolVersion::parse("1.0"));
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["protocolVersion"], ProtocolVersion::SERVER.to_string());
+    }
 }
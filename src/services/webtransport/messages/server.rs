@@ -1,4 +1,4 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,6 +15,29 @@ pub struct ErrorResponseBody {
     pub body: String,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrottledMessage {
+    pub message: String,
+    pub retry_after_millis: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum AnnouncementLevel {
+    Info,
+    Warning,
+}
+
+// Sent on every stream a session holds, clients should deduplicate announcements by `id`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerAnnouncementEventPayload {
+    pub id: String,
+    pub level: AnnouncementLevel,
+    pub message: String,
+}
+
 impl ServerMessage {
     pub fn new(message_type: String, status: String, body: String) -> Self {
         Self {
@@ -0,0 +1,118 @@
+use std::{cmp::Ordering, fmt::Display};
+
+// The major version is raised whenever a change breaks existing clients, the minor version when
+// messages or fields are added. A client is compatible if it speaks the same major version and no
+// newer minor version than the server, older minor versions only miss the additions
+pub const PROTOCOL_VERSION_MAJOR: u32 = 1;
+pub const PROTOCOL_VERSION_MINOR: u32 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    pub const SERVER: ProtocolVersion = ProtocolVersion {
+        major: PROTOCOL_VERSION_MAJOR,
+        minor: PROTOCOL_VERSION_MINOR,
+    };
+    // Accepts `<major>.<minor>` and `<major>`, which stands for `<major>.0`
+    pub fn parse(version: &str) -> Option<ProtocolVersion> {
+        let (major, minor) = match version.trim().split_once('.') {
+            Some((major, minor)) => (major, minor),
+            None => (version.trim(), "0"),
+        };
+        Some(ProtocolVersion {
+            major: major.parse().ok()?,
+            minor: minor.parse().ok()?,
+        })
+    }
+
+    // Clients that send no version get None and keep receiving the responses of version 1.0
+    pub fn negotiate(version: Option<&str>) -> Result<Option<ProtocolVersion>, String> {
+        let Some(version) = version else {
+            return Ok(None);
+        };
+        let protocol_version = ProtocolVersion::parse(version).ok_or(format!(
+            "`protocolVersion` {} is not a `<major>.<minor>` version",
+            version
+        ))?;
+        if !protocol_version.is_supported() {
+            return Err(protocol_version.unsupported_message());
+        }
+        Ok(Some(protocol_version))
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.major == Self::SERVER.major && self.minor.cmp(&Self::SERVER.minor) != Ordering::Greater
+    }
+
+    pub fn unsupported_message(&self) -> String {
+        format!(
+            "Protocol version {} is not supported, this server supports {}.0 to {}",
+            self,
+            Self::SERVER.major,
+            Self::SERVER
+        )
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_major_and_minor() {
+        assert_eq!(
+            ProtocolVersion::parse(" 1.2 "),
+            Some(ProtocolVersion { major: 1, minor: 2 })
+        );
+        assert_eq!(
+            ProtocolVersion::parse("1"),
+            Some(ProtocolVersion { major: 1, minor: 0 })
+        );
+    }
+
+    #[test]
+    fn parse_rejects_malformed_versions() {
+        for version in ["", "v1", "1.", "1.x", "1.2.3", "-1.0"] {
+            assert_eq!(ProtocolVersion::parse(version), None, "{}", version);
+        }
+    }
+
+    #[test]
+    fn is_supported_rejects_other_major_and_newer_minor_versions() {
+        let other_major = ProtocolVersion {
+            major: PROTOCOL_VERSION_MAJOR + 1,
+            minor: 0,
+        };
+        let newer_minor = ProtocolVersion {
+            major: PROTOCOL_VERSION_MAJOR,
+            minor: PROTOCOL_VERSION_MINOR + 1,
+        };
+        assert!(ProtocolVersion::SERVER.is_supported());
+        assert!(!other_major.is_supported());
+        assert!(!newer_minor.is_supported());
+    }
+
+    #[test]
+    fn negotiate_rejects_unsupported_versions() {
+        assert_eq!(ProtocolVersion::negotiate(None), Ok(None));
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("1.0")),
+            Ok(Some(ProtocolVersion { major: 1, minor: 0 }))
+        );
+        assert!(ProtocolVersion::negotiate(Some("one")).is_err());
+        assert_eq!(
+            ProtocolVersion::negotiate(Some("2.0")),
+            Err(ProtocolVersion { major: 2, minor: 0 }.unsupported_message())
+        );
+    }
+}
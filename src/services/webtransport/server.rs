@@ -1,36 +1,145 @@
+use bson::{doc, oid::ObjectId, DateTime};
+use futures::TryStreamExt;
 use rxrust::{observable::ObservableItem, subscription::Subscription};
-use std::{sync::Arc, time::Duration};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    env::var,
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 use tracing::warn;
 
 use mongodb::Client;
-use tokio::sync::{Mutex, MutexGuard};
+use tokio::{
+    sync::{Mutex, MutexGuard},
+    time::timeout,
+};
 use tracing::{error, info, info_span, Instrument};
 use wtransport::{
     endpoint::{endpoint_side::Server, IncomingSession},
     error::{ConnectionError, StreamReadError, StreamWriteError},
-    Endpoint, Identity, RecvStream, SendStream, ServerConfig,
+    Connection, Endpoint, Identity, RecvStream, SendStream, ServerConfig, VarInt,
 };
 
 use crate::{
-    database::collections::board::Board,
-    services::webtransport::messages::base::WebTransportClientBaseMessage, AppState,
+    database::{
+        collections::{
+            active_member::ActiveMember,
+            board::Board,
+            client::Client as ClientDocument,
+            element::Element,
+            element_history::{ElementHistory, ElementHistoryAction},
+            user::User,
+        },
+        document::Document,
+    },
+    services::webtransport::messages::base::WebTransportClientBaseMessage,
+    utils::serialize_or_log::serialize_or_log,
+    AppState,
 };
 
 use super::{
     context::{
-        active_member::ActiveMemberContext, base::EventCategory, board::BoardContext,
-        client::ClientContext, element::ElementContext,
+        active_member::{
+            ActiveMemberContext, ActiveMemberEvent, ActiveMemberEventType,
+            ACTIVE_MEMBER_REMOVAL_GRACE_PERIOD,
+        },
+        base::EventCategory,
+        board::{BoardContext, BoardEvent, BoardEventType},
+        client::ClientContext,
+        element::{ElementContext, ElementEvent, ElementEventType},
+        event_buffer::EVENT_BUFFER_WINDOW,
     },
+    datagram::{self, DatagramBinding},
+    event_delivery::EventDelivery,
+    message_rate_limit::{MessageRateLimiter, RateLimitDecision},
     messages::{
-        active_member::ActiveMemberMessage,
-        board::BoardMessage,
+        active_member::{ActiveMemberMessage, RemovedActiveMemberEventPayload},
+        board::{emit_near_limit_if_reached, BoardEmptiedEventPayload, BoardMessage},
         category::{WebTransportMainCategoryHandler, WebTransportMessageMainCategory},
-        element::ElementMessage,
-        init::InitMessage,
-        server::ServerMessage,
+        element::{ElementMessage, ElementUnlockedEventPayload},
+        init::{InitMessage, InitializedMessage, SubjectSwitchedMessage, SwitchSubjectMessage},
+        server::{ErrorResponseBody, ServerMessage, ThrottledMessage},
     },
+    protocol_version::ProtocolVersion,
 };
 
+// Handled by the stream itself, it is not part of a message category
+pub const SWITCH_SUBJECT_MESSAGE_TYPE: &str = "stream_switchsubject";
+
+// Application error code sent when a connection is closed for exceeding the message rate
+pub const RATE_LIMIT_CLOSE_CODE: u32 = 429;
+// Application error code sent when a connection is closed because a write to it timed out
+const WRITE_TIMEOUT_CLOSE_CODE: u32 = 408;
+const DEFAULT_WRITE_TIMEOUT_MILLIS: u64 = 10000;
+
+#[allow(non_snake_case)]
+fn WRITE_TIMEOUT() -> Duration {
+    static WRITE_TIMEOUT: OnceLock<Duration> = OnceLock::new();
+    *WRITE_TIMEOUT.get_or_init(|| match var("WEBTRANSPORT_WRITE_TIMEOUT_MILLIS") {
+        Ok(write_timeout) => {
+            Duration::from_millis(write_timeout.parse().expect(
+                "Failed to parse `WEBTRANSPORT_WRITE_TIMEOUT_MILLIS` environment variable.",
+            ))
+        }
+        Err(_) => Duration::from_millis(DEFAULT_WRITE_TIMEOUT_MILLIS),
+    })
+}
+
+// Origins sessions may be opened from, e.g. `https://board.example.com`. Empty allows every origin
+#[allow(non_snake_case)]
+fn ALLOWED_ORIGINS() -> &'static Vec<String> {
+    static ALLOWED_ORIGINS: OnceLock<Vec<String>> = OnceLock::new();
+    ALLOWED_ORIGINS.get_or_init(|| match var("WEBTRANSPORT_ALLOWED_ORIGINS") {
+        Ok(allowed_origins) => allowed_origins
+            .split(',')
+            .map(normalize_origin)
+            .filter(|origin| !origin.is_empty())
+            .collect(),
+        Err(_) => Vec::new(),
+    })
+}
+
+fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_lowercase()
+}
+
+// Browsers always send an origin, so sessions without one are only accepted while the allowlist is empty
+fn is_origin_allowed(origin: Option<&str>) -> bool {
+    ALLOWED_ORIGINS().is_empty()
+        || origin.is_some_and(|origin| ALLOWED_ORIGINS().contains(&normalize_origin(origin)))
+}
+
+// The subject a stream currently listens to, boxed so a `switchsubject` message can replace it
+struct StreamSubscription {
+    subject_id: String,
+    unsubscribe: Box<dyn FnOnce() + Send>,
+}
+
+impl StreamSubscription {
+    fn new(subject_id: String, subscription: impl Subscription + Send + 'static) -> Self {
+        Self {
+            subject_id,
+            unsubscribe: Box::new(move || subscription.unsubscribe()),
+        }
+    }
+
+    fn unsubscribe(self) {
+        (self.unsubscribe)()
+    }
+}
+
+struct InitializedStream {
+    subject_id: String,
+    event_category: EventCategory,
+    replay_since: Option<DateTime>,
+    user_id: Option<String>,
+    // None for clients that were built before the handshake
+    protocol_version: Option<ProtocolVersion>,
+}
+
 pub struct WebTransportServer {
     endpoint: Endpoint<Server>,
     pub local_port: u16,
@@ -66,8 +175,30 @@ impl WebTransportServer {
     pub async fn serve(self) -> anyhow::Result<()> {
         info!("WebTransport server running on port: {}", self.local_port());
 
+        WebTransportServer::start_event_buffer_cleanup(
+            self.state.board_context.clone(),
+            self.state.element_context.clone(),
+            self.state.active_member_context.clone(),
+        );
+
         for id in 0.. {
             let incoming_session = self.endpoint.accept().await;
+            let session_permit = match self.state.session_limit.try_acquire() {
+                Some(session_permit) => session_permit,
+                None => {
+                    warn!(
+                        "Maximum of {} concurrent sessions reached, rejecting session",
+                        self.state.session_limit.max_sessions()
+                    );
+                    tokio::spawn(async move {
+                        if let Ok(session_request) = incoming_session.await {
+                            session_request.too_many_requests().await;
+                        }
+                    });
+                    continue;
+                }
+            };
+            let session_limit = self.state.session_limit.clone();
             let client = self.state.database_client.clone();
             let board_context = self.state.board_context.clone();
             let element_context = self.state.element_context.clone();
@@ -90,12 +221,34 @@ impl WebTransportServer {
                     .await
                     .instrument(info_span!("Connection", id));
                 }
+                drop(session_permit);
+                info!(
+                    "Session closed, {} of {} sessions active",
+                    session_limit.active_sessions(),
+                    session_limit.max_sessions()
+                );
             });
         }
 
         Ok(())
     }
 
+    fn start_event_buffer_cleanup(
+        board_context: Arc<Mutex<BoardContext>>,
+        element_context: Arc<Mutex<ElementContext>>,
+        active_member_context: Arc<Mutex<ActiveMemberContext>>,
+    ) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EVENT_BUFFER_WINDOW());
+            loop {
+                interval.tick().await;
+                board_context.lock().await.cleanup_event_buffers();
+                element_context.lock().await.cleanup_event_buffers();
+                active_member_context.lock().await.cleanup_event_buffers();
+            }
+        });
+    }
+
     async fn handle_incoming_session(
         board_context: Arc<Mutex<BoardContext>>,
         element_context: Arc<Mutex<ElementContext>>,
@@ -121,14 +274,28 @@ impl WebTransportServer {
             session_request.path(),
         );
 
+        if !is_origin_allowed(session_request.origin()) {
+            warn!(
+                "Rejected session from disallowed origin '{}', Authority: '{}'",
+                session_request.origin().unwrap_or("<none>"),
+                session_request.authority(),
+            );
+            session_request.forbidden().await;
+            return Err(());
+        }
+
         let connection = match session_request.accept().await {
-            Ok(connection) => connection,
+            Ok(connection) => Arc::new(connection),
             Err(_) => {
                 error!("Error during session request acception");
                 return Err(());
             }
         };
 
+        // Started by the first active member stream, datagrams carry no init of their own
+        let mut datagrams_started = false;
+        let message_rate_limiter = Arc::new(Mutex::new(MessageRateLimiter::new()));
+
         info!("Waiting for data from client...");
 
         loop {
@@ -191,72 +358,83 @@ impl WebTransportServer {
             let mut element_context_guard = element_context.lock().await;
             let mut client_context_guard = client_context.lock().await;
             let mut active_member_context_guard = active_member_context.lock().await;
-            let (subject_id, event_category) =
-                match WebTransportServer::init_with_id_and_event_category(
-                    &mut board_context_guard,
-                    &mut element_context_guard,
-                    &mut client_context_guard,
-                    &mut active_member_context_guard,
-                    database_client.clone(),
-                    message,
-                )
-                .await
-                {
-                    Ok(board_id) => board_id,
-                    Err(message) => {
-                        error!("{}", message.clone());
-                        return Err(());
-                    }
-                };
+            let InitializedStream {
+                subject_id,
+                event_category,
+                replay_since,
+                user_id,
+                protocol_version,
+            } = match WebTransportServer::init_with_id_and_event_category(
+                &mut board_context_guard,
+                &mut element_context_guard,
+                &mut client_context_guard,
+                &mut active_member_context_guard,
+                database_client.clone(),
+                message,
+            )
+            .await
+            {
+                Ok(initialized_stream) => initialized_stream,
+                Err(message) => {
+                    error!("{}", message.clone());
+                    drop(board_context_guard);
+                    drop(element_context_guard);
+                    drop(client_context_guard);
+                    drop(active_member_context_guard);
+                    let _ = WebTransportServer::send_message_to_stream(
+                        stream.0.lock().await,
+                        &connection,
+                        ServerMessage::new("error".to_string(), "ERROR".to_string(), message),
+                    )
+                    .await;
+                    return Err(());
+                }
+            };
             drop(board_context_guard);
             drop(element_context_guard);
             drop(client_context_guard);
             drop(active_member_context_guard);
-            let _ = stream
-                .0
-                .lock()
-                .await
-                .write_all(
-                    serde_json::to_string(&ServerMessage::new(
-                        "success".to_string(),
-                        "OK".to_string(),
-                        "initialized".to_string(),
-                    ))
-                    .unwrap()
-                    .as_bytes(),
-                )
-                .await;
+            let _ = WebTransportServer::send_message_to_stream(
+                stream.0.lock().await,
+                &connection,
+                ServerMessage::new(
+                    "success".to_string(),
+                    "OK".to_string(),
+                    InitializedMessage::body_for(protocol_version),
+                ),
+            )
+            .await;
             match event_category {
                 EventCategory::Board => {
-                    let context = board_context.clone();
-                    let mut board_context_guard = context.lock().await;
-                    let copied_send_stream = stream.0.clone();
-                    let subscription = board_context_guard
-                        .get_or_create_subject(subject_id.clone())
-                        .subject
-                        .clone()
-                        .subscribe(move |event| {
-                            let another_copy_of_stream = copied_send_stream.clone();
-                            tokio::spawn(async move {
-                                WebTransportServer::send_message_to_stream(
-                                    another_copy_of_stream.lock().await,
-                                    ServerMessage::event(event.event_type.to_string(), event.body),
-                                )
-                                .await;
-                            });
-                        });
+                    let mut board_context_guard = board_context.lock().await;
+                    let subscription = StreamSubscription::new(
+                        subject_id.clone(),
+                        Self::subscribe_to_board_subject(
+                            &mut board_context_guard,
+                            subject_id,
+                            stream.0.clone(),
+                            connection.clone(),
+                            replay_since,
+                        ),
+                    );
                     drop(board_context_guard);
                     let cloned_board_context = board_context.clone();
                     let cloned_element_context = element_context.clone();
                     let cloned_active_member_context = active_member_context.clone();
+                    let connection = connection.clone();
+                    let message_rate_limiter = message_rate_limiter.clone();
                     tokio::spawn(async move {
                         match WebTransportServer::handle_stream(
                             database_client,
                             (stream.0, stream.1),
                             subscription,
+                            event_category,
+                            user_id,
                             cloned_board_context,
                             cloned_element_context,
                             cloned_active_member_context,
+                            connection,
+                            message_rate_limiter,
                         )
                         .await
                         {
@@ -270,35 +448,35 @@ impl WebTransportServer {
                     });
                 }
                 EventCategory::Element => {
-                    let context = element_context.clone();
-                    let mut element_context_guard = context.lock().await;
-                    let copied_send_stream = stream.0.clone();
-                    let subscription = element_context_guard
-                        .get_or_create_subject(subject_id.clone())
-                        .subject
-                        .clone()
-                        .subscribe(move |event| {
-                            let another_copy_of_stream = copied_send_stream.clone();
-                            tokio::spawn(async move {
-                                WebTransportServer::send_message_to_stream(
-                                    another_copy_of_stream.lock().await,
-                                    ServerMessage::event(event.event_type.to_string(), event.body),
-                                )
-                                .await;
-                            });
-                        });
+                    let mut element_context_guard = element_context.lock().await;
+                    let subscription = StreamSubscription::new(
+                        subject_id.clone(),
+                        Self::subscribe_to_element_subject(
+                            &mut element_context_guard,
+                            subject_id,
+                            stream.0.clone(),
+                            connection.clone(),
+                            replay_since,
+                        ),
+                    );
                     drop(element_context_guard);
                     let cloned_board_context = board_context.clone();
                     let cloned_element_context = element_context.clone();
                     let cloned_active_member_context = active_member_context.clone();
+                    let connection = connection.clone();
+                    let message_rate_limiter = message_rate_limiter.clone();
                     tokio::spawn(async move {
                         match WebTransportServer::handle_stream(
                             database_client,
                             (stream.0, stream.1),
                             subscription,
+                            event_category,
+                            user_id,
                             cloned_board_context,
                             cloned_element_context,
                             cloned_active_member_context,
+                            connection,
+                            message_rate_limiter,
                         )
                         .await
                         {
@@ -312,33 +490,36 @@ impl WebTransportServer {
                 EventCategory::Client => {
                     let context = client_context.clone();
                     let mut client_context_guard = context.lock().await;
-                    let copied_send_stream = stream.0.clone();
+                    let event_delivery = EventDelivery::start(stream.0.clone(), connection.clone());
                     let subscription = client_context_guard
                         .get_or_create_subject(subject_id.clone())
                         .subject
                         .clone()
                         .subscribe(move |event| {
-                            let another_copy_of_stream = copied_send_stream.clone();
-                            tokio::spawn(async move {
-                                WebTransportServer::send_message_to_stream(
-                                    another_copy_of_stream.lock().await,
-                                    ServerMessage::event(event.event_type.to_string(), event.body),
-                                )
-                                .await;
-                            });
+                            event_delivery.deliver(
+                                ServerMessage::event(event.event_type.to_string(), event.body),
+                                event.event_type.delivery_policy(),
+                            );
                         });
                     drop(client_context_guard);
+                    let subscription = StreamSubscription::new(subject_id, subscription);
                     let cloned_board_context = board_context.clone();
                     let cloned_element_context = element_context.clone();
                     let cloned_active_member_context = active_member_context.clone();
+                    let connection = connection.clone();
+                    let message_rate_limiter = message_rate_limiter.clone();
                     tokio::spawn(async move {
                         match WebTransportServer::handle_stream(
                             database_client,
                             (stream.0, stream.1),
                             subscription,
+                            event_category,
+                            user_id,
                             cloned_board_context,
                             cloned_element_context,
                             cloned_active_member_context,
+                            connection,
+                            message_rate_limiter,
                         )
                         .await
                         {
@@ -352,36 +533,72 @@ impl WebTransportServer {
                 EventCategory::ActiveMember => {
                     let context = active_member_context.clone();
                     let mut active_member_context_guard = context.lock().await;
-                    let copied_send_stream = stream.0.clone();
+                    let event_delivery = EventDelivery::start(stream.0.clone(), connection.clone());
+                    if let Some(replay_since) = replay_since {
+                        for event in active_member_context_guard
+                            .get_or_create_subject(subject_id.clone())
+                            .event_buffer
+                            .events_since(replay_since)
+                        {
+                            event_delivery.deliver(
+                                ServerMessage::event(event.event_type.to_string(), event.body),
+                                event.event_type.delivery_policy(),
+                            );
+                        }
+                    }
+                    if let (false, Some(user_id)) = (datagrams_started, &user_id) {
+                        datagrams_started = true;
+                        if datagram::supports_datagrams(&connection) {
+                            tokio::spawn(datagram::handle_datagrams(
+                                connection.clone(),
+                                database_client.clone(),
+                                active_member_context.clone(),
+                                DatagramBinding {
+                                    user_id: user_id.clone(),
+                                    board_id: subject_id.clone(),
+                                },
+                                message_rate_limiter.clone(),
+                            ));
+                        } else {
+                            info!("Datagrams not supported, cursor positions use the stream");
+                        }
+                    }
+                    let datagram_connection = connection.clone();
                     let subscription = active_member_context_guard
                         .get_or_create_subject(subject_id.clone())
                         .subject
                         .clone()
                         .subscribe(move |event| {
-                            let another_copy_of_stream = copied_send_stream.clone();
-                            tokio::spawn(async move {
-                                WebTransportServer::send_message_to_stream(
-                                    another_copy_of_stream.lock().await,
-                                    ServerMessage::event(
-                                        event.event_type.to_string(),
-                                        event.body.to_string(),
-                                    ),
-                                )
-                                .await;
-                            });
+                            let message = ServerMessage::event(
+                                event.event_type.to_string(),
+                                event.body.to_string(),
+                            );
+                            if matches!(event.event_type, ActiveMemberEventType::PositionUpdated)
+                                && datagram::send_datagram(&datagram_connection, &message)
+                            {
+                                return;
+                            }
+                            event_delivery.deliver(message, event.event_type.delivery_policy());
                         });
                     drop(active_member_context_guard);
+                    let subscription = StreamSubscription::new(subject_id.clone(), subscription);
                     let cloned_board_context = board_context.clone();
                     let cloned_element_context = element_context.clone();
                     let cloned_active_member_context = active_member_context.clone();
+                    let connection = connection.clone();
+                    let message_rate_limiter = message_rate_limiter.clone();
                     tokio::spawn(async move {
                         match WebTransportServer::handle_stream(
-                            database_client,
+                            database_client.clone(),
                             (stream.0, stream.1),
                             subscription,
+                            event_category,
+                            user_id.clone(),
                             cloned_board_context,
-                            cloned_element_context,
-                            cloned_active_member_context,
+                            cloned_element_context.clone(),
+                            cloned_active_member_context.clone(),
+                            connection,
+                            message_rate_limiter,
                         )
                         .await
                         {
@@ -392,19 +609,157 @@ impl WebTransportServer {
                                 error!("Error during handling of Bi-Stream");
                             }
                         }
+                        if let Some(user_id) = user_id {
+                            WebTransportServer::schedule_active_member_removal(
+                                database_client,
+                                user_id,
+                                subject_id,
+                                cloned_element_context,
+                                cloned_active_member_context,
+                            )
+                            .await;
+                        }
                     });
                 }
             };
         }
     }
 
+    async fn schedule_active_member_removal(
+        database_client: Client,
+        user_id: String,
+        board_id: String,
+        element_context: Arc<Mutex<ElementContext>>,
+        active_member_context: Arc<Mutex<ActiveMemberContext>>,
+    ) {
+        let removal_id = active_member_context
+            .lock()
+            .await
+            .schedule_removal(user_id.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(ACTIVE_MEMBER_REMOVAL_GRACE_PERIOD()).await;
+            let mut active_member_context_guard = active_member_context.lock().await;
+            if !active_member_context_guard.take_pending_removal(&user_id, removal_id) {
+                return;
+            }
+            let query_doc = doc! {
+                "userId": user_id.clone(),
+                "boardId": board_id.clone(),
+            };
+            match ActiveMember::delete_document(&database_client, query_doc).await {
+                Ok(result) if result.deleted_count > 0 => {}
+                _ => return,
+            };
+            info!(
+                "Removed Active Member {} after the reconnect grace period",
+                user_id
+            );
+            active_member_context_guard
+                .clear_activity(user_id.clone())
+                .await;
+            if let Ok(event_body) = serialize_or_log(&RemovedActiveMemberEventPayload {
+                user_id: user_id.clone(),
+            }) {
+                active_member_context_guard
+                    .emit_active_member_event(
+                        board_id.clone(),
+                        ActiveMemberEvent {
+                            event_type: ActiveMemberEventType::Removed,
+                            body: event_body,
+                        },
+                    )
+                    .await;
+            }
+            drop(active_member_context_guard);
+            User::touch_guest(&user_id, &database_client).await;
+            let unlocked_ids = match Element::unlock_all_locked_by(
+                &database_client,
+                board_id.clone(),
+                std::slice::from_ref(&user_id),
+            )
+            .await
+            {
+                Ok(unlocked_ids) => unlocked_ids,
+                Err(_) => return,
+            };
+            ElementHistory::record_multiple(
+                &database_client,
+                &unlocked_ids,
+                board_id.clone(),
+                user_id,
+                ElementHistoryAction::Unlocked,
+                None,
+            )
+            .await;
+            let mut element_context_guard = element_context.lock().await;
+            for element_id in unlocked_ids {
+                if let Ok(event_body) =
+                    serialize_or_log(&ElementUnlockedEventPayload { _id: element_id })
+                {
+                    element_context_guard
+                        .emit_element_event(
+                            board_id.clone(),
+                            ElementEvent {
+                                event_type: ElementEventType::Unlocked,
+                                body: event_body,
+                            },
+                        )
+                        .await;
+                }
+            }
+            drop(element_context_guard);
+        });
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn handle_stream(
         database_client: Client,
         stream: (Arc<Mutex<SendStream>>, Arc<Mutex<RecvStream>>),
-        subscription: impl Subscription,
+        subscription: StreamSubscription,
+        event_category: EventCategory,
+        user_id: Option<String>,
+        board_context: Arc<Mutex<BoardContext>>,
+        element_context: Arc<Mutex<ElementContext>>,
+        active_member_context: Arc<Mutex<ActiveMemberContext>>,
+        connection: Arc<Connection>,
+        message_rate_limiter: Arc<Mutex<MessageRateLimiter>>,
+    ) -> Result<(), String> {
+        let mut activity_user_ids = HashSet::new();
+        let result = Self::handle_stream_messages(
+            database_client,
+            stream,
+            subscription,
+            event_category,
+            user_id,
+            board_context,
+            element_context,
+            active_member_context.clone(),
+            connection,
+            message_rate_limiter,
+            &mut activity_user_ids,
+        )
+        .await;
+        let mut active_member_context_guard = active_member_context.lock().await;
+        for user_id in activity_user_ids {
+            active_member_context_guard.clear_activity(user_id).await;
+        }
+        drop(active_member_context_guard);
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn handle_stream_messages(
+        database_client: Client,
+        stream: (Arc<Mutex<SendStream>>, Arc<Mutex<RecvStream>>),
+        mut subscription: StreamSubscription,
+        event_category: EventCategory,
+        user_id: Option<String>,
         board_context: Arc<Mutex<BoardContext>>,
         element_context: Arc<Mutex<ElementContext>>,
         active_member_context: Arc<Mutex<ActiveMemberContext>>,
+        connection: Arc<Connection>,
+        message_rate_limiter: Arc<Mutex<MessageRateLimiter>>,
+        activity_user_ids: &mut HashSet<String>,
     ) -> Result<(), String> {
         loop {
             let mut buffer = vec![0; 65536].into_boxed_slice();
@@ -431,6 +786,45 @@ impl WebTransportServer {
                     return Err(message);
                 }
             };
+            let mut message_rate_limiter_guard = message_rate_limiter.lock().await;
+            let rate_limit_decision = message_rate_limiter_guard.check();
+            let retry_after_millis = message_rate_limiter_guard.retry_after_millis();
+            drop(message_rate_limiter_guard);
+            match rate_limit_decision {
+                RateLimitDecision::Allowed => {}
+                RateLimitDecision::Throttled => {
+                    warn!("Message rate of connection exceeded, message is dropped");
+                    if let Err(message) = Self::send_message_to_stream(
+                        stream.0.lock().await,
+                        &connection,
+                        ServerMessage::error_response(
+                            "throttle".to_string(),
+                            serde_json::to_string(&ThrottledMessage {
+                                message: "Too many messages, message has been dropped".to_string(),
+                                retry_after_millis,
+                            })
+                            .unwrap(),
+                        ),
+                    )
+                    .await
+                    {
+                        subscription.unsubscribe();
+                        return Err(message);
+                    }
+                    continue;
+                }
+                RateLimitDecision::Exceeded => {
+                    subscription.unsubscribe();
+                    let message =
+                        "Connection closed after repeatedly exceeding the message rate".to_string();
+                    error!("{}", message.clone());
+                    connection.close(
+                        VarInt::from_u32(RATE_LIMIT_CLOSE_CODE),
+                        b"message rate exceeded",
+                    );
+                    return Err(message);
+                }
+            }
             let str_data = match std::str::from_utf8(&buffer[..bytes_read]) {
                 Ok(str_data) => str_data,
                 Err(_) => {
@@ -447,35 +841,18 @@ impl WebTransportServer {
                     let message =
                         "Error during parsing of WebTransportClientBaseMessage JSON Message";
                     error!("{}", message.to_string());
-                    match stream
-                        .0
-                        .lock()
-                        .await
-                        .write_all(
-                            serde_json::to_string(&ServerMessage::error_response(
-                                "basemessage".to_string(),
-                                message.to_string(),
-                            ))
-                            .unwrap()
-                            .as_bytes(),
-                        )
-                        .await
+                    match Self::send_message_to_stream(
+                        stream.0.lock().await,
+                        &connection,
+                        ServerMessage::error_response(
+                            "basemessage".to_string(),
+                            message.to_string(),
+                        ),
+                    )
+                    .await
                     {
                         Ok(_) => continue,
-                        Err(error) => {
-                            let message = match error {
-                                StreamWriteError::NotConnected => {
-                                    "Cannot write Stream, Stream lost connection".to_string()
-                                }
-                                StreamWriteError::Stopped(stopped) => {
-                                    format!("Stream writing stopped, {:?}", stopped)
-                                }
-                                StreamWriteError::QuicProto => {
-                                    "Stream could not be written because of quic protocol error"
-                                        .to_string()
-                                }
-                            };
-                            error!("{}", message.clone());
+                        Err(message) => {
                             subscription.unsubscribe();
                             return Err(message);
                         }
@@ -483,59 +860,78 @@ impl WebTransportServer {
                 }
             };
             info!("Recieved (bi) '{str_data}' from client");
-            let response_message = Self::handle_with_corresponding_category(
-                json_message.clone(),
-                database_client.clone(),
-                board_context.clone(),
-                element_context.clone(),
-                active_member_context.clone(),
-            )
-            .await;
+            let response_message = if json_message.message_type == SWITCH_SUBJECT_MESSAGE_TYPE {
+                match Self::switch_subject(
+                    &json_message.body,
+                    event_category,
+                    user_id.as_deref(),
+                    &database_client,
+                    &board_context,
+                    &element_context,
+                    stream.0.clone(),
+                    connection.clone(),
+                )
+                .await
+                {
+                    Ok((switched_subscription, response)) => {
+                        info!(
+                            "Stream switched from subject {} to {}",
+                            subscription.subject_id, switched_subscription.subject_id
+                        );
+                        std::mem::replace(&mut subscription, switched_subscription).unsubscribe();
+                        Ok(response)
+                    }
+                    Err(error_message) => Err(error_message),
+                }
+            } else {
+                Self::handle_with_corresponding_category(
+                    json_message.clone(),
+                    database_client.clone(),
+                    board_context.clone(),
+                    element_context.clone(),
+                    active_member_context.clone(),
+                )
+                .await
+            };
+            if response_message.is_ok() {
+                if let Some(user_id) = json_message.body.get("userId").and_then(Value::as_str) {
+                    match json_message.message_type.as_str() {
+                        "activemember_setactivity" => {
+                            activity_user_ids.insert(user_id.to_string());
+                        }
+                        "activemember_clearactivity" => {
+                            activity_user_ids.remove(user_id);
+                        }
+                        _ => {}
+                    }
+                }
+            }
             match response_message {
                 Ok(message) => {
                     info!(
                         "WebTransport Antwort vom Server: type: {}, body: {}",
                         message.message_type, message.body
                     );
-                    match stream
-                        .0
-                        .lock()
-                        .await
-                        .write_all(serde_json::to_string(&message).unwrap().as_bytes())
+                    match Self::send_message_to_stream(stream.0.lock().await, &connection, message)
                         .await
                     {
                         Ok(_) => continue,
-                        Err(error) => {
-                            let message = match error {
-                                StreamWriteError::NotConnected => {
-                                    "Cannot write Stream, Stream lost connection".to_string()
-                                }
-                                StreamWriteError::Stopped(stopped) => {
-                                    format!("Stream writing stopped, {:?}", stopped)
-                                }
-                                StreamWriteError::QuicProto => {
-                                    "Stream could not be written because of quic protocol error"
-                                        .to_string()
-                                }
-                            };
-                            error!("{}", message.clone());
+                        Err(message) => {
                             subscription.unsubscribe();
                             return Err(message);
                         }
                     }
                 }
-                Err(error_message) => match stream
-                    .0
-                    .lock()
-                    .await
-                    .write_all(serde_json::to_string(&error_message).unwrap().as_bytes())
-                    .await
+                Err(error_message) => match Self::send_message_to_stream(
+                    stream.0.lock().await,
+                    &connection,
+                    error_message,
+                )
+                .await
                 {
                     Ok(_) => continue,
-                    Err(error) => {
+                    Err(message) => {
                         subscription.unsubscribe();
-                        let message = format!("{:?}", error);
-                        error!("{}", message);
                         return Err(message);
                     }
                 },
@@ -543,16 +939,218 @@ impl WebTransportServer {
         }
     }
 
-    async fn send_message_to_stream(
+    fn subscribe_to_board_subject(
+        board_context: &mut BoardContext,
+        subject_id: String,
+        send_stream: Arc<Mutex<SendStream>>,
+        connection: Arc<Connection>,
+        replay_since: Option<DateTime>,
+    ) -> impl Subscription + Send + 'static {
+        let event_delivery = EventDelivery::start(send_stream, connection);
+        if let Some(replay_since) = replay_since {
+            for event in board_context
+                .get_or_create_subject(subject_id.clone())
+                .event_buffer
+                .events_since(replay_since)
+            {
+                event_delivery.deliver(
+                    ServerMessage::event(event.event_type.to_string(), event.body),
+                    event.event_type.delivery_policy(),
+                );
+            }
+        }
+        board_context
+            .get_or_create_subject(subject_id)
+            .subject
+            .clone()
+            .subscribe(move |event| {
+                event_delivery.deliver(
+                    ServerMessage::event(event.event_type.to_string(), event.body),
+                    event.event_type.delivery_policy(),
+                );
+            })
+    }
+
+    fn subscribe_to_element_subject(
+        element_context: &mut ElementContext,
+        subject_id: String,
+        send_stream: Arc<Mutex<SendStream>>,
+        connection: Arc<Connection>,
+        replay_since: Option<DateTime>,
+    ) -> impl Subscription + Send + 'static {
+        let event_delivery = EventDelivery::start(send_stream, connection);
+        if let Some(replay_since) = replay_since {
+            for event in element_context
+                .get_or_create_subject(subject_id.clone())
+                .event_buffer
+                .events_since(replay_since)
+            {
+                event_delivery.deliver(
+                    ServerMessage::event(event.event_type.to_string(), event.body),
+                    event.event_type.delivery_policy(),
+                );
+            }
+        }
+        element_context
+            .get_or_create_subject(subject_id)
+            .subject
+            .clone()
+            .subscribe(move |event| {
+                event_delivery.deliver(
+                    ServerMessage::event(event.event_type.to_string(), event.body),
+                    event.event_type.delivery_policy(),
+                );
+            })
+    }
+
+    // Moves a board or element stream to another board without a new init, the user of the init
+    // message needs access to the new board. Client and active member streams cannot switch since
+    // their subject is bound to the user or to the board membership of the session.
+    #[allow(clippy::too_many_arguments)]
+    async fn switch_subject(
+        body: &Value,
+        event_category: EventCategory,
+        user_id: Option<&str>,
+        database_client: &Client,
+        board_context: &Arc<Mutex<BoardContext>>,
+        element_context: &Arc<Mutex<ElementContext>>,
+        send_stream: Arc<Mutex<SendStream>>,
+        connection: Arc<Connection>,
+    ) -> Result<(StreamSubscription, ServerMessage), ServerMessage> {
+        let switch_message = match serde_json::from_value::<SwitchSubjectMessage>(body.clone()) {
+            Ok(switch_message) => switch_message,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "switchsubject".to_string(),
+                    "Switch Subject Message is invalid".to_string(),
+                ))
+            }
+        };
+        if !matches!(
+            event_category,
+            EventCategory::Board | EventCategory::Element
+        ) {
+            return Err(ServerMessage::error_response(
+                "switchsubject".to_string(),
+                "Only board and element streams can switch their subject".to_string(),
+            ));
+        }
+        let Some(user_id) = user_id else {
+            return Err(ServerMessage::error_response(
+                "switchsubject".to_string(),
+                "Stream was initialized without a `userId`".to_string(),
+            ));
+        };
+        let board = match ObjectId::from_str(switch_message.board_id.as_str()) {
+            Ok(board_object_id) => {
+                Board::get_document(database_client, doc! { "_id": board_object_id }).await
+            }
+            Err(_) => Ok(None),
+        };
+        let board = match board {
+            Ok(Some(board)) => board,
+            _ => {
+                return Err(ServerMessage::error_response(
+                    "switchsubject".to_string(),
+                    serde_json::to_string(&ErrorResponseBody {
+                        message: "Board does not exist".to_string(),
+                        body: switch_message.board_id,
+                    })
+                    .unwrap(),
+                ))
+            }
+        };
+        if !board.can_view(&user_id.to_string()) {
+            return Err(ServerMessage::error_response(
+                "switchsubject".to_string(),
+                serde_json::to_string(&ErrorResponseBody {
+                    message: "User is not part of this board".to_string(),
+                    body: switch_message.board_id,
+                })
+                .unwrap(),
+            ));
+        }
+        let (subscription, snapshot) = match event_category {
+            EventCategory::Board => {
+                let mut board_context_guard = board_context.lock().await;
+                let subscription = StreamSubscription::new(
+                    board._id.clone(),
+                    Self::subscribe_to_board_subject(
+                        &mut board_context_guard,
+                        board._id.clone(),
+                        send_stream,
+                        connection,
+                        None,
+                    ),
+                );
+                drop(board_context_guard);
+                let snapshot = match switch_message.include_snapshot {
+                    true => serde_json::to_value(&board).ok(),
+                    false => None,
+                };
+                (subscription, snapshot)
+            }
+            _ => {
+                let snapshot = match switch_message.include_snapshot {
+                    true => match Element::get_multiple_documents(
+                        database_client,
+                        doc! { "boardId": board._id.clone() },
+                    )
+                    .await
+                    {
+                        Ok(element_cursor) => element_cursor
+                            .try_collect::<Vec<Element>>()
+                            .await
+                            .ok()
+                            .and_then(|elements| serde_json::to_value(elements).ok()),
+                        Err(_) => None,
+                    },
+                    false => None,
+                };
+                let mut element_context_guard = element_context.lock().await;
+                let subscription = StreamSubscription::new(
+                    board._id.clone(),
+                    Self::subscribe_to_element_subject(
+                        &mut element_context_guard,
+                        board._id.clone(),
+                        send_stream,
+                        connection,
+                        None,
+                    ),
+                );
+                drop(element_context_guard);
+                (subscription, snapshot)
+            }
+        };
+        Ok((
+            subscription,
+            ServerMessage::ok_response(
+                "switchsubject".to_string(),
+                serde_json::to_string(&SubjectSwitchedMessage {
+                    read_only: !board.can_edit(user_id),
+                    board_id: board._id,
+                    snapshot,
+                })
+                .unwrap(),
+            ),
+        ))
+    }
+
+    // A client that stops reading would block the write, and every task waiting for the stream, forever,
+    // so a write that does not finish within `WRITE_TIMEOUT` closes the session
+    pub async fn send_message_to_stream(
         mut stream: MutexGuard<'_, SendStream>,
+        connection: &Connection,
         message: ServerMessage,
-    ) {
-        match stream
-            .write_all(serde_json::to_string(&message).unwrap().as_bytes())
-            .await
-        {
-            Ok(_) => (),
-            Err(error) => {
+    ) -> Result<(), String> {
+        let write_result = timeout(
+            WRITE_TIMEOUT(),
+            stream.write_all(serde_json::to_string(&message).unwrap().as_bytes()),
+        )
+        .await;
+        match write_result {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(error)) => {
                 let message = match error {
                     StreamWriteError::NotConnected => {
                         "Cannot write Stream, Stream lost connection".to_string()
@@ -565,6 +1163,19 @@ impl WebTransportServer {
                     }
                 };
                 error!("{}", message);
+                Err(message)
+            }
+            Err(_) => {
+                let message = format!(
+                    "Stream write timed out after {} ms, client stopped reading, closing session",
+                    WRITE_TIMEOUT().as_millis()
+                );
+                error!("{}", message);
+                connection.close(
+                    VarInt::from_u32(WRITE_TIMEOUT_CLOSE_CODE),
+                    b"stream write timed out",
+                );
+                Err(message)
             }
         }
     }
@@ -576,7 +1187,7 @@ impl WebTransportServer {
         active_member_context: &'a mut ActiveMemberContext,
         database_client: Client,
         message: &'b str,
-    ) -> Result<(String, EventCategory), String> {
+    ) -> Result<InitializedStream, String> {
         let init_message = match serde_json::from_str::<InitMessage>(message) {
             Ok(init_message) => init_message,
             Err(error) => {
@@ -589,6 +1200,19 @@ impl WebTransportServer {
         if init_message.message_type != *"init".to_string() {
             return Err("Init Message: `messageType` != 'init'".to_string());
         }
+        let protocol_version = ProtocolVersion::negotiate(init_message.protocol_version.as_deref())
+            .map_err(|message| format!("Init Message: {}", message))?;
+        let replay_since = match init_message.replay_since {
+            Some(replay_since) => match DateTime::parse_rfc3339_str(replay_since) {
+                Ok(replay_since) => Some(replay_since),
+                Err(_) => {
+                    return Err(
+                        "Init Message: `replaySince` is not a valid RFC 3339 timestamp".to_string(),
+                    );
+                }
+            },
+            None => None,
+        };
         let event_category =
             match EventCategory::get_category_by_string(init_message.event_category) {
                 Ok(category) => category,
@@ -596,38 +1220,76 @@ impl WebTransportServer {
                     return Err("Invalid event category".to_string());
                 }
             };
+        let user_id = init_message.user_id.clone();
+        if let Some(user_id) = user_id.as_deref() {
+            if let Ok(object_id) = ObjectId::from_str(user_id) {
+                if let Ok(Some(user)) =
+                    User::get_document(&database_client, doc! { "_id": object_id }).await
+                {
+                    if !user.is_guest_token_valid(init_message.guest_token.as_deref()) {
+                        return Err("Init Message: `guestToken` is missing or invalid".to_string());
+                    }
+                }
+            }
+            ClientDocument::touch(user_id, &database_client).await;
+        }
         let subject_id = match event_category {
             EventCategory::Client => init_message.context_id.clone(),
-            _ => match Board::get_existing_board(init_message.context_id.clone(), &database_client)
+            _ => {
+                let board = match Board::get_existing_board(
+                    init_message.context_id.clone(),
+                    &database_client,
+                )
                 .await
-            {
-                Ok(board) => board._id,
-                Err(_) => {
+                {
+                    Ok(board) => board,
+                    Err(_) => {
+                        return Err(format!(
+                            "No Board found with the Board Id: {}",
+                            init_message.context_id
+                        ));
+                    }
+                };
+                let user_id = match init_message.user_id {
+                    Some(user_id) => user_id,
+                    None => {
+                        return Err("Init Message: `userId` is required for this event category"
+                            .to_string());
+                    }
+                };
+                if !board.can_view(&user_id) {
                     return Err(format!(
-                        "No Board found with the Board Id: {}",
-                        init_message.context_id
+                        "User {} is not allowed on the Board with the Board Id: {}",
+                        user_id, board._id
                     ));
                 }
-            },
+                board._id
+            }
         };
-        match event_category {
-            EventCategory::Board => Ok((
-                board_context.get_or_create_subject_return_board_id(subject_id),
-                event_category,
-            )),
-            EventCategory::Client => Ok((
-                client_context.get_or_create_subject_return_user_id(subject_id),
-                event_category,
-            )),
-            EventCategory::ActiveMember => Ok((
-                active_member_context.get_or_create_subject_return_board_id(subject_id),
-                event_category,
-            )),
-            EventCategory::Element => Ok((
-                element_context.get_or_create_subject_return_board_id(subject_id),
-                event_category,
-            )),
-        }
+        let subject_id = match event_category {
+            EventCategory::Board => board_context.get_or_create_subject_return_board_id(subject_id),
+            EventCategory::Client => {
+                client_context.get_or_create_subject_return_user_id(subject_id)
+            }
+            EventCategory::ActiveMember => {
+                if let Some(user_id) = &user_id {
+                    if active_member_context.cancel_pending_removal(user_id) {
+                        info!("User {} reconnected within the grace period", user_id);
+                    }
+                }
+                active_member_context.get_or_create_subject_return_board_id(subject_id)
+            }
+            EventCategory::Element => {
+                element_context.get_or_create_subject_return_board_id(subject_id)
+            }
+        };
+        Ok(InitializedStream {
+            subject_id,
+            event_category,
+            replay_since,
+            user_id,
+            protocol_version,
+        })
     }
 
     async fn handle_with_corresponding_category(
@@ -651,6 +1313,24 @@ impl WebTransportServer {
         let message_category =
             WebTransportMessageMainCategory::to_enum(substrings.first().unwrap());
         let message_subcategory = substrings.get(1).unwrap().as_str();
+        let is_supported_subcategory = match message_category {
+            WebTransportMessageMainCategory::Board => {
+                BoardMessage::supports_subcategory(message_subcategory)
+            }
+            WebTransportMessageMainCategory::Element => {
+                ElementMessage::supports_subcategory(message_subcategory)
+            }
+            WebTransportMessageMainCategory::ActiveMember => {
+                ActiveMemberMessage::supports_subcategory(message_subcategory)
+            }
+            WebTransportMessageMainCategory::Unknown => true,
+        };
+        if !is_supported_subcategory {
+            return Err(ServerMessage::error_response(
+                "messagesubcategory".to_string(),
+                "Message Subcategory unknown".to_string(),
+            ));
+        }
         match message_category {
             WebTransportMessageMainCategory::Board => {
                 BoardMessage::handle_with_corresponding_message(
@@ -662,13 +1342,43 @@ impl WebTransportServer {
                 .await
             }
             WebTransportMessageMainCategory::Element => {
-                ElementMessage::handle_with_corresponding_message(
+                let created_on_board_id = match message_subcategory {
+                    "createelement" => {
+                        match Self::check_element_limit(&json.body, &database_client).await {
+                            Ok(board_element_count) => board_element_count,
+                            Err(error_message) => return Err(error_message),
+                        }
+                    }
+                    _ => None,
+                };
+                let response = ElementMessage::handle_with_corresponding_message(
                     message_subcategory,
-                    json.body,
-                    database_client,
+                    json.body.clone(),
+                    database_client.clone(),
                     element_context,
                 )
-                .await
+                .await;
+                if response.is_ok() {
+                    Self::clear_activity_of_unlocked_elements(
+                        message_subcategory,
+                        &json.body,
+                        active_member_context,
+                    )
+                    .await;
+                    if let Some(board_id) = created_on_board_id {
+                        emit_near_limit_if_reached(&database_client, &board_context, &board_id)
+                            .await;
+                    }
+                    if message_subcategory == "removeelement" {
+                        Self::emit_emptied_if_board_empty(
+                            &json.body,
+                            database_client,
+                            board_context,
+                        )
+                        .await;
+                    }
+                }
+                response
             }
             WebTransportMessageMainCategory::ActiveMember => {
                 ActiveMemberMessage::handle_with_corresponding_message(
@@ -685,4 +1395,100 @@ impl WebTransportServer {
             )),
         }
     }
+
+    async fn check_element_limit(
+        body: &Value,
+        database_client: &Client,
+    ) -> Result<Option<String>, ServerMessage> {
+        let board_id = match body.get("boardId").and_then(Value::as_str) {
+            Some(board_id) => board_id.to_string(),
+            None => return Ok(None),
+        };
+        let element_count = match Element::count_of_board(database_client, board_id.clone()).await {
+            Ok(element_count) => element_count,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "createelement".to_string(),
+                    "Error during Element counting".to_string(),
+                ))
+            }
+        };
+        let element_limit = match Board::get_element_limit(&board_id, database_client).await {
+            Ok(element_limit) => element_limit,
+            Err(_) => {
+                return Err(ServerMessage::error_response(
+                    "createelement".to_string(),
+                    "Error during Board element limit fetching".to_string(),
+                ))
+            }
+        };
+        if element_count >= element_limit {
+            return Err(ServerMessage::error_response(
+                "createelement".to_string(),
+                "Board has reached its element limit".to_string(),
+            ));
+        }
+        Ok(Some(board_id))
+    }
+
+    // Runs only after a successful removal, so a zero count means this removal emptied the board
+    async fn emit_emptied_if_board_empty(
+        body: &Value,
+        database_client: Client,
+        board_context: Arc<Mutex<BoardContext>>,
+    ) {
+        let (Some(board_id), Some(user_id)) = (
+            body.get("boardId").and_then(Value::as_str),
+            body.get("userId").and_then(Value::as_str),
+        ) else {
+            return;
+        };
+        if !matches!(
+            Element::count_of_board(&database_client, board_id.to_string()).await,
+            Ok(0)
+        ) {
+            return;
+        }
+        let mut board_context_guard = board_context.lock().await;
+        if let Ok(event_body) = serialize_or_log(&BoardEmptiedEventPayload {
+            user_id: user_id.to_string(),
+        }) {
+            board_context_guard
+                .emit_board_event(
+                    database_client,
+                    board_id.to_string(),
+                    BoardEvent {
+                        event_type: BoardEventType::Emptied,
+                        body: event_body,
+                    },
+                )
+                .await;
+        }
+        drop(board_context_guard);
+    }
+
+    async fn clear_activity_of_unlocked_elements(
+        message_subcategory: &str,
+        body: &Value,
+        active_member_context: Arc<Mutex<ActiveMemberContext>>,
+    ) {
+        let element_ids = match message_subcategory {
+            "unlockelement" => body
+                .get("_id")
+                .and_then(Value::as_str)
+                .map(|element_id| vec![element_id.to_string()]),
+            "unlockelements" => body
+                .get("ids")
+                .and_then(|ids| serde_json::from_value::<Vec<String>>(ids.clone()).ok()),
+            _ => None,
+        };
+        let user_id = body.get("userId").and_then(Value::as_str);
+        if let (Some(element_ids), Some(user_id)) = (element_ids, user_id) {
+            let mut active_member_context_guard = active_member_context.lock().await;
+            active_member_context_guard
+                .clear_activity_for_elements(user_id.to_string(), &element_ids)
+                .await;
+            drop(active_member_context_guard);
+        }
+    }
 }
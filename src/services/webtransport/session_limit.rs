@@ -0,0 +1,34 @@
+use std::{env::var, sync::Arc};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+pub struct SessionLimit {
+    semaphore: Arc<Semaphore>,
+    max_sessions: usize,
+}
+
+impl SessionLimit {
+    pub fn new() -> Self {
+        let max_sessions: usize = var("WEBTRANSPORT_MAX_SESSIONS")
+            .expect("Failed to load `WEBTRANSPORT_MAX_SESSIONS` environment variable.")
+            .parse()
+            .expect("Failed to parse `WEBTRANSPORT_MAX_SESSIONS` environment variable.");
+
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_sessions)),
+            max_sessions,
+        }
+    }
+
+    pub fn try_acquire(&self) -> Option<OwnedSemaphorePermit> {
+        self.semaphore.clone().try_acquire_owned().ok()
+    }
+
+    pub fn active_sessions(&self) -> usize {
+        self.max_sessions - self.semaphore.available_permits()
+    }
+
+    pub fn max_sessions(&self) -> usize {
+        self.max_sessions
+    }
+}
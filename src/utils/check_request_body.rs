@@ -1,5 +1,10 @@
+use std::error::Error;
+
 use axum::{
-    extract::{rejection::JsonRejection, Json},
+    extract::{
+        rejection::{JsonDataError, JsonRejection},
+        Json,
+    },
     http::StatusCode,
     response::{IntoResponse, Response},
 };
@@ -7,9 +12,17 @@ use axum::{
 pub fn check_request_body<T>(payload: Result<Json<T>, JsonRejection>) -> Result<Json<T>, Response> {
     match payload {
         Ok(success_body) => Ok(success_body),
-        Err(JsonRejection::JsonDataError(_)) => Err((
+        Err(JsonRejection::JsonDataError(data_error)) => Err((
             StatusCode::BAD_REQUEST,
-            "Request Body has wrong fields".to_string(),
+            format!(
+                "Request Body has wrong fields: {}",
+                describe_json_data_error(&data_error)
+            ),
+        )
+            .into_response()),
+        Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "Request Body is too large".to_string(),
         )
             .into_response()),
         Err(_) => Err((
@@ -19,3 +32,43 @@ pub fn check_request_body<T>(payload: Result<Json<T>, JsonRejection>) -> Result<
             .into_response()),
     }
 }
+
+// Only field names and expected types are reported, the submitted values are never echoed back
+fn describe_json_data_error(data_error: &JsonDataError) -> String {
+    let path_error = match data_error
+        .source()
+        .and_then(|axum_error| axum_error.source())
+        .and_then(|inner_error| {
+            inner_error.downcast_ref::<serde_path_to_error::Error<serde_json::Error>>()
+        }) {
+        Some(path_error) => path_error,
+        None => return "unknown field error".to_string(),
+    };
+    let inner_message = path_error.inner().to_string();
+    let message = match inner_message.rsplit_once(" at line ") {
+        Some((message, _)) => message,
+        None => inner_message.as_str(),
+    };
+    if message.starts_with("missing field")
+        || message.starts_with("unknown field")
+        || message.starts_with("duplicate field")
+    {
+        return match path_error.path().to_string().as_str() {
+            "." => message.to_string(),
+            path => format!("{} in `{}`", message, path),
+        };
+    }
+    let error_kind = match message.split_once(':') {
+        Some((error_kind, _)) => error_kind,
+        None => "invalid value",
+    };
+    match message.rsplit_once(", expected ") {
+        Some((_, expected)) => format!(
+            "{} for field `{}`, expected {}",
+            error_kind,
+            path_error.path(),
+            expected
+        ),
+        None => format!("{} for field `{}`", error_kind, path_error.path()),
+    }
+}
@@ -0,0 +1,84 @@
+use std::{env::var, sync::Arc, sync::OnceLock, time::Duration};
+
+use mongodb::Client;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::{
+    database::collections::{client::Client as ClientDocument, user::User},
+    services::webtransport::context::client::{ClientContext, ClientEvent, ClientEventType},
+};
+
+const DEFAULT_CLIENT_CLEANUP_INTERVAL_SECONDS: u64 = 300;
+
+#[allow(non_snake_case)]
+fn CLIENT_CLEANUP_INTERVAL() -> Duration {
+    static CLIENT_CLEANUP_INTERVAL: OnceLock<Duration> = OnceLock::new();
+    *CLIENT_CLEANUP_INTERVAL.get_or_init(|| match var("CLIENT_CLEANUP_INTERVAL_SECONDS") {
+        Ok(client_cleanup_interval) => Duration::from_secs(
+            client_cleanup_interval
+                .parse()
+                .expect("Failed to parse `CLIENT_CLEANUP_INTERVAL_SECONDS` environment variable."),
+        ),
+        Err(_) => Duration::from_secs(DEFAULT_CLIENT_CLEANUP_INTERVAL_SECONDS),
+    })
+}
+
+pub fn start_client_cleanup(database_client: Client, client_context: Arc<Mutex<ClientContext>>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CLIENT_CLEANUP_INTERVAL());
+        loop {
+            interval.tick().await;
+            remove_inactive_clients(&database_client, &client_context).await;
+        }
+    });
+}
+
+// Reaped devices receive the same `Deleted` event as on a logout, so they know they were logged out
+async fn remove_inactive_clients(
+    database_client: &Client,
+    client_context: &Arc<Mutex<ClientContext>>,
+) {
+    let inactive_clients = match ClientDocument::get_inactive(database_client).await {
+        Ok(inactive_clients) => inactive_clients,
+        Err(_) => {
+            error!("Inactive Clients could not be fetched");
+            return;
+        }
+    };
+    let mut removed_clients = 0;
+    for inactive_client in inactive_clients {
+        match ClientDocument::delete_if_inactive(&inactive_client.client_id, database_client).await
+        {
+            Ok(result) if result.deleted_count > 0 => {}
+            _ => continue,
+        }
+        removed_clients += 1;
+        if User::clear_active_client(
+            &inactive_client.user_id,
+            &inactive_client.client_id,
+            database_client,
+        )
+        .await
+        .is_err()
+        {
+            error!(
+                "Active Client of User with ID {} could not be cleared",
+                inactive_client.user_id
+            );
+        }
+        let mut sub_context = client_context.lock().await;
+        sub_context
+            .emit_client_event(
+                database_client.clone(),
+                inactive_client.user_id.clone(),
+                ClientEvent {
+                    event_type: ClientEventType::Deleted,
+                    body: inactive_client.user_id,
+                },
+            )
+            .await;
+        drop(sub_context);
+    }
+    info!("Removed {} inactive Clients", removed_clients);
+}
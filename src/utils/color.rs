@@ -0,0 +1,59 @@
+use std::{env::var, sync::OnceLock};
+
+const HEX_COLOR_LENGTHS: [usize; 4] = [3, 4, 6, 8];
+const DEFAULT_COLOR_NAMES: [&str; 18] = [
+    "transparent",
+    "black",
+    "white",
+    "gray",
+    "silver",
+    "red",
+    "maroon",
+    "orange",
+    "yellow",
+    "olive",
+    "lime",
+    "green",
+    "teal",
+    "aqua",
+    "blue",
+    "navy",
+    "fuchsia",
+    "purple",
+];
+
+// Named colors elements may use besides hex values, restrict them to brand colors via `ELEMENT_COLOR_NAMES`
+#[allow(non_snake_case)]
+fn COLOR_NAMES() -> &'static [String] {
+    static COLOR_NAMES: OnceLock<Vec<String>> = OnceLock::new();
+    COLOR_NAMES.get_or_init(|| match var("ELEMENT_COLOR_NAMES") {
+        Ok(color_names) => color_names
+            .split(',')
+            .map(|color_name| color_name.trim().to_ascii_lowercase())
+            .filter(|color_name| !color_name.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_COLOR_NAMES
+            .iter()
+            .map(|color_name| color_name.to_string())
+            .collect(),
+    })
+}
+
+pub fn validate_color(color: &str) -> Result<(), String> {
+    let is_valid = match color.strip_prefix('#') {
+        Some(hex) => {
+            HEX_COLOR_LENGTHS.contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        }
+        None => COLOR_NAMES()
+            .iter()
+            .any(|color_name| color_name.eq_ignore_ascii_case(color)),
+    };
+    match is_valid {
+        true => Ok(()),
+        false => Err(format!(
+            "Element color '{}' needs to be a hex value or one of: {}",
+            color,
+            COLOR_NAMES().join(", ")
+        )),
+    }
+}
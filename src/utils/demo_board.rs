@@ -0,0 +1,186 @@
+use std::{env::var, sync::OnceLock};
+
+use bson::{doc, oid::ObjectId, DateTime};
+use mongodb::Client;
+use tracing::{info, warn};
+
+use crate::database::{
+    collections::{
+        board::{Board, BoardSettings, CreateBoard},
+        element::{CreateElement, Element},
+        element_type::ElementType,
+        user::{CreateUser, User},
+    },
+    document::Document,
+};
+
+const DEMO_BOARD_NAME: &str = "Demo Board";
+const DEMO_USER_NAME: &str = "Demo";
+const DEMO_USER_EMAIL: &str = "demo@demo.local";
+const DEFAULT_DEMO_USER_PASSWORD: &str = "demo";
+
+// (Element Type name from `assets/elements.json`, x, y, text, color)
+const DEMO_ELEMENTS: [(&str, f32, f32, &str, &str); 4] = [
+    (
+        "Rechteck",
+        100.0,
+        100.0,
+        "Welcome to the demo board",
+        "#4a90d9",
+    ),
+    ("Kreis", 400.0, 100.0, "Drag me around", "#f5a623"),
+    ("Pfeil", 250.0, 300.0, "", "black"),
+    ("Dreieck", 500.0, 300.0, "Double click to edit", "#7ed321"),
+];
+
+// Only seeds if set to `true`, so production deployments never get the demo data by accident
+#[allow(non_snake_case)]
+fn SEED_DEMO() -> bool {
+    static SEED_DEMO: OnceLock<bool> = OnceLock::new();
+    *SEED_DEMO.get_or_init(|| match var("SEED_DEMO") {
+        Ok(seed_demo) => seed_demo
+            .parse()
+            .expect("Failed to parse `SEED_DEMO` environment variable."),
+        Err(_) => false,
+    })
+}
+
+#[allow(non_snake_case)]
+fn SEED_DEMO_PASSWORD() -> &'static str {
+    static SEED_DEMO_PASSWORD: OnceLock<String> = OnceLock::new();
+    SEED_DEMO_PASSWORD.get_or_init(|| {
+        var("SEED_DEMO_PASSWORD")
+            .ok()
+            .filter(|password| !password.is_empty())
+            .unwrap_or_else(|| DEFAULT_DEMO_USER_PASSWORD.to_string())
+    })
+}
+
+// Runs after `generate_elements`, the demo elements use the seeded Element Types. Skipped as soon as
+// any board exists, so restarts and deployments with real data are left alone
+pub async fn seed_demo_board(database_client: &Client) -> Result<(), String> {
+    if !SEED_DEMO() {
+        return Ok(());
+    }
+    match Board::get_page(database_client, None, 1).await {
+        Ok(boards) if !boards.is_empty() => {
+            info!("Boards exist already, skipping demo seeding");
+            return Ok(());
+        }
+        Ok(_) => {}
+        Err(_) => return Err("Couldn't check for existing Boards".to_string()),
+    }
+    let demo_user_id = seed_demo_user(database_client).await?;
+    let demo_board_id = match Board::create_document(
+        database_client,
+        CreateBoard {
+            name: DEMO_BOARD_NAME.to_string(),
+            host: demo_user_id.clone(),
+            allowed_members: vec![demo_user_id.clone()],
+            viewers: vec![],
+            is_public: true,
+            settings: BoardSettings::default(),
+            created_at: DateTime::now(),
+            updated_at: DateTime::now(),
+        },
+    )
+    .await
+    {
+        Ok(result) => match result.inserted_id.as_object_id() {
+            Some(board_id) => board_id.to_hex(),
+            None => return Err("Inserted demo Board ID is not an ObjectId".to_string()),
+        },
+        Err(_) => return Err("Couldn't create demo Board".to_string()),
+    };
+    let mut seeded_elements = 0;
+    for (z_index, (element_type_name, x, y, text, color)) in DEMO_ELEMENTS.iter().enumerate() {
+        let element_type =
+            match ElementType::get_document(database_client, doc! { "name": element_type_name })
+                .await
+            {
+                Ok(Some(element_type)) => element_type,
+                Ok(None) => {
+                    warn!(
+                        "Element Type {} does not exist, skipping its demo Element",
+                        element_type_name
+                    );
+                    continue;
+                }
+                Err(_) => {
+                    return Err(format!(
+                        "Couldn't fetch Element Type with name: {}",
+                        element_type_name
+                    ))
+                }
+            };
+        let defaults = element_type.defaults.unwrap_or_default();
+        if Element::create_document(
+            database_client,
+            CreateElement {
+                _id: ObjectId::new().to_hex(),
+                selected: false,
+                locked_by: None,
+                x: *x,
+                y: *y,
+                rotation: 0.0,
+                scale_x: defaults.scale_x(None),
+                scale_y: defaults.scale_y(None),
+                z_index: z_index as i32,
+                created_at: DateTime::now(),
+                text: text.to_string(),
+                element_type: element_type._id,
+                board_id: demo_board_id.clone(),
+                color: color.to_string(),
+                metadata: None,
+                asset_id: None,
+                created_by: demo_user_id.clone(),
+                updated_at: DateTime::now(),
+                connector: None,
+            },
+        )
+        .await
+        .is_err()
+        {
+            return Err(format!(
+                "Couldn't create demo Element of type: {}",
+                element_type_name
+            ));
+        }
+        seeded_elements += 1;
+    }
+    info!(
+        "Seeded demo Board {} with {} Elements, log in as {} to host it",
+        demo_board_id, seeded_elements, DEMO_USER_EMAIL
+    );
+    Ok(())
+}
+
+// Reuses the demo user of an earlier seeding, e.g. after all boards were deleted
+async fn seed_demo_user(database_client: &Client) -> Result<String, String> {
+    match User::get_document(database_client, doc! { "email": DEMO_USER_EMAIL }).await {
+        Ok(Some(user)) => return Ok(user._id),
+        Ok(None) => {}
+        Err(_) => return Err("Couldn't fetch demo User".to_string()),
+    }
+    let demo_user_id = ObjectId::new();
+    match User::create_document(
+        database_client,
+        CreateUser {
+            _id: demo_user_id,
+            name: DEMO_USER_NAME.to_string(),
+            email: Some(DEMO_USER_EMAIL.to_string()),
+            password: Some(SEED_DEMO_PASSWORD().to_string()),
+            is_guest: false,
+            last_active_at: DateTime::now(),
+            guest_token_hash: None,
+        },
+    )
+    .await
+    {
+        Ok(_) => {
+            info!("Seeded demo User {}", DEMO_USER_EMAIL);
+            Ok(demo_user_id.to_hex())
+        }
+        Err(_) => Err("Couldn't create demo User".to_string()),
+    }
+}
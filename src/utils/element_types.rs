@@ -6,7 +6,9 @@ use serde::Deserialize;
 use tracing::warn;
 
 use crate::database::{
-    collections::element_type::{CreateElementType, ElementType, UpdateElementType},
+    collections::element_type::{
+        CreateElementType, ElementType, ElementTypeDefaults, UpdateElementType,
+    },
     document::Document,
 };
 
@@ -15,6 +17,11 @@ use crate::database::{
 pub struct ElementDefintion {
     name: String,
     path: String,
+    category: Option<String>,
+    #[serde(default)]
+    defaults: Option<ElementTypeDefaults>,
+    #[serde(default)]
+    singleton: bool,
 }
 
 pub async fn generate_elements(database_client: &Client) -> Result<(), String> {
@@ -26,6 +33,9 @@ pub async fn generate_elements(database_client: &Client) -> Result<(), String> {
     let elements = serde_json::from_str::<Vec<ElementDefintion>>(contents.as_str())
         .expect("Element JSON is not valid");
     for element in elements.iter() {
+        if let Err(message) = ElementType::validate_path(&element.path) {
+            return Err(format!("{}: {}", element.name, message));
+        }
         let query_doc = doc! {
             "name": element.name.clone()
         };
@@ -38,6 +48,9 @@ pub async fn generate_elements(database_client: &Client) -> Result<(), String> {
                         UpdateElementType {
                             name: None,
                             path: Some(element.path.clone()),
+                            category: element.category.clone(),
+                            defaults: element.defaults.clone(),
+                            singleton: Some(element.singleton),
                         },
                     )
                     .await
@@ -62,6 +75,9 @@ pub async fn generate_elements(database_client: &Client) -> Result<(), String> {
                     CreateElementType {
                         name: element.name.clone(),
                         path: element.path.clone(),
+                        category: element.category.clone(),
+                        defaults: element.defaults.clone(),
+                        singleton: element.singleton,
                     },
                 )
                 .await
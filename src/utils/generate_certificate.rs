@@ -1,17 +1,89 @@
+use std::env::var;
+
 use anyhow::Context;
 use anyhow::Result;
-use log::info;
-use wtransport::tls::Sha256DigestFmt;
+use log::{info, warn};
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair, PKCS_ECDSA_P256_SHA256};
+use time::{Duration, OffsetDateTime};
+use wtransport::tls::{Certificate, CertificateChain, PrivateKey, Sha256DigestFmt};
 use wtransport::Identity;
 
 const CERT_FILE: &str = "certificates/cert.pem";
 const KEY_FILE: &str = "certificates/key.pem";
+const DEFAULT_SUBJECT_ALT_NAMES: &str = "localhost,127.0.0.1,::1";
+const DEFAULT_VALIDITY_DAYS: i64 = 14;
+// Browsers only accept certificates pinned via `serverCertificateHashes` if they are valid for at most 14 days
+const MAX_HASH_PINNED_VALIDITY_DAYS: i64 = 14;
+const RENEWAL_THRESHOLD_HOURS: i64 = 24;
+
+pub struct CertificateConfig {
+    pub subject_alt_names: Vec<String>,
+    pub validity_days: i64,
+}
+
+impl CertificateConfig {
+    pub fn new() -> Self {
+        let subject_alt_names = var("CERTIFICATE_SUBJECT_ALT_NAMES")
+            .unwrap_or_else(|_| DEFAULT_SUBJECT_ALT_NAMES.to_string())
+            .split(',')
+            .map(|subject_alt_name| subject_alt_name.trim().to_string())
+            .filter(|subject_alt_name| !subject_alt_name.is_empty())
+            .collect::<Vec<String>>();
+
+        let validity_days: i64 = match var("CERTIFICATE_VALIDITY_DAYS") {
+            Ok(validity_days) => validity_days
+                .parse()
+                .expect("Failed to parse `CERTIFICATE_VALIDITY_DAYS` environment variable."),
+            Err(_) => DEFAULT_VALIDITY_DAYS,
+        };
+
+        Self {
+            subject_alt_names,
+            validity_days,
+        }
+    }
+}
+
+pub async fn certificate_needs_renewal() -> bool {
+    if !std::path::Path::new(KEY_FILE).is_file() {
+        return true;
+    }
+    let certificate = match Certificate::load_pemfile(CERT_FILE).await {
+        Ok(certificate) => certificate,
+        Err(_) => {
+            warn!("Existing certificate could not be loaded from '{CERT_FILE}'");
+            return true;
+        }
+    };
+    let not_after = match x509_parser::parse_x509_certificate(certificate.der()) {
+        Ok((_, parsed_certificate)) => parsed_certificate.validity().not_after.timestamp(),
+        Err(_) => {
+            warn!("Existing certificate in '{CERT_FILE}' could not be parsed");
+            return true;
+        }
+    };
+    let renewal_deadline = OffsetDateTime::now_utc() + Duration::hours(RENEWAL_THRESHOLD_HOURS);
+    if not_after <= renewal_deadline.unix_timestamp() {
+        info!("Existing certificate expires within {RENEWAL_THRESHOLD_HOURS} hours");
+        return true;
+    }
+    false
+}
 
-pub async fn generate_certificate() -> Result<()> {
+pub async fn generate_certificate(config: &CertificateConfig) -> Result<()> {
     info!("Generating self signed certificate for WebTransport");
+    info!(
+        "Certificate subject alt names: {}, valid for {} days",
+        config.subject_alt_names.join(", "),
+        config.validity_days
+    );
+    if config.validity_days > MAX_HASH_PINNED_VALIDITY_DAYS {
+        warn!(
+            "Certificates valid for more than {MAX_HASH_PINNED_VALIDITY_DAYS} days are rejected by browsers using certificate hashes"
+        );
+    }
 
-    let identity = Identity::self_signed(["localhost", "127.0.0.1", "::1"])
-        .context("cannot create self signed identity")?;
+    let identity = self_signed_identity(config)?;
 
     info!("Storing certificate to file: '{CERT_FILE}'");
 
@@ -43,3 +115,31 @@ pub async fn generate_certificate() -> Result<()> {
 
     Ok(())
 }
+
+fn self_signed_identity(config: &CertificateConfig) -> Result<Identity> {
+    let mut distinguished_name = DistinguishedName::new();
+    distinguished_name.push(DnType::CommonName, "master-backend self-signed");
+
+    let key_pair =
+        KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256).context("cannot generate key pair")?;
+
+    let mut certificate_params = CertificateParams::new(config.subject_alt_names.clone())
+        .context("cannot use subject alt names for certificate")?;
+    certificate_params.distinguished_name = distinguished_name;
+    certificate_params.not_before = OffsetDateTime::now_utc();
+    certificate_params.not_after = OffsetDateTime::now_utc()
+        .checked_add(Duration::days(config.validity_days))
+        .context("certificate validity is out of range")?;
+
+    let certificate = certificate_params
+        .self_signed(&key_pair)
+        .context("cannot create self signed certificate")?;
+
+    Ok(Identity::new(
+        CertificateChain::single(
+            Certificate::from_der(certificate.der().to_vec())
+                .context("cannot read generated certificate")?,
+        ),
+        PrivateKey::from_der_pkcs8(key_pair.serialize_der()),
+    ))
+}
@@ -0,0 +1,79 @@
+use std::{env::var, str::FromStr, sync::OnceLock, time::Duration};
+
+use bson::{doc, oid::ObjectId};
+use mongodb::Client;
+use tracing::{error, info};
+
+use crate::database::{
+    collections::{
+        active_member::ActiveMember, board::Board, client::Client as ClientDocument, user::User,
+    },
+    document::Document,
+};
+
+const DEFAULT_GUEST_CLEANUP_INTERVAL_SECONDS: u64 = 300;
+
+#[allow(non_snake_case)]
+fn GUEST_CLEANUP_INTERVAL() -> Duration {
+    static GUEST_CLEANUP_INTERVAL: OnceLock<Duration> = OnceLock::new();
+    *GUEST_CLEANUP_INTERVAL.get_or_init(|| match var("GUEST_CLEANUP_INTERVAL_SECONDS") {
+        Ok(guest_cleanup_interval) => Duration::from_secs(
+            guest_cleanup_interval
+                .parse()
+                .expect("Failed to parse `GUEST_CLEANUP_INTERVAL_SECONDS` environment variable."),
+        ),
+        Err(_) => Duration::from_secs(DEFAULT_GUEST_CLEANUP_INTERVAL_SECONDS),
+    })
+}
+
+pub fn start_guest_cleanup(database_client: Client) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(GUEST_CLEANUP_INTERVAL());
+        loop {
+            interval.tick().await;
+            remove_expired_guests(&database_client).await;
+        }
+    });
+}
+
+// Guests that are still active on a board are kept until they leave
+async fn remove_expired_guests(database_client: &Client) {
+    let expired_guests = match User::get_expired_guests(database_client).await {
+        Ok(expired_guests) => expired_guests,
+        Err(_) => {
+            error!("Expired guest users could not be fetched");
+            return;
+        }
+    };
+    let mut removed_guests = 0;
+    for guest in expired_guests {
+        let query_doc = doc! {
+            "userId": guest._id.clone(),
+        };
+        match ActiveMember::get_document(database_client, query_doc.clone()).await {
+            Ok(None) => {}
+            Ok(Some(_)) => continue,
+            Err(_) => continue,
+        }
+        if Board::remove_user_from_all_boards(guest._id.clone(), database_client)
+            .await
+            .is_err()
+        {
+            error!("Guest user {} could not be removed from boards", guest._id);
+            continue;
+        }
+        let _ = ClientDocument::delete_document(database_client, query_doc).await;
+        let query_doc = doc! {
+            "_id": ObjectId::from_str(guest._id.as_str()).unwrap(),
+        };
+        if User::delete_document(database_client, query_doc)
+            .await
+            .is_ok()
+        {
+            removed_guests += 1;
+        }
+    }
+    if removed_guests > 0 {
+        info!("Removed {} expired guest users", removed_guests);
+    }
+}
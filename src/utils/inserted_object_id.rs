@@ -0,0 +1,24 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use mongodb::results::InsertOneResult;
+use tracing::error;
+
+#[allow(clippy::result_large_err)]
+pub fn inserted_object_id_hex(insert_result: &InsertOneResult) -> Result<String, Response> {
+    match insert_result.inserted_id.as_object_id() {
+        Some(inserted_id) => Ok(inserted_id.to_hex()),
+        None => {
+            error!(
+                "Inserted ID is not an ObjectId: {:?}",
+                insert_result.inserted_id
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Inserted ID is not an ObjectId",
+            )
+                .into_response())
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use std::any::type_name;
+
+use serde::Serialize;
+use tracing::error;
+
+// Event bodies are serialized right before emission, a failing payload is logged and its event skipped
+pub fn serialize_or_log<T: Serialize>(payload: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(payload).inspect_err(|error| {
+        error!("Could not serialize {}: {}", type_name::<T>(), error);
+    })
+}
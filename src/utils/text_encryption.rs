@@ -0,0 +1,159 @@
+// Field level encryption of the element `text` on boards flagged as `encrypted`.
+//
+// Threat model: protects the text against anyone who can read the database or its backups but not
+// the server environment. The server decrypts on every read, so it is no protection against a
+// compromised server, an admin with access to `ELEMENT_ENCRYPTION_SECRET` or board members, and
+// someone able to write to the database can copy encrypted texts between elements of the same board.
+// Encrypted texts cannot be searched, the text search only finds plaintext elements.
+//
+// Each board gets its own AES-256-GCM key derived with HKDF-SHA256 from the server secret and the
+// board ID, the board ID is also authenticated. Texts are decrypted with the board the element or
+// history entry is stored on, so a text copied to another board fails to decrypt.
+//
+// Key rotation: move the current secret to `ELEMENT_ENCRYPTION_PREVIOUS_SECRET`, set a new
+// `ELEMENT_ENCRYPTION_SECRET` and enable the encryption of every encrypted board again through
+// `PUT /admin/boards/:id/encryption`, which re-encrypts all texts with the new secret. Texts are
+// decrypted with the previous secret as long as it is configured.
+use std::{env::var, sync::OnceLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    hkdf,
+    rand::{SecureRandom, SystemRandom},
+};
+use tracing::error;
+
+const ENCRYPTED_TEXT_PREFIX: &str = "enc:v1:";
+const KEY_DERIVATION_SALT: &[u8] = b"master-backend element text";
+
+#[allow(non_snake_case)]
+fn ELEMENT_ENCRYPTION_SECRET() -> Option<&'static str> {
+    static ELEMENT_ENCRYPTION_SECRET: OnceLock<Option<String>> = OnceLock::new();
+    ELEMENT_ENCRYPTION_SECRET
+        .get_or_init(|| {
+            var("ELEMENT_ENCRYPTION_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty())
+        })
+        .as_deref()
+}
+
+#[allow(non_snake_case)]
+fn ELEMENT_ENCRYPTION_PREVIOUS_SECRET() -> Option<&'static str> {
+    static ELEMENT_ENCRYPTION_PREVIOUS_SECRET: OnceLock<Option<String>> = OnceLock::new();
+    ELEMENT_ENCRYPTION_PREVIOUS_SECRET
+        .get_or_init(|| {
+            var("ELEMENT_ENCRYPTION_PREVIOUS_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty())
+        })
+        .as_deref()
+}
+
+pub fn is_encryption_configured() -> bool {
+    ELEMENT_ENCRYPTION_SECRET().is_some()
+}
+
+fn board_key(secret: &str, board_id: &str) -> LessSafeKey {
+    let pseudo_random_key =
+        hkdf::Salt::new(hkdf::HKDF_SHA256, KEY_DERIVATION_SALT).extract(secret.as_bytes());
+    let info = [board_id.as_bytes()];
+    let key_material = pseudo_random_key
+        .expand(&info, &AES_256_GCM)
+        .expect("AES-256-GCM key length is valid for HKDF-SHA256");
+    LessSafeKey::new(UnboundKey::from(key_material))
+}
+
+// Stored as `enc:v1:<board id>:<base64 of nonce and ciphertext>`
+pub fn encrypt_text(board_id: &str, text: &str) -> Result<String, String> {
+    let Some(secret) = ELEMENT_ENCRYPTION_SECRET() else {
+        return Err("Element text encryption is not configured".to_string());
+    };
+    let mut nonce = [0u8; NONCE_LEN];
+    if SystemRandom::new().fill(&mut nonce).is_err() {
+        return Err("Nonce for the Element text could not be generated".to_string());
+    }
+    let mut sealed_text = text.as_bytes().to_vec();
+    if board_key(secret, board_id)
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(board_id.as_bytes()),
+            &mut sealed_text,
+        )
+        .is_err()
+    {
+        return Err("Element text could not be encrypted".to_string());
+    }
+    let mut payload = nonce.to_vec();
+    payload.extend(sealed_text);
+    Ok(format!(
+        "{}{}:{}",
+        ENCRYPTED_TEXT_PREFIX,
+        board_id,
+        STANDARD.encode(payload)
+    ))
+}
+
+fn open_text(secret: &str, board_id: &str, payload: &[u8]) -> Option<String> {
+    if payload.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, sealed_text) = payload.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut sealed_text = sealed_text.to_vec();
+    let text = board_key(secret, board_id)
+        .open_in_place(nonce, Aad::from(board_id.as_bytes()), &mut sealed_text)
+        .ok()?;
+    String::from_utf8(text.to_vec()).ok()
+}
+
+// Plaintext is returned unchanged, a text that cannot be decrypted or was encrypted for another board
+// than `board_id` is kept as stored and logged
+pub fn decrypt_text(board_id: &str, text: String) -> String {
+    let Some(envelope) = text.strip_prefix(ENCRYPTED_TEXT_PREFIX) else {
+        return text;
+    };
+    let decrypted_text = envelope
+        .split_once(':')
+        .filter(|(envelope_board_id, _)| *envelope_board_id == board_id)
+        .and_then(|(_, payload)| STANDARD.decode(payload).ok())
+        .and_then(|payload| {
+            [
+                ELEMENT_ENCRYPTION_SECRET(),
+                ELEMENT_ENCRYPTION_PREVIOUS_SECRET(),
+            ]
+            .into_iter()
+            .flatten()
+            .find_map(|secret| open_text(secret, board_id, &payload))
+        });
+    match decrypted_text {
+        Some(decrypted_text) => decrypted_text,
+        None => {
+            error!("Encrypted Element text could not be decrypted");
+            text
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_text_is_bound_to_the_board() {
+        std::env::set_var("ELEMENT_ENCRYPTION_SECRET", "test secret");
+        let encrypted_text = encrypt_text("board", "secret note").unwrap();
+        assert_ne!(encrypted_text, "secret note");
+        assert_eq!(decrypt_text("board", encrypted_text.clone()), "secret note");
+        assert_eq!(
+            decrypt_text("other board", encrypted_text.clone()),
+            encrypted_text
+        );
+    }
+
+    #[test]
+    fn decrypt_text_keeps_plaintext() {
+        assert_eq!(decrypt_text("board", "note".to_string()), "note");
+    }
+}